@@ -293,6 +293,13 @@ impl ContextAwareConfig {
 
         merged_data
     }
+
+    pub fn get_default_config_schema(&self, key: &str) -> Option<&Value> {
+        self.toml_value
+            .get("default-config")
+            .and_then(|default_config| default_config.get(key))
+            .and_then(|config| config.get("schema"))
+    }
 }
 
 fn compute_priority(
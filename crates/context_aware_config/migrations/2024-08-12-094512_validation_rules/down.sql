@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS validation_rules_audit ON public.validation_rules;
+DROP TABLE IF EXISTS public.validation_rules;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+-- Name: validation_rules; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE IF NOT EXISTS public.validation_rules (
+    rule_name text PRIMARY KEY,
+    description text NOT NULL,
+    rule json NOT NULL,
+    created_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by varchar(200) NOT NULL,
+    last_modified_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by varchar(200) NOT NULL
+);
+--
+-- Name: validation_rules validation_rules_audit; Type: TRIGGER; Schema: public; Owner: -
+--
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON public.validation_rules FOR EACH ROW EXECUTE FUNCTION public.event_logger();
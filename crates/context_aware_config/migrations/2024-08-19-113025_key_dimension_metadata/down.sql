@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS default_configs_owner_index;
+DROP INDEX IF EXISTS dimensions_owner_index;
+
+ALTER TABLE public.default_configs
+drop column description,
+drop column owner,
+drop column labels,
+drop column deprecated,
+drop column sunset_date;
+
+ALTER TABLE public.dimensions
+drop column description,
+drop column owner,
+drop column labels,
+drop column deprecated,
+drop column sunset_date;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+ALTER TABLE public.default_configs
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+ALTER TABLE public.dimensions
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+CREATE INDEX IF NOT EXISTS default_configs_owner_index ON public.default_configs(owner);
+CREATE INDEX IF NOT EXISTS dimensions_owner_index ON public.dimensions(owner);
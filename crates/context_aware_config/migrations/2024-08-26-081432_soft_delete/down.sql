@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS contexts_deleted_at_index;
+DROP INDEX IF EXISTS default_configs_deleted_at_index;
+DROP INDEX IF EXISTS dimensions_deleted_at_index;
+
+DELETE FROM public.contexts WHERE deleted_at IS NOT NULL;
+DELETE FROM public.default_configs WHERE deleted_at IS NOT NULL;
+DELETE FROM public.dimensions WHERE deleted_at IS NOT NULL;
+
+ALTER TABLE public.contexts
+drop column deleted_at,
+drop column deleted_by;
+
+ALTER TABLE public.default_configs
+drop column deleted_at,
+drop column deleted_by;
+
+ALTER TABLE public.dimensions
+drop column deleted_at,
+drop column deleted_by;
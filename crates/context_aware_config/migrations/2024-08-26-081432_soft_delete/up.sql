@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+ALTER TABLE public.contexts
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE public.default_configs
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE public.dimensions
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+CREATE INDEX IF NOT EXISTS contexts_deleted_at_index ON public.contexts(deleted_at);
+CREATE INDEX IF NOT EXISTS default_configs_deleted_at_index ON public.default_configs(deleted_at);
+CREATE INDEX IF NOT EXISTS dimensions_deleted_at_index ON public.dimensions(deleted_at);
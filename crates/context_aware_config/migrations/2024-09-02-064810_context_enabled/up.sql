@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE public.contexts
+add column enabled BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS contexts_audit ON public.contexts;
+CREATE TRIGGER contexts_audit AFTER INSERT OR DELETE OR UPDATE ON public.contexts FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+DROP TRIGGER IF EXISTS default_configs_audit ON public.default_configs;
+CREATE TRIGGER default_configs_audit AFTER INSERT OR DELETE OR UPDATE ON public.default_configs FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+DROP TRIGGER IF EXISTS dimensions_audit ON public.dimensions;
+CREATE TRIGGER dimensions_audit AFTER INSERT OR DELETE OR UPDATE ON public.dimensions FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+DROP TRIGGER IF EXISTS functions_audit ON public.functions;
+CREATE TRIGGER functions_audit AFTER INSERT OR DELETE OR UPDATE ON public.functions FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+DROP TRIGGER IF EXISTS validation_rules_audit ON public.validation_rules;
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON public.validation_rules FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+DROP FUNCTION IF EXISTS public.cac_event_logger();
+ALTER TABLE public.event_log drop column IF EXISTS change_reason;
+ALTER TABLE public.config_versions drop column change_reason;
@@ -0,0 +1,69 @@
+-- Your SQL goes here
+ALTER TABLE public.config_versions add column change_reason TEXT;
+ALTER TABLE public.event_log add column IF NOT EXISTS change_reason TEXT;
+--
+-- Name: cac_event_logger(); Type: FUNCTION; Schema: public; Owner: -
+-- The change reason is set per transaction through the superposition.change_reason setting.
+-- event_logger() is left as it is, the experimentation tables share it.
+--
+CREATE OR REPLACE FUNCTION public.cac_event_logger() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+DECLARE
+    old_data json;
+    new_data json;
+    change_reason text;
+BEGIN
+    change_reason := NULLIF(current_setting('superposition.change_reason', true), '');
+    IF (TG_OP = 'UPDATE') THEN
+        old_data := row_to_json(OLD);
+        new_data := row_to_json(NEW);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, original_data, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'DELETE') THEN
+        old_data := row_to_json(OLD);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, original_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'INSERT') THEN
+        new_data = row_to_json(NEW);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    END IF;
+    RETURN NULL;
+END;
+$$;
+DROP TRIGGER IF EXISTS contexts_audit ON public.contexts;
+CREATE TRIGGER contexts_audit AFTER INSERT OR DELETE OR UPDATE ON public.contexts FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS default_configs_audit ON public.default_configs;
+CREATE TRIGGER default_configs_audit AFTER INSERT OR DELETE OR UPDATE ON public.default_configs FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS dimensions_audit ON public.dimensions;
+CREATE TRIGGER dimensions_audit AFTER INSERT OR DELETE OR UPDATE ON public.dimensions FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS functions_audit ON public.functions;
+CREATE TRIGGER functions_audit AFTER INSERT OR DELETE OR UPDATE ON public.functions FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS validation_rules_audit ON public.validation_rules;
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON public.validation_rules FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE public.default_configs
+add column secret BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+-- versions stored as deltas only keep their patch in config, so they have to be
+-- pruned, or written again as full configs, before this can be reverted
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM public.config_versions WHERE parent_id IS NOT NULL) THEN
+        RAISE EXCEPTION 'config versions stored as deltas would lose their config';
+    END IF;
+END $$;
+
+DROP INDEX IF EXISTS public.config_versions_checkpoint_id_index;
+
+ALTER TABLE public.config_versions
+drop column parent_id,
+drop column checkpoint_id;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+ALTER TABLE public.config_versions
+add column parent_id BIGINT,
+add column checkpoint_id BIGINT;
+
+UPDATE public.config_versions SET checkpoint_id = id;
+
+ALTER TABLE public.config_versions
+alter column checkpoint_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS config_versions_checkpoint_id_index
+ON public.config_versions (checkpoint_id);
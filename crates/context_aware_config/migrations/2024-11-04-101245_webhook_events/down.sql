@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+
+-- The experimentation_platform migrations create the same table when both run in
+-- the same schema, so it is only dropped once neither needs it.
+DO $$
+BEGIN
+    IF to_regclass('public.__diesel_schema_migrations') IS NOT NULL THEN
+        IF EXISTS (
+            SELECT 1 FROM public.__diesel_schema_migrations WHERE version = '20241104101312'
+        ) THEN
+            RETURN;
+        END IF;
+    END IF;
+    DROP INDEX IF EXISTS public.webhook_events_status_next_attempt_at_index;
+    DROP TABLE IF EXISTS public.webhook_events;
+END $$;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS public.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON public.webhook_events (status, next_attempt_at);
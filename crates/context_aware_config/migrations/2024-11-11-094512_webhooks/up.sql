@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS public.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
@@ -2,7 +2,7 @@ use actix_web::{get, web::Query, HttpResponse, Scope};
 use chrono::{Duration, Utc};
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
 use serde_json::json;
-use service_utils::service::types::DbConnection;
+use service_utils::{encryption::mask_secrets, service::types::ReadDbConnection};
 use superposition_types::{
     cac::{models::EventLog, schema::event_log::dsl as event_log},
     result as superposition,
@@ -17,43 +17,50 @@ pub fn endpoints() -> Scope {
 #[get("")]
 async fn get_audit_logs(
     filters: Query<AuditQueryFilters>,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
-
-    let query_builder = |filters: &AuditQueryFilters| {
-        let mut builder = event_log::event_log.into_boxed();
-        if let Some(tables) = filters.table.clone() {
-            builder = builder.filter(event_log::table_name.eq_any(tables.0));
-        }
-        if let Some(actions) = filters.action.clone() {
-            builder = builder.filter(event_log::action.eq_any(actions.0));
-        }
-        if let Some(username) = filters.username.clone() {
-            builder = builder.filter(event_log::user_name.eq(username));
-        }
-        let now = Utc::now().naive_utc();
-        builder
-            .filter(
-                event_log::timestamp
-                    .ge(filters.from_date.unwrap_or(now - Duration::hours(24))),
-            )
-            .filter(event_log::timestamp.le(filters.to_date.unwrap_or(now)))
-    };
     let filters = filters.into_inner();
-    let base_query = query_builder(&filters);
-    let count_query = query_builder(&filters);
-
     let limit = filters.count.unwrap_or(10);
     let offset = (filters.page.unwrap_or(1) - 1) * limit;
-    let query = base_query
-        .order(event_log::timestamp.desc())
-        .limit(limit)
-        .offset(offset);
 
-    let log_count: i64 = count_query.count().get_result(&mut conn)?;
+    let (log_count, mut logs) = db_conn
+        .run(move |mut conn| {
+            let query_builder = |filters: &AuditQueryFilters| {
+                let mut builder = event_log::event_log.into_boxed();
+                if let Some(tables) = filters.table.clone() {
+                    builder = builder.filter(event_log::table_name.eq_any(tables.0));
+                }
+                if let Some(actions) = filters.action.clone() {
+                    builder = builder.filter(event_log::action.eq_any(actions.0));
+                }
+                if let Some(username) = filters.username.clone() {
+                    builder = builder.filter(event_log::user_name.eq(username));
+                }
+                let now = Utc::now().naive_utc();
+                builder
+                    .filter(
+                        event_log::timestamp
+                            .ge(filters.from_date.unwrap_or(now - Duration::hours(24))),
+                    )
+                    .filter(event_log::timestamp.le(filters.to_date.unwrap_or(now)))
+            };
+            let base_query = query_builder(&filters);
+            let count_query = query_builder(&filters);
+
+            let query = base_query
+                .order(event_log::timestamp.desc())
+                .limit(limit)
+                .offset(offset);
 
-    let logs: Vec<EventLog> = query.load(&mut conn)?;
+            let log_count: i64 = count_query.count().get_result(&mut conn)?;
+            let logs: Vec<EventLog> = query.load(&mut conn)?;
+            Ok((log_count, logs))
+        })
+        .await?;
+    for event in logs.iter_mut() {
+        event.original_data.iter_mut().for_each(mask_secrets);
+        event.new_data.iter_mut().for_each(mask_secrets);
+    }
 
     let total_pages = (log_count as f64 / limit as f64).ceil() as i64;
 
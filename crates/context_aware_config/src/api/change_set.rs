@@ -0,0 +1,3 @@
+mod handlers;
+pub mod types;
+pub use handlers::endpoints;
@@ -0,0 +1,199 @@
+use actix_web::{
+    post,
+    web::{Data, Json},
+    HttpResponse, Scope,
+};
+use diesel::Connection;
+use serde_json::json;
+#[cfg(feature = "high-performance-mode")]
+use service_utils::service::types::Tenant;
+use service_utils::{
+    encryption::{mask_secrets, TenantCipher},
+    helpers::parse_config_tags,
+    service::types::{AppHeader, AppState, CustomHeaders, DbConnection},
+};
+use superposition_macros::{bad_argument, forbidden};
+use superposition_types::{
+    authorization::KeyAccess, result as superposition, webhook::WebhookEvent, Condition,
+    TenantConfig, User,
+};
+
+#[cfg(feature = "high-performance-mode")]
+use crate::helpers::put_config_in_redis;
+use crate::{
+    api::{
+        context::apply_context_action,
+        default_config::{default_config_change, upsert_default_config},
+        dimension::upsert_dimension,
+        type_templates::create_type_template,
+        validation_rules::helpers::{
+            validate_rules_on_affected_contexts, validate_rules_on_default_configs,
+        },
+    },
+    helpers::{
+        add_config_version, enqueue_config_webhooks, resolve_change_reason,
+        set_change_reason, validate_collection_if_match, ConfigChange,
+    },
+};
+
+use super::types::{
+    ChangeSetAction, ChangeSetRequest, ChangeSetResponse, ChangeSetResult,
+};
+
+pub fn endpoints() -> Scope {
+    Scope::new("").service(apply_change_set)
+}
+
+#[post("")]
+async fn apply_change_set(
+    state: Data<AppState>,
+    custom_headers: CustomHeaders,
+    request: Json<ChangeSetRequest>,
+    db_conn: DbConnection,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    user: User,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
+) -> superposition::Result<HttpResponse> {
+    let ChangeSetRequest {
+        changes,
+        change_reason,
+    } = request.into_inner();
+    if changes.is_empty() {
+        return Err(bad_argument!(
+            "change set should contain at least one change"
+        ));
+    }
+    let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        change_reason,
+    )?;
+
+    /*
+     * All changes are applied on a single transaction so that either the whole
+     * set lands as one config version or nothing is written at all. Later
+     * changes can refer to entities created earlier in the same set, e.g. a
+     * context on a dimension introduced a few entries before it.
+     */
+    let config_webhooks = tenant_config.config_webhooks.clone();
+    let (results, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_collection_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                    )?;
+                    let mut results =
+                        Vec::<ChangeSetResult>::with_capacity(changes.len());
+                    let mut written_conditions = Vec::<Condition>::new();
+                    let mut wrote_default_configs = false;
+                    let mut config_changes = Vec::<ConfigChange>::new();
+                    for (index, change) in changes.into_iter().enumerate() {
+                        let result = match change {
+                            ChangeSetAction::TypeTemplate(req) => key_access
+                                .validate_unrestricted()
+                                .map_err(|err| forbidden!(err))
+                                .and_then(|_| {
+                                    create_type_template(req, &user, transaction_conn)
+                                })
+                                .map(ChangeSetResult::TypeTemplate),
+                            ChangeSetAction::Dimension(req) => key_access
+                                .validate_unrestricted()
+                                .map_err(|err| forbidden!(err))
+                                .and_then(|_| {
+                                    upsert_dimension(req, &state, &user, transaction_conn)
+                                })
+                                .map(|dimension| {
+                                    config_changes.push(ConfigChange {
+                                        event: WebhookEvent::DimensionChanged,
+                                        keys: Vec::new(),
+                                        context: None,
+                                        payload: json!(dimension),
+                                    });
+                                    ChangeSetResult::Dimension(dimension)
+                                }),
+                            ChangeSetAction::DefaultConfig(change) => {
+                                let key: String = change.key.into();
+                                key_access
+                                    .validate_keys([&key])
+                                    .map_err(|err| forbidden!(err))
+                                    .and_then(|_| {
+                                        upsert_default_config(
+                                            key,
+                                            change.request,
+                                            &user,
+                                            &cipher,
+                                            &tenant_config.quotas,
+                                            transaction_conn,
+                                        )
+                                    })
+                                    .map(|mut default_config| {
+                                        wrote_default_configs = true;
+                                        mask_secrets(&mut default_config.value);
+                                        config_changes
+                                            .push(default_config_change(&default_config));
+                                        ChangeSetResult::DefaultConfig(default_config)
+                                    })
+                            }
+                            ChangeSetAction::Context(action) => apply_context_action(
+                                action,
+                                transaction_conn,
+                                &user,
+                                &tenant_config,
+                                &key_access,
+                                &cipher,
+                                &mut written_conditions,
+                                &mut config_changes,
+                            )
+                            .map(ChangeSetResult::Context),
+                        };
+                        results.push(result.map_err(|err| {
+                            log::error!("change set failed at change {index}: {:?}", err);
+                            err
+                        })?);
+                    }
+
+                    if wrote_default_configs {
+                        validate_rules_on_default_configs(transaction_conn)?;
+                    } else {
+                        validate_rules_on_affected_contexts(
+                            transaction_conn,
+                            &written_conditions,
+                        )?;
+                    }
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        config_changes,
+                    )?;
+                    Ok((results, version_id))
+                })
+            }
+        })
+        .await?;
+
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    let mut http_resp = HttpResponse::Ok();
+    http_resp.insert_header((
+        AppHeader::XConfigVersion.to_string(),
+        version_id.to_string(),
+    ));
+    Ok(http_resp.json(ChangeSetResponse {
+        version_id: version_id.to_string(),
+        results,
+    }))
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use superposition_types::cac::models::{DefaultConfig, Dimension, TypeTemplates};
+
+use crate::api::{
+    context::{ContextAction, ContextBulkResponse},
+    default_config::{DefaultConfigCreateReq, DefaultConfigKey},
+    dimension::DimensionCreateReq,
+    type_templates::types::TypeTemplateRequest,
+};
+
+#[derive(Deserialize)]
+pub struct ChangeSetRequest {
+    pub changes: Vec<ChangeSetAction>,
+    pub change_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DefaultConfigChange {
+    pub key: DefaultConfigKey,
+    #[serde(flatten)]
+    pub request: DefaultConfigCreateReq,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSetAction {
+    TypeTemplate(TypeTemplateRequest),
+    Dimension(DimensionCreateReq),
+    DefaultConfig(DefaultConfigChange),
+    Context(ContextAction),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSetResult {
+    TypeTemplate(TypeTemplates),
+    Dimension(Dimension),
+    DefaultConfig(DefaultConfig),
+    Context(ContextBulkResponse),
+}
+
+#[derive(Serialize)]
+pub struct ChangeSetResponse {
+    pub version_id: String,
+    pub results: Vec<ChangeSetResult>,
+}
@@ -1,19 +1,17 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use actix_http::header::HeaderValue;
 #[cfg(feature = "high-performance-mode")]
 use actix_http::StatusCode;
-#[cfg(feature = "high-performance-mode")]
+#[cfg(any(feature = "high-performance-mode", feature = "config-cache"))]
 use actix_web::http::header::ContentType;
-#[cfg(feature = "high-performance-mode")]
-use actix_web::web::Data;
 use actix_web::{
     get, put,
-    web::{self, Json, Query},
+    web::{self, Data, Json, Query},
     HttpRequest, HttpResponse, HttpResponseBuilder, Scope,
 };
 use cac_client::{eval_cac, eval_cac_with_reasoning, MergeStrategy};
-use chrono::{DateTime, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
 use diesel::{
     dsl::max,
     r2d2::{ConnectionManager, PooledConnection},
@@ -24,19 +22,27 @@ use fred::interfaces::KeysInterface;
 use itertools::Itertools;
 use jsonschema::JSONSchema;
 use serde_json::{json, Map, Value};
-#[cfg(feature = "high-performance-mode")]
-use service_utils::service::types::{AppState, Tenant};
 use service_utils::{
-    helpers::extract_dimensions,
-    service::types::{AppHeader, DbConnection},
+    deprecations::Deprecations,
+    encryption::{mask_secrets, TenantCipher},
+    helpers::{extract_dimensions, parse_etags},
+    middlewares::authorization::authorize,
+    service::types::{
+        AppExecutionNamespace, AppHeader, AppState, DbConnection, ReadDbConnection,
+        Tenant,
+    },
 };
 #[cfg(feature = "high-performance-mode")]
 use superposition_macros::response_error;
-use superposition_macros::{bad_argument, db_error, unexpected_error};
+use superposition_macros::{bad_argument, unexpected_error};
 use superposition_types::{
+    authorization::Action,
     cac::{
         models::ConfigVersion,
-        schema::{config_versions::dsl as config_versions, event_log::dsl as event_log},
+        schema::{
+            config_versions::dsl as config_versions, default_configs::dsl as def_conf,
+            dimensions::dsl as dims, event_log::dsl as event_log,
+        },
     },
     custom_query::{
         self as superposition_query, CustomQuery, PaginationParams, QueryMap,
@@ -50,9 +56,14 @@ use crate::api::context::{
     delete_context_api, hash, put, validate_dimensions_and_calculate_priority, PutReq,
 };
 use crate::api::dimension::get_all_dimension_schema_map;
+use crate::config_versions::{
+    get_config_version, get_latest_config_version, materialize,
+};
 use crate::helpers::generate_cac;
+#[cfg(feature = "config-cache")]
+use crate::helpers::load_cached_config;
 
-use super::helpers::apply_prefix_filter_to_config;
+use super::helpers::{apply_prefix_filter_to_config, reveal_config_secrets};
 
 pub fn endpoints() -> Scope {
     let scope = Scope::new("")
@@ -60,7 +71,7 @@ pub fn endpoints() -> Scope {
         .service(get_resolved_config)
         .service(reduce_config)
         .service(get_config_versions);
-    #[cfg(feature = "high-performance-mode")]
+    #[cfg(any(feature = "high-performance-mode", feature = "config-cache"))]
     let scope = scope.service(get_config_fast);
     scope
 }
@@ -81,19 +92,24 @@ fn validate_version_in_params(
         })
 }
 
-pub fn add_audit_id_to_header(
+fn get_audit_id(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
-    resp_builder: &mut HttpResponseBuilder,
-) {
-    if let Ok(uuid) = event_log::event_log
+) -> Option<Uuid> {
+    event_log::event_log
         .select(event_log::id)
         .filter(event_log::table_name.eq("contexts"))
         .order_by(event_log::timestamp.desc())
         .first::<Uuid>(conn)
-    {
+        .map_err(|_| log::error!("Failed to fetch contexts from event_log"))
+        .ok()
+}
+
+fn add_audit_id_to_header(
+    audit_id: &Option<Uuid>,
+    resp_builder: &mut HttpResponseBuilder,
+) {
+    if let Some(uuid) = audit_id {
         resp_builder.insert_header((AppHeader::XAuditId.to_string(), uuid.to_string()));
-    } else {
-        log::error!("Failed to fetch contexts from event_log");
     }
 }
 
@@ -125,6 +141,108 @@ fn add_config_version_to_header(
     }
 }
 
+fn load_deprecations(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> diesel::QueryResult<Deprecations> {
+    let keys = def_conf::default_configs
+        .filter(def_conf::deprecated.eq(true))
+        .filter(def_conf::deleted_at.is_null())
+        .select((def_conf::key, def_conf::sunset_date))
+        .load::<(String, Option<NaiveDate>)>(conn)?;
+    let dimensions = dims::dimensions
+        .filter(dims::deprecated.eq(true))
+        .filter(dims::deleted_at.is_null())
+        .select((dims::dimension, dims::sunset_date))
+        .load::<(String, Option<NaiveDate>)>(conn)?;
+    Ok(Deprecations {
+        keys: keys.into_iter().collect(),
+        dimensions: dimensions.into_iter().collect(),
+    })
+}
+
+async fn get_deprecations(
+    state: &Data<AppState>,
+    namespace: &str,
+) -> Option<Arc<Deprecations>> {
+    if let Some(deprecations) = state.deprecations.get(namespace) {
+        return Some(deprecations);
+    }
+    let generation = state.deprecations.generation();
+    // never from the replica, stale deprecations would be cached until the next write
+    let loaded = match DbConnection::checkout(
+        state.db_pool.clone(),
+        namespace.to_string(),
+    )
+    .await
+    {
+        Ok(db_conn) => {
+            db_conn
+                .run(|mut conn| Ok(load_deprecations(&mut conn)?))
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match loaded {
+        Ok(deprecations) => Some(state.deprecations.insert(
+            namespace.to_string(),
+            generation,
+            deprecations,
+        )),
+        Err(e) => {
+            log::error!("failed to fetch deprecated configs: {e}");
+            None
+        }
+    }
+}
+
+/*
+  Builds the `Warning` listing the deprecated keys present in the served
+  config and the deprecated dimensions used in the query, along with their
+  sunset dates, so that clients can move off them before they are removed.
+*/
+fn get_deprecation_warning(
+    deprecations: Option<Arc<Deprecations>>,
+    served_keys: Vec<&String>,
+    queried_dimensions: Vec<&String>,
+) -> Option<String> {
+    let deprecations = deprecations?;
+    let format_deprecated = |deprecated: &HashMap<String, Option<NaiveDate>>,
+                             names: Vec<&String>| {
+        let mut found = names
+            .into_iter()
+            .filter_map(|name| {
+                deprecated.get(name).map(|sunset_date| {
+                    sunset_date
+                        .map_or(name.clone(), |date| format!("{name} (sunset {date})"))
+                })
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        (!found.is_empty()).then(|| found.join(", "))
+    };
+
+    let warnings = [
+        format_deprecated(&deprecations.keys, served_keys)
+            .map(|keys| format!("deprecated keys served: {keys}")),
+        format_deprecated(&deprecations.dimensions, queried_dimensions)
+            .map(|dimensions| format!("deprecated dimensions used: {dimensions}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    (!warnings.is_empty()).then(|| format!("299 - \"{}\"", warnings.join("; ")))
+}
+
+fn add_deprecation_warning_to_header(
+    warning: &Option<String>,
+    resp_builder: &mut HttpResponseBuilder,
+) {
+    if let Some(warning) = warning {
+        resp_builder.insert_header((AppHeader::Warning.to_string(), warning.clone()));
+    }
+}
+
 fn get_max_created_at(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> Result<NaiveDateTime, diesel::result::Error> {
@@ -135,7 +253,61 @@ fn get_max_created_at(
         .and_then(|res| res.ok_or(diesel::result::Error::NotFound))
 }
 
-fn is_not_modified(max_created_at: Option<NaiveDateTime>, req: &HttpRequest) -> bool {
+fn add_etag_to_header(
+    config_hash: &Option<String>,
+    resp_builder: &mut HttpResponseBuilder,
+) {
+    if let Some(hash) = config_hash {
+        resp_builder.insert_header((AppHeader::ETag.to_string(), format!("\"{hash}\"")));
+    }
+}
+
+/*
+  Pins the requested (or else the latest) config version and returns its
+  hash, which is served as the ETag of the config.
+*/
+fn get_config_hash(
+    version: &mut Option<i64>,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> Option<String> {
+    let query = config_versions::config_versions
+        .select((config_versions::id, config_versions::config_hash))
+        .into_boxed();
+    let query = match version {
+        Some(val) => query.filter(config_versions::id.eq(*val)),
+        None => query.order(config_versions::created_at.desc()),
+    };
+    match query.first::<(i64, String)>(conn) {
+        Ok((version_id, config_hash)) => {
+            *version = Some(version_id);
+            Some(config_hash)
+        }
+        Err(err) => {
+            log::error!("failed to fetch config hash: {err}");
+            None
+        }
+    }
+}
+
+/*
+  If-None-Match takes precedence over If-Modified-Since when both are sent.
+*/
+fn is_not_modified(
+    max_created_at: Option<NaiveDateTime>,
+    config_hash: &Option<String>,
+    req: &HttpRequest,
+) -> bool {
+    if let Some(if_none_match) = req
+        .headers()
+        .get("If-None-Match")
+        .and_then(|header_val| header_val.to_str().ok())
+    {
+        return config_hash.as_ref().is_some_and(|hash| {
+            parse_etags(if_none_match)
+                .iter()
+                .any(|tag| tag == "*" || tag == hash)
+        });
+    }
     let nanosecond_erasure = |t: NaiveDateTime| t.with_nanosecond(0);
     let last_modified = req
         .headers()
@@ -157,33 +329,27 @@ pub fn generate_config_from_version(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<Config> {
     if let Some(val) = version {
-        let val = val.clone();
-        let config = config_versions::config_versions
-            .select(config_versions::config)
-            .filter(config_versions::id.eq(val))
-            .get_result::<Value>(conn)
+        let config = get_config_version(conn, *val)
             .map_err(|err| {
                 log::error!("failed to fetch config with error: {}", err);
-                db_error!(err)
-            })?;
+                err
+            })?
+            .config;
         serde_json::from_value::<Config>(config).map_err(|err| {
             log::error!("failed to decode config: {}", err);
             unexpected_error!("failed to decode config")
         })
     } else {
-        match config_versions::config_versions
-            .select((config_versions::id, config_versions::config))
-            .order(config_versions::created_at.desc())
-            .first::<(i64, Value)>(conn)
-        {
-            Ok((latest_version, config)) => {
-                *version = Some(latest_version);
+        match get_latest_config_version(conn) {
+            Ok(Some(latest_version)) => {
+                *version = Some(latest_version.id);
 
-                serde_json::from_value::<Config>(config).map_err(|err| {
+                serde_json::from_value::<Config>(latest_version.config).map_err(|err| {
                     log::error!("failed to decode config: {}", err);
                     unexpected_error!("failed to decode config")
                 })
             }
+            Ok(None) => generate_cac(conn),
             Err(err) => {
                 log::error!("failed to find latest config: {err}");
                 generate_cac(conn)
@@ -381,14 +547,16 @@ fn construct_new_payload(
     return Ok(web::Json(PutReq {
         context: context,
         r#override: override_,
+        change_reason: None,
     }));
 }
 
 #[allow(clippy::too_many_arguments)]
-async fn reduce_config_key(
+fn reduce_config_key(
     user: User,
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
     tenant_config: &TenantConfig,
+    cipher: &TenantCipher,
     mut og_contexts: Vec<Context>,
     mut og_overrides: HashMap<String, Overrides>,
     check_key: &str,
@@ -474,8 +642,15 @@ async fn reduce_config_key(
                     if is_approve {
                         let _ = delete_context_api(cid.clone(), user.clone(), conn);
                         if let Ok(put_req) = construct_new_payload(request_payload) {
-                            let _ =
-                                put(put_req, conn, false, &user, &tenant_config, false);
+                            let _ = put(
+                                put_req,
+                                conn,
+                                false,
+                                &user,
+                                &tenant_config,
+                                cipher,
+                                false,
+                            );
                         }
                     }
 
@@ -519,37 +694,43 @@ async fn reduce_config(
     user: User,
     db_conn: DbConnection,
     tenant_config: TenantConfig,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let is_approve = req
         .headers()
         .get("x-approve")
         .and_then(|value| value.to_str().ok().and_then(|s| s.parse::<bool>().ok()))
         .unwrap_or(false);
 
-    let dimensions_schema_map = get_all_dimension_schema_map(&mut conn)?;
-    let mut config = generate_cac(&mut conn)?;
-    let default_config = (config.default_configs).clone();
-    for (key, _) in default_config {
-        let contexts = config.contexts;
-        let overrides = config.overrides;
-        let default_config = config.default_configs;
-        config = reduce_config_key(
-            user.clone(),
-            &mut conn,
-            &tenant_config,
-            contexts.clone(),
-            overrides.clone(),
-            key.as_str(),
-            &dimensions_schema_map,
-            default_config.clone(),
-            is_approve,
-        )
+    let config = db_conn
+        .run(move |mut conn| {
+            let dimensions_schema_map = get_all_dimension_schema_map(&mut conn)?;
+            let mut config = generate_cac(&mut conn)?;
+            let default_config = (config.default_configs).clone();
+            for (key, _) in default_config {
+                let contexts = config.contexts;
+                let overrides = config.overrides;
+                let default_config = config.default_configs;
+                config = reduce_config_key(
+                    user.clone(),
+                    &mut conn,
+                    &tenant_config,
+                    &cipher,
+                    contexts.clone(),
+                    overrides.clone(),
+                    key.as_str(),
+                    &dimensions_schema_map,
+                    default_config.clone(),
+                    is_approve,
+                )?;
+                if is_approve {
+                    config = generate_cac(&mut conn)?;
+                }
+            }
+            reveal_config_secrets(&mut config, &cipher, None);
+            Ok(config)
+        })
         .await?;
-        if is_approve {
-            config = generate_cac(&mut conn)?;
-        }
-    }
 
     Ok(HttpResponse::Ok().json(config))
 }
@@ -630,6 +811,16 @@ async fn get_config_fast(
                 );
                 add_config_version_to_header(&config_version, &mut response);
             }
+            // the config is served as cached, secrets are never revealed here
+            let config =
+                if config.contains(service_utils::encryption::ENCRYPTED_VALUE_PREFIX) {
+                    let mut config = serde_json::from_str::<Value>(&config)
+                        .map_err(|err| unexpected_error!(err))?;
+                    mask_secrets(&mut config);
+                    config.to_string()
+                } else {
+                    config
+                };
             response.insert_header(ContentType::json());
             Ok(response.body(config))
         }
@@ -643,67 +834,162 @@ async fn get_config_fast(
     }
 }
 
+/*
+  Serves the latest config from the cache of this replica, it is read from
+  the database only after a new config version invalidates it.
+*/
+#[cfg(feature = "config-cache")]
+#[get("/fast")]
+async fn get_config_fast(
+    namespace: AppExecutionNamespace,
+    state: Data<AppState>,
+) -> superposition::Result<HttpResponse> {
+    let AppExecutionNamespace(namespace) = namespace;
+    let cached = match state.config_cache.get(&namespace) {
+        Some(cached) => cached,
+        None => {
+            let generation = state.config_cache.generation();
+            // never from the replica, a stale config would be cached until the next write
+            let config = DbConnection::checkout(state.db_pool.clone(), namespace.clone())
+                .await?
+                .run(|mut conn| load_cached_config(&mut conn))
+                .await?;
+            state.config_cache.insert(namespace, generation, config)
+        }
+    };
+    let mut response = HttpResponse::Ok();
+    response.insert_header((
+        AppHeader::LastModified.to_string(),
+        cached.last_modified.clone(),
+    ));
+    if let Some(audit_id) = &cached.audit_id {
+        response.insert_header((AppHeader::XAuditId.to_string(), audit_id.clone()));
+    }
+    add_config_version_to_header(&cached.version, &mut response);
+    response.insert_header(ContentType::json());
+    Ok(response.body(cached.config.clone()))
+}
+
 #[get("")]
 async fn get_config(
     req: HttpRequest,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     query_map: superposition_query::Query<QueryMap>,
+    state: Data<AppState>,
+    tenant: Tenant,
+    namespace: AppExecutionNamespace,
+    user: Option<User>,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
-
-    let max_created_at = get_max_created_at(&mut conn)
-        .map_err(|e| log::error!("failed to fetch max timestamp from event_log: {e}"))
-        .ok();
+    let AppExecutionNamespace(namespace) = namespace;
+    let mut query_params_map = query_map.into_inner();
+    let config_version = validate_version_in_params(&mut query_params_map)?;
+    let (config_version, config_hash, max_created_at, db_conn) = db_conn
+        .run(move |mut conn| {
+            let mut config_version = config_version;
+            let config_hash = get_config_hash(&mut config_version, &mut conn);
+            let max_created_at = get_max_created_at(&mut conn)
+                .map_err(|e| {
+                    log::error!("failed to fetch max timestamp from event_log: {e}")
+                })
+                .ok();
+            Ok((
+                config_version,
+                config_hash,
+                max_created_at,
+                ReadDbConnection(conn),
+            ))
+        })
+        .await?;
 
     log::info!("Max created at: {max_created_at:?}");
 
-    let is_not_modified = is_not_modified(max_created_at, &req);
+    let is_not_modified = is_not_modified(max_created_at, &config_hash, &req);
 
     if is_not_modified {
-        return Ok(HttpResponse::NotModified().finish());
+        let mut response = HttpResponse::NotModified();
+        add_etag_to_header(&config_hash, &mut response);
+        return Ok(response.finish());
     }
 
-    let mut query_params_map = query_map.into_inner();
-    let mut config_version = validate_version_in_params(&mut query_params_map)?;
-    let mut config = generate_config_from_version(&mut config_version, &mut conn)?;
+    let deprecations = get_deprecations(&state, &namespace).await;
+    let (mut config, config_version, audit_id, deprecation_warning) = db_conn
+        .run(move |mut conn| {
+            let mut config_version = config_version;
+            let mut config =
+                generate_config_from_version(&mut config_version, &mut conn)?;
 
-    config = apply_prefix_filter_to_config(&mut query_params_map, config)?;
+            config = apply_prefix_filter_to_config(&mut query_params_map, config)?;
 
-    if !query_params_map.is_empty() {
-        config = config.filter_by_dimensions(&query_params_map)
-    }
+            if !query_params_map.is_empty() {
+                config = config.filter_by_dimensions(&query_params_map)
+            }
+            let audit_id = get_audit_id(&mut conn);
+            let deprecation_warning = get_deprecation_warning(
+                deprecations,
+                config.default_configs.keys().collect(),
+                query_params_map.keys().collect(),
+            );
+            Ok((config, config_version, audit_id, deprecation_warning))
+        })
+        .await?;
+    let secret_access = user.and_then(|user| {
+        authorize(&state, &user, Some(tenant.as_str()), Action::ReadSecrets).ok()
+    });
+    reveal_config_secrets(&mut config, &cipher, secret_access.as_ref());
 
     let mut response = HttpResponse::Ok();
     add_last_modified_to_header(max_created_at, &mut response);
-    add_audit_id_to_header(&mut conn, &mut response);
+    add_audit_id_to_header(&audit_id, &mut response);
     add_config_version_to_header(&config_version, &mut response);
+    add_etag_to_header(&config_hash, &mut response);
+    add_deprecation_warning_to_header(&deprecation_warning, &mut response);
     Ok(response.json(config))
 }
 
 #[get("/resolve")]
 async fn get_resolved_config(
     req: HttpRequest,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     query_map: superposition_query::Query<QueryMap>,
+    state: Data<AppState>,
+    tenant: Tenant,
+    namespace: AppExecutionNamespace,
+    user: Option<User>,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
+    let AppExecutionNamespace(namespace) = namespace;
     let mut query_params_map = query_map.into_inner();
+    let config_version = validate_version_in_params(&mut query_params_map)?;
+    let (config_version, config_hash, max_created_at, db_conn) = db_conn
+        .run(move |mut conn| {
+            let mut config_version = config_version;
+            let config_hash = get_config_hash(&mut config_version, &mut conn);
+            let max_created_at = get_max_created_at(&mut conn)
+                .map_err(|e| {
+                    log::error!("failed to fetch max timestamp from event_log : {e}")
+                })
+                .ok();
+            Ok((
+                config_version,
+                config_hash,
+                max_created_at,
+                ReadDbConnection(conn),
+            ))
+        })
+        .await?;
 
-    let max_created_at = get_max_created_at(&mut conn)
-        .map_err(|e| log::error!("failed to fetch max timestamp from event_log : {e}"))
-        .ok();
-
-    let is_not_modified = is_not_modified(max_created_at, &req);
+    let is_not_modified = is_not_modified(max_created_at, &config_hash, &req);
 
     if is_not_modified {
-        return Ok(HttpResponse::NotModified().finish());
+        let mut resp = HttpResponse::NotModified();
+        add_etag_to_header(&config_hash, &mut resp);
+        return Ok(resp.finish());
     }
 
-    let mut config_version = validate_version_in_params(&mut query_params_map)?;
-    let mut config = generate_config_from_version(&mut config_version, &mut conn)?;
-
-    config = apply_prefix_filter_to_config(&mut query_params_map, config)?;
-
+    let secret_access = user.and_then(|user| {
+        authorize(&state, &user, Some(tenant.as_str()), Action::ReadSecrets).ok()
+    });
     let merge_strategy = req
         .headers()
         .get("x-merge-strategy")
@@ -711,80 +997,107 @@ async fn get_resolved_config(
         .and_then(|val| MergeStrategy::from_str(val).ok())
         .unwrap_or_default();
 
-    let mut override_map = HashMap::new();
-    for (key, val) in config.overrides {
-        override_map.insert(key, val);
-    }
+    let deprecations = get_deprecations(&state, &namespace).await;
+    let (response, config_version, audit_id, deprecation_warning) = db_conn
+        .run(move |mut conn| {
+            let mut config_version = config_version;
+            let mut config =
+                generate_config_from_version(&mut config_version, &mut conn)?;
 
-    let response = if let Some(Value::String(_)) = query_params_map.get("show_reasoning")
-    {
-        eval_cac_with_reasoning(
-            config.default_configs,
-            &config.contexts,
-            &override_map,
-            &query_params_map,
-            merge_strategy,
-        )
-        .map_err(|err| {
-            log::error!("failed to eval cac with err: {}", err);
-            unexpected_error!("cac eval failed")
-        })?
-    } else {
-        eval_cac(
-            config.default_configs,
-            &config.contexts,
-            &override_map,
-            &query_params_map,
-            merge_strategy,
-        )
-        .map_err(|err| {
-            log::error!("failed to eval cac with err: {}", err);
-            unexpected_error!("cac eval failed")
-        })?
-    };
+            config = apply_prefix_filter_to_config(&mut query_params_map, config)?;
+            reveal_config_secrets(&mut config, &cipher, secret_access.as_ref());
+
+            let mut override_map = HashMap::new();
+            for (key, val) in config.overrides {
+                override_map.insert(key, val);
+            }
+
+            let response =
+                if let Some(Value::String(_)) = query_params_map.get("show_reasoning") {
+                    eval_cac_with_reasoning(
+                        config.default_configs,
+                        &config.contexts,
+                        &override_map,
+                        &query_params_map,
+                        merge_strategy,
+                    )
+                    .map_err(|err| {
+                        log::error!("failed to eval cac with err: {}", err);
+                        unexpected_error!("cac eval failed")
+                    })?
+                } else {
+                    eval_cac(
+                        config.default_configs,
+                        &config.contexts,
+                        &override_map,
+                        &query_params_map,
+                        merge_strategy,
+                    )
+                    .map_err(|err| {
+                        log::error!("failed to eval cac with err: {}", err);
+                        unexpected_error!("cac eval failed")
+                    })?
+                };
+            let audit_id = get_audit_id(&mut conn);
+            let deprecation_warning = get_deprecation_warning(
+                deprecations,
+                response.keys().collect(),
+                query_params_map.keys().collect(),
+            );
+            Ok((response, config_version, audit_id, deprecation_warning))
+        })
+        .await?;
     let mut resp = HttpResponse::Ok();
     add_last_modified_to_header(max_created_at, &mut resp);
-    add_audit_id_to_header(&mut conn, &mut resp);
+    add_audit_id_to_header(&audit_id, &mut resp);
     add_config_version_to_header(&config_version, &mut resp);
+    add_etag_to_header(&config_hash, &mut resp);
+    add_deprecation_warning_to_header(&deprecation_warning, &mut resp);
 
     Ok(resp.json(response))
 }
 
 #[get("/versions")]
 async fn get_config_versions(
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     filters: Query<PaginationParams>,
 ) -> superposition::Result<Json<PaginatedResponse<ConfigVersion>>> {
-    let DbConnection(mut conn) = db_conn;
-
-    if let Some(true) = filters.all {
-        let config_versions: Vec<ConfigVersion> =
-            config_versions::config_versions.get_results(&mut conn)?;
-        return Ok(Json(PaginatedResponse {
-            total_pages: 1,
-            total_items: config_versions.len() as i64,
-            data: config_versions,
-        }));
-    }
+    let filters = filters.into_inner();
+    let (mut config_versions, total_pages, total_items) = db_conn
+        .run(move |mut conn| {
+            if let Some(true) = filters.all {
+                let mut config_versions: Vec<ConfigVersion> =
+                    config_versions::config_versions.get_results(&mut conn)?;
+                materialize(&mut conn, &mut config_versions)?;
+                let total_items = config_versions.len() as i64;
+                return Ok((config_versions, 1, total_items));
+            }
 
-    let n_version: i64 = config_versions::config_versions
-        .count()
-        .get_result(&mut conn)?;
-
-    let limit = filters.count.unwrap_or(10);
-    let mut builder = config_versions::config_versions
-        .into_boxed()
-        .order(config_versions::created_at.desc())
-        .limit(limit);
-    if let Some(page) = filters.page {
-        let offset = (page - 1) * limit;
-        builder = builder.offset(offset);
-    }
-    let config_versions: Vec<ConfigVersion> = builder.load(&mut conn)?;
-    let total_pages = (n_version as f64 / limit as f64).ceil() as i64;
+            let n_version: i64 = config_versions::config_versions
+                .count()
+                .get_result(&mut conn)?;
+
+            let limit = filters.count.unwrap_or(10);
+            let mut builder = config_versions::config_versions
+                .into_boxed()
+                .order(config_versions::created_at.desc())
+                .limit(limit);
+            if let Some(page) = filters.page {
+                let offset = (page - 1) * limit;
+                builder = builder.offset(offset);
+            }
+            let mut config_versions: Vec<ConfigVersion> = builder.load(&mut conn)?;
+            materialize(&mut conn, &mut config_versions)?;
+            let total_pages = (n_version as f64 / limit as f64).ceil() as i64;
+            Ok((config_versions, total_pages, n_version))
+        })
+        .await?;
+    config_versions
+        .iter_mut()
+        .for_each(|version| mask_secrets(&mut version.config));
     Ok(Json(PaginatedResponse {
         total_pages,
-        total_items: n_version,
+        total_items,
         data: config_versions,
     }))
 }
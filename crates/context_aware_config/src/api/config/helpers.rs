@@ -1,5 +1,6 @@
 use serde_json::{Map, Value};
-use superposition_types::{result as superposition, Config};
+use service_utils::encryption::{is_encrypted, mask_secrets, TenantCipher};
+use superposition_types::{authorization::KeyAccess, result as superposition, Config};
 
 pub fn apply_prefix_filter_to_config(
     query_params_map: &mut Map<String, Value>,
@@ -15,3 +16,37 @@ pub fn apply_prefix_filter_to_config(
     query_params_map.remove("prefix");
     Ok(config)
 }
+
+/*
+  Secret values of a config are decrypted for the keys `secret_access`
+  allows and masked everywhere else.
+*/
+pub fn reveal_config_secrets(
+    config: &mut Config,
+    cipher: &TenantCipher,
+    secret_access: Option<&KeyAccess>,
+) {
+    let reveal = |key: &String, value: &mut Value| {
+        if !is_encrypted(value) {
+            return;
+        }
+        match secret_access {
+            Some(key_access) if key_access.allows(key) => match cipher.decrypt(value) {
+                Ok(decrypted) => *value = decrypted,
+                Err(err) => {
+                    log::error!("failed to decrypt the value of {key}: {err}");
+                    mask_secrets(value);
+                }
+            },
+            _ => mask_secrets(value),
+        }
+    };
+    for (key, value) in config.default_configs.iter_mut() {
+        reveal(key, value);
+    }
+    for override_ in config.overrides.values_mut() {
+        for (key, value) in override_.iter_mut() {
+            reveal(key, value);
+        }
+    }
+}
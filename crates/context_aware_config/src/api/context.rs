@@ -1,9 +1,10 @@
 mod handlers;
 pub mod helpers;
 mod types;
+pub use handlers::apply_context_action;
 pub use handlers::delete_context_api;
 pub use handlers::endpoints;
 pub use handlers::hash;
 pub use handlers::put;
 pub use handlers::validate_dimensions_and_calculate_priority;
-pub use types::PutReq;
+pub use types::{ContextAction, ContextBulkResponse, PutReq};
@@ -10,36 +10,39 @@ use actix_web::{
     HttpResponse, Scope,
 };
 use cac_client::utils::json_to_sorted_string;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{
-    delete,
     r2d2::{ConnectionManager, PooledConnection},
     result::{DatabaseErrorKind::*, Error::DatabaseError},
     upsert::excluded,
-    Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
 };
 use jsonschema::{Draft, JSONSchema, ValidationError};
 use serde_json::{from_value, json, Map, Value};
 #[cfg(feature = "high-performance-mode")]
 use service_utils::service::types::Tenant;
 use service_utils::{
+    encryption::TenantCipher,
     helpers::{parse_config_tags, validation_err_to_str},
-    service::types::{AppHeader, AppState, CustomHeaders, DbConnection},
+    service::types::{
+        AppHeader, AppState, CustomHeaders, DbConnection, ReadDbConnection,
+    },
 };
 use superposition_macros::{
-    bad_argument, db_error, not_found, unexpected_error, validation_error,
+    bad_argument, db_error, forbidden, not_found, unexpected_error, validation_error,
 };
 use superposition_types::{
+    authorization::KeyAccess,
     cac::{
         models::Context,
-        schema::{
-            contexts::{self, id},
-            default_configs::dsl,
-        },
+        schema::{contexts, default_configs::dsl},
     },
     custom_query::{self as superposition_query, CustomQuery, PlatformQuery, QueryMap},
-    result as superposition, Cac, Contextual, Overridden, Overrides, PaginatedResponse,
-    TenantConfig, User,
+    result as superposition,
+    webhook::WebhookEvent,
+    Cac, Condition, Contextual, Overridden, Overrides, PaginatedResponse, TenantConfig,
+    User,
 };
 
 #[cfg(feature = "high-performance-mode")]
@@ -51,14 +54,21 @@ use crate::{
             DimensionCondition, MoveReq, PriorityRecomputeResponse, PutReq, PutResp,
         },
         dimension::get_all_dimension_schema_map,
+        validation_rules::helpers::validate_rules_on_affected_contexts,
     },
     helpers::{
-        add_config_version, calculate_context_priority, validate_context_jsonschema,
+        add_config_version, calculate_context_priority, enqueue_config_webhooks,
+        resolve_change_reason, revision_etag, set_change_reason, soft_delete_cutoff,
+        validate_collection_if_match, validate_context_jsonschema, validate_if_match,
+        ConfigChange,
     },
 };
 
 use super::helpers::{
-    validate_condition_with_functions, validate_condition_with_mandatory_dimensions,
+    context_change, context_write_event, decrypt_secret_overrides,
+    encrypt_secret_overrides, mask_context_secrets, validate_condition_with_functions,
+    validate_condition_with_mandatory_dimensions, validate_context_key_access,
+    validate_context_quota, validate_override_key_access,
     validate_override_with_functions,
 };
 
@@ -68,6 +78,9 @@ pub fn endpoints() -> Scope {
         .service(update_override_handler)
         .service(move_handler)
         .service(delete_context)
+        .service(restore_context)
+        .service(enable_context)
+        .service(disable_context)
         .service(bulk_operations)
         .service(list_contexts)
         .service(get_context_from_condition)
@@ -158,6 +171,7 @@ fn validate_override_with_default_configs(
     let keys_array: Vec<&String> = override_.keys().collect();
     let res: Vec<(String, Value)> = dsl::default_configs
         .filter(dsl::key.eq_any(keys_array))
+        .filter(dsl::deleted_at.is_null())
         .select((dsl::key, dsl::schema))
         .get_results::<(String, Value)>(conn)?;
 
@@ -201,11 +215,14 @@ fn create_ctx_from_put_req(
     conn: &mut DBConnection,
     user: &User,
     tenant_config: &TenantConfig,
+    cipher: &TenantCipher,
 ) -> superposition::Result<Context> {
     let ctx_condition = req.context.to_owned().into_inner();
     let condition_val = Value::Object(ctx_condition.clone().into());
-    let r_override = req.r#override.clone().into_inner();
-    let ctx_override = Value::Object(r_override.clone().into());
+    let context_id = hash(&condition_val);
+    let mut r_override = req.r#override.clone().into_inner();
+    let (secret_keys, stored_override) =
+        decrypt_secret_overrides(conn, cipher, &context_id, &mut r_override)?;
     validate_condition_with_mandatory_dimensions(
         &ctx_condition,
         &tenant_config.mandatory_dimensions,
@@ -213,6 +230,13 @@ fn create_ctx_from_put_req(
     validate_override_with_default_configs(conn, &r_override)?;
     validate_condition_with_functions(conn, &ctx_condition)?;
     validate_override_with_functions(conn, &r_override)?;
+    encrypt_secret_overrides(
+        cipher,
+        &secret_keys,
+        stored_override.as_ref(),
+        &mut r_override,
+    )?;
+    let ctx_override = Value::Object(r_override.clone().into());
 
     let dimension_schema_map = get_all_dimension_schema_map(conn)?;
 
@@ -226,7 +250,6 @@ fn create_ctx_from_put_req(
         return Err(bad_argument!("No dimension found in context"));
     }
 
-    let context_id = hash(&condition_val);
     let override_id = hash(&ctx_override);
     Ok(Context {
         id: context_id,
@@ -238,6 +261,9 @@ fn create_ctx_from_put_req(
         created_by: user.get_email(),
         last_modified_at: Utc::now().naive_utc(),
         last_modified_by: user.get_email(),
+        deleted_at: None,
+        deleted_by: None,
+        enabled: true,
     })
 }
 
@@ -312,6 +338,22 @@ fn db_update_override(
     Ok(get_put_resp(update_resp))
 }
 
+/*
+  A soft deleted context is superseded when a context with the same
+  condition is written again, it is dropped so that the write starts afresh.
+*/
+fn purge_deleted_context(
+    conn: &mut PgConnection,
+    ctx_id: &String,
+) -> superposition::Result<()> {
+    use contexts::dsl;
+    diesel::delete(dsl::contexts)
+        .filter(dsl::id.eq(ctx_id))
+        .filter(dsl::deleted_at.is_not_null())
+        .execute(conn)?;
+    Ok(())
+}
+
 fn get_put_resp(ctx: Context) -> PutResp {
     PutResp {
         context_id: ctx.id,
@@ -326,10 +368,13 @@ pub fn put(
     already_under_txn: bool,
     user: &User,
     tenant_config: &TenantConfig,
+    cipher: &TenantCipher,
     replace: bool,
 ) -> superposition::Result<PutResp> {
     use contexts::dsl::contexts;
-    let new_ctx = create_ctx_from_put_req(req, conn, user, tenant_config)?;
+    let new_ctx = create_ctx_from_put_req(req, conn, user, tenant_config, cipher)?;
+    purge_deleted_context(conn, &new_ctx.id)?;
+    validate_context_quota(conn, &tenant_config.quotas, &new_ctx.id)?;
 
     if already_under_txn {
         diesel::sql_query("SAVEPOINT put_ctx_savepoint").execute(conn)?;
@@ -360,36 +405,76 @@ async fn put_handler(
     state: Data<AppState>,
     custom_headers: CustomHeaders,
     req: Json<PutReq>,
-    mut db_conn: DbConnection,
+    db_conn: DbConnection,
     user: User,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
     let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        req.change_reason.clone(),
+    )?;
+    validate_override_key_access(&key_access, &req.r#override.clone().into_inner())?;
+    let condition = req.context.clone().into_inner();
+    let ctx_id = hash(&Value::Object(condition.clone().into()));
+    let config_webhooks = tenant_config.config_webhooks.clone();
     let (put_response, version_id) = db_conn
-        .transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let put_response =
-                put(req, transaction_conn, true, &user, &tenant_config, false).map_err(
-                    |err: superposition::AppError| {
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &ctx_id),
+                    )?;
+                    validate_context_key_access(transaction_conn, &key_access, &ctx_id)?;
+                    let event = context_write_event(transaction_conn, &ctx_id)?;
+                    let put_response = put(
+                        req,
+                        transaction_conn,
+                        true,
+                        &user,
+                        &tenant_config,
+                        &cipher,
+                        false,
+                    )
+                    .map_err(|err: superposition::AppError| {
                         log::info!("context put failed with error: {:?}", err);
                         err
-                    },
-                )?;
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            Ok((put_response, version_id))
-        })?;
+                    })?;
+                    validate_rules_on_affected_contexts(transaction_conn, &[condition])?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    let change = context_change(transaction_conn, event, &ctx_id)?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        vec![change],
+                    )?;
+                    Ok((put_response, version_id))
+                })
+            }
+        })
+        .await?;
     let mut http_resp = HttpResponse::Ok();
 
     http_resp.insert_header((
         AppHeader::XConfigVersion.to_string(),
         version_id.to_string(),
     ));
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "high-performance-mode")] {
-            let DbConnection(mut conn) = db_conn;
-            put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-        }
-    }
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
     Ok(http_resp.json(put_response))
 }
 
@@ -398,36 +483,75 @@ async fn update_override_handler(
     state: Data<AppState>,
     custom_headers: CustomHeaders,
     req: Json<PutReq>,
-    mut db_conn: DbConnection,
+    db_conn: DbConnection,
     user: User,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
     let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        req.change_reason.clone(),
+    )?;
+    validate_override_key_access(&key_access, &req.r#override.clone().into_inner())?;
+    let condition = req.context.clone().into_inner();
+    let ctx_id = hash(&Value::Object(condition.clone().into()));
+    let config_webhooks = tenant_config.config_webhooks.clone();
     let (override_resp, version_id) = db_conn
-        .transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let override_resp =
-                put(req, transaction_conn, true, &user, &tenant_config, true).map_err(
-                    |err: superposition::AppError| {
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &ctx_id),
+                    )?;
+                    let event = context_write_event(transaction_conn, &ctx_id)?;
+                    let override_resp = put(
+                        req,
+                        transaction_conn,
+                        true,
+                        &user,
+                        &tenant_config,
+                        &cipher,
+                        true,
+                    )
+                    .map_err(|err: superposition::AppError| {
                         log::info!("context put failed with error: {:?}", err);
                         err
-                    },
-                )?;
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            Ok((override_resp, version_id))
-        })?;
+                    })?;
+                    validate_rules_on_affected_contexts(transaction_conn, &[condition])?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    let change = context_change(transaction_conn, event, &ctx_id)?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        vec![change],
+                    )?;
+                    Ok((override_resp, version_id))
+                })
+            }
+        })
+        .await?;
     let mut http_resp = HttpResponse::Ok();
 
     http_resp.insert_header((
         AppHeader::XConfigVersion.to_string(),
         version_id.to_string(),
     ));
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "high-performance-mode")] {
-            let DbConnection(mut conn) = db_conn;
-            put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-        }
-    }
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
     Ok(http_resp.json(override_resp))
 }
 
@@ -458,6 +582,7 @@ fn r#move(
     if priority == 0 {
         return Err(bad_argument!("no dimension found in context"));
     }
+    purge_deleted_context(conn, &new_ctx_id)?;
 
     if already_under_txn {
         diesel::sql_query("SAVEPOINT update_ctx_savepoint").execute(conn)?;
@@ -465,6 +590,7 @@ fn r#move(
 
     let context = diesel::update(dsl::contexts)
         .filter(dsl::id.eq(&old_ctx_id))
+        .filter(dsl::deleted_at.is_null())
         .set((
             dsl::id.eq(&new_ctx_id),
             dsl::value.eq(&ctx_condition_value),
@@ -484,6 +610,9 @@ fn r#move(
         override_: ctx.override_,
         last_modified_at: Utc::now().naive_utc(),
         last_modified_by: user.get_email(),
+        deleted_at: None,
+        deleted_by: None,
+        enabled: ctx.enabled,
     };
 
     let handle_unique_violation =
@@ -491,6 +620,7 @@ fn r#move(
             if already_under_txn {
                 let deleted_ctxt = diesel::delete(dsl::contexts)
                     .filter(dsl::id.eq(&old_ctx_id))
+                    .filter(dsl::deleted_at.is_null())
                     .get_result(db_conn)?;
 
                 let ctx = contruct_new_ctx_with_old_overrides(deleted_ctxt);
@@ -499,6 +629,7 @@ fn r#move(
                 db_conn.build_transaction().read_write().run(|conn| {
                     let deleted_ctxt = diesel::delete(dsl::contexts)
                         .filter(dsl::id.eq(&old_ctx_id))
+                        .filter(dsl::deleted_at.is_null())
                         .get_result(conn)?;
                     let ctx = contruct_new_ctx_with_old_overrides(deleted_ctxt);
                     update_override_of_existing_ctx(conn, ctx, user)
@@ -527,44 +658,127 @@ async fn move_handler(
     path: Path<String>,
     custom_headers: CustomHeaders,
     req: Json<MoveReq>,
-    mut db_conn: DbConnection,
+    db_conn: DbConnection,
     user: User,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
 ) -> superposition::Result<HttpResponse> {
     let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        req.change_reason.clone(),
+    )?;
+    let new_condition = req.context.clone().into_inner();
+    let config_webhooks = tenant_config.config_webhooks.clone();
     let (move_response, version_id) = db_conn
-        .transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let move_response = r#move(
-                path.into_inner(),
-                req,
-                transaction_conn,
-                true,
-                &user,
-                &tenant_config,
-            )
-            .map_err(|err| {
-                log::info!("move api failed with error: {:?}", err);
-                err
-            })?;
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            Ok((move_response, version_id))
-        })?;
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    let old_ctx_id = path.into_inner();
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &old_ctx_id),
+                    )?;
+                    validate_context_key_access(
+                        transaction_conn,
+                        &key_access,
+                        &old_ctx_id,
+                    )?;
+                    let old_condition =
+                        get_context_condition(transaction_conn, &old_ctx_id)?;
+                    let mut changes = vec![context_change(
+                        transaction_conn,
+                        WebhookEvent::ContextDeleted,
+                        &old_ctx_id,
+                    )?];
+                    let event = context_write_event(
+                        transaction_conn,
+                        &hash(&Value::Object(new_condition.clone().into())),
+                    )?;
+                    let move_response = r#move(
+                        old_ctx_id,
+                        req,
+                        transaction_conn,
+                        true,
+                        &user,
+                        &tenant_config,
+                    )
+                    .map_err(|err| {
+                        log::info!("move api failed with error: {:?}", err);
+                        err
+                    })?;
+                    validate_rules_on_affected_contexts(
+                        transaction_conn,
+                        &[old_condition, new_condition],
+                    )?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    changes.push(context_change(
+                        transaction_conn,
+                        event,
+                        &move_response.context_id,
+                    )?);
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        changes,
+                    )?;
+                    Ok((move_response, version_id))
+                })
+            }
+        })
+        .await?;
     let mut http_resp = HttpResponse::Ok();
 
     http_resp.insert_header((
         AppHeader::XConfigVersion.to_string(),
         version_id.to_string(),
     ));
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "high-performance-mode")] {
-            let DbConnection(mut conn) = db_conn;
-            put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-        }
-    }
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
     Ok(http_resp.json(move_response))
 }
 
+fn get_context_last_modified_at(
+    conn: &mut DBConnection,
+    ctx_id: &String,
+) -> diesel::QueryResult<Option<NaiveDateTime>> {
+    use contexts::dsl;
+    dsl::contexts
+        .filter(dsl::id.eq(ctx_id))
+        .select(dsl::last_modified_at)
+        .get_result::<NaiveDateTime>(conn)
+        .optional()
+}
+
+fn get_context_condition(
+    conn: &mut DBConnection,
+    ctx_id: &String,
+) -> superposition::Result<Condition> {
+    use contexts::dsl;
+    dsl::contexts
+        .filter(dsl::id.eq(ctx_id))
+        .filter(dsl::deleted_at.is_null())
+        .select(dsl::value)
+        .get_result::<Condition>(conn)
+        .map_err(|err| match err {
+            diesel::result::Error::NotFound => {
+                not_found!("Context Id `{}` doesn't exists", ctx_id)
+            }
+            err => db_error!(err),
+        })
+}
+
 #[post("/get")]
 async fn get_context_from_condition(
     db_conn: DbConnection,
@@ -573,11 +787,16 @@ async fn get_context_from_condition(
     use superposition_types::cac::schema::contexts::dsl::*;
 
     let context_id = hash(&Value::Object(req.into_inner()));
-    let DbConnection(mut conn) = db_conn;
 
-    let ctx: Context = contexts
-        .filter(id.eq(context_id))
-        .get_result::<Context>(&mut conn)?;
+    let mut ctx: Context = db_conn
+        .run(move |mut conn| {
+            Ok(contexts
+                .filter(id.eq(context_id))
+                .filter(deleted_at.is_null())
+                .get_result::<Context>(&mut conn)?)
+        })
+        .await?;
+    mask_context_secrets(&mut ctx);
 
     Ok(Json(ctx))
 }
@@ -585,28 +804,38 @@ async fn get_context_from_condition(
 #[get("/{ctx_id}")]
 async fn get_context(
     path: Path<String>,
-    db_conn: DbConnection,
-) -> superposition::Result<Json<Context>> {
+    db_conn: ReadDbConnection,
+) -> superposition::Result<HttpResponse> {
     use superposition_types::cac::schema::contexts::dsl::*;
 
     let ctx_id = path.into_inner();
-    let DbConnection(mut conn) = db_conn;
 
-    let ctx: Context = contexts
-        .filter(id.eq(ctx_id))
-        .get_result::<Context>(&mut conn)?;
+    let mut ctx: Context = db_conn
+        .run(move |mut conn| {
+            Ok(contexts
+                .filter(id.eq(ctx_id))
+                .filter(deleted_at.is_null())
+                .get_result::<Context>(&mut conn)?)
+        })
+        .await?;
+    mask_context_secrets(&mut ctx);
 
-    Ok(Json(ctx))
+    // the revision clients can send back in If-Match when writing this context
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            AppHeader::ETag.to_string(),
+            revision_etag(&ctx.last_modified_at),
+        ))
+        .json(ctx))
 }
 
 #[get("/list")]
 async fn list_contexts(
     filter_params: PlatformQuery<ContextFilters>,
     dimension_params: superposition_query::Query<QueryMap>,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
 ) -> superposition::Result<Json<PaginatedResponse<Context>>> {
     use superposition_types::cac::schema::contexts::dsl::*;
-    let DbConnection(mut conn) = db_conn;
 
     let filter_params = filter_params.into_inner();
     let page = filter_params.page.unwrap_or(1);
@@ -620,65 +849,92 @@ async fn list_contexts(
 
     let dimension_params = dimension_params.into_inner();
 
-    let mut builder = contexts.into_boxed();
-    match filter_params.sort_by.unwrap_or_default() {
-        ContextFilterSortBy::PriorityAsc => builder = builder.order(priority.asc()),
-        ContextFilterSortBy::PriorityDesc => builder = builder.order(priority.desc()),
-        ContextFilterSortBy::CreatedAtAsc => builder = builder.order(created_at.asc()),
-        ContextFilterSortBy::CreatedAtDesc => builder = builder.order(created_at.desc()),
-    }
+    let (mut data, total_items) = db_conn
+        .run(move |mut conn| {
+            let mut builder = contexts.filter(deleted_at.is_null()).into_boxed();
+            match filter_params.sort_by.unwrap_or_default() {
+                ContextFilterSortBy::PriorityAsc => {
+                    builder = builder.order(priority.asc())
+                }
+                ContextFilterSortBy::PriorityDesc => {
+                    builder = builder.order(priority.desc())
+                }
+                ContextFilterSortBy::CreatedAtAsc => {
+                    builder = builder.order(created_at.asc())
+                }
+                ContextFilterSortBy::CreatedAtDesc => {
+                    builder = builder.order(created_at.desc())
+                }
+            }
 
-    if let Some(created_by_filter) = filter_params.created_by.clone() {
-        builder = builder
-            .filter(created_by.eq_any(created_by_filter.split(',').map(String::from)))
-    }
+            if let Some(created_by_filter) = filter_params.created_by.clone() {
+                builder = builder.filter(
+                    created_by.eq_any(created_by_filter.split(',').map(String::from)),
+                )
+            }
+            if let Some(enabled_filter) = filter_params.enabled {
+                builder = builder.filter(enabled.eq(enabled_filter))
+            }
 
-    let (data, total_items) = if dimension_params.len() > 0
-        || filter_params.prefix.is_some()
-    {
-        let mut all_contexts: Vec<Context> = builder.load(&mut conn)?;
-        if let Some(prefix) = filter_params.prefix {
-            let prefix_list = prefix.split(',').map(String::from).collect::<HashSet<_>>();
-            all_contexts = all_contexts
-                .into_iter()
-                .filter_map(|mut context| {
-                    Context::filter_keys_by_prefix(&context, &prefix_list)
-                        .map(|filtered_overrides_map| {
-                            context.override_ = filtered_overrides_map.into_inner();
-                            context
+            let (data, total_items) = if dimension_params.len() > 0
+                || filter_params.prefix.is_some()
+            {
+                let mut all_contexts: Vec<Context> = builder.load(&mut conn)?;
+                if let Some(prefix) = filter_params.prefix {
+                    let prefix_list =
+                        prefix.split(',').map(String::from).collect::<HashSet<_>>();
+                    all_contexts = all_contexts
+                        .into_iter()
+                        .filter_map(|mut context| {
+                            Context::filter_keys_by_prefix(&context, &prefix_list)
+                                .map(|filtered_overrides_map| {
+                                    context.override_ =
+                                        filtered_overrides_map.into_inner();
+                                    context
+                                })
+                                .ok()
                         })
-                        .ok()
-                })
-                .collect()
-        }
-        let dimension_keys = dimension_params.keys().cloned().collect::<Vec<_>>();
-        let dimension_filter_contexts =
-            Context::filter_by_dimension(all_contexts, &dimension_keys);
-        let eval_filter_contexts =
-            Context::filter_by_eval(dimension_filter_contexts, &dimension_params);
-
-        let total_items = eval_filter_contexts.len();
-        let start = (size * (page - 1)) as usize;
-        let end = min((size * page) as usize, total_items);
-        let data = eval_filter_contexts
-            .get(start..end)
-            .map_or(vec![], |slice| slice.to_vec());
-
-        (data, total_items as i64)
-    } else {
-        let mut total_count_builder = contexts.into_boxed();
-        if let Some(created_bys) = filter_params.created_by {
-            total_count_builder = total_count_builder
-                .filter(created_by.eq_any(created_bys.split(',').map(String::from)))
-        }
-        let total_items: i64 = total_count_builder.count().get_result(&mut conn)?;
-        let data = builder
-            .limit(i64::from(size))
-            .offset(i64::from(size * (page - 1)))
-            .load::<Context>(&mut conn)?;
-
-        (data, total_items)
-    };
+                        .collect()
+                }
+                let dimension_keys = dimension_params.keys().cloned().collect::<Vec<_>>();
+                let dimension_filter_contexts =
+                    Context::filter_by_dimension(all_contexts, &dimension_keys);
+                let eval_filter_contexts =
+                    Context::filter_by_eval(dimension_filter_contexts, &dimension_params);
+
+                let total_items = eval_filter_contexts.len();
+                let start = (size * (page - 1)) as usize;
+                let end = min((size * page) as usize, total_items);
+                let data = eval_filter_contexts
+                    .get(start..end)
+                    .map_or(vec![], |slice| slice.to_vec());
+
+                (data, total_items as i64)
+            } else {
+                let mut total_count_builder =
+                    contexts.filter(deleted_at.is_null()).into_boxed();
+                if let Some(created_bys) = filter_params.created_by {
+                    total_count_builder = total_count_builder.filter(
+                        created_by.eq_any(created_bys.split(',').map(String::from)),
+                    )
+                }
+                if let Some(enabled_filter) = filter_params.enabled {
+                    total_count_builder =
+                        total_count_builder.filter(enabled.eq(enabled_filter))
+                }
+                let total_items: i64 =
+                    total_count_builder.count().get_result(&mut conn)?;
+                let data = builder
+                    .limit(i64::from(size))
+                    .offset(i64::from(size * (page - 1)))
+                    .load::<Context>(&mut conn)?;
+
+                (data, total_items)
+            };
+            Ok((data, total_items))
+        })
+        .await?;
+    data.iter_mut().for_each(mask_context_secrets);
 
     Ok(Json(PaginatedResponse {
         total_pages: (total_items as f64 / size as f64).ceil() as i64,
@@ -693,14 +949,16 @@ pub fn delete_context_api(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<()> {
     use contexts::dsl;
-    diesel::update(dsl::contexts)
+    let deleted_row = diesel::update(dsl::contexts)
         .filter(dsl::id.eq(&ctx_id))
+        .filter(dsl::deleted_at.is_null())
         .set((
+            dsl::deleted_at.eq(Utc::now().naive_utc()),
+            dsl::deleted_by.eq(user.get_email()),
             dsl::last_modified_at.eq(Utc::now().naive_utc()),
             dsl::last_modified_by.eq(user.get_email()),
         ))
-        .execute(conn)?;
-    let deleted_row = delete(dsl::contexts.filter(dsl::id.eq(&ctx_id))).execute(conn);
+        .execute(conn);
     match deleted_row {
         Ok(0) => Err(not_found!("Context Id `{}` doesn't exists", ctx_id)),
         Ok(_) => {
@@ -721,22 +979,54 @@ async fn delete_context(
     custom_headers: CustomHeaders,
     user: User,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
-    mut db_conn: DbConnection,
+    tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    db_conn: DbConnection,
 ) -> superposition::Result<HttpResponse> {
     let ctx_id = path.into_inner();
     let tags = parse_config_tags(custom_headers.config_tags)?;
-    let version_id =
-        db_conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            delete_context_api(ctx_id, user, transaction_conn)?;
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            Ok(version_id)
-        })?;
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "high-performance-mode")] {
-            let DbConnection(mut conn) = db_conn;
-            put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-        }
-    }
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+    let config_webhooks = tenant_config.config_webhooks.clone();
+    let version_id = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &ctx_id),
+                    )?;
+                    validate_context_key_access(transaction_conn, &key_access, &ctx_id)?;
+                    let condition = get_context_condition(transaction_conn, &ctx_id)?;
+                    delete_context_api(ctx_id.clone(), user, transaction_conn)?;
+                    let change = context_change(
+                        transaction_conn,
+                        WebhookEvent::ContextDeleted,
+                        &ctx_id,
+                    )?;
+                    validate_rules_on_affected_contexts(transaction_conn, &[condition])?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        vec![change],
+                    )?;
+                    Ok(version_id)
+                })
+            }
+        })
+        .await?;
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
     Ok(HttpResponse::NoContent()
         .insert_header((
             AppHeader::XConfigVersion.to_string().as_str(),
@@ -745,6 +1035,387 @@ async fn delete_context(
         .finish())
 }
 
+fn restore_context_api(
+    ctx_id: &String,
+    state: &Data<AppState>,
+    user: &User,
+    conn: &mut DBConnection,
+) -> superposition::Result<Context> {
+    use contexts::dsl;
+    let context = dsl::contexts
+        .filter(dsl::id.eq(ctx_id))
+        .filter(dsl::deleted_at.is_not_null())
+        .get_result::<Context>(conn)
+        .map_err(|err| match err {
+            diesel::result::Error::NotFound => {
+                not_found!("No deleted context with id `{}` found", ctx_id)
+            }
+            err => db_error!(err),
+        })?;
+    if context.deleted_at < Some(soft_delete_cutoff(state)) {
+        return Err(bad_argument!(
+            "Context `{}` was deleted before the retention window and can't be restored",
+            ctx_id
+        ));
+    }
+
+    // the dimensions and keys used by the context could have been deleted since
+    validate_override_with_default_configs(conn, &context.override_)?;
+    validate_condition_with_functions(conn, &context.value)?;
+    validate_override_with_functions(conn, &context.override_)?;
+    let dimension_schema_map = get_all_dimension_schema_map(conn)?;
+    let priority = validate_dimensions_and_calculate_priority(
+        "context",
+        &Value::Object(context.value.clone().into()),
+        &dimension_schema_map,
+    )?;
+
+    let restored_context = diesel::update(dsl::contexts)
+        .filter(dsl::id.eq(ctx_id))
+        .set((
+            dsl::priority.eq(priority),
+            dsl::deleted_at.eq(None::<NaiveDateTime>),
+            dsl::deleted_by.eq(None::<String>),
+            dsl::last_modified_at.eq(Utc::now().naive_utc()),
+            dsl::last_modified_by.eq(user.get_email()),
+        ))
+        .get_result::<Context>(conn)?;
+    log::info!("{ctx_id} context restored by {}", user.get_email());
+    Ok(restored_context)
+}
+
+#[post("/{ctx_id}/restore")]
+async fn restore_context(
+    state: Data<AppState>,
+    path: Path<String>,
+    custom_headers: CustomHeaders,
+    user: User,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    let ctx_id = path.into_inner();
+    let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+    let config_webhooks = tenant_config.config_webhooks.clone();
+    let (context, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &ctx_id),
+                    )?;
+                    validate_context_key_access(transaction_conn, &key_access, &ctx_id)?;
+                    validate_context_quota(
+                        transaction_conn,
+                        &tenant_config.quotas,
+                        &ctx_id,
+                    )?;
+                    let context =
+                        restore_context_api(&ctx_id, &state, &user, transaction_conn)?;
+                    let change = context_change(
+                        transaction_conn,
+                        WebhookEvent::ContextCreated,
+                        &ctx_id,
+                    )?;
+                    validate_rules_on_affected_contexts(
+                        transaction_conn,
+                        std::slice::from_ref(&context.value),
+                    )?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        vec![change],
+                    )?;
+                    Ok((context, version_id))
+                })
+            }
+        })
+        .await?;
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    let mut http_resp = HttpResponse::Ok();
+    http_resp.insert_header((
+        AppHeader::XConfigVersion.to_string(),
+        version_id.to_string(),
+    ));
+    Ok(http_resp.json(context))
+}
+
+fn set_context_enabled(
+    ctx_id: &String,
+    enabled: bool,
+    user: &User,
+    conn: &mut DBConnection,
+) -> superposition::Result<Context> {
+    use contexts::dsl;
+    let context = diesel::update(dsl::contexts)
+        .filter(dsl::id.eq(ctx_id))
+        .filter(dsl::deleted_at.is_null())
+        .set((
+            dsl::enabled.eq(enabled),
+            dsl::last_modified_at.eq(Utc::now().naive_utc()),
+            dsl::last_modified_by.eq(user.get_email()),
+        ))
+        .get_result::<Context>(conn)
+        .map_err(|err| match err {
+            diesel::result::Error::NotFound => {
+                not_found!("Context Id `{}` doesn't exists", ctx_id)
+            }
+            err => db_error!(err),
+        })?;
+    let state = if enabled { "enabled" } else { "disabled" };
+    log::info!("{ctx_id} context {state} by {}", user.get_email());
+    Ok(context)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn toggle_context(
+    state: Data<AppState>,
+    ctx_id: String,
+    enabled: bool,
+    custom_headers: CustomHeaders,
+    user: User,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+    let config_webhooks = tenant_config.config_webhooks.clone();
+    let (context, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_context_last_modified_at(conn, &ctx_id),
+                    )?;
+                    validate_context_key_access(transaction_conn, &key_access, &ctx_id)?;
+                    let context =
+                        set_context_enabled(&ctx_id, enabled, &user, transaction_conn)?;
+                    let change = context_change(
+                        transaction_conn,
+                        WebhookEvent::ContextUpdated,
+                        &ctx_id,
+                    )?;
+                    validate_rules_on_affected_contexts(
+                        transaction_conn,
+                        std::slice::from_ref(&context.value),
+                    )?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        vec![change],
+                    )?;
+                    Ok((context, version_id))
+                })
+            }
+        })
+        .await?;
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    let mut http_resp = HttpResponse::Ok();
+    http_resp.insert_header((
+        AppHeader::XConfigVersion.to_string(),
+        version_id.to_string(),
+    ));
+    Ok(http_resp.json(context))
+}
+
+#[put("/{ctx_id}/enable")]
+async fn enable_context(
+    state: Data<AppState>,
+    path: Path<String>,
+    custom_headers: CustomHeaders,
+    user: User,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    toggle_context(
+        state,
+        path.into_inner(),
+        true,
+        custom_headers,
+        user,
+        #[cfg(feature = "high-performance-mode")]
+        tenant,
+        tenant_config,
+        key_access,
+        db_conn,
+    )
+    .await
+}
+
+#[put("/{ctx_id}/disable")]
+async fn disable_context(
+    state: Data<AppState>,
+    path: Path<String>,
+    custom_headers: CustomHeaders,
+    user: User,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    db_conn: DbConnection,
+) -> superposition::Result<HttpResponse> {
+    toggle_context(
+        state,
+        path.into_inner(),
+        false,
+        custom_headers,
+        user,
+        #[cfg(feature = "high-performance-mode")]
+        tenant,
+        tenant_config,
+        key_access,
+        db_conn,
+    )
+    .await
+}
+
+/// Applies a single bulk context action on `conn`, recording every condition
+/// it touches into `written_conditions` so callers can validate dependent
+/// rules once all actions of a batch have been written, and its changes into
+/// `changes` for the config webhooks.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_context_action(
+    action: ContextAction,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    user: &User,
+    tenant_config: &TenantConfig,
+    key_access: &KeyAccess,
+    cipher: &TenantCipher,
+    written_conditions: &mut Vec<Condition>,
+    changes: &mut Vec<ConfigChange>,
+) -> superposition::Result<ContextBulkResponse> {
+    use contexts::dsl;
+    match action {
+        ContextAction::Put(put_req) => {
+            validate_override_key_access(
+                key_access,
+                &put_req.r#override.clone().into_inner(),
+            )?;
+            let ctx_id =
+                hash(&Value::Object(put_req.context.clone().into_inner().into()));
+            validate_context_key_access(conn, key_access, &ctx_id)?;
+            written_conditions.push(put_req.context.clone().into_inner());
+            let event = context_write_event(conn, &ctx_id)?;
+            let put_resp = put(
+                Json(put_req),
+                conn,
+                true,
+                user,
+                tenant_config,
+                cipher,
+                false,
+            )
+            .map_err(|err| {
+                log::error!("Failed at insert into contexts due to {:?}", err);
+                err
+            })?;
+            changes.push(context_change(conn, event, &ctx_id)?);
+            Ok(ContextBulkResponse::Put(put_resp))
+        }
+        ContextAction::Delete(ctx_id) => {
+            validate_context_key_access(conn, key_access, &ctx_id)?;
+            let email: String = user.get_email();
+            let deleted_row = diesel::update(dsl::contexts)
+                .filter(dsl::id.eq(&ctx_id))
+                .filter(dsl::deleted_at.is_null())
+                .set((
+                    dsl::deleted_at.eq(Utc::now().naive_utc()),
+                    dsl::deleted_by.eq(&email),
+                    dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                    dsl::last_modified_by.eq(&email),
+                ))
+                .get_result::<Context>(conn);
+            match deleted_row {
+                // Any kind of error would rollback the tranction but explicitly returning rollback tranction allows you to rollback from any point in transaction.
+                Err(diesel::result::Error::NotFound) => {
+                    Err(bad_argument!("context with id {} not found", ctx_id))
+                }
+                Ok(deleted_ctx) => {
+                    log::info!("{ctx_id} context deleted by {email}");
+                    changes.push(context_change(
+                        conn,
+                        WebhookEvent::ContextDeleted,
+                        &ctx_id,
+                    )?);
+                    written_conditions.push(deleted_ctx.value);
+                    Ok(ContextBulkResponse::Delete(format!(
+                        "{ctx_id} deleted succesfully"
+                    )))
+                }
+                Err(e) => {
+                    log::error!("Delete context failed due to {:?}", e);
+                    Err(db_error!(e))
+                }
+            }
+        }
+        ContextAction::Move((old_ctx_id, move_req)) => {
+            validate_context_key_access(conn, key_access, &old_ctx_id)?;
+            written_conditions.push(get_context_condition(conn, &old_ctx_id)?);
+            written_conditions.push(move_req.context.clone().into_inner());
+            changes.push(context_change(
+                conn,
+                WebhookEvent::ContextDeleted,
+                &old_ctx_id,
+            )?);
+            let event = context_write_event(
+                conn,
+                &hash(&Value::Object(move_req.context.clone().into_inner().into())),
+            )?;
+            let move_context_resp =
+                r#move(old_ctx_id, Json(move_req), conn, true, user, tenant_config)
+                    .map_err(|err| {
+                        log::error!("Failed at moving context reponse due to {:?}", err);
+                        err
+                    })?;
+            changes.push(context_change(conn, event, &move_context_resp.context_id)?);
+            Ok(ContextBulkResponse::Move(move_context_resp))
+        }
+    }
+}
+
+/// The distinct change reasons of the actions of a bulk operation, recorded
+/// together on the config version it creates.
+fn bulk_change_reason(change_reasons: &[Option<String>]) -> Option<String> {
+    let mut distinct = Vec::<&str>::new();
+    for reason in change_reasons.iter().flatten() {
+        if !distinct.contains(&reason.as_str()) {
+            distinct.push(reason);
+        }
+    }
+    (!distinct.is_empty()).then(|| distinct.join("; "))
+}
+
 #[put("/bulk-operations")]
 async fn bulk_operations(
     state: Data<AppState>,
@@ -754,81 +1425,71 @@ async fn bulk_operations(
     user: User,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    use contexts::dsl::contexts;
-    let DbConnection(mut conn) = db_conn;
     let tags = parse_config_tags(custom_headers.config_tags)?;
-    let (response, version_id) =
-        conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let mut response = Vec::<ContextBulkResponse>::new();
-            for action in reqs.into_inner().into_iter() {
-                match action {
-                    ContextAction::Put(put_req) => {
-                        let put_resp = put(
-                            Json(put_req),
-                            transaction_conn,
-                            true,
-                            &user,
-                            &tenant_config,
-                            false,
-                        )
-                        .map_err(|err| {
-                            log::error!(
-                                "Failed at insert into contexts due to {:?}",
-                                err
-                            );
-                            err
-                        })?;
-                        response.push(ContextBulkResponse::Put(put_resp));
-                    }
-                    ContextAction::Delete(ctx_id) => {
-                        let deleted_row = delete(contexts.filter(id.eq(&ctx_id)))
-                            .execute(transaction_conn);
-                        let email: String = user.get_email();
-                        match deleted_row {
-                            // Any kind of error would rollback the tranction but explicitly returning rollback tranction allows you to rollback from any point in transaction.
-                            Ok(0) => {
-                                return Err(bad_argument!(
-                                    "context with id {} not found",
-                                    ctx_id
-                                ))
-                            }
-                            Ok(_) => {
-                                log::info!("{ctx_id} context deleted by {email}");
-                                response.push(ContextBulkResponse::Delete(format!(
-                                    "{ctx_id} deleted succesfully"
-                                )))
-                            }
-                            Err(e) => {
-                                log::error!("Delete context failed due to {:?}", e);
-                                return Err(db_error!(e));
-                            }
-                        };
-                    }
-                    ContextAction::Move((old_ctx_id, move_req)) => {
-                        let move_context_resp = r#move(
-                            old_ctx_id,
-                            Json(move_req),
+    let reqs = reqs.into_inner();
+    let change_reasons = reqs
+        .iter()
+        .map(|action| {
+            resolve_change_reason(
+                &tenant_config,
+                custom_headers.change_reason.clone(),
+                action.change_reason(),
+            )
+        })
+        .collect::<superposition::Result<Vec<_>>>()?;
+    let change_reason = bulk_change_reason(&change_reasons);
+    let config_webhooks = tenant_config.config_webhooks.clone();
+    let (response, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    validate_collection_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                    )?;
+                    let mut response = Vec::<ContextBulkResponse>::new();
+                    let mut written_conditions = Vec::<Condition>::new();
+                    let mut changes = Vec::<ConfigChange>::new();
+                    for (action, action_reason) in reqs.into_iter().zip(change_reasons) {
+                        // the audit log entries of each action get its own reason
+                        set_change_reason(transaction_conn, &action_reason)?;
+                        response.push(apply_context_action(
+                            action,
                             transaction_conn,
-                            true,
                             &user,
                             &tenant_config,
-                        )
-                        .map_err(|err| {
-                            log::error!(
-                                "Failed at moving context reponse due to {:?}",
-                                err
-                            );
-                            err
-                        })?;
-                        response.push(ContextBulkResponse::Move(move_context_resp));
+                            &key_access,
+                            &cipher,
+                            &mut written_conditions,
+                            &mut changes,
+                        )?);
                     }
-                }
-            }
 
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            Ok((response, version_id))
-        })?;
+                    validate_rules_on_affected_contexts(
+                        transaction_conn,
+                        &written_conditions,
+                    )?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &config_webhooks,
+                        Some(version_id),
+                        changes,
+                    )?;
+                    Ok((response, version_id))
+                })
+            }
+        })
+        .await?;
     let mut http_resp = HttpResponse::Ok();
     http_resp.insert_header((
         AppHeader::XConfigVersion.to_string(),
@@ -837,7 +1498,7 @@ async fn bulk_operations(
 
     // Commit the transaction
     #[cfg(feature = "high-performance-mode")]
-    put_config_in_redis(version_id, state, tenant, &mut conn).await?;
+    put_config_in_redis(version_id, state, tenant).await?;
     Ok(http_resp.json(response))
 }
 
@@ -847,73 +1508,106 @@ async fn priority_recompute(
     custom_headers: CustomHeaders,
     db_conn: DbConnection,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
     _user: User,
+    key_access: KeyAccess,
 ) -> superposition::Result<HttpResponse> {
     use superposition_types::cac::schema::contexts::dsl::*;
-    let DbConnection(mut conn) = db_conn;
-
-    let result: Vec<Context> = contexts.load(&mut conn).map_err(|err| {
-        log::error!("failed to fetch contexts with error: {}", err);
-        unexpected_error!("Something went wrong")
-    })?;
-
-    let dimension_schema_map = get_all_dimension_schema_map(&mut conn)?;
-    let mut response: Vec<PriorityRecomputeResponse> = vec![];
+    key_access
+        .validate_unrestricted()
+        .map_err(|err| forbidden!(err))?;
     let tags = parse_config_tags(custom_headers.config_tags)?;
-
-    let update_contexts = result
-        .clone()
-        .into_iter()
-        .map(|context| {
-            let new_priority = calculate_context_priority(
-                "context",
-                &Value::Object(context.value.clone().into()),
-                &dimension_schema_map,
-            )
-            .map_err(|err| {
-                log::error!("failed to calculate context priority: {}", err);
-                unexpected_error!("Something went wrong")
-            });
-
-            match new_priority {
-                Ok(val) => {
-                    response.push(PriorityRecomputeResponse {
-                        id: context.id.clone(),
-                        condition: context.value.clone(),
-                        old_priority: context.priority,
-                        new_priority: val,
-                    });
-                    Ok(Context {
-                        priority: val,
-                        ..context.clone()
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+
+    let (response, config_version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                let result: Vec<Context> = contexts
+                    .filter(deleted_at.is_null())
+                    .load(&mut conn)
+                    .map_err(|err| {
+                        log::error!("failed to fetch contexts with error: {}", err);
+                        unexpected_error!("Something went wrong")
+                    })?;
+
+                let dimension_schema_map = get_all_dimension_schema_map(&mut conn)?;
+                let mut response: Vec<PriorityRecomputeResponse> = vec![];
+
+                let update_contexts = result
+                    .clone()
+                    .into_iter()
+                    .map(|context| {
+                        let new_priority = calculate_context_priority(
+                            "context",
+                            &Value::Object(context.value.clone().into()),
+                            &dimension_schema_map,
+                        )
+                        .map_err(|err| {
+                            log::error!("failed to calculate context priority: {}", err);
+                            unexpected_error!("Something went wrong")
+                        });
+
+                        match new_priority {
+                            Ok(val) => {
+                                response.push(PriorityRecomputeResponse {
+                                    id: context.id.clone(),
+                                    condition: context.value.clone(),
+                                    old_priority: context.priority,
+                                    new_priority: val,
+                                });
+                                Ok(Context {
+                                    priority: val,
+                                    ..context.clone()
+                                })
+                            }
+                            Err(e) => Err(e),
+                        }
                     })
-                }
-                Err(e) => Err(e),
-            }
-        })
-        .collect::<superposition::Result<Vec<Context>>>()?;
-
-    let config_version_id =
-        conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let insert = diesel::insert_into(contexts)
-                .values(&update_contexts)
-                .on_conflict(id)
-                .do_update()
-                .set(priority.eq(excluded(priority)))
-                .execute(transaction_conn);
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            match insert {
-                Ok(_) => Ok(version_id),
-                Err(err) => {
-                    log::error!(
+                    .collect::<superposition::Result<Vec<Context>>>()?;
+
+                let config_version_id = conn
+                    .transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                        set_change_reason(transaction_conn, &change_reason)?;
+                        validate_collection_if_match(
+                            transaction_conn,
+                            &custom_headers.if_match,
+                        )?;
+                        let insert = diesel::insert_into(contexts)
+                            .values(&update_contexts)
+                            .on_conflict(id)
+                            .do_update()
+                            .set(priority.eq(excluded(priority)))
+                            .execute(transaction_conn);
+                        let version_id = add_config_version(
+                            &state,
+                            tags,
+                            change_reason,
+                            transaction_conn,
+                        )?;
+                        enqueue_config_webhooks(
+                            transaction_conn,
+                            &tenant_config.config_webhooks,
+                            Some(version_id),
+                            Vec::new(),
+                        )?;
+                        match insert {
+                            Ok(_) => Ok(version_id),
+                            Err(err) => {
+                                log::error!(
                     "Failed to execute query while recomputing priority, error: {err}"
                 );
-                    Err(db_error!(err))
-                }
+                                Err(db_error!(err))
+                            }
+                        }
+                    })?;
+                Ok((response, config_version_id))
             }
-        })?;
+        })
+        .await?;
     #[cfg(feature = "high-performance-mode")]
-    put_config_in_redis(config_version_id, state, tenant, &mut conn).await?;
+    put_config_in_redis(config_version_id, state, tenant).await?;
     let mut http_resp = HttpResponse::Ok();
     http_resp.insert_header((
         AppHeader::XConfigVersion.to_string(),
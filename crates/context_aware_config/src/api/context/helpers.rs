@@ -1,26 +1,37 @@
 extern crate base64;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str;
 
 use base64::prelude::*;
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
 };
-use serde_json::{Map, Value};
-use service_utils::helpers::extract_dimensions;
-use superposition_macros::{unexpected_error, validation_error};
+use serde_json::{json, Map, Value};
+use service_utils::{
+    encryption::{is_encrypted, is_mask, mask_secrets, TenantCipher},
+    helpers::extract_dimensions,
+};
+use superposition_macros::{bad_argument, forbidden, unexpected_error, validation_error};
 use superposition_types::{
-    cac::schema::{
-        default_configs::dsl,
-        dimensions::{self},
+    authorization::KeyAccess,
+    cac::{
+        models::Context,
+        schema::{
+            contexts,
+            default_configs::dsl,
+            dimensions::{self},
+        },
     },
-    result as superposition, Condition,
+    result as superposition,
+    webhook::WebhookEvent,
+    Condition, Overrides, TenantQuotas,
 };
 
 use crate::api::context::types::FunctionsInfo;
 use crate::api::functions::helpers::get_published_functions_by_names;
+use crate::helpers::ConfigChange;
 use crate::validation_functions::execute_fn;
 
 type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
@@ -52,6 +63,7 @@ pub fn validate_condition_with_functions(
     let dimensions_list: Vec<String> = context.keys().cloned().collect();
     let keys_function_array: Vec<(String, Option<String>)> = dsl::dimensions
         .filter(dsl::dimension.eq_any(dimensions_list))
+        .filter(dsl::deleted_at.is_null())
         .select((dsl::dimension, dsl::function_name))
         .load(conn)?;
     let new_keys_function_array: Vec<(String, String)> = keys_function_array
@@ -79,6 +91,7 @@ pub fn validate_override_with_functions(
     let default_config_keys: Vec<String> = override_.keys().cloned().collect();
     let keys_function_array: Vec<(String, Option<String>)> = dsl::default_configs
         .filter(dsl::key.eq_any(default_config_keys))
+        .filter(dsl::deleted_at.is_null())
         .select((dsl::key, dsl::function_name))
         .load(conn)?;
     let new_keys_function_array: Vec<(String, String)> = keys_function_array
@@ -99,6 +112,184 @@ pub fn validate_override_with_functions(
     Ok(())
 }
 
+pub fn validate_override_key_access(
+    key_access: &KeyAccess,
+    override_: &Map<String, Value>,
+) -> superposition::Result<()> {
+    key_access
+        .validate_keys(override_.keys())
+        .map_err(|err| forbidden!(err))
+}
+
+/// Checks the keys overridden by the context `ctx_id`, contexts which do
+/// not exist are left to the caller to report.
+pub fn validate_context_key_access(
+    conn: &mut DBConnection,
+    key_access: &KeyAccess,
+    ctx_id: &str,
+) -> superposition::Result<()> {
+    if key_access.is_unrestricted() {
+        return Ok(());
+    }
+    let override_ = contexts::table
+        .find(ctx_id)
+        .select(contexts::override_)
+        .first::<Overrides>(conn)
+        .optional()?;
+    match override_ {
+        Some(override_) => validate_override_key_access(key_access, &override_),
+        None => Ok(()),
+    }
+}
+
+/// Fails when saving the context `ctx_id` would take the tenant past its
+/// quota of contexts, saving an active context never does.
+pub fn validate_context_quota(
+    conn: &mut DBConnection,
+    quotas: &TenantQuotas,
+    ctx_id: &str,
+) -> superposition::Result<()> {
+    let Some(max_contexts) = quotas.max_contexts else {
+        return Ok(());
+    };
+    let other_contexts: i64 = contexts::table
+        .filter(contexts::deleted_at.is_null())
+        .filter(contexts::id.ne(ctx_id))
+        .count()
+        .get_result(conn)?;
+    if other_contexts >= max_contexts {
+        return Err(bad_argument!(
+            "The tenant has reached its quota of {} contexts, delete unused contexts or ask an admin to raise the quota",
+            max_contexts
+        ));
+    }
+    Ok(())
+}
+
+pub fn get_secret_keys<'a, I>(
+    conn: &mut DBConnection,
+    keys: I,
+) -> superposition::Result<HashSet<String>>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let keys: Vec<&String> = keys.into_iter().collect();
+    let secret_keys: Vec<String> = dsl::default_configs
+        .filter(dsl::key.eq_any(keys))
+        .filter(dsl::secret.eq(true))
+        .filter(dsl::deleted_at.is_null())
+        .select(dsl::key)
+        .load(conn)?;
+    Ok(secret_keys.into_iter().collect())
+}
+
+/*
+  Values of secret keys reach a write in plain text, encrypted when copied
+  from an existing config, or masked when edited from a listing, where the
+  mask stands for the value the context `ctx_id` already has. They are all
+  brought to plain text to be validated, the secret keys are returned along
+  with the stored override of the context for the values to be encrypted
+  again before they are stored.
+*/
+pub fn decrypt_secret_overrides(
+    conn: &mut DBConnection,
+    cipher: &TenantCipher,
+    ctx_id: &str,
+    override_: &mut Map<String, Value>,
+) -> superposition::Result<(HashSet<String>, Option<Overrides>)> {
+    let secret_keys = get_secret_keys(conn, override_.keys())?;
+    let stored_override = if secret_keys.is_empty() {
+        None
+    } else {
+        contexts::table
+            .find(ctx_id)
+            .select(contexts::override_)
+            .first::<Overrides>(conn)
+            .optional()?
+    };
+
+    for key in secret_keys.iter() {
+        let Some(value) = override_.get_mut(key) else {
+            continue;
+        };
+        if is_mask(value) {
+            *value = stored_override
+                .as_ref()
+                .and_then(|stored| stored.get(key))
+                .cloned()
+                .ok_or(bad_argument!(
+                    "A value is needed for the secret key {}",
+                    key
+                ))?;
+        }
+        if is_encrypted(value) {
+            *value = cipher.decrypt(value).map_err(|err| {
+                bad_argument!("Invalid value for the secret key {}: {}", key, err)
+            })?;
+        }
+    }
+    Ok((secret_keys, stored_override))
+}
+
+/// Encrypts the values of the secret keys, keeping the stored ones which are
+/// unchanged.
+pub fn encrypt_secret_overrides(
+    cipher: &TenantCipher,
+    secret_keys: &HashSet<String>,
+    stored_override: Option<&Overrides>,
+    override_: &mut Map<String, Value>,
+) -> superposition::Result<()> {
+    for (key, value) in override_.iter_mut() {
+        if secret_keys.contains(key) {
+            let stored = stored_override.and_then(|stored| stored.get(key));
+            *value = cipher
+                .encrypt_keeping(value, stored)
+                .map_err(|err| bad_argument!(err))?;
+        }
+    }
+    Ok(())
+}
+
+/// Masks the values of secret keys, contexts never return them.
+pub fn mask_context_secrets(context: &mut Context) {
+    context.override_.values_mut().for_each(mask_secrets);
+}
+
+/// Reads back the context `ctx_id` written by a change to be delivered to
+/// the config webhooks, deleted or not.
+pub fn context_change(
+    conn: &mut DBConnection,
+    event: WebhookEvent,
+    ctx_id: &str,
+) -> superposition::Result<ConfigChange> {
+    let mut context = contexts::table.find(ctx_id).get_result::<Context>(conn)?;
+    mask_context_secrets(&mut context);
+    Ok(ConfigChange {
+        event,
+        keys: context.override_.keys().cloned().collect(),
+        context: Some(context.value.clone()),
+        payload: json!(context),
+    })
+}
+
+/// Whether a write of the context `ctx_id` creates it or updates it.
+pub fn context_write_event(
+    conn: &mut DBConnection,
+    ctx_id: &str,
+) -> superposition::Result<WebhookEvent> {
+    let exists = diesel::select(diesel::dsl::exists(
+        contexts::table
+            .filter(contexts::id.eq(ctx_id))
+            .filter(contexts::deleted_at.is_null()),
+    ))
+    .get_result::<bool>(conn)?;
+    Ok(if exists {
+        WebhookEvent::ContextUpdated
+    } else {
+        WebhookEvent::ContextCreated
+    })
+}
+
 fn get_functions_map(
     conn: &mut DBConnection,
     keys_function_array: Vec<(String, String)>,
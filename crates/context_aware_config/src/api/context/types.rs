@@ -6,12 +6,14 @@ use superposition_types::{Cac, Condition, Overrides};
 pub struct PutReq {
     pub context: Cac<Condition>,
     pub r#override: Cac<Overrides>,
+    pub change_reason: Option<String>,
 }
 
 #[cfg_attr(test, derive(Debug, PartialEq))] // Derive traits only when running tests
 #[derive(Deserialize, Clone)]
 pub struct MoveReq {
     pub context: Cac<Condition>,
+    pub change_reason: Option<String>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -48,6 +50,7 @@ pub struct ContextFilters {
     pub prefix: Option<String>,
     pub sort_by: Option<ContextFilterSortBy>,
     pub created_by: Option<String>,
+    pub enabled: Option<bool>,
 }
 
 #[cfg_attr(test, derive(Debug, PartialEq))] // Derive traits only when running tests
@@ -59,6 +62,17 @@ pub enum ContextAction {
     Move((String, MoveReq)),
 }
 
+impl ContextAction {
+    /// Change reason sent with the action itself, deletes carry none.
+    pub fn change_reason(&self) -> Option<String> {
+        match self {
+            Self::Put(put_req) => put_req.change_reason.clone(),
+            Self::Move((_, move_req)) => move_req.change_reason.clone(),
+            Self::Delete(_) => None,
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ContextBulkResponse {
@@ -120,6 +134,7 @@ mod tests {
         let expected_action = ContextAction::Put(PutReq {
             context: context,
             r#override: override_,
+            change_reason: None,
         });
 
         let action_deserialized =
@@ -1,3 +1,5 @@
 mod handlers;
 mod types;
 pub use handlers::endpoints;
+pub use handlers::{default_config_change, upsert_default_config};
+pub use types::{CreateReq as DefaultConfigCreateReq, DefaultConfigKey};
@@ -1,51 +1,69 @@
 extern crate base64;
 
 use actix_web::{
-    delete, get, put,
+    delete, get, post, put,
     web::{self, Data, Json, Path, Query},
     HttpResponse, Scope,
 };
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{
+    dsl::sql,
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    sql_types::{Bool, Text},
+    ExpressionMethods, OptionalExtension, PgArrayExpressionMethods, PgConnection,
+    QueryDsl, RunQueryDsl,
 };
 use diesel::{Connection, SelectableHelper};
 use jsonschema::{Draft, JSONSchema, ValidationError};
-use serde_json::Value;
+use serde_json::{json, Value};
 #[cfg(feature = "high-performance-mode")]
 use service_utils::service::types::Tenant;
 use service_utils::{
+    encryption::{is_encrypted, is_mask, mask_secrets, TenantCipher},
     helpers::{parse_config_tags, validation_err_to_str},
-    service::types::{AppHeader, AppState, CustomHeaders, DbConnection},
+    service::types::{
+        AppHeader, AppState, CustomHeaders, DbConnection, ReadDbConnection,
+    },
 };
 use superposition_macros::{
-    bad_argument, db_error, not_found, unexpected_error, validation_error,
+    bad_argument, db_error, forbidden, not_found, unexpected_error, validation_error,
 };
 use superposition_types::{
+    authorization::KeyAccess,
     cac::{
         models::{self as models, Context, DefaultConfig},
-        schema::{self, contexts::dsl::contexts, default_configs::dsl},
+        schema::{self, contexts::dsl as ctxt, default_configs::dsl},
     },
-    custom_query::PaginationParams,
-    result as superposition, PaginatedResponse, User,
+    custom_query::{MetadataFilters, PaginationParams},
+    result as superposition,
+    webhook::WebhookEvent,
+    PaginatedResponse, TenantConfig, TenantQuotas, User,
 };
 
 #[cfg(feature = "high-performance-mode")]
 use crate::helpers::put_config_in_redis;
 use crate::{
     api::{
-        context::helpers::validate_value_with_function,
+        context::{hash, helpers::validate_value_with_function},
         default_config::types::DefaultConfigKey,
         functions::helpers::get_published_function_code,
+        validation_rules::helpers::validate_rules_on_default_configs,
+    },
+    helpers::{
+        add_config_version, enqueue_config_webhooks, resolve_change_reason,
+        resolve_entity_metadata, set_change_reason, soft_delete_cutoff,
+        validate_if_match, ConfigChange, EntityMetadata,
     },
-    helpers::add_config_version,
 };
 
 use super::types::CreateReq;
 
 pub fn endpoints() -> Scope {
-    Scope::new("").service(create).service(get).service(delete)
+    Scope::new("")
+        .service(create)
+        .service(get)
+        .service(delete)
+        .service(restore)
 }
 
 #[put("/{key}")]
@@ -56,14 +74,99 @@ async fn create(
     request: web::Json<CreateReq>,
     db_conn: DbConnection,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
     user: User,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let req = request.into_inner();
-    let key = key.into_inner().into();
+    let key: String = key.into_inner().into();
+    key_access
+        .validate_keys([&key])
+        .map_err(|err| forbidden!(err))?;
     let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        req.change_reason.clone(),
+    )?;
+
+    let (mut default_config, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_default_config_last_modified_at(conn, &key),
+                    )?;
+                    let default_config = upsert_default_config(
+                        key,
+                        req,
+                        &user,
+                        &cipher,
+                        &tenant_config.quotas,
+                        transaction_conn,
+                    )?;
+                    validate_rules_on_default_configs(transaction_conn)?;
+                    let version_id = add_config_version(
+                        &state,
+                        tags,
+                        change_reason,
+                        transaction_conn,
+                    )?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &tenant_config.config_webhooks,
+                        Some(version_id),
+                        vec![default_config_change(&default_config)],
+                    )?;
+                    Ok((default_config, version_id))
+                })
+            }
+        })
+        .await?;
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    let mut http_resp = HttpResponse::Ok();
+
+    http_resp.insert_header((
+        AppHeader::XConfigVersion.to_string(),
+        version_id.to_string(),
+    ));
+    mask_secrets(&mut default_config.value);
+    Ok(http_resp.json(default_config))
+}
+
+/// The change queued for the config webhooks by a write of `default_config`,
+/// with its value masked when it is a secret.
+pub fn default_config_change(default_config: &DefaultConfig) -> ConfigChange {
+    let mut default_config = default_config.clone();
+    mask_secrets(&mut default_config.value);
+    ConfigChange {
+        event: WebhookEvent::DefaultConfigChanged,
+        keys: vec![default_config.key.clone()],
+        context: None,
+        payload: json!(default_config),
+    }
+}
 
-    if req.value.is_none() && req.schema.is_none() && req.function_name.is_none() {
+pub fn upsert_default_config(
+    key: String,
+    req: CreateReq,
+    user: &User,
+    cipher: &TenantCipher,
+    quotas: &TenantQuotas,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<DefaultConfig> {
+    if req.value.is_none()
+        && req.schema.is_none()
+        && req.function_name.is_none()
+        && req.metadata.is_empty()
+        && req.secret.is_none()
+    {
         log::error!("No data provided in the request body for {key}");
         return Err(bad_argument!("Please provide data in the request body."));
     }
@@ -78,11 +181,34 @@ async fn create(
         }
     };
 
-    let result = fetch_default_key(&key, &mut conn);
+    let result = fetch_default_key(&key, conn);
+
+    let metadata = resolve_entity_metadata(
+        req.metadata,
+        result.as_ref().ok().map(|row| EntityMetadata {
+            description: row.description.clone(),
+            owner: row.owner.clone(),
+            labels: row.labels.clone(),
+            deprecated: row.deprecated,
+            sunset_date: row.sunset_date,
+        }),
+    )?;
+
+    let was_secret = result.as_ref().is_ok_and(|row| row.secret);
+    let stored_value = result
+        .as_ref()
+        .ok()
+        .filter(|row| row.secret)
+        .map(|row| row.value.clone());
+    let secret = req.secret.unwrap_or(was_secret);
 
     let (value, schema, function_name, created_at_val, created_by_val) = match result {
         Ok(default_config_row) => {
-            let val = req.value.unwrap_or(default_config_row.value);
+            // the mask read back from a listing leaves a secret value unchanged
+            let val = match req.value {
+                Some(value) if !(was_secret && is_mask(&value)) => value,
+                _ => default_config_row.value,
+            };
             let schema = req
                 .schema
                 .map_or_else(|| default_config_row.schema, Value::Object);
@@ -100,6 +226,7 @@ async fn create(
             )
         }
         Err(superposition::AppError::DbError(diesel::NotFound)) => {
+            validate_default_config_quota(conn, quotas, &key)?;
             match (req.value, req.schema) {
                 (Some(val), Some(schema)) => (
                     val,
@@ -120,7 +247,15 @@ async fn create(
         }
     };
 
-    let default_config = DefaultConfig {
+    let value = if is_encrypted(&value) {
+        cipher
+            .decrypt(&value)
+            .map_err(|err| bad_argument!("Invalid value for {}: {}", key, err))?
+    } else {
+        value
+    };
+
+    let mut default_config = DefaultConfig {
         key: key.to_owned(),
         value,
         schema,
@@ -129,6 +264,14 @@ async fn create(
         created_at: created_at_val,
         last_modified_at: Utc::now().naive_utc(),
         last_modified_by: user.get_email(),
+        description: metadata.description,
+        owner: metadata.owner,
+        labels: metadata.labels,
+        deprecated: metadata.deprecated,
+        sunset_date: metadata.sunset_date,
+        deleted_at: None,
+        deleted_by: None,
+        secret,
     };
 
     let schema_compile_result = JSONSchema::options()
@@ -157,7 +300,7 @@ async fn create(
     }
 
     if let Some(f_name) = &default_config.function_name {
-        let function_code = get_published_function_code(&mut conn, f_name.to_string())
+        let function_code = get_published_function_code(conn, f_name.to_string())
             .map_err(|e| {
                 log::info!("Function not found with error : {e}");
                 bad_argument!("Function {} doesn't exists.", f_name)
@@ -171,34 +314,98 @@ async fn create(
             )?;
         }
     }
-    let version_id =
-        conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            let upsert = diesel::insert_into(dsl::default_configs)
-                .values(&default_config)
-                .on_conflict(schema::default_configs::key)
-                .do_update()
-                .set(&default_config)
-                .execute(transaction_conn);
-            let version_id = add_config_version(&state, tags, transaction_conn)?;
-            match upsert {
-                Ok(_) => Ok(version_id),
-                Err(e) => {
-                    log::info!("DefaultConfig creation failed with error: {e}");
-                    Err(unexpected_error!(
-                        "Something went wrong, failed to create DefaultConfig"
-                    ))
-                }
-            }
+    if secret {
+        default_config.value = cipher
+            .encrypt_keeping(&default_config.value, stored_value.as_ref())
+            .map_err(|err| bad_argument!(err))?;
+    }
+    if secret != was_secret {
+        update_override_secrecy(conn, cipher, &key, secret)?;
+    }
+    diesel::insert_into(dsl::default_configs)
+        .values(&default_config)
+        .on_conflict(schema::default_configs::key)
+        .do_update()
+        .set(&default_config)
+        .execute(conn)
+        .map_err(|e| {
+            log::info!("DefaultConfig creation failed with error: {e}");
+            unexpected_error!("Something went wrong, failed to create DefaultConfig")
         })?;
-    #[cfg(feature = "high-performance-mode")]
-    put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-    let mut http_resp = HttpResponse::Ok();
+    Ok(default_config)
+}
 
-    http_resp.insert_header((
-        AppHeader::XConfigVersion.to_string(),
-        version_id.to_string(),
-    ));
-    Ok(http_resp.json(default_config))
+/*
+  Overrides of a key follow its secret flag, they are encrypted when the
+  flag is set and decrypted when it is cleared. Deleted contexts are
+  included, as they keep their overrides until they are purged.
+*/
+fn update_override_secrecy(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    cipher: &TenantCipher,
+    key: &String,
+    secret: bool,
+) -> superposition::Result<()> {
+    let contexts: Vec<Context> = ctxt::contexts
+        .filter(sql::<Bool>("\"override\"::jsonb ? ").bind::<Text, _>(key))
+        .load(conn)?;
+    for context in contexts {
+        let Some(value) = context.override_.get(key) else {
+            continue;
+        };
+        let value = match (secret, is_encrypted(value)) {
+            (true, false) => cipher.encrypt(value),
+            (false, true) => cipher.decrypt(value),
+            _ => continue,
+        }
+        .map_err(|err| bad_argument!(err))?;
+        let mut override_ = context.override_;
+        override_.insert(key.to_owned(), value);
+        let override_id = hash(&Value::Object(override_.clone().into()));
+        diesel::update(ctxt::contexts)
+            .filter(ctxt::id.eq(&context.id))
+            .set((
+                ctxt::override_.eq(override_),
+                ctxt::override_id.eq(override_id),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn get_default_config_last_modified_at(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    key: &String,
+) -> diesel::QueryResult<Option<NaiveDateTime>> {
+    dsl::default_configs
+        .filter(dsl::key.eq(key))
+        .select(dsl::last_modified_at)
+        .get_result::<NaiveDateTime>(conn)
+        .optional()
+}
+
+/// Fails when adding the key `key` would take the tenant past its quota of
+/// default configs.
+fn validate_default_config_quota(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    quotas: &TenantQuotas,
+    key: &str,
+) -> superposition::Result<()> {
+    let Some(max_default_configs) = quotas.max_default_configs else {
+        return Ok(());
+    };
+    let other_keys: i64 = dsl::default_configs
+        .filter(dsl::deleted_at.is_null())
+        .filter(dsl::key.ne(key))
+        .count()
+        .get_result(conn)?;
+    if other_keys >= max_default_configs {
+        return Err(bad_argument!(
+            "The tenant has reached its quota of {} default configs, delete unused keys or ask an admin to raise the quota",
+            max_default_configs
+        ));
+    }
+    Ok(())
 }
 
 fn fetch_default_key(
@@ -207,6 +414,7 @@ fn fetch_default_key(
 ) -> superposition::Result<models::DefaultConfig> {
     let res = dsl::default_configs
         .filter(schema::default_configs::key.eq(key))
+        .filter(dsl::deleted_at.is_null())
         .select(models::DefaultConfig::as_select())
         .get_result(conn)?;
     Ok(res)
@@ -214,47 +422,71 @@ fn fetch_default_key(
 
 #[get("")]
 async fn get(
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     filters: Query<PaginationParams>,
+    metadata_filters: Query<MetadataFilters>,
 ) -> superposition::Result<Json<PaginatedResponse<DefaultConfig>>> {
-    let DbConnection(mut conn) = db_conn;
-
-    if let Some(true) = filters.all {
-        let result: Vec<DefaultConfig> = dsl::default_configs.get_results(&mut conn)?;
-        return Ok(Json(PaginatedResponse {
-            total_pages: 1,
-            total_items: result.len() as i64,
-            data: result,
-        }));
-    }
+    db_conn
+        .run(move |mut conn| {
+            let filtered_query = || {
+                let mut builder = dsl::default_configs
+                    .filter(dsl::deleted_at.is_null())
+                    .into_boxed();
+                if let Some(owners) = metadata_filters.owners() {
+                    builder = builder.filter(dsl::owner.eq_any(owners));
+                }
+                if let Some(labels) = metadata_filters.labels() {
+                    builder = builder.filter(dsl::labels.overlaps_with(labels));
+                }
+                builder
+            };
 
-    let n_default_configs: i64 = dsl::default_configs.count().get_result(&mut conn)?;
-    let limit = filters.count.unwrap_or(10);
-    let mut builder = dsl::default_configs
-        .into_boxed()
-        .order(dsl::created_at.desc())
-        .limit(limit);
-    if let Some(page) = filters.page {
-        let offset = (page - 1) * limit;
-        builder = builder.offset(offset);
-    }
-    let result: Vec<DefaultConfig> = builder.load(&mut conn)?;
-    let total_pages = (n_default_configs as f64 / limit as f64).ceil() as i64;
-    Ok(Json(PaginatedResponse {
-        total_pages,
-        total_items: n_default_configs,
-        data: result,
-    }))
+            if let Some(true) = filters.all {
+                let mut result: Vec<DefaultConfig> =
+                    filtered_query().get_results(&mut conn)?;
+                result
+                    .iter_mut()
+                    .for_each(|config| mask_secrets(&mut config.value));
+                return Ok(Json(PaginatedResponse {
+                    total_pages: 1,
+                    total_items: result.len() as i64,
+                    data: result,
+                }));
+            }
+
+            let n_default_configs: i64 =
+                filtered_query().count().get_result(&mut conn)?;
+            let limit = filters.count.unwrap_or(10);
+            let mut builder = filtered_query().order(dsl::created_at.desc()).limit(limit);
+            if let Some(page) = filters.page {
+                let offset = (page - 1) * limit;
+                builder = builder.offset(offset);
+            }
+            let mut result: Vec<DefaultConfig> = builder.load(&mut conn)?;
+            result
+                .iter_mut()
+                .for_each(|config| mask_secrets(&mut config.value));
+            let total_pages = (n_default_configs as f64 / limit as f64).ceil() as i64;
+            Ok(Json(PaginatedResponse {
+                total_pages,
+                total_items: n_default_configs,
+                data: result,
+            }))
+        })
+        .await
 }
 
 pub fn get_key_usage_context_ids(
     key: &str,
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<Vec<String>> {
-    let result: Vec<Context> = contexts.load(conn).map_err(|err| {
-        log::error!("failed to fetch contexts with error: {}", err);
-        db_error!(err)
-    })?;
+    let result: Vec<Context> = ctxt::contexts
+        .filter(ctxt::deleted_at.is_null())
+        .load(conn)
+        .map_err(|err| {
+            log::error!("failed to fetch contexts with error: {}", err);
+            db_error!(err)
+        })?;
 
     let mut context_ids = vec![];
     for context in result.iter() {
@@ -273,60 +505,183 @@ async fn delete(
     custom_headers: CustomHeaders,
     db_conn: DbConnection,
     #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
     user: User,
+    key_access: KeyAccess,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
 
     let key: String = path.into_inner().into();
-    let mut version_id = 0;
-    fetch_default_key(&key, &mut conn)?;
-    let context_ids = get_key_usage_context_ids(&key, &mut conn)
-        .map_err(|_| unexpected_error!("Something went wrong"))?;
-    if context_ids.is_empty() {
-        let resp =
-            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-                diesel::update(dsl::default_configs)
-                    .filter(dsl::key.eq(&key))
-                    .set((
-                        dsl::last_modified_at.eq(Utc::now().naive_utc()),
-                        dsl::last_modified_by.eq(user.get_email()),
-                    ))
-                    .execute(transaction_conn)?;
-
-                let deleted_row =
-                    diesel::delete(dsl::default_configs.filter(dsl::key.eq(&key)))
+    key_access
+        .validate_keys([&key])
+        .map_err(|err| forbidden!(err))?;
+    let version_id = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                fetch_default_key(&key, &mut conn)?;
+                let context_ids = get_key_usage_context_ids(&key, &mut conn)
+                    .map_err(|_| unexpected_error!("Something went wrong"))?;
+                if !context_ids.is_empty() {
+                    return Err(bad_argument!(
+                        "Given key already in use in contexts: {}",
+                        context_ids.join(",")
+                    ));
+                }
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_default_config_last_modified_at(conn, &key),
+                    )?;
+                    let deleted_row = diesel::update(dsl::default_configs)
+                        .filter(dsl::key.eq(&key))
+                        .filter(dsl::deleted_at.is_null())
+                        .set((
+                            dsl::deleted_at.eq(Utc::now().naive_utc()),
+                            dsl::deleted_by.eq(user.get_email()),
+                            dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                            dsl::last_modified_by.eq(user.get_email()),
+                        ))
                         .execute(transaction_conn);
-                match deleted_row {
-                    Ok(0) => {
-                        Err(not_found!("default config key `{}` doesn't exists", key))
-                    }
-                    Ok(_) => {
-                        version_id = add_config_version(&state, tags, transaction_conn)?;
-                        log::info!(
-                            "default config key: {key} deleted by {}",
-                            user.get_email()
-                        );
-                        Ok(HttpResponse::NoContent()
-                            .insert_header((
-                                AppHeader::XConfigVersion.to_string(),
-                                version_id.to_string(),
-                            ))
-                            .finish())
+                    match deleted_row {
+                        Ok(0) => {
+                            Err(not_found!("default config key `{}` doesn't exists", key))
+                        }
+                        Ok(_) => {
+                            validate_rules_on_default_configs(transaction_conn)?;
+                            let version_id = add_config_version(
+                                &state,
+                                tags,
+                                change_reason,
+                                transaction_conn,
+                            )?;
+                            enqueue_config_webhooks(
+                                transaction_conn,
+                                &tenant_config.config_webhooks,
+                                Some(version_id),
+                                vec![ConfigChange {
+                                    event: WebhookEvent::DefaultConfigChanged,
+                                    keys: vec![key.clone()],
+                                    context: None,
+                                    payload: json!({ "key": key, "deleted": true }),
+                                }],
+                            )?;
+                            log::info!(
+                                "default config key: {key} deleted by {}",
+                                user.get_email()
+                            );
+                            Ok(version_id)
+                        }
+                        Err(e) => {
+                            log::error!(
+                                "default config delete query failed with error: {e}"
+                            );
+                            Err(unexpected_error!("Something went wrong."))
+                        }
                     }
-                    Err(e) => {
-                        log::error!("default config delete query failed with error: {e}");
-                        Err(unexpected_error!("Something went wrong."))
-                    }
-                }
-            });
-        #[cfg(feature = "high-performance-mode")]
-        put_config_in_redis(version_id, state, tenant, &mut conn).await?;
-        resp
-    } else {
-        Err(bad_argument!(
-            "Given key already in use in contexts: {}",
-            context_ids.join(",")
+                })
+            }
+        })
+        .await?;
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    Ok(HttpResponse::NoContent()
+        .insert_header((
+            AppHeader::XConfigVersion.to_string(),
+            version_id.to_string(),
         ))
-    }
+        .finish())
+}
+
+#[post("/{key}/restore")]
+async fn restore(
+    state: Data<AppState>,
+    path: Path<DefaultConfigKey>,
+    custom_headers: CustomHeaders,
+    db_conn: DbConnection,
+    #[cfg(feature = "high-performance-mode")] tenant: Tenant,
+    tenant_config: TenantConfig,
+    user: User,
+    key_access: KeyAccess,
+) -> superposition::Result<HttpResponse> {
+    let tags = parse_config_tags(custom_headers.config_tags)?;
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+    let key: String = path.into_inner().into();
+    key_access
+        .validate_keys([&key])
+        .map_err(|err| forbidden!(err))?;
+    let restored_key = key.clone();
+    let email = user.get_email();
+
+    let (restored_config, version_id) = db_conn
+        .run({
+            let state = state.clone();
+            move |mut conn| {
+                let deleted_config = dsl::default_configs
+                    .filter(dsl::key.eq(&key))
+                    .filter(dsl::deleted_at.is_not_null())
+                    .select(DefaultConfig::as_select())
+                    .get_result(&mut conn)
+                    .map_err(|err| match err {
+                        diesel::result::Error::NotFound => {
+                            not_found!("No deleted default config key `{}` found", key)
+                        }
+                        err => db_error!(err),
+                    })?;
+                if deleted_config.deleted_at < Some(soft_delete_cutoff(&state)) {
+                    return Err(bad_argument!(
+                        "default config key `{}` was deleted before the retention window and can't be restored",
+                        key
+                    ));
+                }
+
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    set_change_reason(transaction_conn, &change_reason)?;
+                    validate_if_match(
+                        transaction_conn,
+                        &custom_headers.if_match,
+                        |conn| get_default_config_last_modified_at(conn, &key),
+                    )?;
+                    validate_default_config_quota(
+                        transaction_conn,
+                        &tenant_config.quotas,
+                        &key,
+                    )?;
+                    let restored_config = diesel::update(dsl::default_configs)
+                        .filter(dsl::key.eq(&key))
+                        .set((
+                            dsl::deleted_at.eq(None::<NaiveDateTime>),
+                            dsl::deleted_by.eq(None::<String>),
+                            dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                            dsl::last_modified_by.eq(user.get_email()),
+                        ))
+                        .get_result::<DefaultConfig>(transaction_conn)?;
+                    validate_rules_on_default_configs(transaction_conn)?;
+                    let version_id =
+                        add_config_version(&state, tags, change_reason, transaction_conn)?;
+                    enqueue_config_webhooks(
+                        transaction_conn,
+                        &tenant_config.config_webhooks,
+                        Some(version_id),
+                        vec![default_config_change(&restored_config)],
+                    )?;
+                    Ok((restored_config, version_id))
+                })
+            }
+        })
+        .await?;
+    log::info!("default config key: {restored_key} restored by {email}");
+    #[cfg(feature = "high-performance-mode")]
+    put_config_in_redis(version_id, state, tenant).await?;
+    let mut http_resp = HttpResponse::Ok();
+    http_resp.insert_header((
+        AppHeader::XConfigVersion.to_string(),
+        version_id.to_string(),
+    ));
+    Ok(http_resp.json(restored_config))
 }
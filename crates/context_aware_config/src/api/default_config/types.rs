@@ -3,6 +3,8 @@ use serde::{Deserialize, Deserializer};
 use serde_json::{Map, Value};
 use superposition_types::RegexEnum;
 
+use crate::helpers::MetadataReq;
+
 #[derive(Debug, Deserialize)]
 pub struct CreateReq {
     #[serde(default, deserialize_with = "deserialize_option")]
@@ -10,6 +12,11 @@ pub struct CreateReq {
     pub schema: Option<Map<String, Value>>,
     #[serde(default, deserialize_with = "deserialize_option")]
     pub function_name: Option<Value>,
+    #[serde(flatten)]
+    pub metadata: MetadataReq,
+    pub change_reason: Option<String>,
+    /// Stores the value and the overrides of the key encrypted.
+    pub secret: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, AsRef, Deref, DerefMut, Into)]
@@ -2,4 +2,6 @@ mod handlers;
 mod types;
 mod utils;
 pub use handlers::endpoints;
+pub use handlers::upsert_dimension;
+pub use types::CreateReq as DimensionCreateReq;
 pub use utils::get_all_dimension_schema_map;
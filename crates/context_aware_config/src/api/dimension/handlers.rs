@@ -1,52 +1,107 @@
 extern crate base64;
 
 use actix_web::{
-    delete, get, put,
+    delete, get, post, put,
     web::{self, Data, Json, Path, Query},
     HttpResponse, Scope,
 };
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{
-    delete, Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper,
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, OptionalExtension, PgArrayExpressionMethods,
+    PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
 };
 use jsonschema::{Draft, JSONSchema};
-use serde_json::Value;
-use service_utils::service::types::{AppState, DbConnection};
-use superposition_macros::{bad_argument, not_found, unexpected_error};
+use serde_json::{json, Value};
+use service_utils::service::types::{
+    AppState, CustomHeaders, DbConnection, ReadDbConnection,
+};
+use superposition_macros::{bad_argument, db_error, not_found, unexpected_error};
 use superposition_types::{
     cac::{
         models::Dimension,
         schema::{dimensions, dimensions::dsl::*},
     },
-    custom_query::PaginationParams,
-    result as superposition, PaginatedResponse, TenantConfig, User,
+    custom_query::{MetadataFilters, PaginationParams},
+    result as superposition,
+    webhook::WebhookEvent,
+    PaginatedResponse, TenantConfig, User,
 };
 
 use crate::{
     api::dimension::{types::CreateReq, utils::get_dimension_usage_context_ids},
-    helpers::validate_jsonschema,
+    helpers::{
+        enqueue_config_webhooks, resolve_change_reason, resolve_entity_metadata,
+        set_change_reason, soft_delete_cutoff, validate_if_match, validate_jsonschema,
+        ConfigChange, EntityMetadata,
+    },
 };
 
-use super::types::{DeleteReq, DimensionWithMandatory};
+#[cfg(feature = "config-cache")]
+use crate::helpers::notify_config_change;
+
+use super::types::{DeleteReq, DimensionName, DimensionWithMandatory};
 
 pub fn endpoints() -> Scope {
     Scope::new("")
         .service(create)
         .service(get)
         .service(delete_dimension)
+        .service(restore_dimension)
 }
 
 #[put("")]
 async fn create(
     state: Data<AppState>,
+    custom_headers: CustomHeaders,
     req: web::Json<CreateReq>,
     user: User,
     db_conn: DbConnection,
     tenant_config: TenantConfig,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
+    let mut create_req = req.into_inner();
+    let change_reason = resolve_change_reason(
+        &tenant_config,
+        custom_headers.change_reason,
+        create_req.change_reason.take(),
+    )?;
+    let dimension_name: String = create_req.dimension.to_string();
+
+    let upserted_dimension = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                set_change_reason(transaction_conn, &change_reason)?;
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_dimension_last_modified_at(conn, &dimension_name)
+                })?;
+                let dimension =
+                    upsert_dimension(create_req, &state, &user, transaction_conn)?;
+                enqueue_config_webhooks(
+                    transaction_conn,
+                    &tenant_config.config_webhooks,
+                    None,
+                    vec![dimension_change(json!(dimension))],
+                )?;
+                Ok(dimension)
+            })
+        })
+        .await?;
 
-    let create_req = req.into_inner();
+    let is_mandatory = tenant_config
+        .mandatory_dimensions
+        .contains(&upserted_dimension.dimension);
+    Ok(HttpResponse::Created().json(DimensionWithMandatory::new(
+        upserted_dimension,
+        is_mandatory,
+    )))
+}
+
+pub fn upsert_dimension(
+    create_req: CreateReq,
+    state: &Data<AppState>,
+    user: &User,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Dimension> {
     let schema_value = create_req.schema;
 
     validate_jsonschema(&state.meta_schema, &schema_value)?;
@@ -73,8 +128,24 @@ async fn create(
         }
     };
 
+    let dimension_name: String = create_req.dimension.into();
+    let existing_metadata = dimensions
+        .filter(dimensions::dimension.eq(&dimension_name))
+        .filter(deleted_at.is_null())
+        .select(Dimension::as_select())
+        .get_result(conn)
+        .optional()?
+        .map(|existing| EntityMetadata {
+            description: existing.description,
+            owner: existing.owner,
+            labels: existing.labels,
+            deprecated: existing.deprecated,
+            sunset_date: existing.sunset_date,
+        });
+    let metadata = resolve_entity_metadata(create_req.metadata, existing_metadata)?;
+
     let new_dimension = Dimension {
-        dimension: create_req.dimension.into(),
+        dimension: dimension_name,
         priority: create_req.priority.into(),
         schema: schema_value,
         created_by: user.get_email(),
@@ -82,6 +153,13 @@ async fn create(
         function_name: fun_name.clone(),
         last_modified_at: Utc::now().naive_utc(),
         last_modified_by: user.get_email(),
+        description: metadata.description,
+        owner: metadata.owner,
+        labels: metadata.labels,
+        deprecated: metadata.deprecated,
+        sunset_date: metadata.sunset_date,
+        deleted_at: None,
+        deleted_by: None,
     };
 
     let upsert = diesel::insert_into(dimensions)
@@ -89,17 +167,14 @@ async fn create(
         .on_conflict(dimensions::dimension)
         .do_update()
         .set(&new_dimension)
-        .get_result::<Dimension>(&mut conn);
+        .get_result::<Dimension>(conn);
 
     match upsert {
         Ok(upserted_dimension) => {
-            let is_mandatory = tenant_config
-                .mandatory_dimensions
-                .contains(&upserted_dimension.dimension);
-            Ok(HttpResponse::Created().json(DimensionWithMandatory::new(
-                upserted_dimension,
-                is_mandatory,
-            )))
+            // dimensions create no config version, but are deprecated
+            #[cfg(feature = "config-cache")]
+            notify_config_change(conn)?;
+            Ok(upserted_dimension)
         }
         Err(diesel::result::Error::DatabaseError(
             diesel::result::DatabaseErrorKind::ForeignKeyViolation,
@@ -120,35 +195,60 @@ async fn create(
     }
 }
 
+fn get_dimension_last_modified_at(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    name: &String,
+) -> diesel::QueryResult<Option<NaiveDateTime>> {
+    dimensions
+        .filter(dimensions::dimension.eq(name))
+        .select(last_modified_at)
+        .get_result::<NaiveDateTime>(conn)
+        .optional()
+}
+
 #[get("")]
 async fn get(
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     tenant_config: TenantConfig,
     filters: Query<PaginationParams>,
+    metadata_filters: Query<MetadataFilters>,
 ) -> superposition::Result<Json<PaginatedResponse<DimensionWithMandatory>>> {
-    let DbConnection(mut conn) = db_conn;
+    let (total_pages, total_items, result) = db_conn
+        .run(move |mut conn| {
+            let filtered_query = || {
+                let mut builder = dimensions.filter(deleted_at.is_null()).into_boxed();
+                if let Some(owners) = metadata_filters.owners() {
+                    builder = builder.filter(owner.eq_any(owners));
+                }
+                if let Some(label_list) = metadata_filters.labels() {
+                    builder = builder.filter(labels.overlaps_with(label_list));
+                }
+                builder
+            };
 
-    let (total_pages, total_items, result) = match filters.all {
-        Some(true) => {
-            let result: Vec<Dimension> = dimensions.get_results(&mut conn)?;
-            (1, result.len() as i64, result)
-        }
-        _ => {
-            let n_dimensions: i64 = dimensions.count().get_result(&mut conn)?;
-            let limit = filters.count.unwrap_or(10);
-            let mut builder = dimensions
-                .into_boxed()
-                .order(created_at.desc())
-                .limit(limit);
-            if let Some(page) = filters.page {
-                let offset = (page - 1) * limit;
-                builder = builder.offset(offset);
+            match filters.all {
+                Some(true) => {
+                    let result: Vec<Dimension> =
+                        filtered_query().get_results(&mut conn)?;
+                    Ok((1, result.len() as i64, result))
+                }
+                _ => {
+                    let n_dimensions: i64 =
+                        filtered_query().count().get_result(&mut conn)?;
+                    let limit = filters.count.unwrap_or(10);
+                    let mut builder =
+                        filtered_query().order(created_at.desc()).limit(limit);
+                    if let Some(page) = filters.page {
+                        let offset = (page - 1) * limit;
+                        builder = builder.offset(offset);
+                    }
+                    let result: Vec<Dimension> = builder.load(&mut conn)?;
+                    let total_pages = (n_dimensions as f64 / limit as f64).ceil() as i64;
+                    Ok((total_pages, n_dimensions, result))
+                }
             }
-            let result: Vec<Dimension> = builder.load(&mut conn)?;
-            let total_pages = (n_dimensions as f64 / limit as f64).ceil() as i64;
-            (total_pages, n_dimensions, result)
-        }
-    };
+        })
+        .await?;
 
     let dimensions_with_mandatory: Vec<DimensionWithMandatory> = result
         .into_iter()
@@ -169,42 +269,149 @@ async fn get(
 #[delete("/{name}")]
 async fn delete_dimension(
     path: Path<DeleteReq>,
+    custom_headers: CustomHeaders,
     user: User,
     db_conn: DbConnection,
+    tenant_config: TenantConfig,
 ) -> superposition::Result<HttpResponse> {
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
     let name: String = path.into_inner().into();
-    let DbConnection(mut conn) = db_conn;
-    dimensions::dsl::dimensions
-        .filter(dimensions::dimension.eq(&name))
-        .select(Dimension::as_select())
-        .get_result(&mut conn)?;
-    let context_ids = get_dimension_usage_context_ids(&name, &mut conn)
-        .map_err(|_| unexpected_error!("Something went wrong"))?;
-    if context_ids.is_empty() {
-        conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
-            use dimensions::dsl;
-            diesel::update(dsl::dimensions)
-                .filter(dsl::dimension.eq(&name))
-                .set((
-                    dsl::last_modified_at.eq(Utc::now().naive_utc()),
-                    dsl::last_modified_by.eq(user.get_email()),
-                ))
-                .execute(transaction_conn)?;
-            let deleted_row = delete(dsl::dimensions.filter(dsl::dimension.eq(&name)))
-                .execute(transaction_conn);
-            match deleted_row {
-                Ok(0) => Err(not_found!("Dimension `{}` doesn't exists", name)),
-                Ok(_) => Ok(HttpResponse::NoContent().finish()),
-                Err(e) => {
-                    log::error!("dimension delete query failed with error: {e}");
-                    Err(unexpected_error!("Something went wrong."))
+    db_conn
+        .run(move |mut conn| {
+            dimensions::dsl::dimensions
+                .filter(dimensions::dimension.eq(&name))
+                .filter(dimensions::deleted_at.is_null())
+                .select(Dimension::as_select())
+                .get_result(&mut conn)?;
+            let context_ids = get_dimension_usage_context_ids(&name, &mut conn)
+                .map_err(|_| unexpected_error!("Something went wrong"))?;
+            if !context_ids.is_empty() {
+                return Err(bad_argument!(
+                    "Given key already in use in contexts: {}",
+                    context_ids.join(",")
+                ));
+            }
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                use dimensions::dsl;
+                set_change_reason(transaction_conn, &change_reason)?;
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_dimension_last_modified_at(conn, &name)
+                })?;
+                let deleted_row = diesel::update(dsl::dimensions)
+                    .filter(dsl::dimension.eq(&name))
+                    .filter(dsl::deleted_at.is_null())
+                    .set((
+                        dsl::deleted_at.eq(Utc::now().naive_utc()),
+                        dsl::deleted_by.eq(user.get_email()),
+                        dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                        dsl::last_modified_by.eq(user.get_email()),
+                    ))
+                    .execute(transaction_conn);
+                match deleted_row {
+                    Ok(0) => Err(not_found!("Dimension `{}` doesn't exists", name)),
+                    Ok(_) => {
+                        #[cfg(feature = "config-cache")]
+                        notify_config_change(transaction_conn)?;
+                        enqueue_config_webhooks(
+                            transaction_conn,
+                            &tenant_config.config_webhooks,
+                            None,
+                            vec![dimension_change(
+                                json!({ "dimension": name, "deleted": true }),
+                            )],
+                        )?;
+                        log::info!("dimension {name} deleted by {}", user.get_email());
+                        Ok(())
+                    }
+                    Err(e) => {
+                        log::error!("dimension delete query failed with error: {e}");
+                        Err(unexpected_error!("Something went wrong."))
+                    }
                 }
+            })
+        })
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/{name}/restore")]
+async fn restore_dimension(
+    state: Data<AppState>,
+    path: Path<DimensionName>,
+    custom_headers: CustomHeaders,
+    user: User,
+    db_conn: DbConnection,
+    tenant_config: TenantConfig,
+) -> superposition::Result<HttpResponse> {
+    let change_reason =
+        resolve_change_reason(&tenant_config, custom_headers.change_reason, None)?;
+    let name: String = path.into_inner().into();
+    let email = user.get_email();
+    let restored_name = name.clone();
+    let restored_dimension = db_conn
+        .run(move |mut conn| {
+            let name = restored_name;
+            let deleted_dimension = dimensions
+                .filter(dimensions::dimension.eq(&name))
+                .filter(deleted_at.is_not_null())
+                .select(Dimension::as_select())
+                .get_result(&mut conn)
+                .map_err(|err| match err {
+                    diesel::result::Error::NotFound => {
+                        not_found!("No deleted dimension `{}` found", name)
+                    }
+                    err => db_error!(err),
+                })?;
+            if deleted_dimension.deleted_at < Some(soft_delete_cutoff(&state)) {
+                return Err(bad_argument!(
+                    "Dimension `{}` was deleted before the retention window and can't be restored",
+                    name
+                ));
             }
+
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                set_change_reason(transaction_conn, &change_reason)?;
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_dimension_last_modified_at(conn, &name)
+                })?;
+                let restored_dimension = diesel::update(dimensions)
+                    .filter(dimensions::dimension.eq(&name))
+                    .set((
+                        deleted_at.eq(None::<NaiveDateTime>),
+                        deleted_by.eq(None::<String>),
+                        last_modified_at.eq(Utc::now().naive_utc()),
+                        last_modified_by.eq(email),
+                    ))
+                    .get_result::<Dimension>(transaction_conn)?;
+                #[cfg(feature = "config-cache")]
+                notify_config_change(transaction_conn)?;
+                enqueue_config_webhooks(
+                    transaction_conn,
+                    &tenant_config.config_webhooks,
+                    None,
+                    vec![dimension_change(json!(restored_dimension))],
+                )?;
+                Ok(restored_dimension)
+            })
         })
-    } else {
-        Err(bad_argument!(
-            "Given key already in use in contexts: {}",
-            context_ids.join(",")
-        ))
+        .await?;
+    log::info!("dimension {name} restored by {}", user.get_email());
+    let is_mandatory = tenant_config
+        .mandatory_dimensions
+        .contains(&restored_dimension.dimension);
+    Ok(HttpResponse::Ok().json(DimensionWithMandatory::new(
+        restored_dimension,
+        is_mandatory,
+    )))
+}
+
+/* Dimensions are not part of a config version, their changes carry no keys. */
+fn dimension_change(payload: Value) -> ConfigChange {
+    ConfigChange {
+        event: WebhookEvent::DimensionChanged,
+        keys: Vec::new(),
+        context: None,
+        payload,
     }
 }
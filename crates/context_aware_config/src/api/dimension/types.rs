@@ -1,9 +1,11 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use derive_more::{AsRef, Deref, DerefMut, Into};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use superposition_types::{cac::models::Dimension, RegexEnum};
 
+use crate::helpers::MetadataReq;
+
 #[derive(Debug, Deserialize)]
 pub struct CreateReq {
     pub dimension: DimensionName,
@@ -11,6 +13,9 @@ pub struct CreateReq {
     pub schema: Value,
     #[serde(default, deserialize_with = "deserialize_option")]
     pub function_name: Option<Value>,
+    #[serde(flatten)]
+    pub metadata: MetadataReq,
+    pub change_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize, AsRef, Deref, DerefMut, Into)]
@@ -70,6 +75,11 @@ pub struct DimensionWithMandatory {
     pub function_name: Option<String>,
     pub last_modified_at: NaiveDateTime,
     pub last_modified_by: String,
+    pub description: String,
+    pub owner: Option<String>,
+    pub labels: Vec<String>,
+    pub deprecated: bool,
+    pub sunset_date: Option<NaiveDate>,
     pub mandatory: bool,
 }
 
@@ -84,6 +94,11 @@ impl DimensionWithMandatory {
             function_name: value.function_name,
             last_modified_at: value.last_modified_at,
             last_modified_by: value.last_modified_by,
+            description: value.description,
+            owner: value.owner,
+            labels: value.labels,
+            deprecated: value.deprecated,
+            sunset_date: value.sunset_date,
             mandatory,
         }
     }
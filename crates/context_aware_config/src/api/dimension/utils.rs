@@ -1,6 +1,6 @@
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
-    PgConnection, RunQueryDsl,
+    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
 };
 use jsonschema::{Draft, JSONSchema};
 use service_utils::helpers::extract_dimensions;
@@ -9,7 +9,7 @@ use superposition_macros::{db_error, unexpected_error};
 use superposition_types::{
     cac::{
         models::{Context, Dimension},
-        schema::{contexts::dsl::contexts, dimensions::dsl::*},
+        schema::{contexts::dsl as ctxt, dimensions::dsl::*},
     },
     result as superposition, Cac, Condition,
 };
@@ -17,7 +17,9 @@ use superposition_types::{
 pub fn get_all_dimension_schema_map(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<HashMap<String, (JSONSchema, i32)>> {
-    let dimensions_vec = dimensions.load::<Dimension>(conn)?;
+    let dimensions_vec = dimensions
+        .filter(deleted_at.is_null())
+        .load::<Dimension>(conn)?;
 
     let dimension_schema_map = dimensions_vec
         .into_iter()
@@ -38,10 +40,13 @@ pub fn get_dimension_usage_context_ids(
     key: &str,
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<Vec<String>> {
-    let result: Vec<Context> = contexts.load(conn).map_err(|err| {
-        log::error!("failed to fetch contexts with error: {}", err);
-        db_error!(err)
-    })?;
+    let result: Vec<Context> = ctxt::contexts
+        .filter(ctxt::deleted_at.is_null())
+        .load(conn)
+        .map_err(|err| {
+            log::error!("failed to fetch contexts with error: {}", err);
+            db_error!(err)
+        })?;
 
     let mut context_ids = vec![];
     for context in result.iter() {
@@ -1,3 +1,4 @@
 mod handlers;
 pub mod types;
+pub use handlers::create_type_template;
 pub use handlers::endpoints;
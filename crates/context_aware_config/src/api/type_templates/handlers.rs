@@ -1,10 +1,14 @@
 use actix_web::web::{Json, Path, Query};
 use actix_web::{delete, get, post, put, HttpResponse, Scope};
-use chrono::Utc;
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
+};
 use jsonschema::JSONSchema;
 use serde_json::Value;
-use service_utils::service::types::DbConnection;
+use service_utils::service::types::{CustomHeaders, DbConnection, ReadDbConnection};
 use superposition_macros::{bad_argument, db_error};
 use superposition_types::cac::models::TypeTemplates;
 use superposition_types::{
@@ -13,7 +17,10 @@ use superposition_types::{
     result as superposition, PaginatedResponse, User,
 };
 
-use crate::api::type_templates::types::{TypeTemplateName, TypeTemplateRequest};
+use crate::{
+    api::type_templates::types::{TypeTemplateName, TypeTemplateRequest},
+    helpers::validate_if_match,
+};
 
 pub fn endpoints() -> Scope {
     Scope::new("")
@@ -26,10 +33,30 @@ pub fn endpoints() -> Scope {
 #[post("")]
 async fn create_type(
     request: Json<TypeTemplateRequest>,
+    custom_headers: CustomHeaders,
     db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
+    let request = request.into_inner();
+    let type_name: String = request.type_name.clone().into();
+    let type_template = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_type_last_modified_at(conn, &type_name)
+                })?;
+                create_type_template(request, &user, transaction_conn)
+            })
+        })
+        .await?;
+    Ok(HttpResponse::Ok().json(type_template))
+}
+
+pub fn create_type_template(
+    request: TypeTemplateRequest,
+    user: &User,
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<TypeTemplates> {
     let _ = JSONSchema::compile(&request.type_schema).map_err(|err| {
         log::error!(
             "Invalid jsonschema sent in the request, schema: {:?} error: {}",
@@ -41,30 +68,29 @@ async fn create_type(
             err.to_string()
         )
     })?;
-    let type_name: String = request.type_name.clone().into();
-    let type_template = diesel::insert_into(type_templates::table)
+    let type_name: String = request.type_name.into();
+    diesel::insert_into(type_templates::table)
         .values((
-            type_templates::type_schema.eq(request.type_schema.clone()),
+            type_templates::type_schema.eq(request.type_schema),
             type_templates::type_name.eq(type_name),
             type_templates::created_by.eq(user.email.clone()),
             type_templates::last_modified_by.eq(user.email.clone()),
         ))
-        .get_result::<TypeTemplates>(&mut conn)
+        .get_result::<TypeTemplates>(conn)
         .map_err(|err| {
             log::error!("failed to insert custom type with error: {}", err);
             db_error!(err)
-        })?;
-    Ok(HttpResponse::Ok().json(type_template))
+        })
 }
 
 #[put("/{type_name}")]
 async fn update_type(
     request: Json<Value>,
     path: Path<TypeTemplateName>,
+    custom_headers: CustomHeaders,
     db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let _ = JSONSchema::compile(&request).map_err(|err| {
         log::error!(
             "Invalid jsonschema sent in the request, schema: {:?} error: {}",
@@ -79,76 +105,110 @@ async fn update_type(
     let type_name: String = path.into_inner().into();
 
     let timestamp = Utc::now().naive_utc();
-    let updated_type = diesel::update(type_templates::table)
-        .filter(type_templates::type_name.eq(type_name))
-        .set((
-            type_templates::type_schema.eq(request.clone()),
-            type_templates::last_modified_at.eq(timestamp),
-            type_templates::last_modified_by.eq(user.email),
-        ))
-        .get_result::<TypeTemplates>(&mut conn)
-        .map_err(|err| {
-            log::error!("failed to insert custom type with error: {}", err);
-            db_error!(err)
-        })?;
+    let type_schema = request.into_inner();
+    let updated_type = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_type_last_modified_at(conn, &type_name)
+                })?;
+                diesel::update(type_templates::table)
+                    .filter(type_templates::type_name.eq(type_name))
+                    .set((
+                        type_templates::type_schema.eq(type_schema),
+                        type_templates::last_modified_at.eq(timestamp),
+                        type_templates::last_modified_by.eq(user.email),
+                    ))
+                    .get_result::<TypeTemplates>(transaction_conn)
+                    .map_err(|err| {
+                        log::error!("failed to insert custom type with error: {}", err);
+                        db_error!(err)
+                    })
+            })
+        })
+        .await?;
     Ok(HttpResponse::Ok().json(updated_type))
 }
 
 #[delete("/{type_name}")]
 async fn delete_type(
     path: Path<TypeTemplateName>,
+    custom_headers: CustomHeaders,
     db_conn: DbConnection,
     user: User,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let type_name: String = path.into_inner().into();
-    diesel::update(dsl::type_templates)
-        .filter(dsl::type_name.eq(type_name.clone()))
-        .set((
-            dsl::last_modified_at.eq(Utc::now().naive_utc()),
-            dsl::last_modified_by.eq(user.email),
-        ))
-        .execute(&mut conn)?;
-    let deleted_type =
-        diesel::delete(dsl::type_templates.filter(dsl::type_name.eq(type_name)))
-            .get_result::<TypeTemplates>(&mut conn)?;
+    let deleted_type = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_type_last_modified_at(conn, &type_name)
+                })?;
+                diesel::update(dsl::type_templates)
+                    .filter(dsl::type_name.eq(type_name.clone()))
+                    .set((
+                        dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                        dsl::last_modified_by.eq(user.email),
+                    ))
+                    .execute(transaction_conn)?;
+                let deleted_type = diesel::delete(
+                    dsl::type_templates.filter(dsl::type_name.eq(type_name)),
+                )
+                .get_result::<TypeTemplates>(transaction_conn)?;
+                Ok(deleted_type)
+            })
+        })
+        .await?;
     Ok(HttpResponse::Ok().json(deleted_type))
 }
 
+fn get_type_last_modified_at(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    name: &String,
+) -> diesel::QueryResult<Option<NaiveDateTime>> {
+    dsl::type_templates
+        .filter(dsl::type_name.eq(name))
+        .select(dsl::last_modified_at)
+        .get_result::<NaiveDateTime>(conn)
+        .optional()
+}
+
 #[get("")]
 async fn list_types(
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
     filters: Query<PaginationParams>,
 ) -> superposition::Result<Json<PaginatedResponse<TypeTemplates>>> {
-    let DbConnection(mut conn) = db_conn;
-
-    if let Some(true) = filters.all {
-        let result: Vec<TypeTemplates> =
-            type_templates::dsl::type_templates.get_results(&mut conn)?;
-        return Ok(Json(PaginatedResponse {
-            total_pages: 1,
-            total_items: result.len() as i64,
-            data: result,
-        }));
-    };
+    db_conn
+        .run(move |mut conn| {
+            if let Some(true) = filters.all {
+                let result: Vec<TypeTemplates> =
+                    type_templates::dsl::type_templates.get_results(&mut conn)?;
+                return Ok(Json(PaginatedResponse {
+                    total_pages: 1,
+                    total_items: result.len() as i64,
+                    data: result,
+                }));
+            };
 
-    let n_types: i64 = type_templates::dsl::type_templates
-        .count()
-        .get_result(&mut conn)?;
-    let limit = filters.count.unwrap_or(10);
-    let mut builder = type_templates::dsl::type_templates
-        .into_boxed()
-        .order(type_templates::dsl::created_at.desc())
-        .limit(limit);
-    if let Some(page) = filters.page {
-        let offset = (page - 1) * limit;
-        builder = builder.offset(offset);
-    }
-    let custom_types: Vec<TypeTemplates> = builder.load(&mut conn)?;
-    let total_pages = (n_types as f64 / limit as f64).ceil() as i64;
-    Ok(Json(PaginatedResponse {
-        total_pages,
-        total_items: n_types,
-        data: custom_types,
-    }))
+            let n_types: i64 = type_templates::dsl::type_templates
+                .count()
+                .get_result(&mut conn)?;
+            let limit = filters.count.unwrap_or(10);
+            let mut builder = type_templates::dsl::type_templates
+                .into_boxed()
+                .order(type_templates::dsl::created_at.desc())
+                .limit(limit);
+            if let Some(page) = filters.page {
+                let offset = (page - 1) * limit;
+                builder = builder.offset(offset);
+            }
+            let custom_types: Vec<TypeTemplates> = builder.load(&mut conn)?;
+            let total_pages = (n_types as f64 / limit as f64).ceil() as i64;
+            Ok(Json(PaginatedResponse {
+                total_pages,
+                total_items: n_types,
+                data: custom_types,
+            }))
+        })
+        .await
 }
@@ -0,0 +1,242 @@
+use actix_web::web::{Json, Path, Query};
+use actix_web::{delete, get, patch, post, HttpResponse, Scope};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
+};
+use service_utils::service::types::{
+    AppHeader, CustomHeaders, DbConnection, ReadDbConnection,
+};
+use superposition_macros::{bad_argument, not_found};
+use superposition_types::{
+    cac::{
+        models::ValidationRule,
+        schema::validation_rules::{self, dsl},
+    },
+    custom_query::PaginationParams,
+    result as superposition, PaginatedResponse, User,
+};
+
+use crate::helpers::{revision_etag, validate_if_match};
+
+use super::{
+    helpers::validate_rules_on_config,
+    types::{CreateRuleRequest, UpdateRuleRequest, ValidationRuleName},
+};
+
+pub fn endpoints() -> Scope {
+    Scope::new("")
+        .service(list_rules)
+        .service(get_rule)
+        .service(create_rule)
+        .service(update_rule)
+        .service(delete_rule)
+}
+
+#[post("")]
+async fn create_rule(
+    request: Json<CreateRuleRequest>,
+    custom_headers: CustomHeaders,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let inserted_rule = db_conn
+        .run(move |mut conn| {
+            let request = request.into_inner();
+            if !request.rule.is_object() {
+                return Err(bad_argument!("rule should be a json logic object"));
+            }
+            let timestamp = Utc::now().naive_utc();
+            let rule = ValidationRule {
+                rule_name: request.rule_name.into(),
+                description: request.description,
+                rule: request.rule,
+                created_at: timestamp,
+                created_by: user.get_email(),
+                last_modified_at: timestamp,
+                last_modified_by: user.get_email(),
+            };
+
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_rule_last_modified_at(conn, &rule.rule_name)
+                })?;
+                let inserted_rule = diesel::insert_into(validation_rules::table)
+                    .values(&rule)
+                    .get_result::<ValidationRule>(transaction_conn)?;
+                validate_rules_on_config(
+                    transaction_conn,
+                    std::slice::from_ref(&inserted_rule),
+                )?;
+                Ok(inserted_rule)
+            })
+        })
+        .await?;
+    Ok(HttpResponse::Ok().json(inserted_rule))
+}
+
+#[patch("/{rule_name}")]
+async fn update_rule(
+    request: Json<UpdateRuleRequest>,
+    path: Path<ValidationRuleName>,
+    custom_headers: CustomHeaders,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let updated_rule = db_conn
+        .run(move |mut conn| {
+            let rule_name: String = path.into_inner().into();
+            let request = request.into_inner();
+
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_rule_last_modified_at(conn, &rule_name)
+                })?;
+                let existing_rule = dsl::validation_rules
+                    .filter(dsl::rule_name.eq(&rule_name))
+                    .get_result::<ValidationRule>(transaction_conn)
+                    .map_err(|_| {
+                        not_found!("Validation rule {} doesn't exist", rule_name)
+                    })?;
+
+                let rule = request.rule.unwrap_or(existing_rule.rule);
+                if !rule.is_object() {
+                    return Err(bad_argument!("rule should be a json logic object"));
+                }
+                let updated_rule = ValidationRule {
+                    description: request.description.unwrap_or(existing_rule.description),
+                    rule,
+                    last_modified_at: Utc::now().naive_utc(),
+                    last_modified_by: user.get_email(),
+                    ..existing_rule
+                };
+
+                let updated_rule = diesel::update(dsl::validation_rules)
+                    .filter(dsl::rule_name.eq(&rule_name))
+                    .set(&updated_rule)
+                    .get_result::<ValidationRule>(transaction_conn)?;
+                validate_rules_on_config(
+                    transaction_conn,
+                    std::slice::from_ref(&updated_rule),
+                )?;
+                Ok(updated_rule)
+            })
+        })
+        .await?;
+    Ok(HttpResponse::Ok().json(updated_rule))
+}
+
+#[get("/{rule_name}")]
+async fn get_rule(
+    path: Path<ValidationRuleName>,
+    db_conn: ReadDbConnection,
+) -> superposition::Result<HttpResponse> {
+    let rule = db_conn
+        .run(move |mut conn| {
+            let rule_name: String = path.into_inner().into();
+            Ok(dsl::validation_rules
+                .filter(dsl::rule_name.eq(rule_name))
+                .get_result::<ValidationRule>(&mut conn)?)
+        })
+        .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            AppHeader::ETag.to_string(),
+            revision_etag(&rule.last_modified_at),
+        ))
+        .json(rule))
+}
+
+#[delete("/{rule_name}")]
+async fn delete_rule(
+    path: Path<ValidationRuleName>,
+    custom_headers: CustomHeaders,
+    db_conn: DbConnection,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    let rule_name: String = path.into_inner().into();
+    let email = user.get_email();
+    let deleted_name = rule_name.clone();
+    let deleted_row = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                validate_if_match(transaction_conn, &custom_headers.if_match, |conn| {
+                    get_rule_last_modified_at(conn, &deleted_name)
+                })?;
+                diesel::update(dsl::validation_rules)
+                    .filter(dsl::rule_name.eq(&deleted_name))
+                    .set((
+                        dsl::last_modified_at.eq(Utc::now().naive_utc()),
+                        dsl::last_modified_by.eq(email),
+                    ))
+                    .execute(transaction_conn)?;
+                let deleted_row = diesel::delete(
+                    dsl::validation_rules.filter(dsl::rule_name.eq(&deleted_name)),
+                )
+                .execute(transaction_conn)?;
+                Ok(deleted_row)
+            })
+        })
+        .await?;
+    match deleted_row {
+        0 => Err(not_found!("Validation rule {} doesn't exist", rule_name)),
+        _ => {
+            log::info!(
+                "validation rule {rule_name} deleted by {}",
+                user.get_email()
+            );
+            Ok(HttpResponse::NoContent().finish())
+        }
+    }
+}
+
+#[get("")]
+async fn list_rules(
+    db_conn: ReadDbConnection,
+    filters: Query<PaginationParams>,
+) -> superposition::Result<Json<PaginatedResponse<ValidationRule>>> {
+    db_conn
+        .run(move |mut conn| {
+            if let Some(true) = filters.all {
+                let result: Vec<ValidationRule> =
+                    dsl::validation_rules.get_results(&mut conn)?;
+                return Ok(Json(PaginatedResponse {
+                    total_pages: 1,
+                    total_items: result.len() as i64,
+                    data: result,
+                }));
+            };
+
+            let n_rules: i64 = dsl::validation_rules.count().get_result(&mut conn)?;
+            let limit = filters.count.unwrap_or(10);
+            let mut builder = dsl::validation_rules
+                .into_boxed()
+                .order(dsl::created_at.desc())
+                .limit(limit);
+            if let Some(page) = filters.page {
+                let offset = (page - 1) * limit;
+                builder = builder.offset(offset);
+            }
+            let rules: Vec<ValidationRule> = builder.load(&mut conn)?;
+            let total_pages = (n_rules as f64 / limit as f64).ceil() as i64;
+            Ok(Json(PaginatedResponse {
+                total_pages,
+                total_items: n_rules,
+                data: rules,
+            }))
+        })
+        .await
+}
+
+fn get_rule_last_modified_at(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    name: &String,
+) -> diesel::QueryResult<Option<NaiveDateTime>> {
+    dsl::validation_rules
+        .filter(dsl::rule_name.eq(name))
+        .select(dsl::last_modified_at)
+        .get_result::<NaiveDateTime>(conn)
+        .optional()
+}
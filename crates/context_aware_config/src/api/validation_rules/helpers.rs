@@ -0,0 +1,248 @@
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    PgConnection, RunQueryDsl,
+};
+use serde_json::{json, Map, Value};
+use service_utils::helpers::extract_dimensions;
+use superposition_macros::{bad_argument, validation_error};
+use superposition_types::{
+    cac::{models::ValidationRule, schema::validation_rules::dsl},
+    result as superposition, Condition, Config, Context,
+};
+
+use crate::{api::context::hash, helpers::generate_cac};
+
+type DBConnection = PooledConnection<ConnectionManager<PgConnection>>;
+
+fn condition_matches(condition: &Condition, query_data: &Value) -> bool {
+    matches!(
+        jsonlogic::apply(&Value::Object(condition.clone().into()), query_data),
+        Ok(Value::Bool(true))
+    )
+}
+
+/*
+  Resolves the config a client would receive for exactly the dimensions
+  of the given context, i.e. default configs with the overrides of the
+  context and every less specific context applicable to it merged in
+  priority order.
+*/
+fn resolve_config_for_context(
+    config: &Config,
+    target: &Context,
+) -> superposition::Result<Map<String, Value>> {
+    let query_data = Value::Object(extract_dimensions(&target.condition)?);
+    let mut resolved = Value::Object(config.default_configs.clone());
+    for context in config.contexts.iter() {
+        if context.id != target.id && !condition_matches(&context.condition, &query_data)
+        {
+            continue;
+        }
+        for override_key in context.override_with_keys.iter() {
+            if let Some(overrides) = config.overrides.get(override_key) {
+                cac_client::merge(
+                    &mut resolved,
+                    &Value::Object(overrides.clone().into()),
+                );
+            }
+        }
+    }
+    Ok(resolved.as_object().cloned().unwrap_or_default())
+}
+
+pub fn evaluate_rule(
+    rule_name: &str,
+    rule: &Value,
+    resolved_config: &Map<String, Value>,
+) -> superposition::Result<bool> {
+    match jsonlogic::apply(rule, &Value::Object(resolved_config.clone())) {
+        Ok(Value::Bool(result)) => Ok(result),
+        Ok(other) => Err(bad_argument!(
+            "validation rule {} evaluated to {} instead of a boolean",
+            rule_name,
+            other
+        )),
+        Err(err) => {
+            log::error!("failed to evaluate validation rule {rule_name}: {err}");
+            Err(bad_argument!(
+                "failed to evaluate validation rule {}: {}",
+                rule_name,
+                err
+            ))
+        }
+    }
+}
+
+fn validate_rules_on_contexts(
+    config: &Config,
+    contexts: &[&Context],
+    rules: &[ValidationRule],
+) -> superposition::Result<()> {
+    for context in contexts {
+        let resolved_config = resolve_config_for_context(config, context)?;
+        for rule in rules {
+            if !evaluate_rule(&rule.rule_name, &rule.rule, &resolved_config)? {
+                return Err(validation_error!(
+                    "validation rule {} ({}) failed for context {} with condition {}",
+                    rule.rule_name,
+                    rule.description,
+                    context.id,
+                    json!(context.condition)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/*
+  Checks the given rules against the default configs and the resolved
+  config of every existing context, used while creating or updating a rule.
+*/
+pub fn validate_rules_on_config(
+    conn: &mut DBConnection,
+    rules: &[ValidationRule],
+) -> superposition::Result<()> {
+    let config = generate_cac(conn)?;
+    for rule in rules {
+        if !evaluate_rule(&rule.rule_name, &rule.rule, &config.default_configs)? {
+            return Err(validation_error!(
+                "validation rule {} ({}) failed for the default configs",
+                rule.rule_name,
+                rule.description
+            ));
+        }
+    }
+    let contexts = config.contexts.iter().collect::<Vec<_>>();
+    validate_rules_on_contexts(&config, &contexts, rules)
+}
+
+/*
+  Checks all validation rules after a write to the default configs, which
+  every resolved config starts from, so all of them are affected. Has to be
+  called inside the write transaction, after the write.
+*/
+pub fn validate_rules_on_default_configs(
+    conn: &mut DBConnection,
+) -> superposition::Result<()> {
+    let rules: Vec<ValidationRule> = dsl::validation_rules.load(conn)?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    validate_rules_on_config(conn, &rules)
+}
+
+/*
+  Checks all validation rules against the resolved configs of the contexts
+  affected by a write. A context is affected when it is one of the written
+  contexts, or when one of the written conditions applies to it, as its
+  resolved config inherits the written overrides. Has to be called inside
+  the write transaction, after the write, so that the new state is seen.
+*/
+pub fn validate_rules_on_affected_contexts(
+    conn: &mut DBConnection,
+    written_conditions: &[Condition],
+) -> superposition::Result<()> {
+    if written_conditions.is_empty() {
+        return Ok(());
+    }
+    let rules: Vec<ValidationRule> = dsl::validation_rules.load(conn)?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let config = generate_cac(conn)?;
+    let written_ids = written_conditions
+        .iter()
+        .map(|condition| hash(&Value::Object(condition.clone().into())))
+        .collect::<Vec<_>>();
+    let mut affected_contexts = Vec::new();
+    for context in config.contexts.iter() {
+        let query_data = Value::Object(extract_dimensions(&context.condition)?);
+        let is_affected = written_ids.contains(&context.id)
+            || written_conditions
+                .iter()
+                .any(|condition| condition_matches(condition, &query_data));
+        if is_affected {
+            affected_contexts.push(context);
+        }
+    }
+
+    validate_rules_on_contexts(&config, &affected_contexts, &rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> Config {
+        serde_json::from_value(json!({
+            "default_configs": { "min_amount": 10, "max_amount": 100 },
+            "contexts": [
+                {
+                    "id": "ctx-city",
+                    "condition": { "==": [{ "var": "city" }, "Bangalore"] },
+                    "priority": 1,
+                    "override_with_keys": ["ovr-city"]
+                },
+                {
+                    "id": "ctx-city-os",
+                    "condition": {
+                        "and": [
+                            { "==": [{ "var": "city" }, "Bangalore"] },
+                            { "==": [{ "var": "os" }, "android"] }
+                        ]
+                    },
+                    "priority": 3,
+                    "override_with_keys": ["ovr-city-os"]
+                },
+                {
+                    "id": "ctx-os",
+                    "condition": { "==": [{ "var": "os" }, "ios"] },
+                    "priority": 2,
+                    "override_with_keys": ["ovr-os"]
+                }
+            ],
+            "overrides": {
+                "ovr-city": { "max_amount": 50 },
+                "ovr-city-os": { "min_amount": 20 },
+                "ovr-os": { "min_amount": 500 }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_config_for_context() {
+        let config = sample_config();
+
+        let resolved = resolve_config_for_context(&config, &config.contexts[1]).unwrap();
+        assert_eq!(
+            Value::Object(resolved),
+            json!({ "min_amount": 20, "max_amount": 50 })
+        );
+
+        // contexts not applicable to the target are left out
+        let resolved = resolve_config_for_context(&config, &config.contexts[0]).unwrap();
+        assert_eq!(
+            Value::Object(resolved),
+            json!({ "min_amount": 10, "max_amount": 50 })
+        );
+    }
+
+    #[test]
+    fn test_evaluate_rule() {
+        let rule = json!({ "<=": [{ "var": "min_amount" }, { "var": "max_amount" }] });
+        let config = sample_config();
+
+        let resolved = resolve_config_for_context(&config, &config.contexts[2]).unwrap();
+        assert!(!evaluate_rule("amount_range", &rule, &resolved).unwrap());
+        assert!(evaluate_rule("amount_range", &rule, &config.default_configs).unwrap());
+
+        let non_boolean_rule = json!({ "var": "min_amount" });
+        assert!(matches!(
+            evaluate_rule("amount", &non_boolean_rule, &config.default_configs),
+            Err(superposition::AppError::BadArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+use derive_more::{AsRef, Deref, DerefMut, Into};
+use serde::Deserialize;
+use serde_json::Value;
+use superposition_types::RegexEnum;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRuleRequest {
+    pub rule_name: ValidationRuleName,
+    pub description: String,
+    pub rule: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRuleRequest {
+    pub description: Option<String>,
+    pub rule: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, AsRef, Deref, DerefMut, Into)]
+#[serde(try_from = "String")]
+pub struct ValidationRuleName(String);
+impl ValidationRuleName {
+    pub fn validate_data(name: String) -> Result<Self, String> {
+        let name = name.trim();
+        RegexEnum::ValidationRuleName
+            .match_regex(name)
+            .map(|_| Self(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for ValidationRuleName {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::validate_data(value)
+    }
+}
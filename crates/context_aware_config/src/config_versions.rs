@@ -0,0 +1,478 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
+};
+use json_patch::Patch;
+use serde::Deserialize;
+use serde_json::Value;
+use superposition_macros::unexpected_error;
+use superposition_types::{
+    cac::{models::ConfigVersion, schema::config_versions::dsl as config_versions},
+    result as superposition,
+};
+
+/*
+  Config versions are stored in chains, a full checkpoint of the config
+  followed by JSON patches each against the version before it. Every version
+  of a chain holds the id of its checkpoint so that a chain is read in one
+  query, and a new checkpoint is started every `checkpoint_interval`
+  versions to bound the patches replayed to read a version.
+*/
+
+/// Serialises the writers of config versions of the current schema until the
+/// transaction ends.
+pub fn lock_config_versions(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> diesel::QueryResult<()> {
+    diesel::sql_query(
+        "SELECT pg_advisory_xact_lock(hashtext(current_schema() || '.config_versions'))",
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Stores `config` as a patch against the latest version, or as a checkpoint
+/// when the chain of the latest version is full or the patch is not smaller.
+pub fn insert_config_version(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    id: i64,
+    config: Value,
+    tags: Option<Vec<String>>,
+    change_reason: Option<String>,
+    checkpoint_interval: i64,
+) -> superposition::Result<()> {
+    lock_config_versions(conn)?;
+    let serialized_config = config.to_string();
+    let config_hash = blake3::hash(serialized_config.as_bytes()).to_string();
+    let latest_version = config_versions::config_versions
+        .order(config_versions::created_at.desc())
+        .first::<ConfigVersion>(conn)
+        .optional()?;
+
+    let (stored_config, parent_id, checkpoint_id) = match latest_version {
+        Some(latest_version) => {
+            let chain = load_chain(conn, latest_version.checkpoint_id)?;
+            let (stored_config, parent_id) = encode_config_version(
+                &chain,
+                latest_version.id,
+                config,
+                serialized_config.len(),
+                checkpoint_interval,
+            )?;
+            let checkpoint_id = match parent_id {
+                Some(_) => latest_version.checkpoint_id,
+                None => id,
+            };
+            (stored_config, parent_id, checkpoint_id)
+        }
+        None => (config, None, id),
+    };
+
+    diesel::insert_into(config_versions::config_versions)
+        .values(ConfigVersion {
+            id,
+            config: stored_config,
+            config_hash,
+            tags,
+            created_at: Utc::now().naive_utc(),
+            change_reason,
+            parent_id,
+            checkpoint_id,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/*
+  What is stored for a new version of `config`, whose serialization is
+  `config_len` bytes long, after the version `latest_id` of `chain`: a patch
+  against it with its id as the parent, or `config` itself to start a new
+  checkpoint.
+*/
+fn encode_config_version(
+    chain: &HashMap<i64, ConfigVersion>,
+    latest_id: i64,
+    config: Value,
+    config_len: usize,
+    checkpoint_interval: i64,
+) -> superposition::Result<(Value, Option<i64>)> {
+    if chain_depth(chain, latest_id) + 1 >= checkpoint_interval {
+        return Ok((config, None));
+    }
+    let latest_config = replay(chain, latest_id, &mut HashMap::new())?;
+    let delta = serde_json::to_value(json_patch::diff(&latest_config, &config)).map_err(
+        |err| {
+            log::error!("failed to encode config delta: {err}");
+            unexpected_error!("failed to encode config delta")
+        },
+    )?;
+    if delta.to_string().len() < config_len {
+        Ok((delta, Some(latest_id)))
+    } else {
+        Ok((config, None))
+    }
+}
+
+/// The config version `id` with its full config.
+pub fn get_config_version(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    id: i64,
+) -> superposition::Result<ConfigVersion> {
+    let mut version = config_versions::config_versions
+        .filter(config_versions::id.eq(id))
+        .get_result::<ConfigVersion>(conn)?;
+    materialize(conn, std::slice::from_mut(&mut version))?;
+    Ok(version)
+}
+
+/// The latest config version with its full config, if one was created.
+pub fn get_latest_config_version(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<Option<ConfigVersion>> {
+    let mut version = config_versions::config_versions
+        .order(config_versions::created_at.desc())
+        .first::<ConfigVersion>(conn)
+        .optional()?;
+    if let Some(version) = version.as_mut() {
+        materialize(conn, std::slice::from_mut(version))?;
+    }
+    Ok(version)
+}
+
+/// Replaces the patch of every version in `versions` by its full config.
+pub fn materialize(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    versions: &mut [ConfigVersion],
+) -> superposition::Result<()> {
+    materialize_with(versions, |checkpoint_id| load_chain(conn, checkpoint_id))
+}
+
+/// `materialize`, reading each chain through `load_chain` once.
+fn materialize_with(
+    versions: &mut [ConfigVersion],
+    mut load_chain: impl FnMut(i64) -> superposition::Result<HashMap<i64, ConfigVersion>>,
+) -> superposition::Result<()> {
+    let mut chains = HashMap::new();
+    let mut replayed = HashMap::new();
+    for version in versions
+        .iter_mut()
+        .filter(|version| version.parent_id.is_some())
+    {
+        let chain = match chains.entry(version.checkpoint_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(load_chain(version.checkpoint_id)?),
+        };
+        version.config = replay(chain, version.id, &mut replayed)?;
+        version.parent_id = None;
+    }
+    Ok(())
+}
+
+/*
+  Deletes the untagged versions past the newest `max_versions` or created
+  before `cutoff`, the latest version is always kept. Versions which are
+  patches against a deleted one are turned into checkpoints first.
+*/
+pub fn prune_config_versions(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    max_versions: Option<i64>,
+    cutoff: Option<NaiveDateTime>,
+) -> superposition::Result<usize> {
+    if max_versions.is_none() && cutoff.is_none() {
+        return Ok(0);
+    }
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        lock_config_versions(transaction_conn)?;
+        let versions = config_versions::config_versions
+            .select((
+                config_versions::id,
+                config_versions::tags,
+                config_versions::created_at,
+            ))
+            .order(config_versions::created_at.desc())
+            .load::<(i64, Option<Vec<String>>, NaiveDateTime)>(transaction_conn)?;
+        let pruned = select_pruned(versions, max_versions, cutoff);
+        if pruned.is_empty() {
+            return Ok(0);
+        }
+
+        let mut orphans = config_versions::config_versions
+            .filter(config_versions::parent_id.eq_any(&pruned))
+            .filter(config_versions::id.ne_all(&pruned))
+            .load::<ConfigVersion>(transaction_conn)?;
+        materialize(transaction_conn, &mut orphans)?;
+        for orphan in orphans {
+            diesel::update(config_versions::config_versions)
+                .filter(config_versions::id.eq(orphan.id))
+                .set((
+                    config_versions::config.eq(orphan.config),
+                    config_versions::parent_id.eq(None::<i64>),
+                ))
+                .execute(transaction_conn)?;
+        }
+        let deleted = diesel::delete(config_versions::config_versions)
+            .filter(config_versions::id.eq_any(&pruned))
+            .execute(transaction_conn)?;
+        Ok(deleted)
+    })
+}
+
+/// Ids of the versions `prune_config_versions` deletes out of `versions`,
+/// which are ordered newest first.
+fn select_pruned(
+    versions: Vec<(i64, Option<Vec<String>>, NaiveDateTime)>,
+    max_versions: Option<i64>,
+    cutoff: Option<NaiveDateTime>,
+) -> Vec<i64> {
+    versions
+        .into_iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, (_, tags, _))| tags.as_ref().map_or(true, Vec::is_empty))
+        .filter(|(position, (_, _, created_at))| {
+            max_versions.is_some_and(|max| *position as i64 >= max)
+                || cutoff.is_some_and(|cutoff| *created_at < cutoff)
+        })
+        .map(|(_, (id, _, _))| id)
+        .collect()
+}
+
+fn load_chain(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    checkpoint_id: i64,
+) -> superposition::Result<HashMap<i64, ConfigVersion>> {
+    Ok(config_versions::config_versions
+        .filter(config_versions::checkpoint_id.eq(checkpoint_id))
+        .load::<ConfigVersion>(conn)?
+        .into_iter()
+        .map(|version| (version.id, version))
+        .collect())
+}
+
+/// Patches between the checkpoint of the chain and the version `id`.
+fn chain_depth(chain: &HashMap<i64, ConfigVersion>, id: i64) -> i64 {
+    let mut depth = 0;
+    let mut current = chain.get(&id);
+    while let Some(parent_id) = current.and_then(|version| version.parent_id) {
+        depth += 1;
+        current = chain.get(&parent_id);
+    }
+    depth
+}
+
+/*
+  Replays the patches leading to the version `id` onto the checkpoint of its
+  chain. The configs of the versions on the way are kept in `replayed`, and
+  reused by later calls walking through them.
+*/
+fn replay(
+    chain: &HashMap<i64, ConfigVersion>,
+    id: i64,
+    replayed: &mut HashMap<i64, Value>,
+) -> superposition::Result<Value> {
+    let mut patches = Vec::new();
+    let mut current = id;
+    let mut config = loop {
+        if let Some(config) = replayed.get(&current) {
+            break config.clone();
+        }
+        let version = chain.get(&current).ok_or_else(|| {
+            log::error!("config version {current} is missing from the chain of {id}");
+            unexpected_error!("failed to read config version {}", id)
+        })?;
+        match version.parent_id {
+            Some(parent_id) => {
+                patches.push(version);
+                current = parent_id;
+            }
+            None => break version.config.clone(),
+        }
+    };
+    for version in patches.into_iter().rev() {
+        let patch = Patch::deserialize(&version.config).map_err(|err| {
+            log::error!(
+                "failed to decode the delta of config version {}: {err}",
+                version.id
+            );
+            unexpected_error!("failed to read config version {}", id)
+        })?;
+        json_patch::patch(&mut config, &patch).map_err(|err| {
+            log::error!(
+                "failed to apply the delta of config version {}: {err}",
+                version.id
+            );
+            unexpected_error!("failed to read config version {}", id)
+        })?;
+        replayed.insert(version.id, config.clone());
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn version(id: i64, config: Value, parent_id: Option<i64>) -> ConfigVersion {
+        ConfigVersion {
+            id,
+            config,
+            config_hash: String::new(),
+            tags: None,
+            created_at: Utc::now().naive_utc(),
+            change_reason: None,
+            parent_id,
+            checkpoint_id: 1,
+        }
+    }
+
+    #[test]
+    fn test_replay_chain() {
+        let configs = [
+            json!({"default_configs": {"a": 1}, "contexts": [], "overrides": {}}),
+            json!({"default_configs": {"a": 2}, "contexts": [], "overrides": {}}),
+            json!({
+                "default_configs": {"a": 2, "b": [1, 2]},
+                "contexts": [],
+                "overrides": {}
+            }),
+        ];
+        let mut chain = HashMap::new();
+        chain.insert(1, version(1, configs[0].clone(), None));
+        for id in 2..=3 {
+            let index = id as usize - 1;
+            let delta = json_patch::diff(&configs[index - 1], &configs[index]);
+            chain.insert(
+                id,
+                version(id, serde_json::to_value(delta).unwrap(), Some(id - 1)),
+            );
+        }
+
+        let mut replayed = HashMap::new();
+        for (id, config) in (1..=3).zip(configs.iter()) {
+            assert_eq!(
+                replay(&chain, id, &mut replayed).ok().as_ref(),
+                Some(config)
+            );
+        }
+        assert_eq!(chain_depth(&chain, 3), 2);
+        assert_eq!(chain_depth(&chain, 1), 0);
+        assert!(replay(&chain, 4, &mut replayed).is_err());
+    }
+
+    fn config(a: i64) -> Value {
+        json!({
+            "default_configs": {"a": a, "b": "x".repeat(64)},
+            "contexts": [],
+            "overrides": {}
+        })
+    }
+
+    fn encode(
+        chain: &HashMap<i64, ConfigVersion>,
+        latest_id: i64,
+        config: &Value,
+        checkpoint_interval: i64,
+    ) -> (Value, Option<i64>) {
+        let config_len = config.to_string().len();
+        let Ok(encoded) = encode_config_version(
+            chain,
+            latest_id,
+            config.clone(),
+            config_len,
+            checkpoint_interval,
+        ) else {
+            panic!("failed to encode config version");
+        };
+        encoded
+    }
+
+    #[test]
+    fn test_insert_config_version() {
+        let mut chain = HashMap::from([(1, version(1, config(1), None))]);
+        for id in 2..=3 {
+            let (stored, parent_id) = encode(&chain, id - 1, &config(id), 3);
+            assert_eq!(parent_id, Some(id - 1));
+            assert_ne!(stored, config(id));
+            chain.insert(id, version(id, stored, parent_id));
+            assert_eq!(
+                replay(&chain, id, &mut HashMap::new()).ok(),
+                Some(config(id))
+            );
+        }
+
+        // the chain of 3 is full, the next version starts a new checkpoint
+        assert_eq!(encode(&chain, 3, &config(4), 3), (config(4), None));
+        assert_eq!(encode(&chain, 3, &config(4), 4).1, Some(3));
+
+        // a patch which is not smaller than the config itself is not stored
+        let small = json!({"default_configs": {}, "contexts": [], "overrides": {}});
+        assert_eq!(encode(&chain, 3, &small, 10), (small, None));
+    }
+
+    #[test]
+    fn test_prune_config_versions() {
+        let now = Utc::now().naive_utc();
+        let at = |days: i64| now - chrono::Duration::days(days);
+        let tagged = Some(vec![String::from("release")]);
+        let versions = vec![
+            (6, None, at(0)),
+            (5, Some(vec![]), at(1)),
+            (4, tagged.clone(), at(2)),
+            (3, None, at(3)),
+            (2, None, at(4)),
+            (1, None, at(5)),
+        ];
+
+        assert_eq!(
+            select_pruned(versions.clone(), Some(3), None),
+            vec![3, 2, 1]
+        );
+        assert_eq!(
+            select_pruned(versions.clone(), None, Some(at(3))),
+            vec![2, 1]
+        );
+        assert_eq!(
+            select_pruned(versions.clone(), Some(1), None),
+            vec![5, 3, 2, 1]
+        );
+        assert_eq!(
+            select_pruned(versions.clone(), Some(0), Some(at(10))),
+            vec![5, 3, 2, 1]
+        );
+        // the latest version is kept even when it is too old
+        assert_eq!(
+            select_pruned(vec![(7, None, at(30))], Some(0), Some(now)),
+            Vec::<i64>::new()
+        );
+
+        // a version left without its parent becomes a checkpoint of its own
+        let mut chain = HashMap::from([(1, version(1, config(1), None))]);
+        for id in 2..=4 {
+            let (stored, parent_id) = encode(&chain, id - 1, &config(id), 10);
+            chain.insert(id, version(id, stored, parent_id));
+        }
+        let mut orphans = vec![chain[&3].clone()];
+        let mut loads = 0;
+        assert!(materialize_with(&mut orphans, |checkpoint_id| {
+            loads += 1;
+            assert_eq!(checkpoint_id, 1);
+            Ok(chain.clone())
+        })
+        .is_ok());
+        assert_eq!(loads, 1);
+        assert_eq!(orphans[0].config, config(3));
+        assert_eq!(orphans[0].parent_id, None);
+
+        chain.remove(&1);
+        chain.remove(&2);
+        chain.insert(3, orphans.remove(0));
+        assert_eq!(replay(&chain, 4, &mut HashMap::new()).ok(), Some(config(4)));
+        assert_eq!(chain_depth(&chain, 4), 1);
+    }
+}
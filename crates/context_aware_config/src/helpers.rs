@@ -1,41 +1,62 @@
 use std::collections::HashMap;
 
-use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
 use actix_web::web::Data;
-#[cfg(feature = "high-performance-mode")]
+#[cfg(any(feature = "high-performance-mode", feature = "config-cache"))]
 use chrono::DateTime;
-use chrono::Utc;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    sql_types::Text,
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
 };
 #[cfg(feature = "high-performance-mode")]
 use fred::interfaces::KeysInterface;
 use jsonschema::{Draft, JSONSchema, ValidationError};
+use serde::Deserialize;
 use serde_json::{json, Map, Value};
 #[cfg(feature = "high-performance-mode")]
 use service_utils::service::types::Tenant;
+#[cfg(feature = "config-cache")]
 use service_utils::{
-    helpers::{generate_snowflake_id, validation_err_to_str},
-    service::types::AppState,
+    config_cache::{CachedConfig, CONFIG_VERSIONS_CHANNEL},
+    encryption::mask_secrets,
 };
-use superposition_macros::{db_error, unexpected_error, validation_error};
-#[cfg(feature = "high-performance-mode")]
+use service_utils::{
+    helpers::{generate_snowflake_id, parse_etags, validation_err_to_str},
+    metrics::METRICS,
+    service::types::{AppScope, AppState, DbConnection},
+    webhooks::registry::{enabled_webhook_endpoints, enqueue_endpoint_events},
+};
+use superposition_macros::{
+    bad_argument, db_error, response_error, unexpected_error, validation_error,
+};
+#[cfg(any(feature = "high-performance-mode", feature = "config-cache"))]
 use superposition_types::cac::schema::event_log::dsl as event_log;
 use superposition_types::{
-    cac::{
-        models::ConfigVersion,
-        schema::{
-            config_versions,
-            contexts::dsl::{self as ctxt},
-            default_configs::dsl as def_conf,
-        },
+    cac::schema::{
+        config_versions,
+        contexts::dsl::{self as ctxt},
+        default_configs::dsl as def_conf,
+        dimensions::dsl as dims,
     },
-    result as superposition, Cac, Condition, Config, Context, Overrides,
+    result as superposition,
+    webhook::{ConfigWebhook, WebhookEvent},
+    Cac, Condition, Config, Context, Overrides, TenantConfig,
 };
-#[cfg(feature = "high-performance-mode")]
+#[cfg(any(feature = "high-performance-mode", feature = "config-cache"))]
 use uuid::Uuid;
 
+#[cfg(feature = "config-cache")]
+use crate::config_versions::get_latest_config_version;
+use crate::config_versions::{
+    insert_config_version, lock_config_versions, prune_config_versions,
+};
+
 pub fn parse_headermap_safe(headermap: &HeaderMap) -> HashMap<String, String> {
     let mut req_headers = HashMap::new();
     let record_header = |(header_name, header_val): (&HeaderName, &HeaderValue)| {
@@ -195,6 +216,84 @@ pub fn calculate_context_priority(
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct MetadataReq {
+    pub description: Option<String>,
+    pub owner: Option<String>,
+    pub labels: Option<Vec<String>>,
+    pub deprecated: Option<bool>,
+    pub sunset_date: Option<NaiveDate>,
+}
+
+impl MetadataReq {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.owner.is_none()
+            && self.labels.is_none()
+            && self.deprecated.is_none()
+            && self.sunset_date.is_none()
+    }
+}
+
+pub struct EntityMetadata {
+    pub description: String,
+    pub owner: Option<String>,
+    pub labels: Vec<String>,
+    pub deprecated: bool,
+    pub sunset_date: Option<NaiveDate>,
+}
+
+/*
+  Fields missing in the request are carried over from the existing
+  entity, an empty owner clears it. A sunset date is only allowed on
+  deprecated entities and is cleared when an entity is un-deprecated.
+*/
+pub fn resolve_entity_metadata(
+    req: MetadataReq,
+    existing: Option<EntityMetadata>,
+) -> superposition::Result<EntityMetadata> {
+    let existing = existing.unwrap_or(EntityMetadata {
+        description: String::new(),
+        owner: None,
+        labels: Vec::new(),
+        deprecated: false,
+        sunset_date: None,
+    });
+    let deprecated = req.deprecated.unwrap_or(existing.deprecated);
+    let sunset_date = match (deprecated, req.sunset_date) {
+        (false, Some(_)) => {
+            return Err(bad_argument!(
+                "sunset_date can only be set when deprecated is true"
+            ))
+        }
+        (false, None) => None,
+        (true, sunset_date) => sunset_date.or(existing.sunset_date),
+    };
+    let owner = match req.owner {
+        Some(owner) if owner.trim().is_empty() => None,
+        Some(owner) => Some(owner.trim().to_string()),
+        None => existing.owner,
+    };
+    let labels = req
+        .labels
+        .map(|labels| {
+            labels
+                .into_iter()
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty())
+                .collect()
+        })
+        .unwrap_or(existing.labels);
+
+    Ok(EntityMetadata {
+        description: req.description.unwrap_or(existing.description),
+        owner,
+        labels,
+        deprecated,
+        sunset_date,
+    })
+}
+
 pub fn generate_cac(
     conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<Config> {
@@ -206,6 +305,8 @@ pub fn generate_cac(
             ctxt::override_id,
             ctxt::override_,
         ))
+        .filter(ctxt::deleted_at.is_null())
+        .filter(ctxt::enabled.eq(true))
         .order_by((ctxt::priority.asc(), ctxt::created_at.asc()))
         .load::<(String, Condition, i32, String, Overrides)>(conn)
         .map_err(|err| {
@@ -241,6 +342,7 @@ pub fn generate_cac(
     }
 
     let default_config_vec = def_conf::default_configs
+        .filter(def_conf::deleted_at.is_null())
         .select((def_conf::key, def_conf::value))
         .load::<(String, Value)>(conn)
         .map_err(|err| {
@@ -263,41 +365,366 @@ pub fn generate_cac(
     })
 }
 
+/// Notifies every replica that the config of the schema of `db_conn` changed,
+/// dropping its cached config and deprecations. Delivered when the
+/// transaction commits, with the schema of the tenant.
+#[cfg(feature = "config-cache")]
+pub fn notify_config_change(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> diesel::QueryResult<()> {
+    diesel::sql_query(format!(
+        "SELECT pg_notify('{CONFIG_VERSIONS_CHANNEL}', current_schema())"
+    ))
+    .execute(db_conn)?;
+    Ok(())
+}
+
 pub fn add_config_version(
     state: &Data<AppState>,
     tags: Option<Vec<String>>,
+    change_reason: Option<String>,
     db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<i64> {
-    use config_versions::dsl::config_versions;
     let version_id = generate_snowflake_id(state)?;
     let config = generate_cac(db_conn)?;
-    let json_config = json!(config);
-    let config_hash = blake3::hash(json_config.to_string().as_bytes()).to_string();
-    let config_version = ConfigVersion {
-        id: version_id,
-        config: json_config,
-        config_hash,
+    insert_config_version(
+        db_conn,
+        version_id,
+        json!(config),
         tags,
-        created_at: Utc::now().naive_utc(),
-    };
-    diesel::insert_into(config_versions)
-        .values(&config_version)
-        .execute(db_conn)?;
+        change_reason,
+        state.config_version_checkpoint_interval,
+    )?;
+    #[cfg(feature = "config-cache")]
+    notify_config_change(db_conn)?;
+    METRICS.config_versions_created.inc();
     Ok(version_id)
 }
 
+/// A change made by a write, delivered to the config webhooks of the tenant
+/// once the write is committed.
+pub struct ConfigChange {
+    pub event: WebhookEvent,
+    /// Config keys the change touched.
+    pub keys: Vec<String>,
+    /// Condition of the context the change was made to, if any.
+    pub context: Option<Condition>,
+    pub payload: Value,
+}
+
+/*
+  Queues every change of a write and then ConfigVersionCreated, with all the
+  keys they touched, for the config webhooks and the endpoints of the
+  registry subscribed to them. This runs in the transaction of the write, so
+  the events are only sent once it is committed.
+*/
+pub fn enqueue_config_webhooks(
+    conn: &mut PgConnection,
+    webhooks: &[ConfigWebhook],
+    config_version: Option<i64>,
+    mut changes: Vec<ConfigChange>,
+) -> superposition::Result<()> {
+    let endpoints = enabled_webhook_endpoints(conn)?;
+    if webhooks.is_empty() && endpoints.is_empty() {
+        return Ok(());
+    }
+    if config_version.is_some() {
+        let mut keys = changes
+            .iter()
+            .flat_map(|change| change.keys.iter().cloned())
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        changes.push(ConfigChange {
+            event: WebhookEvent::ConfigVersionCreated,
+            payload: json!({ "keys": keys }),
+            keys,
+            context: None,
+        });
+    }
+    let config_version = config_version.map(|version| version.to_string());
+    for change in changes.iter() {
+        enqueue_endpoint_events(
+            conn,
+            webhooks,
+            &endpoints,
+            change.event,
+            &change.keys,
+            change.context.as_ref(),
+            &change.payload,
+            &config_version,
+        )?;
+    }
+    Ok(())
+}
+
+/*
+  A change reason in the request body takes precedence over the
+  x-change-reason header, tenants can mandate that one of them is sent.
+*/
+pub fn resolve_change_reason(
+    tenant_config: &TenantConfig,
+    header_reason: Option<String>,
+    body_reason: Option<String>,
+) -> superposition::Result<Option<String>> {
+    let change_reason = body_reason
+        .or(header_reason)
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if change_reason.is_none() && tenant_config.change_reason_required {
+        return Err(bad_argument!(
+            "A change reason is required, set the x-change-reason header or change_reason in the request body"
+        ));
+    }
+    Ok(change_reason)
+}
+
+/*
+  The audit trigger reads the change reason from this setting, it is scoped
+  to the current transaction so it has to be set before any writes. It is
+  set even without a reason, as an empty setting records none, so that a
+  reason set earlier in the transaction does not carry over.
+*/
+pub fn set_change_reason(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    change_reason: &Option<String>,
+) -> diesel::QueryResult<()> {
+    diesel::sql_query("SELECT set_config('superposition.change_reason', $1, true)")
+        .bind::<Text, _>(change_reason.as_deref().unwrap_or_default())
+        .execute(db_conn)?;
+    Ok(())
+}
+
+/*
+  Writes guarded by If-Match go through only when the header holds the
+  latest config version or the current last_modified_at of the entity being
+  written, or `*` when that entity exists. Guarded writers are serialised on
+  an advisory lock so that two of them can't pass the check against the same
+  state, which is why the entity revision is fetched only after the lock is
+  taken.
+*/
+pub fn validate_if_match<F>(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    if_match: &Option<String>,
+    get_last_modified_at: F,
+) -> superposition::Result<()>
+where
+    F: FnOnce(
+        &mut PooledConnection<ConnectionManager<PgConnection>>,
+    ) -> diesel::QueryResult<Option<NaiveDateTime>>,
+{
+    check_if_match(db_conn, if_match, |conn| {
+        let last_modified_at = get_last_modified_at(conn)?;
+        Ok((last_modified_at.is_some(), last_modified_at))
+    })
+}
+
+/*
+  Writes spanning many entities (change sets, bulk context operations) can
+  only be guarded by the config version; `*` always holds for them as the
+  config itself always exists.
+*/
+pub fn validate_collection_if_match(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    if_match: &Option<String>,
+) -> superposition::Result<()> {
+    check_if_match(db_conn, if_match, |_| Ok((true, None)))
+}
+
+fn check_if_match<F>(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    if_match: &Option<String>,
+    get_revision: F,
+) -> superposition::Result<()>
+where
+    F: FnOnce(
+        &mut PooledConnection<ConnectionManager<PgConnection>>,
+    ) -> diesel::QueryResult<(bool, Option<NaiveDateTime>)>,
+{
+    let if_match = match if_match {
+        Some(if_match) => if_match,
+        None => return Ok(()),
+    };
+    lock_config_versions(db_conn)?;
+    let latest_version = config_versions::table
+        .select((config_versions::id, config_versions::config_hash))
+        .order(config_versions::created_at.desc())
+        .first::<(i64, String)>(db_conn)
+        .optional()?;
+    let (exists, last_modified_at) = get_revision(db_conn)?;
+
+    if if_match_holds(
+        &parse_etags(if_match),
+        exists,
+        latest_version.as_ref(),
+        last_modified_at,
+    ) {
+        Ok(())
+    } else {
+        log::info!(
+            "stale If-Match {if_match}, latest config version is {latest_version:?}"
+        );
+        Err(response_error!(
+            StatusCode::PRECONDITION_FAILED,
+            "The resource has been modified since it was read, fetch it again and retry"
+        ))
+    }
+}
+
+/*
+  The latest config version is matched by its id or by its hash, which
+  `/config` serves as its ETag, and an entity by the revision its GET serves
+  as the ETag.
+*/
+fn if_match_holds(
+    tags: &[String],
+    exists: bool,
+    latest_version: Option<&(i64, String)>,
+    last_modified_at: Option<NaiveDateTime>,
+) -> bool {
+    tags.iter().any(|tag| {
+        (tag == "*" && exists)
+            || latest_version.is_some_and(|(version, config_hash)| {
+                version.to_string() == *tag || config_hash == tag
+            })
+            || last_modified_at.is_some_and(|modified_at| {
+                NaiveDateTime::parse_from_str(tag, REVISION_FORMAT)
+                    .is_ok_and(|revision| revision == modified_at)
+            })
+    })
+}
+
+const REVISION_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// ETag of an entity last modified at `last_modified_at`, the revision
+/// If-Match is checked against when writing it.
+pub fn revision_etag(last_modified_at: &NaiveDateTime) -> String {
+    format!("\"{}\"", last_modified_at.format(REVISION_FORMAT))
+}
+
+/*
+  Soft deleted contexts, default configs and dimensions can be restored
+  as long as they were deleted after this cutoff.
+*/
+pub fn soft_delete_cutoff(state: &AppState) -> NaiveDateTime {
+    Utc::now().naive_utc() - Duration::days(state.soft_delete_retention_days)
+}
+
+/// Untagged config versions created before this cutoff are pruned, if set.
+pub fn config_version_cutoff(state: &AppState) -> Option<NaiveDateTime> {
+    state
+        .config_version_retention_days
+        .map(|days| Utc::now().naive_utc() - Duration::days(days))
+}
+
+pub fn purge_deleted_entities(
+    conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+    cutoff: NaiveDateTime,
+) -> superposition::Result<usize> {
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        let purged_contexts =
+            diesel::delete(ctxt::contexts.filter(ctxt::deleted_at.lt(cutoff)))
+                .execute(transaction_conn)?;
+        let purged_default_configs = diesel::delete(
+            def_conf::default_configs.filter(def_conf::deleted_at.lt(cutoff)),
+        )
+        .execute(transaction_conn)?;
+        let purged_dimensions =
+            diesel::delete(dims::dimensions.filter(dims::deleted_at.lt(cutoff)))
+                .execute(transaction_conn)?;
+        Ok(purged_contexts + purged_default_configs + purged_dimensions)
+    })
+}
+
+/*
+  Periodically purges the soft deleted entities and prunes the config
+  versions which are past their retention, for every tenant.
+*/
+pub async fn run_purge_job(state: Data<AppState>, period: std::time::Duration) {
+    let mut interval = actix_web::rt::time::interval(period);
+    loop {
+        interval.tick().await;
+        let namespaces = if state.enable_tenant_and_scope {
+            state
+                .tenants
+                .read()
+                .map(|tenants| {
+                    tenants
+                        .iter()
+                        .map(|tenant| format!("{}_{}", tenant, AppScope::CAC))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        } else {
+            vec![String::from("cac_v1")]
+        };
+        let cutoff = soft_delete_cutoff(&state);
+        let max_versions = state.config_version_retention_count;
+        let version_cutoff = config_version_cutoff(&state);
+        for namespace in namespaces.iter() {
+            let purged = async {
+                DbConnection::checkout(state.db_pool.clone(), namespace.clone())
+                    .await?
+                    .run(move |mut conn| {
+                        let purged = purge_deleted_entities(&mut conn, cutoff)?;
+                        let pruned = prune_config_versions(
+                            &mut conn,
+                            max_versions,
+                            version_cutoff,
+                        )?;
+                        Ok((purged, pruned))
+                    })
+                    .await
+            }
+            .await;
+            match purged {
+                Ok((purged, pruned)) => {
+                    if purged > 0 {
+                        log::info!("purged {purged} deleted entities from {namespace}")
+                    }
+                    if pruned > 0 {
+                        log::info!("pruned {pruned} config versions from {namespace}")
+                    }
+                }
+                Err(e) => log::error!("failed to purge {namespace}: {e}"),
+            }
+        }
+    }
+}
+
+/*
+  Runs after the connection of the write is returned to the pool, so it
+  checks out one of its own to read the config to store.
+*/
 #[cfg(feature = "high-performance-mode")]
 pub async fn put_config_in_redis(
     version_id: i64,
     state: Data<AppState>,
     tenant: Tenant,
-    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
 ) -> superposition::Result<()> {
-    let raw_config = generate_cac(db_conn)?;
-    let parsed_config = serde_json::to_string(&json!(raw_config)).map_err(|e| {
-        log::error!("failed to convert cac config to string: {}", e);
-        unexpected_error!("could not convert cac config to string")
-    })?;
+    let namespace = if state.enable_tenant_and_scope {
+        format!("{}_{}", *tenant, AppScope::CAC)
+    } else {
+        String::from("cac_v1")
+    };
+    let db_conn = DbConnection::checkout(state.db_pool.clone(), namespace).await?;
+    let (parsed_config, audit_id) = db_conn
+        .run(|mut db_conn| {
+            let raw_config = generate_cac(&mut db_conn)?;
+            let parsed_config =
+                serde_json::to_string(&json!(raw_config)).map_err(|e| {
+                    log::error!("failed to convert cac config to string: {}", e);
+                    unexpected_error!("could not convert cac config to string")
+                })?;
+            let audit_id = event_log::event_log
+                .select(event_log::id)
+                .filter(event_log::table_name.eq("contexts"))
+                .order_by(event_log::timestamp.desc())
+                .first::<Uuid>(&mut db_conn)
+                .ok();
+            Ok((parsed_config, audit_id))
+        })
+        .await?;
     let config_key = format!("{}::cac_config", *tenant);
     let last_modified_at_key = format!("{}::cac_config::last_modified_at", *tenant);
     let audit_id_key = format!("{}::cac_config::audit_id", *tenant);
@@ -311,12 +738,7 @@ pub async fn put_config_in_redis(
         .redis
         .set::<(), String, String>(last_modified_at_key, last_modified, None, None, false)
         .await;
-    if let Ok(uuid) = event_log::event_log
-        .select(event_log::id)
-        .filter(event_log::table_name.eq("contexts"))
-        .order_by(event_log::timestamp.desc())
-        .first::<Uuid>(db_conn)
-    {
+    if let Some(uuid) = audit_id {
         let _ = state
             .redis
             .set::<(), String, String>(audit_id_key, uuid.to_string(), None, None, false)
@@ -329,6 +751,38 @@ pub async fn put_config_in_redis(
     Ok(())
 }
 
+/*
+  Reads the config of the latest config version, or generates it when no
+  version was created yet, to be cached with secrets masked.
+*/
+#[cfg(feature = "config-cache")]
+pub fn load_cached_config(
+    db_conn: &mut PooledConnection<ConnectionManager<PgConnection>>,
+) -> superposition::Result<CachedConfig> {
+    let latest_version = get_latest_config_version(db_conn)?;
+    let (mut config, version, last_modified) = match latest_version {
+        Some(version) => (
+            version.config,
+            Some(version.id),
+            version.created_at.and_utc(),
+        ),
+        None => (json!(generate_cac(db_conn)?), None, Utc::now()),
+    };
+    mask_secrets(&mut config);
+    let audit_id = event_log::event_log
+        .select(event_log::id)
+        .filter(event_log::table_name.eq("contexts"))
+        .order_by(event_log::timestamp.desc())
+        .first::<Uuid>(db_conn)
+        .optional()?;
+    Ok(CachedConfig {
+        config: config.to_string(),
+        version,
+        last_modified: DateTime::to_rfc2822(&last_modified),
+        audit_id: audit_id.map(|uuid| uuid.to_string()),
+    })
+}
+
 // ************ Tests *************
 
 #[cfg(test)]
@@ -388,4 +842,51 @@ mod tests {
         assert!(err_arr_context);
         assert!(ok_arr_context.is_ok());
     }
+    #[test]
+    fn test_parse_etags() {
+        assert_eq!(parse_etags("\"abc\""), vec!["abc".to_string()]);
+        assert_eq!(
+            parse_etags("W/\"abc\", \"7231337433548156928\""),
+            vec!["abc".to_string(), "7231337433548156928".to_string()]
+        );
+        assert_eq!(parse_etags("*"), vec!["*".to_string()]);
+        assert!(parse_etags(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_if_match_holds() {
+        let tags = |header: &str| parse_etags(header);
+        let modified_at = NaiveDateTime::parse_from_str(
+            "2024-05-01T10:00:00.123456",
+            "%Y-%m-%dT%H:%M:%S%.f",
+        )
+        .unwrap();
+
+        let latest = (7, String::from("c0ffee"));
+        let latest = Some(&latest);
+
+        assert!(if_match_holds(&tags("*"), true, None, None));
+        assert!(!if_match_holds(&tags("*"), false, latest, None));
+        assert!(if_match_holds(&tags("\"1\", \"7\""), false, latest, None));
+        assert!(if_match_holds(&tags("W/\"c0ffee\""), false, latest, None));
+        assert!(!if_match_holds(&tags("\"decaf\""), false, latest, None));
+        assert!(!if_match_holds(
+            &tags("\"6\""),
+            true,
+            latest,
+            Some(modified_at)
+        ));
+        assert!(if_match_holds(
+            &tags(&revision_etag(&modified_at)),
+            true,
+            latest,
+            Some(modified_at)
+        ));
+        assert!(!if_match_holds(
+            &tags("\"2024-05-01T10:00:00\""),
+            true,
+            latest,
+            Some(modified_at)
+        ));
+    }
 }
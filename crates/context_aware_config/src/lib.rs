@@ -1,5 +1,12 @@
 #![deny(unused_crate_dependencies)]
 pub mod api;
+pub mod config_versions;
 pub mod helpers;
 pub mod middlewares;
 pub mod validation_functions;
+
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+
+/// Migrations of this crate, embedded so that schemas of tenants created at
+/// runtime can be migrated without the diesel cli.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
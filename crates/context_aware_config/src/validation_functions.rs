@@ -1,6 +1,11 @@
 use serde_json::Value;
+use service_utils::{
+    metrics::{outcome_label, METRICS},
+    telemetry::{in_span, KeyValue},
+};
 use std::process::Command;
 use std::str;
+use std::time::Instant;
 use superposition_macros::{unexpected_error, validation_error};
 use superposition_types::result as superposition;
 
@@ -118,12 +123,19 @@ pub fn execute_fn(
     value: Value,
 ) -> Result<String, (String, Option<String>)> {
     let exec_code = execute_validate_fun(code_str, format!(r#""{key}""#), value);
-    let output = Command::new("node")
-        .arg("-e")
-        .arg(generate_code(&exec_code))
-        .output();
+    let started_at = Instant::now();
+    let output = in_span(
+        "execute validation function",
+        vec![KeyValue::new("config.key", key.to_string())],
+        || {
+            Command::new("node")
+                .arg("-e")
+                .arg(generate_code(&exec_code))
+                .output()
+        },
+    );
     log::trace!("{}", format!("validation function output : {:?}", output));
-    match output {
+    let result = match output {
         Ok(val) => {
             let stdout = str::from_utf8(&val.stdout)
                 .unwrap_or("[Invalid UTF-8 in stdout]")
@@ -145,15 +157,22 @@ pub fn execute_fn(
             log::error!("js_eval error: {}", e);
             Err((format!("js_eval error: {}", e), None))
         }
-    }
+    };
+    METRICS
+        .validation_function_duration
+        .with_label_values(&[outcome_label(&result)])
+        .observe(started_at.elapsed().as_secs_f64());
+    result
 }
 
 pub fn compile_fn(code_str: &str) -> superposition::Result<()> {
     let type_check_code = type_check_validate(code_str);
-    let output = Command::new("node")
-        .arg("-e")
-        .arg(generate_code(&type_check_code))
-        .output();
+    let output = in_span("compile validation function", Vec::new(), || {
+        Command::new("node")
+            .arg("-e")
+            .arg(generate_code(&type_check_code))
+            .output()
+    });
 
     log::trace!("{}", format!("validation function output : {:?}", output));
     match output {
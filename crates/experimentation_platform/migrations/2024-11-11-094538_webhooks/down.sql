@@ -0,0 +1,17 @@
+-- This file should undo anything in `up.sql`
+
+-- The context_aware_config migrations make the same changes when both run in
+-- the same schema, so they are only undone once neither needs them.
+DO $$
+BEGIN
+    IF to_regclass('public.__diesel_schema_migrations') IS NOT NULL THEN
+        IF EXISTS (
+            SELECT 1 FROM public.__diesel_schema_migrations WHERE version = '20241111094512'
+        ) THEN
+            RETURN;
+        END IF;
+    END IF;
+    ALTER TABLE public.webhook_events DROP COLUMN IF EXISTS payload_template;
+    ALTER TABLE public.webhook_events DROP COLUMN IF EXISTS endpoint;
+    DROP TABLE IF EXISTS public.webhooks;
+END $$;
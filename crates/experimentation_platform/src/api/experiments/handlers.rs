@@ -10,18 +10,27 @@ use anyhow::anyhow;
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
-    ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl,
 };
 use reqwest::{Method, Response, StatusCode};
 use serde_json::{json, Map, Value};
-use service_utils::helpers::{
-    construct_request_headers, execute_webhook_call, generate_snowflake_id, request,
+use service_utils::encryption::{mask_secrets, TenantCipher};
+use service_utils::helpers::{construct_request_headers, generate_snowflake_id, request};
+use service_utils::middlewares::{
+    authorization::authorize, idempotency::derive_idempotency_key,
 };
 use service_utils::service::types::{
-    AppHeader, AppState, CustomHeaders, DbConnection, Tenant,
+    AppHeader, AppState, CustomHeaders, DbConnection, ReadDbConnection, Tenant,
 };
-use superposition_macros::{bad_argument, response_error, unexpected_error};
+use service_utils::telemetry::send_traced;
+use service_utils::webhooks::{
+    enqueue_webhook_event,
+    registry::{enabled_webhook_endpoints, enqueue_endpoint_events},
+};
+use superposition_macros::{bad_argument, forbidden, response_error, unexpected_error};
 use superposition_types::{
+    authorization::{Action, KeyAccess},
     custom_query::PaginationParams,
     experimentation::{
         models::{EventLog, Experiment, ExperimentStatusType, Variant, Variants},
@@ -35,8 +44,11 @@ use superposition_types::{
 use super::{
     helpers::{
         add_variant_dimension_to_ctx, check_variant_types,
-        check_variants_override_coverage, decide_variant, extract_override_keys,
-        fetch_cac_config, validate_experiment, validate_override_keys,
+        check_variants_override_coverage, decide_variant, encrypt_variant_secrets,
+        experiment_etag, extract_override_keys, fetch_cac_config, get_secret_keys,
+        lock_experiment_if_match, mask_experiment_secrets, reveal_experiment_secrets,
+        validate_active_experiments_quota, validate_experiment, validate_if_match,
+        validate_override_keys,
     },
     types::{
         ApplicableVariantsQuery, AuditQueryFilters, ConcludeExperimentRequest,
@@ -69,6 +81,32 @@ fn add_config_version_to_header(
     }
 }
 
+/// Queues `event` for the experiments webhook of the tenant, when it is
+/// enabled, and for the endpoints of the registry subscribed to it.
+fn enqueue_experiment_event(
+    conn: &mut PgConnection,
+    webhook_config: &WebhookConfig,
+    event: WebhookEvent,
+    experiment: &Experiment,
+    config_version: &Option<String>,
+) -> superposition::Result<()> {
+    let experiment = &mask_experiment_secrets(experiment.clone());
+    if let WebhookConfig::Enabled(webhook) = webhook_config {
+        enqueue_webhook_event(conn, webhook, event, experiment, config_version)?;
+    }
+    let endpoints = enabled_webhook_endpoints(conn)?;
+    enqueue_endpoint_events(
+        conn,
+        &[],
+        &endpoints,
+        event,
+        &experiment.override_keys,
+        Some(&experiment.context),
+        experiment,
+        config_version,
+    )
+}
+
 async fn parse_error_response(
     response: reqwest::Response,
 ) -> superposition::Result<(StatusCode, superposition::ErrorResponse)> {
@@ -130,10 +168,11 @@ async fn create(
     tenant: Tenant,
     user: User,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
     use superposition_types::experimentation::schema::experiments::dsl::experiments;
     let mut variants = req.variants.to_vec();
-    let DbConnection(mut conn) = db_conn;
 
     // Checking if experiment has exactly 1 control variant, and
     // atleast 1 experimental variant
@@ -142,6 +181,9 @@ async fn create(
         extract_override_keys(&variants[0].overrides.clone().into_inner())
             .into_iter()
             .collect();
+    key_access
+        .validate_keys(&unique_override_keys)
+        .map_err(|err| forbidden!(err))?;
 
     let unique_ids_of_variants_from_req: HashSet<&str> =
         HashSet::from_iter(variants.iter().map(|v| v.id.as_str()));
@@ -166,6 +208,9 @@ async fn create(
             )
         );
     }
+    let secret_keys =
+        get_secret_keys(&state, &tenant, unique_override_keys.clone()).await?;
+    encrypt_variant_secrets(&cipher, &secret_keys, &mut variants, &HashMap::new())?;
 
     // validating context
     let exp_context = Exp::<Condition>::try_from(req.context.clone())
@@ -176,12 +221,29 @@ async fn create(
         .into_inner();
 
     // validating experiment against other active experiments based on permission flags
-    let flags = &state.experimentation_flags;
-    let (valid, reason) =
-        validate_experiment(&exp_context, &unique_override_keys, None, flags, &mut conn)?;
-    if !valid {
-        return Err(bad_argument!(reason));
-    }
+    let db_conn = db_conn
+        .run({
+            let state = state.clone();
+            let exp_context = exp_context.clone();
+            let unique_override_keys = unique_override_keys.clone();
+            move |mut conn| {
+                validate_active_experiments_quota(&tenant_config.quotas, &mut conn)?;
+
+                let flags = &state.experimentation_flags;
+                let (valid, reason) = validate_experiment(
+                    &exp_context,
+                    &unique_override_keys,
+                    None,
+                    flags,
+                    &mut conn,
+                )?;
+                if !valid {
+                    return Err(bad_argument!(reason));
+                }
+                Ok(DbConnection(conn))
+            }
+        })
+        .await?;
 
     // generating snowflake id for experiment
     let experiment_id = generate_snowflake_id(&state)?;
@@ -222,9 +284,14 @@ async fn create(
             err
         )
     })?;
+    let idempotency_key = custom_headers.idempotency_key.map(|key| {
+        derive_idempotency_key(&key, &format!("experiments/{experiment_id}/create"))
+    });
     let extra_headers = vec![
         ("x-user", Some(user_str)),
         ("x-config-tags", custom_headers.config_tags),
+        ("x-change-reason", custom_headers.change_reason),
+        ("idempotency-key", idempotency_key),
     ]
     .into_iter()
     .filter_map(|(key, val)| val.map(|v| (key, v)))
@@ -233,16 +300,18 @@ async fn create(
     let headers_map = construct_header_map(tenant.as_str(), extra_headers)?;
 
     // Step 1: Perform the HTTP request and handle errors
-    let response = http_client
-        .put(&url)
-        .headers(headers_map.into())
-        .header(
-            header::AUTHORIZATION,
-            format!("Internal {}", state.superposition_token),
-        )
-        .json(&cac_operations)
-        .send()
-        .await;
+    let response = send_traced(
+        "cac bulk operations",
+        http_client
+            .put(&url)
+            .headers(headers_map.into())
+            .header(
+                header::AUTHORIZATION,
+                format!("Internal {}", state.superposition_token),
+            )
+            .json(&cac_operations),
+    )
+    .await;
 
     // directly return an error response if not a 200 response
     let (resp_contexts, config_version_id) = process_cac_http_response(response).await?;
@@ -279,25 +348,29 @@ async fn create(
         chosen_variant: None,
     };
 
-    let mut inserted_experiments = diesel::insert_into(experiments)
-        .values(&new_experiment)
-        .get_results(&mut conn)?;
-
-    let inserted_experiment: Experiment = inserted_experiments.remove(0);
-    let response = ExperimentCreateResponse::from(inserted_experiment.clone());
-    if let WebhookConfig::Enabled(experiments_webhook_config) =
-        tenant_config.experiments_webhook_config
-    {
-        execute_webhook_call(
-            &experiments_webhook_config,
-            &inserted_experiment,
-            &config_version_id,
-            &tenant,
-            WebhookEvent::ExperimentCreated,
-            &state.http_client,
-        )
+    let inserted_experiment = db_conn
+        .run({
+            let config_version_id = config_version_id.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    let mut inserted_experiments = diesel::insert_into(experiments)
+                        .values(&new_experiment)
+                        .get_results::<Experiment>(transaction_conn)?;
+                    let inserted_experiment = inserted_experiments.remove(0);
+                    enqueue_experiment_event(
+                        transaction_conn,
+                        &tenant_config.experiments_webhook_config,
+                        WebhookEvent::ExperimentCreated,
+                        &inserted_experiment,
+                        &config_version_id,
+                    )?;
+                    Ok(inserted_experiment)
+                })
+            }
+        })
         .await?;
-    }
+
+    let response = ExperimentCreateResponse::from(inserted_experiment);
     let mut http_resp = HttpResponse::Ok();
     add_config_version_to_header(&config_version_id, &mut http_resp);
     Ok(http_resp.json(response))
@@ -313,33 +386,21 @@ async fn conclude_handler(
     tenant: Tenant,
     tenant_config: TenantConfig,
     user: User,
+    key_access: KeyAccess,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(conn) = db_conn;
     let (response, config_version_id) = conclude(
         &state,
         path.into_inner(),
-        custom_headers.config_tags,
+        custom_headers,
         req.into_inner(),
-        conn,
-        tenant.clone(),
+        db_conn,
+        tenant,
+        tenant_config.experiments_webhook_config,
         user,
+        &key_access,
     )
     .await?;
 
-    if let WebhookConfig::Enabled(experiments_webhook_config) =
-        tenant_config.experiments_webhook_config
-    {
-        execute_webhook_call(
-            &experiments_webhook_config,
-            &response,
-            &config_version_id,
-            &tenant,
-            WebhookEvent::ExperimentConcluded,
-            &state.http_client,
-        )
-        .await?;
-    }
-
     let mut http_resp = HttpResponse::Ok();
     add_config_version_to_header(&config_version_id, &mut http_resp);
     Ok(http_resp.json(ExperimentResponse::from(response)))
@@ -348,19 +409,41 @@ async fn conclude_handler(
 pub async fn conclude(
     state: &Data<AppState>,
     experiment_id: i64,
-    config_tags: Option<String>,
+    custom_headers: CustomHeaders,
     req: ConcludeExperimentRequest,
-    mut conn: PooledConnection<ConnectionManager<PgConnection>>,
+    db_conn: DbConnection,
     tenant: Tenant,
+    experiments_webhook_config: WebhookConfig,
     user: User,
+    key_access: &KeyAccess,
 ) -> superposition::Result<(Experiment, Option<String>)> {
     use superposition_types::experimentation::schema::experiments::dsl;
 
     let winner_variant_id: String = req.chosen_variant.to_owned();
-
-    let experiment: Experiment = dsl::experiments
-        .find(experiment_id)
-        .get_result::<Experiment>(&mut conn)?;
+    let if_match = custom_headers.if_match.clone();
+
+    let (experiment, db_conn) = db_conn
+        .run({
+            let if_match = if_match.clone();
+            move |mut conn| {
+                let experiment = dsl::experiments
+                    .find(experiment_id)
+                    .get_result::<Experiment>(&mut conn)
+                    .optional()?;
+                validate_if_match(
+                    &if_match,
+                    experiment
+                        .as_ref()
+                        .map(|experiment| experiment.last_modified),
+                )?;
+                let experiment = experiment.ok_or(diesel::result::Error::NotFound)?;
+                Ok((experiment, DbConnection(conn)))
+            }
+        })
+        .await?;
+    key_access
+        .validate_keys(&experiment.override_keys)
+        .map_err(|err| forbidden!(err))?;
 
     if matches!(experiment.status, ExperimentStatusType::CONCLUDED) {
         return Err(bad_argument!(
@@ -447,51 +530,99 @@ pub async fn conclude(
             err
         )
     })?;
-    let extra_headers = vec![("x-user", Some(user_str)), ("x-config-tags", config_tags)]
-        .into_iter()
-        .filter_map(|(key, val)| val.map(|v| (key, v)))
-        .collect::<Vec<_>>();
+    let idempotency_key = custom_headers.idempotency_key.map(|key| {
+        derive_idempotency_key(&key, &format!("experiments/{experiment_id}/conclude"))
+    });
+    let extra_headers = vec![
+        ("x-user", Some(user_str)),
+        ("x-config-tags", custom_headers.config_tags),
+        ("x-change-reason", custom_headers.change_reason),
+        ("idempotency-key", idempotency_key),
+    ]
+    .into_iter()
+    .filter_map(|(key, val)| val.map(|v| (key, v)))
+    .collect::<Vec<_>>();
 
     let headers_map = construct_header_map(tenant.as_str(), extra_headers)?;
 
-    let response = http_client
-        .put(&url)
-        .headers(headers_map.into())
-        .header(
-            header::AUTHORIZATION,
-            format!("Internal {}", state.superposition_token),
-        )
-        .json(&operations)
-        .send()
-        .await;
+    let response = send_traced(
+        "cac bulk operations",
+        http_client
+            .put(&url)
+            .headers(headers_map.into())
+            .header(
+                header::AUTHORIZATION,
+                format!("Internal {}", state.superposition_token),
+            )
+            .json(&operations),
+    )
+    .await;
 
     let (_, config_version_id) = process_cac_http_response(response).await?;
 
     // updating experiment status in db
-    let updated_experiment = diesel::update(dsl::experiments)
-        .filter(dsl::id.eq(experiment_id))
-        .set((
-            dsl::status.eq(ExperimentStatusType::CONCLUDED),
-            dsl::last_modified.eq(Utc::now()),
-            dsl::last_modified_by.eq(user.get_email()),
-            dsl::chosen_variant.eq(Some(winner_variant_id)),
-        ))
-        .get_result::<Experiment>(&mut conn)?;
+    let updated_experiment = db_conn
+        .run({
+            let config_version_id = config_version_id.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    lock_experiment_if_match(transaction_conn, experiment_id, &if_match)?;
+                    let updated_experiment = diesel::update(dsl::experiments)
+                        .filter(dsl::id.eq(experiment_id))
+                        .set((
+                            dsl::status.eq(ExperimentStatusType::CONCLUDED),
+                            dsl::last_modified.eq(Utc::now()),
+                            dsl::last_modified_by.eq(user.get_email()),
+                            dsl::chosen_variant.eq(Some(winner_variant_id)),
+                        ))
+                        .get_result::<Experiment>(transaction_conn)?;
+                    enqueue_experiment_event(
+                        transaction_conn,
+                        &experiments_webhook_config,
+                        WebhookEvent::ExperimentConcluded,
+                        &updated_experiment,
+                        &config_version_id,
+                    )?;
+                    Ok(updated_experiment)
+                })
+            }
+        })
+        .await?;
 
     Ok((updated_experiment, config_version_id))
 }
 
+/// Who can read the secret values of the experiments of `tenant`, and of
+/// which keys.
+fn secret_access(
+    state: &AppState,
+    tenant: &Tenant,
+    user: Option<User>,
+) -> Option<KeyAccess> {
+    user.and_then(|user| {
+        authorize(state, &user, Some(tenant.as_str()), Action::ReadSecrets).ok()
+    })
+}
+
 #[get("/applicable-variants")]
 async fn get_applicable_variants(
-    db_conn: DbConnection,
+    state: Data<AppState>,
+    db_conn: ReadDbConnection,
     query_data: Query<ApplicableVariantsQuery>,
+    tenant: Tenant,
+    user: Option<User>,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let query_data = query_data.into_inner();
+    let secret_access = secret_access(&state, &tenant, user);
 
-    let experiments = experiments::experiments
-        .filter(experiments::status.ne(ExperimentStatusType::CONCLUDED))
-        .load::<Experiment>(&mut conn)?;
+    let experiments = db_conn
+        .run(|mut conn| {
+            Ok(experiments::experiments
+                .filter(experiments::status.ne(ExperimentStatusType::CONCLUDED))
+                .load::<Experiment>(&mut conn)?)
+        })
+        .await?;
 
     let experiments = experiments.into_iter().filter(|exp| {
         let context: Map<String, Value> = exp.context.clone().into();
@@ -504,6 +635,7 @@ async fn get_applicable_variants(
 
     let mut variants = Vec::new();
     for exp in experiments {
+        let exp = reveal_experiment_secrets(exp, &cipher, secret_access.as_ref());
         if let Some(v) = decide_variant(
             exp.traffic_percentage as u8,
             exp.variants.into_inner(),
@@ -523,17 +655,22 @@ async fn get_applicable_variants(
 #[get("")]
 async fn list_experiments(
     req: HttpRequest,
+    state: Data<AppState>,
     pagination_params: Query<PaginationParams>,
     filters: Query<ExpListFilters>,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
+    tenant: Tenant,
+    user: Option<User>,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
-
-    let max_event_timestamp: Option<NaiveDateTime> = event_log::event_log
-        .filter(event_log::table_name.eq("experiments"))
-        .select(diesel::dsl::max(event_log::timestamp))
-        .first(&mut conn)?;
-
+    let secret_access = secret_access(&state, &tenant, user);
+    let to_response = move |experiment| {
+        ExperimentResponse::from(reveal_experiment_secrets(
+            experiment,
+            &cipher,
+            secret_access.as_ref(),
+        ))
+    };
     let last_modified = req
         .headers()
         .get("If-Modified-Since")
@@ -543,67 +680,95 @@ async fn list_experiments(
                 .map(|datetime| datetime.with_timezone(&Utc).naive_utc())
                 .ok()
         });
-
-    if max_event_timestamp.is_some() && max_event_timestamp < last_modified {
-        return Ok(HttpResponse::NotModified().finish());
-    };
-
-    let query_builder = |filters: &ExpListFilters| {
-        let mut builder = experiments::experiments.into_boxed();
-        if let Some(states) = filters.status.clone() {
-            builder = builder.filter(experiments::status.eq_any(states.0.clone()));
-        }
-        let now = Utc::now();
-        builder
-            .filter(
-                experiments::last_modified
-                    .ge(filters.from_date.unwrap_or(now - Duration::hours(24))),
-            )
-            .filter(experiments::last_modified.le(filters.to_date.unwrap_or(now)))
-    };
+    let pagination_params = pagination_params.into_inner();
     let filters = filters.into_inner();
-    let base_query = query_builder(&filters);
 
-    if let Some(true) = pagination_params.all {
-        let result = base_query.get_results::<Experiment>(&mut conn)?;
-        return Ok(HttpResponse::Ok().json(ExperimentsResponse {
-            total_pages: 1,
-            total_items: result.len() as i64,
-            data: result.into_iter().map(ExperimentResponse::from).collect(),
-        }));
-    }
+    // nothing is listed when the experiments were not modified since
+    let response = db_conn
+        .run(move |mut conn| {
+            let max_event_timestamp: Option<NaiveDateTime> = event_log::event_log
+                .filter(event_log::table_name.eq("experiments"))
+                .select(diesel::dsl::max(event_log::timestamp))
+                .first(&mut conn)?;
+
+            if max_event_timestamp.is_some() && max_event_timestamp < last_modified {
+                return Ok(None);
+            };
+
+            let query_builder = |filters: &ExpListFilters| {
+                let mut builder = experiments::experiments.into_boxed();
+                if let Some(states) = filters.status.clone() {
+                    builder =
+                        builder.filter(experiments::status.eq_any(states.0.clone()));
+                }
+                let now = Utc::now();
+                builder
+                    .filter(
+                        experiments::last_modified
+                            .ge(filters.from_date.unwrap_or(now - Duration::hours(24))),
+                    )
+                    .filter(experiments::last_modified.le(filters.to_date.unwrap_or(now)))
+            };
+            let base_query = query_builder(&filters);
+
+            if let Some(true) = pagination_params.all {
+                let result = base_query.get_results::<Experiment>(&mut conn)?;
+                return Ok(Some(ExperimentsResponse {
+                    total_pages: 1,
+                    total_items: result.len() as i64,
+                    data: result.into_iter().map(to_response).collect(),
+                }));
+            }
 
-    let count_query = query_builder(&filters);
-    let number_of_experiments = count_query.count().get_result(&mut conn)?;
-    let limit = pagination_params.count.unwrap_or(10);
-    let offset = (pagination_params.page.unwrap_or(1) - 1) * limit;
-    let query = base_query
-        .order(experiments::last_modified.desc())
-        .limit(limit)
-        .offset(offset);
+            let count_query = query_builder(&filters);
+            let number_of_experiments = count_query.count().get_result(&mut conn)?;
+            let limit = pagination_params.count.unwrap_or(10);
+            let offset = (pagination_params.page.unwrap_or(1) - 1) * limit;
+            let query = base_query
+                .order(experiments::last_modified.desc())
+                .limit(limit)
+                .offset(offset);
 
-    let experiment_list = query.load::<Experiment>(&mut conn)?;
+            let experiment_list = query.load::<Experiment>(&mut conn)?;
 
-    let total_pages = (number_of_experiments as f64 / limit as f64).ceil() as i64;
+            let total_pages = (number_of_experiments as f64 / limit as f64).ceil() as i64;
 
-    Ok(HttpResponse::Ok().json(ExperimentsResponse {
-        total_pages,
-        total_items: number_of_experiments,
-        data: experiment_list
-            .into_iter()
-            .map(ExperimentResponse::from)
-            .collect(),
-    }))
+            Ok(Some(ExperimentsResponse {
+                total_pages,
+                total_items: number_of_experiments,
+                data: experiment_list.into_iter().map(to_response).collect(),
+            }))
+        })
+        .await?;
+
+    match response {
+        Some(response) => Ok(HttpResponse::Ok().json(response)),
+        None => Ok(HttpResponse::NotModified().finish()),
+    }
 }
 
 #[get("/{id}")]
 async fn get_experiment_handler(
+    state: Data<AppState>,
     params: web::Path<i64>,
-    db_conn: DbConnection,
-) -> superposition::Result<Json<ExperimentResponse>> {
-    let DbConnection(mut conn) = db_conn;
-    let response = get_experiment(params.into_inner(), &mut conn)?;
-    Ok(Json(ExperimentResponse::from(response)))
+    db_conn: ReadDbConnection,
+    tenant: Tenant,
+    user: Option<User>,
+    cipher: TenantCipher,
+) -> superposition::Result<HttpResponse> {
+    let experiment_id = params.into_inner();
+    let response = db_conn
+        .run(move |mut conn| get_experiment(experiment_id, &mut conn))
+        .await?;
+    let etag = experiment_etag(&response.last_modified);
+    let secret_access = secret_access(&state, &tenant, user);
+    Ok(HttpResponse::Ok()
+        .insert_header((AppHeader::ETag.to_string(), etag))
+        .json(ExperimentResponse::from(reveal_experiment_secrets(
+            response,
+            &cipher,
+            secret_access.as_ref(),
+        ))))
 }
 
 pub fn get_experiment(
@@ -623,17 +788,38 @@ async fn ramp(
     data: Data<AppState>,
     params: web::Path<i64>,
     req: web::Json<RampRequest>,
+    custom_headers: CustomHeaders,
     db_conn: DbConnection,
     user: User,
     tenant: Tenant,
     tenant_config: TenantConfig,
+    key_access: KeyAccess,
 ) -> superposition::Result<Json<ExperimentResponse>> {
-    let DbConnection(mut conn) = db_conn;
     let exp_id = params.into_inner();
-
-    let experiment: Experiment = experiments::experiments
-        .find(exp_id)
-        .get_result::<Experiment>(&mut conn)?;
+    let if_match = custom_headers.if_match;
+
+    let (experiment, db_conn) = db_conn
+        .run({
+            let if_match = if_match.clone();
+            move |mut conn| {
+                let experiment = experiments::experiments
+                    .find(exp_id)
+                    .get_result::<Experiment>(&mut conn)
+                    .optional()?;
+                validate_if_match(
+                    &if_match,
+                    experiment
+                        .as_ref()
+                        .map(|experiment| experiment.last_modified),
+                )?;
+                let experiment = experiment.ok_or(diesel::result::Error::NotFound)?;
+                Ok((experiment, DbConnection(conn)))
+            }
+        })
+        .await?;
+    key_access
+        .validate_keys(&experiment.override_keys)
+        .map_err(|err| forbidden!(err))?;
 
     let old_traffic_percentage = experiment.traffic_percentage as u8;
     let new_traffic_percentage = req.traffic_percentage as u8;
@@ -653,18 +839,8 @@ async fn ramp(
     {
         return Err(bad_argument!("The traffic_percentage is same as provided"))?;
     }
-    let updated_experiment: Experiment = diesel::update(experiments::experiments)
-        .filter(experiments::id.eq(exp_id))
-        .set((
-            experiments::traffic_percentage.eq(req.traffic_percentage as i32),
-            experiments::last_modified.eq(Utc::now()),
-            experiments::last_modified_by.eq(user.get_email()),
-            experiments::status.eq(ExperimentStatusType::INPROGRESS),
-        ))
-        .get_result(&mut conn)?;
-
+    let traffic_percentage = req.traffic_percentage as i32;
     let (_, config_version_id) = fetch_cac_config(&tenant, &data).await?;
-
     let webhook_event = if new_traffic_percentage == 0
         && matches!(experiment.status, ExperimentStatusType::CREATED)
     {
@@ -672,19 +848,30 @@ async fn ramp(
     } else {
         WebhookEvent::ExperimentInprogress
     };
-    if let WebhookConfig::Enabled(experiments_webhook_config) =
-        tenant_config.experiments_webhook_config
-    {
-        execute_webhook_call(
-            &experiments_webhook_config,
-            &updated_experiment,
-            &config_version_id,
-            &tenant,
-            webhook_event,
-            &data.http_client,
-        )
+    let updated_experiment: Experiment = db_conn
+        .run(move |mut conn| {
+            conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                lock_experiment_if_match(transaction_conn, exp_id, &if_match)?;
+                let updated_experiment = diesel::update(experiments::experiments)
+                    .filter(experiments::id.eq(exp_id))
+                    .set((
+                        experiments::traffic_percentage.eq(traffic_percentage),
+                        experiments::last_modified.eq(Utc::now()),
+                        experiments::last_modified_by.eq(user.get_email()),
+                        experiments::status.eq(ExperimentStatusType::INPROGRESS),
+                    ))
+                    .get_result(transaction_conn)?;
+                enqueue_experiment_event(
+                    transaction_conn,
+                    &tenant_config.experiments_webhook_config,
+                    webhook_event,
+                    &updated_experiment,
+                    &config_version_id,
+                )?;
+                Ok(updated_experiment)
+            })
+        })
         .await?;
-    }
 
     Ok(Json(ExperimentResponse::from(updated_experiment)))
 }
@@ -699,8 +886,9 @@ async fn update_overrides(
     tenant: Tenant,
     tenant_config: TenantConfig,
     user: User,
+    key_access: KeyAccess,
+    cipher: TenantCipher,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
     let experiment_id = params.into_inner();
 
     let payload = req.into_inner();
@@ -709,15 +897,35 @@ async fn update_overrides(
     let first_variant = variants.first().ok_or(bad_argument!(
         "Variant not found in request. Provide at least one entry in variant's list",
     ))?;
-    let override_keys =
+    let override_keys: Vec<String> =
         extract_override_keys(&first_variant.overrides.clone().into_inner())
             .into_iter()
             .collect();
 
     // fetch the current variants of the experiment
-    let experiment = experiments::experiments
-        .find(experiment_id)
-        .first::<Experiment>(&mut conn)?;
+    let if_match = custom_headers.if_match.clone();
+    let (experiment, db_conn) = db_conn
+        .run({
+            let if_match = if_match.clone();
+            move |mut conn| {
+                let experiment = experiments::experiments
+                    .find(experiment_id)
+                    .first::<Experiment>(&mut conn)
+                    .optional()?;
+                validate_if_match(
+                    &if_match,
+                    experiment
+                        .as_ref()
+                        .map(|experiment| experiment.last_modified),
+                )?;
+                let experiment = experiment.ok_or(diesel::result::Error::NotFound)?;
+                Ok((experiment, DbConnection(conn)))
+            }
+        })
+        .await?;
+    key_access
+        .validate_keys(experiment.override_keys.iter().chain(&override_keys))
+        .map_err(|err| forbidden!(err))?;
 
     if experiment.status != ExperimentStatusType::CREATED {
         return Err(bad_argument!(
@@ -782,6 +990,13 @@ async fn update_overrides(
             )
         )?;
     }
+    let secret_keys = get_secret_keys(&state, &tenant, override_keys.clone()).await?;
+    encrypt_variant_secrets(
+        &cipher,
+        &secret_keys,
+        &mut new_variants,
+        &id_to_existing_variant,
+    )?;
     let experiment_condition =
         Exp::<Condition>::validate_db_data(experiment.context.into())
             .map_err(|err| {
@@ -794,17 +1009,27 @@ async fn update_overrides(
             .into_inner();
 
     // validating experiment against other active experiments based on permission flags
-    let flags = &state.experimentation_flags;
-    let (valid, reason) = validate_experiment(
-        &experiment_condition,
-        &override_keys,
-        Some(experiment_id),
-        flags,
-        &mut conn,
-    )?;
-    if !valid {
-        return Err(bad_argument!(reason));
-    }
+    let db_conn = db_conn
+        .run({
+            let state = state.clone();
+            let experiment_condition = experiment_condition.clone();
+            let override_keys = override_keys.clone();
+            move |mut conn| {
+                let flags = &state.experimentation_flags;
+                let (valid, reason) = validate_experiment(
+                    &experiment_condition,
+                    &override_keys,
+                    Some(experiment_id),
+                    flags,
+                    &mut conn,
+                )?;
+                if !valid {
+                    return Err(bad_argument!(reason));
+                }
+                Ok(DbConnection(conn))
+            }
+        })
+        .await?;
 
     /******************************* Updating contexts ************************************/
     let mut cac_operations: Vec<ContextAction> = vec![];
@@ -852,9 +1077,14 @@ async fn update_overrides(
             err
         )
     })?;
+    let idempotency_key = custom_headers.idempotency_key.map(|key| {
+        derive_idempotency_key(&key, &format!("experiments/{experiment_id}/overrides"))
+    });
     let extra_headers = vec![
         ("x-user", Some(user_str)),
         ("x-config-tags", custom_headers.config_tags),
+        ("x-change-reason", custom_headers.change_reason),
+        ("idempotency-key", idempotency_key),
     ]
     .into_iter()
     .filter_map(|(key, val)| val.map(|v| (key, v)))
@@ -862,16 +1092,18 @@ async fn update_overrides(
 
     let headers_map = construct_header_map(tenant.as_str(), extra_headers)?;
 
-    let response = http_client
-        .put(&url)
-        .headers(headers_map.into())
-        .header(
-            header::AUTHORIZATION,
-            format!("Internal {}", state.superposition_token),
-        )
-        .json(&cac_operations)
-        .send()
-        .await;
+    let response = send_traced(
+        "cac bulk operations",
+        http_client
+            .put(&url)
+            .headers(headers_map.into())
+            .header(
+                header::AUTHORIZATION,
+                format!("Internal {}", state.superposition_token),
+            )
+            .json(&cac_operations),
+    )
+    .await;
 
     // directly return an error response if not a 200 response
     let (resp_contexts, config_version_id) = process_cac_http_response(response).await?;
@@ -898,28 +1130,33 @@ async fn update_overrides(
         log::error!("failed to serialize new variants to json with error: {e}");
         bad_argument!("failed to update experiment, bad variant data")
     })?;
-    let updated_experiment = diesel::update(experiments::experiments.find(experiment_id))
-        .set((
-            experiments::variants.eq(new_variants_json),
-            experiments::override_keys.eq(override_keys),
-            experiments::last_modified.eq(Utc::now()),
-            experiments::last_modified_by.eq(user.get_email()),
-        ))
-        .get_result::<Experiment>(&mut conn)?;
-
-    if let WebhookConfig::Enabled(experiments_webhook_config) =
-        tenant_config.experiments_webhook_config
-    {
-        execute_webhook_call(
-            &experiments_webhook_config,
-            &updated_experiment,
-            &config_version_id,
-            &tenant,
-            WebhookEvent::ExperimentUpdated,
-            &state.http_client,
-        )
+    let updated_experiment = db_conn
+        .run({
+            let config_version_id = config_version_id.clone();
+            move |mut conn| {
+                conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+                    lock_experiment_if_match(transaction_conn, experiment_id, &if_match)?;
+                    let updated_experiment =
+                        diesel::update(experiments::experiments.find(experiment_id))
+                            .set((
+                                experiments::variants.eq(new_variants_json),
+                                experiments::override_keys.eq(override_keys),
+                                experiments::last_modified.eq(Utc::now()),
+                                experiments::last_modified_by.eq(user.get_email()),
+                            ))
+                            .get_result::<Experiment>(transaction_conn)?;
+                    enqueue_experiment_event(
+                        transaction_conn,
+                        &tenant_config.experiments_webhook_config,
+                        WebhookEvent::ExperimentUpdated,
+                        &updated_experiment,
+                        &config_version_id,
+                    )?;
+                    Ok(updated_experiment)
+                })
+            }
+        })
         .await?;
-    }
 
     let mut http_resp = HttpResponse::Ok();
     add_config_version_to_header(&config_version_id, &mut http_resp);
@@ -929,10 +1166,8 @@ async fn update_overrides(
 #[get("/audit")]
 async fn get_audit_logs(
     filters: Query<AuditQueryFilters>,
-    db_conn: DbConnection,
+    db_conn: ReadDbConnection,
 ) -> superposition::Result<HttpResponse> {
-    let DbConnection(mut conn) = db_conn;
-
     let query_builder = |filters: &AuditQueryFilters| {
         let mut builder = event_log::event_log.into_boxed();
         if let Some(tables) = filters.table.clone() {
@@ -963,9 +1198,18 @@ async fn get_audit_logs(
         .limit(limit)
         .offset(offset);
 
-    let log_count: i64 = count_query.count().get_result(&mut conn)?;
+    let (log_count, mut logs) = db_conn
+        .run(move |mut conn| {
+            let log_count: i64 = count_query.count().get_result(&mut conn)?;
 
-    let logs: Vec<EventLog> = query.load(&mut conn)?;
+            let logs: Vec<EventLog> = query.load(&mut conn)?;
+            Ok((log_count, logs))
+        })
+        .await?;
+    for log in logs.iter_mut() {
+        log.original_data.iter_mut().for_each(mask_secrets);
+        log.new_data.iter_mut().for_each(mask_secrets);
+    }
 
     let total_pages = (log_count as f64 / limit as f64).ceil() as i64;
 
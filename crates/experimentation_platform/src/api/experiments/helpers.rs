@@ -1,16 +1,28 @@
 use actix_http::header::{self, HeaderMap, HeaderName, HeaderValue};
-use actix_web::web::Data;
+use actix_web::{http::StatusCode, web::Data};
+use chrono::{DateTime, Utc};
 use diesel::pg::PgConnection;
-use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
 use serde_json::{Map, Value};
-use service_utils::helpers::extract_dimensions;
-use service_utils::service::types::{AppState, ExperimentationFlags, Tenant};
-use std::collections::HashSet;
+use service_utils::db::utils::get_tenant_namespaces;
+use service_utils::encryption::{is_encrypted, is_mask, mask_secrets, TenantCipher};
+use service_utils::helpers::{extract_dimensions, parse_etags};
+use service_utils::service::types::{
+    AppState, DbConnection, ExperimentationFlags, Tenant,
+};
+use service_utils::telemetry::send_traced;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
-use superposition_macros::{bad_argument, unexpected_error};
+use superposition_macros::{bad_argument, response_error, unexpected_error};
 use superposition_types::{
-    experimentation::models::{Experiment, ExperimentStatusType, Variant, VariantType},
-    result as superposition, Condition, Config, Exp, Overrides,
+    authorization::KeyAccess,
+    cac::schema::default_configs::dsl as default_configs,
+    experimentation::models::{
+        Experiment, ExperimentStatusType, Variant, VariantType, Variants,
+    },
+    result as superposition, Condition, Config, Exp, Overrides, TenantQuotas,
 };
 
 pub fn check_variant_types(variants: &Vec<Variant>) -> superposition::Result<()> {
@@ -199,6 +211,93 @@ pub fn validate_experiment(
     is_valid_experiment(context, override_keys, flags, &active_experiments)
 }
 
+/*
+  Experiment writes guarded by If-Match go through only when the header holds
+  the current last_modified of the experiment, or `*` when it exists. The
+  handlers check it before calling CAC, so that a stale write has no side
+  effects there, and again on the locked row right before updating it.
+*/
+pub fn validate_if_match(
+    if_match: &Option<String>,
+    last_modified: Option<DateTime<Utc>>,
+) -> superposition::Result<()> {
+    let Some(if_match) = if_match else {
+        return Ok(());
+    };
+    let is_current = |tag: &String| {
+        last_modified.is_some_and(|modified| {
+            tag == "*"
+                || DateTime::parse_from_rfc3339(tag)
+                    .is_ok_and(|revision| revision == modified)
+        })
+    };
+    if parse_etags(if_match).iter().any(is_current) {
+        Ok(())
+    } else {
+        log::info!(
+            "stale If-Match {if_match}, experiment was modified at {last_modified:?}"
+        );
+        Err(response_error!(
+            StatusCode::PRECONDITION_FAILED,
+            "The experiment has been modified since it was read, fetch it again and retry"
+        ))
+    }
+}
+
+/// ETag of an experiment last modified at `last_modified`, the revision
+/// If-Match is checked against when writing it.
+pub fn experiment_etag(last_modified: &DateTime<Utc>) -> String {
+    format!("\"{}\"", last_modified.to_rfc3339())
+}
+
+/// Locks the experiment row for the rest of the transaction and checks
+/// If-Match against it.
+pub fn lock_experiment_if_match(
+    conn: &mut PgConnection,
+    experiment_id: i64,
+    if_match: &Option<String>,
+) -> superposition::Result<()> {
+    use superposition_types::experimentation::schema::experiments::dsl as experiments_dsl;
+
+    if if_match.is_none() {
+        return Ok(());
+    }
+    let last_modified = experiments_dsl::experiments
+        .find(experiment_id)
+        .select(experiments_dsl::last_modified)
+        .for_update()
+        .get_result::<DateTime<Utc>>(conn)
+        .optional()?;
+    validate_if_match(if_match, last_modified)
+}
+
+/// Fails when the tenant already runs as many experiments as its quota allows.
+pub fn validate_active_experiments_quota(
+    quotas: &TenantQuotas,
+    conn: &mut PgConnection,
+) -> superposition::Result<()> {
+    use superposition_types::experimentation::schema::experiments::dsl as experiments_dsl;
+
+    let Some(max_active_experiments) = quotas.max_active_experiments else {
+        return Ok(());
+    };
+    let active_experiments: i64 = experiments_dsl::experiments
+        .filter(
+            experiments_dsl::status
+                .eq(ExperimentStatusType::CREATED)
+                .or(experiments_dsl::status.eq(ExperimentStatusType::INPROGRESS)),
+        )
+        .count()
+        .get_result(conn)?;
+    if active_experiments >= max_active_experiments {
+        return Err(bad_argument!(
+            "The tenant has reached its quota of {} active experiments, conclude running experiments or ask an admin to raise the quota",
+            max_active_experiments
+        ));
+    }
+    Ok(())
+}
+
 pub fn add_variant_dimension_to_ctx(
     context: &Condition,
     variant: String,
@@ -305,15 +404,14 @@ pub async fn fetch_cac_config(
     let url = state.cac_host.clone() + "/config";
     let headers_map = construct_header_map(tenant.as_str(), Vec::new())?;
 
-    let response = http_client
-        .get(&url)
-        .headers(headers_map.into())
-        .header(
+    let response = send_traced(
+        "cac config",
+        http_client.get(&url).headers(headers_map.into()).header(
             header::AUTHORIZATION,
             format!("Internal {}", state.superposition_token),
-        )
-        .send()
-        .await;
+        ),
+    )
+    .await;
 
     match response {
         Ok(res) => {
@@ -333,3 +431,134 @@ pub async fn fetch_cac_config(
         }
     }
 }
+
+/*
+  Which keys are secret is kept by the default configs, read over a
+  connection to the cac schema of the tenant.
+*/
+pub async fn get_secret_keys(
+    state: &Data<AppState>,
+    tenant: &Tenant,
+    keys: Vec<String>,
+) -> superposition::Result<HashSet<String>> {
+    let namespace = if state.enable_tenant_and_scope {
+        let [cac_schema, _] = get_tenant_namespaces(tenant);
+        cac_schema
+    } else {
+        String::from("cac_v1")
+    };
+    DbConnection::checkout(state.db_pool.clone(), namespace)
+        .await?
+        .run(move |mut conn| {
+            let secret_keys: Vec<String> = default_configs::default_configs
+                .filter(default_configs::key.eq_any(keys))
+                .filter(default_configs::secret.eq(true))
+                .filter(default_configs::deleted_at.is_null())
+                .select(default_configs::key)
+                .load(&mut conn)?;
+            Ok(secret_keys.into_iter().collect())
+        })
+        .await
+}
+
+/*
+  Values of secret keys are stored encrypted in the variants, like in the
+  contexts they are sent to, so the experiments and their audit log never
+  hold them in plain text. A mask stands for the value the variant of the
+  same id already has.
+*/
+pub fn encrypt_variant_secrets(
+    cipher: &TenantCipher,
+    secret_keys: &HashSet<String>,
+    variants: &mut [Variant],
+    existing_variants: &HashMap<String, &Variant>,
+) -> superposition::Result<()> {
+    if secret_keys.is_empty() {
+        return Ok(());
+    }
+    for variant in variants.iter_mut() {
+        let mut overrides: Map<String, Value> =
+            variant.overrides.clone().into_inner().into();
+        let existing_overrides = existing_variants
+            .get(&variant.id)
+            .map(|existing| existing.overrides.clone().into_inner());
+        for (key, value) in overrides.iter_mut() {
+            if !secret_keys.contains(key) || is_encrypted(value) {
+                continue;
+            }
+            *value = if is_mask(value) {
+                existing_overrides
+                    .as_ref()
+                    .and_then(|existing| existing.get(key))
+                    .filter(|existing| is_encrypted(existing))
+                    .cloned()
+                    .ok_or(bad_argument!(
+                        "A value is needed for the secret key {} in variant {}",
+                        key,
+                        variant.id
+                    ))?
+            } else {
+                let stored = existing_overrides
+                    .as_ref()
+                    .and_then(|existing| existing.get(key));
+                cipher
+                    .encrypt_keeping(value, stored)
+                    .map_err(|err| bad_argument!(err))?
+            };
+        }
+        variant.overrides =
+            Exp::<Overrides>::try_from(overrides).map_err(|err| bad_argument!(err))?;
+    }
+    Ok(())
+}
+
+fn map_secret_values<F>(experiment: Experiment, mut f: F) -> Experiment
+where
+    F: FnMut(&String, &mut Value),
+{
+    let variants = experiment
+        .variants
+        .into_inner()
+        .into_iter()
+        .map(|mut variant| {
+            let mut overrides: Map<String, Value> =
+                variant.overrides.clone().into_inner().into();
+            overrides
+                .iter_mut()
+                .filter(|(_, value)| is_encrypted(value))
+                .for_each(|(key, value)| f(key, value));
+            if let Ok(overrides) = Exp::<Overrides>::try_from(overrides) {
+                variant.overrides = overrides;
+            }
+            variant
+        })
+        .collect();
+    Experiment {
+        variants: Variants::new(variants),
+        ..experiment
+    }
+}
+
+/// Decrypts the secret values of `experiment` for the keys `secret_access`
+/// allows and masks them everywhere else.
+pub fn reveal_experiment_secrets(
+    experiment: Experiment,
+    cipher: &TenantCipher,
+    secret_access: Option<&KeyAccess>,
+) -> Experiment {
+    map_secret_values(experiment, |key, value| match secret_access {
+        Some(key_access) if key_access.allows(key) => match cipher.decrypt(value) {
+            Ok(decrypted) => *value = decrypted,
+            Err(err) => {
+                log::error!("failed to decrypt the value of {key}: {err}");
+                mask_secrets(value);
+            }
+        },
+        _ => mask_secrets(value),
+    })
+}
+
+/// Masks the secret values of `experiment`, for responses and webhooks.
+pub fn mask_experiment_secrets(experiment: Experiment) -> Experiment {
+    map_secret_values(experiment, |_, value| mask_secrets(value))
+}
@@ -9,6 +9,8 @@ use superposition_types::{
     Condition, Exp, Overrides,
 };
 
+use super::helpers::mask_experiment_secrets;
+
 /********** Experiment Create Req Types ************/
 
 #[derive(Deserialize)]
@@ -53,8 +55,10 @@ pub struct ExperimentResponse {
     pub chosen_variant: Option<String>,
 }
 
+// secret values are masked unless they were revealed before
 impl From<Experiment> for ExperimentResponse {
     fn from(experiment: Experiment) -> Self {
+        let experiment = mask_experiment_secrets(experiment);
         Self {
             id: experiment.id.to_string(),
             created_at: experiment.created_at,
@@ -1,2 +1,8 @@
 #![deny(unused_crate_dependencies)]
 pub mod api;
+
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+
+/// Migrations of this crate, embedded so that schemas of tenants created at
+/// runtime can be migrated without the diesel cli.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
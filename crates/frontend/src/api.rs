@@ -3,9 +3,9 @@ use superposition_types::Config;
 
 use crate::{
     types::{
-        ConfigVersionListResponse, DefaultConfig, Dimension, ExpListFilters,
-        ExperimentResponse, FetchTypeTemplateResponse, FunctionResponse, ListFilters,
-        PaginatedResponse,
+        ConfigVersionListResponse, ContextResponse, DefaultConfig, Dimension,
+        ExpListFilters, ExperimentResponse, FetchTypeTemplateResponse, FunctionResponse,
+        ListFilters, MetadataFilters, PaginatedResponse, TenantConfigResponse,
     },
     utils::{
         construct_request_headers, get_host, parse_json_response, request,
@@ -13,9 +13,24 @@ use crate::{
     },
 };
 
+fn metadata_query_params(metadata_filters: MetadataFilters) -> Vec<String> {
+    let encode = |value: String| {
+        url::form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+    };
+    let mut query_params = vec![];
+    if let Some(owner) = metadata_filters.owner.filter(|owner| !owner.is_empty()) {
+        query_params.push(format!("owner={}", encode(owner)));
+    }
+    if let Some(label) = metadata_filters.label.filter(|label| !label.is_empty()) {
+        query_params.push(format!("label={}", encode(label)));
+    }
+    query_params
+}
+
 // #[server(GetDimensions, "/fxn", "GetJson")]
 pub async fn fetch_dimensions(
     filters: ListFilters,
+    metadata_filters: MetadataFilters,
     tenant: String,
 ) -> Result<PaginatedResponse<Dimension>, ServerFnError> {
     let client = reqwest::Client::new();
@@ -31,6 +46,7 @@ pub async fn fetch_dimensions(
     if let Some(all) = filters.all {
         query_params.push(format!("all={}", all));
     }
+    query_params.extend(metadata_query_params(metadata_filters));
 
     let url = format!("{}/dimension?{}", host, query_params.join("&"));
     let response: PaginatedResponse<Dimension> = client
@@ -49,6 +65,7 @@ pub async fn fetch_dimensions(
 // #[server(GetDefaultConfig, "/fxn", "GetJson")]
 pub async fn fetch_default_config(
     filters: ListFilters,
+    metadata_filters: MetadataFilters,
     tenant: String,
 ) -> Result<PaginatedResponse<DefaultConfig>, ServerFnError> {
     let client = reqwest::Client::new();
@@ -64,6 +81,7 @@ pub async fn fetch_default_config(
     if let Some(all) = filters.all {
         query_params.push(format!("all={}", all));
     }
+    query_params.extend(metadata_query_params(metadata_filters));
 
     let url = format!("{}/default-config?{}", host, query_params.join("&"));
     let response: PaginatedResponse<DefaultConfig> = client
@@ -129,6 +147,56 @@ pub async fn delete_context(
     }
 }
 
+pub async fn update_context_state(
+    tenant: String,
+    context_id: String,
+    enabled: bool,
+) -> Result<(), ServerFnError> {
+    let client = reqwest::Client::new();
+    let host = use_host_server();
+    let action = if enabled { "enable" } else { "disable" };
+    let url = format!("{host}/context/{context_id}/{action}");
+
+    let response = client
+        .put(&url)
+        .header("x-tenant", tenant)
+        .send()
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(ServerFnError::new(format!(
+            "Failed to {action} context with status: {}",
+            response.status()
+        )))
+    }
+}
+
+/*
+  Disabled contexts are left out of /config, the page lists them separately
+  so that they can be switched back on.
+*/
+pub async fn fetch_disabled_contexts(
+    tenant: String,
+) -> Result<PaginatedResponse<ContextResponse>, ServerFnError> {
+    let host = use_host_server();
+    let url = format!("{host}/context/list?enabled=false&page=1&size=10000");
+    let err_handler = |e: String| ServerFnError::new(e.to_string());
+    let response = request::<()>(
+        url,
+        reqwest::Method::GET,
+        None,
+        construct_request_headers(&[("x-tenant", &tenant)]).map_err(err_handler)?,
+    )
+    .await
+    .map_err(err_handler)?;
+    parse_json_response::<PaginatedResponse<ContextResponse>>(response)
+        .await
+        .map_err(err_handler)
+}
+
 // #[server(GetExperiments, "/fxn", "GetJson")]
 pub async fn fetch_experiments(
     exp_filters: ExpListFilters,
@@ -321,3 +389,22 @@ pub async fn fetch_types(
         .await
         .map_err(err_handler)
 }
+
+pub async fn fetch_tenant_config(
+    tenant: String,
+) -> Result<TenantConfigResponse, ServerFnError> {
+    let host = use_host_server();
+    let url = format!("{host}/tenants/{tenant}/config");
+    let err_handler = |e: String| ServerFnError::new(e.to_string());
+    let response = request::<()>(
+        url,
+        reqwest::Method::GET,
+        None,
+        construct_request_headers(&[]).map_err(err_handler)?,
+    )
+    .await
+    .map_err(err_handler)?;
+    parse_json_response::<TenantConfigResponse>(response)
+        .await
+        .map_err(err_handler)
+}
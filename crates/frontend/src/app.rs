@@ -14,6 +14,7 @@ use crate::pages::function::{
 use crate::pages::{
     context_override::ContextOverride, custom_types::TypesPage,
     default_config::DefaultConfig, experiment::ExperimentPage, home::Home,
+    tenant_settings::TenantSettings,
 };
 use crate::providers::alert_provider::AlertProvider;
 use crate::types::Envs;
@@ -237,6 +238,18 @@ pub fn app(app_envs: Envs) -> impl IntoView {
                                 }
                             />
 
+                            <Route
+                                ssr=SsrMode::Async
+                                path="/admin/:tenant/settings"
+                                view=move || {
+                                    view! {
+                                        <Layout>
+                                            <TenantSettings/>
+                                        </Layout>
+                                    }
+                                }
+                            />
+
                         // <Route
                         // path="/*any"
                         // view=move || {
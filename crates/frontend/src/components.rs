@@ -15,6 +15,7 @@ pub mod experiment_ramp_form;
 pub mod function_form;
 pub mod input;
 pub mod input_components;
+pub mod metadata_form;
 pub mod modal;
 pub mod monaco_editor;
 pub mod nav_item;
@@ -24,6 +25,7 @@ pub mod side_nav;
 pub mod skeleton;
 pub mod stat;
 pub mod table;
+pub mod tenant_config_form;
 pub mod toast;
 pub mod type_template_form;
 pub mod variant_form;
@@ -22,6 +22,8 @@ pub fn context_card(
         (),
     >,
     #[prop(default=Callback::new(|_| {}))] handle_delete: Callback<String, ()>,
+    #[prop(default = true)] enabled: bool,
+    #[prop(default=Callback::new(|_| {}))] handle_toggle: Callback<(String, bool), ()>,
 ) -> impl IntoView {
     let conditions: Conditions = (&context).try_into().unwrap_or_default();
 
@@ -57,12 +59,28 @@ pub fn context_card(
         .iter()
         .any(|condition| matches!(condition.operator, ConditionOperator::Other(_)));
 
+    let card_class = if enabled {
+        "rounded-lg shadow bg-base-100 p-6 flex flex-col gap-4"
+    } else {
+        "rounded-lg shadow bg-base-100 p-6 flex flex-col gap-4 opacity-60"
+    };
+    let toggle_class = if enabled {
+        "ri-toggle-fill ri-lg text-green-500 cursor-pointer"
+    } else {
+        "ri-toggle-line ri-lg text-gray-500 cursor-pointer"
+    };
+
     view! {
-        <div class="rounded-lg shadow bg-base-100 p-6 flex flex-col gap-4">
+        <div class=card_class>
             <div class="flex justify-between">
-                <h3 class="card-title text-base timeline-box text-gray-800 bg-base-100 shadow-md font-mono m-0 w-max">
-                    "Condition"
-                </h3>
+                <div class="flex items-center gap-2">
+                    <h3 class="card-title text-base timeline-box text-gray-800 bg-base-100 shadow-md font-mono m-0 w-max">
+                        "Condition"
+                    </h3>
+                    <Show when=move || !enabled>
+                        <span class="badge badge-ghost text-xs">{"Disabled"}</span>
+                    </Show>
+                </div>
                 <Show when=move || actions_supported>
                     <div class="h-fit text-right space-x-4">
                         <Show when=move || !edit_unsupported>
@@ -88,6 +106,13 @@ pub fn context_card(
                                 {"Edit Unsupported"}
                             </span>
                         </Show>
+                        <i
+                            class=toggle_class
+                            on:click=move |_| {
+                                handle_toggle.call((context_id.get_value(), !enabled));
+                            }
+                        >
+                        </i>
                         <i
                             class="ri-delete-bin-5-line ri-lg text-red-500 cursor-pointer"
                             on:click=move |_| {
@@ -16,9 +16,10 @@ use crate::{
         button::Button,
         dropdown::{Dropdown, DropdownBtnType, DropdownDirection},
         input::{Input, InputType},
+        metadata_form::MetadataForm,
     },
     schema::{JsonSchemaType, SchemaType},
-    types::{FunctionsName, TypeTemplate},
+    types::{EntityMetadata, FunctionsName, TypeTemplate},
 };
 
 use self::{types::DefaultConfigCreateReq, utils::create_default_config};
@@ -32,6 +33,8 @@ pub fn default_config_form<NF>(
     #[prop(default = Value::Null)] config_value: Value,
     #[prop(default = None)] function_name: Option<Value>,
     #[prop(default = None)] prefix: Option<String>,
+    #[prop(default = EntityMetadata::default())] metadata: EntityMetadata,
+    #[prop(default = false)] secret: bool,
     handle_submit: NF,
 ) -> impl IntoView
 where
@@ -44,6 +47,8 @@ where
     let (config_schema_rs, config_schema_ws) = create_signal(type_schema);
     let (config_value_rs, config_value_ws) = create_signal(config_value);
     let (function_name_rs, function_name_ws) = create_signal(function_name);
+    let metadata_rws = create_rw_signal(metadata);
+    let secret_rws = create_rw_signal(secret);
     let (req_inprogess_rs, req_inprogress_ws) = create_signal(false);
 
     let functions_resource: Resource<String, Vec<crate::types::FunctionResponse>> =
@@ -105,6 +110,8 @@ where
             schema: f_schema,
             value: f_value,
             function_name: fun_name,
+            metadata: metadata_rws.get(),
+            secret: secret_rws.get(),
         };
 
         let handle_submit_clone = handle_submit.clone();
@@ -269,6 +276,22 @@ where
                             </label>
                             {input_format}
                         </div>
+                        <div class="form-control w-full max-w-md">
+                            <label class="label cursor-pointer">
+                                <span class="label-text">Secret</span>
+                                <input
+                                    type="checkbox"
+                                    class="toggle toggle-primary"
+                                    prop:checked=move || secret_rws.get()
+                                    on:change=move |ev| secret_rws.set(event_target_checked(&ev))
+                                />
+                            </label>
+                            <label class="label">
+                                <span class="label-text text-slate-400">
+                                    Stored encrypted and shown masked, leave the mask to keep the current value
+                                </span>
+                            </label>
+                        </div>
                         <div class="divider"></div>
                     }
                 }}
@@ -318,6 +341,10 @@ where
 
                 </Suspense>
 
+                <div class="divider"></div>
+
+                <MetadataForm metadata=metadata_rws/>
+
                 <div class="form-control grid w-full justify-start">
                     {move || {
                         let loading = req_inprogess_rs.get();
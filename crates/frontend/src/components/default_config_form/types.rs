@@ -1,9 +1,14 @@
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::types::EntityMetadata;
+
 #[derive(Serialize, Clone)]
 pub struct DefaultConfigCreateReq {
     pub schema: Value,
     pub value: Value,
     pub function_name: Option<Value>,
+    #[serde(flatten)]
+    pub metadata: EntityMetadata,
+    pub secret: bool,
 }
@@ -7,10 +7,11 @@ use crate::api::fetch_types;
 use crate::components::{
     dropdown::{Dropdown, DropdownBtnType, DropdownDirection},
     input::{Input, InputType},
+    metadata_form::MetadataForm,
 };
 use crate::providers::editor_provider::EditorProvider;
 use crate::schema::{JsonSchemaType, SchemaType};
-use crate::types::{FunctionsName, ListFilters, TypeTemplate};
+use crate::types::{EntityMetadata, FunctionsName, ListFilters, TypeTemplate};
 use crate::{api::fetch_functions, components::button::Button};
 use leptos::*;
 use serde_json::{json, Value};
@@ -24,6 +25,7 @@ pub fn dimension_form<NF>(
     #[prop(default = String::new())] dimension_type: String,
     #[prop(default = Value::Null)] dimension_schema: Value,
     #[prop(default = None)] function_name: Option<Value>,
+    #[prop(default = EntityMetadata::default())] metadata: EntityMetadata,
     handle_submit: NF,
 ) -> impl IntoView
 where
@@ -36,6 +38,7 @@ where
     let (dimension_type_rs, dimension_type_ws) = create_signal(dimension_type);
     let (dimension_schema_rs, dimension_schema_ws) = create_signal(dimension_schema);
     let (function_name, set_function_name) = create_signal(function_name);
+    let metadata_rws = create_rw_signal(metadata);
     let (req_inprogess_rs, req_inprogress_ws) = create_signal(false);
     let functions_resource: Resource<String, Vec<crate::types::FunctionResponse>> =
         create_blocking_resource(
@@ -96,6 +99,7 @@ where
             priority: f_priority,
             schema: f_schema,
             function_name: fun_name,
+            metadata: metadata_rws.get(),
         };
 
         let handle_submit_clone = handle_submit.clone();
@@ -267,6 +271,10 @@ where
 
             </Suspense>
 
+            <div class="divider"></div>
+
+            <MetadataForm metadata=metadata_rws/>
+
             <div class="form-control grid w-full justify-start">
                 {move || {
                     let loading = req_inprogess_rs.get();
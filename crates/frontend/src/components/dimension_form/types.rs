@@ -1,10 +1,14 @@
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::types::EntityMetadata;
+
 #[derive(Serialize, Clone)]
 pub struct DimensionCreateReq {
     pub dimension: String,
     pub priority: u32,
     pub schema: Value,
     pub function_name: Option<Value>,
+    #[serde(flatten)]
+    pub metadata: EntityMetadata,
 }
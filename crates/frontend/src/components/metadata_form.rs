@@ -0,0 +1,188 @@
+use chrono::NaiveDate;
+use leptos::*;
+use serde_json::{Map, Value};
+
+use crate::components::table::types::Column;
+use crate::types::{EntityMetadata, MetadataFilters};
+
+#[component]
+pub fn metadata_form(metadata: RwSignal<EntityMetadata>) -> impl IntoView {
+    view! {
+        <div class="form-control">
+            <label class="label">
+                <span class="label-text">Description</span>
+            </label>
+            <textarea
+                placeholder="What is this used for?"
+                class="textarea textarea-bordered w-full max-w-md"
+                prop:value=move || metadata.get().description
+                on:change=move |ev| {
+                    let value = event_target_value(&ev);
+                    metadata.update(|m| m.description = value);
+                }
+            ></textarea>
+        </div>
+
+        <div class="form-control">
+            <label class="label">
+                <span class="label-text">Owner</span>
+            </label>
+            <input
+                type="text"
+                placeholder="Owning team or person"
+                class="input input-bordered w-full max-w-md"
+                prop:value=move || metadata.get().owner.unwrap_or_default()
+                on:change=move |ev| {
+                    let value = event_target_value(&ev);
+                    metadata.update(|m| m.owner = Some(value.trim().to_string()));
+                }
+            />
+        </div>
+
+        <div class="form-control">
+            <label class="label flex-col justify-center items-start">
+                <span class="label-text">Labels</span>
+                <span class="label-text text-slate-400">Comma separated</span>
+            </label>
+            <input
+                type="text"
+                placeholder="payments, checkout"
+                class="input input-bordered w-full max-w-md"
+                prop:value=move || metadata.get().labels.join(", ")
+                on:change=move |ev| {
+                    let labels = event_target_value(&ev)
+                        .split(',')
+                        .map(|label| label.trim().to_string())
+                        .filter(|label| !label.is_empty())
+                        .collect::<Vec<String>>();
+                    metadata.update(|m| m.labels = labels);
+                }
+            />
+        </div>
+
+        <div class="form-control w-full max-w-md">
+            <label class="label cursor-pointer">
+                <span class="label-text">Deprecated</span>
+                <input
+                    type="checkbox"
+                    class="toggle toggle-warning"
+                    prop:checked=move || metadata.get().deprecated
+                    on:change=move |ev| {
+                        let deprecated = event_target_checked(&ev);
+                        metadata
+                            .update(|m| {
+                                m.deprecated = deprecated;
+                                if !deprecated {
+                                    m.sunset_date = None;
+                                }
+                            });
+                    }
+                />
+            </label>
+        </div>
+
+        <Show when=move || metadata.get().deprecated>
+            <div class="form-control">
+                <label class="label">
+                    <span class="label-text">Sunset Date</span>
+                </label>
+                <input
+                    type="date"
+                    class="input input-bordered w-full max-w-md"
+                    prop:value=move || {
+                        metadata.get().sunset_date.map(|d| d.to_string()).unwrap_or_default()
+                    }
+                    on:change=move |ev| {
+                        let sunset_date = NaiveDate::parse_from_str(
+                                &event_target_value(&ev),
+                                "%Y-%m-%d",
+                            )
+                            .ok();
+                        metadata.update(|m| m.sunset_date = sunset_date);
+                    }
+                />
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+pub fn metadata_filter_bar(filters: RwSignal<MetadataFilters>) -> impl IntoView {
+    let to_filter = |value: String| {
+        let value = value.trim().to_string();
+        (!value.is_empty()).then_some(value)
+    };
+    view! {
+        <div class="flex gap-4">
+            <input
+                type="text"
+                placeholder="Filter by owner"
+                class="input input-bordered input-sm w-48"
+                prop:value=move || filters.get().owner.unwrap_or_default()
+                on:change=move |ev| {
+                    let owner = to_filter(event_target_value(&ev));
+                    filters.update(|f| f.owner = owner);
+                }
+            />
+            <input
+                type="text"
+                placeholder="Filter by labels"
+                class="input input-bordered input-sm w-48"
+                prop:value=move || filters.get().label.unwrap_or_default()
+                on:change=move |ev| {
+                    let label = to_filter(event_target_value(&ev));
+                    filters.update(|f| f.label = label);
+                }
+            />
+        </div>
+    }
+}
+
+/*
+  Table columns rendering the metadata of a row, the row is expected to
+  carry the flattened EntityMetadata fields along with a `status` entry.
+*/
+pub fn metadata_columns() -> Vec<Column> {
+    let owner_formatter = |_: &str, row: &Map<String, Value>| {
+        let owner = row
+            .get("owner")
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+            .to_string();
+        view! { <span>{owner}</span> }.into_view()
+    };
+    let labels_formatter = |value: &str, row: &Map<String, Value>| {
+        let labels = row.get("labels").and_then(Value::as_array);
+        match labels {
+            Some(labels) => labels
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|label| {
+                    let label = label.to_string();
+                    view! { <span class="badge badge-ghost mr-1">{label}</span> }
+                })
+                .collect_view(),
+            None => view! { <span>{value.to_string()}</span> }.into_view(),
+        }
+    };
+    let status_formatter = |_: &str, row: &Map<String, Value>| {
+        let status = row
+            .get("status")
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+            .to_string();
+        let class = if status.starts_with("deprecated") {
+            "badge badge-warning"
+        } else if status == "active" {
+            "badge badge-success"
+        } else {
+            ""
+        };
+        view! { <span class=class>{status}</span> }.into_view()
+    };
+    vec![
+        Column::new("owner".to_string(), None, owner_formatter),
+        Column::new("labels".to_string(), None, labels_formatter),
+        Column::new("status".to_string(), None, status_formatter),
+    ]
+}
@@ -58,6 +58,12 @@ fn create_routes(tenant: &str) -> Vec<AppRoute> {
             icon: "ri-camera-lens-fill".to_string(),
             label: "Config Versions".to_string(),
         },
+        AppRoute {
+            key: format!("{base}/admin/{tenant}/settings"),
+            path: format!("{base}/admin/{tenant}/settings"),
+            icon: "ri-settings-3-fill".to_string(),
+            label: "Settings".to_string(),
+        },
     ]
 }
 
@@ -0,0 +1,194 @@
+pub mod utils;
+
+use crate::components::{
+    alert::AlertType,
+    button::Button,
+    input::{Input, InputType},
+    tenant_config_form::utils::update_tenant_config,
+};
+use crate::providers::{alert_provider::enqueue_alert, editor_provider::EditorProvider};
+use crate::schema::{JsonSchemaType, SchemaType};
+use leptos::*;
+use serde_json::{json, Value};
+use web_sys::MouseEvent;
+
+#[component]
+pub fn tenant_config_form<NF>(config: Value, handle_submit: NF) -> impl IntoView
+where
+    NF: Fn() + 'static + Clone,
+{
+    let tenant_rs = use_context::<ReadSignal<String>>().unwrap();
+
+    let (mandatory_dimensions_rs, mandatory_dimensions_ws) = create_signal(
+        config
+            .get("mandatory_dimensions")
+            .cloned()
+            .unwrap_or(json!([])),
+    );
+    let (webhook_config_rs, webhook_config_ws) = create_signal(
+        config
+            .get("experiments_webhook_config")
+            .cloned()
+            .unwrap_or(json!({ "enabled": false })),
+    );
+    let (config_webhooks_rs, config_webhooks_ws) =
+        create_signal(config.get("config_webhooks").cloned().unwrap_or(json!([])));
+    let (change_reason_required_rs, change_reason_required_ws) = create_signal(
+        config
+            .get("change_reason_required")
+            .cloned()
+            .unwrap_or(json!(false)),
+    );
+    let (rate_limits_rs, rate_limits_ws) =
+        create_signal(config.get("rate_limits").cloned().unwrap_or(json!({})));
+    let (quotas_rs, quotas_ws) =
+        create_signal(config.get("quotas").cloned().unwrap_or(json!({})));
+    let (req_inprogess_rs, req_inprogress_ws) = create_signal(false);
+
+    let on_submit = move |ev: MouseEvent| {
+        req_inprogress_ws.set(true);
+        ev.prevent_default();
+        let payload = json!({
+            "mandatory_dimensions": mandatory_dimensions_rs.get(),
+            "experiments_webhook_config": webhook_config_rs.get(),
+            "config_webhooks": config_webhooks_rs.get(),
+            "change_reason_required": change_reason_required_rs.get(),
+            "rate_limits": rate_limits_rs.get(),
+            "quotas": quotas_rs.get(),
+        });
+
+        let handle_submit_clone = handle_submit.clone();
+        spawn_local({
+            let handle_submit = handle_submit_clone;
+            async move {
+                match update_tenant_config(tenant_rs.get(), payload).await {
+                    Ok(_) => {
+                        handle_submit();
+                        enqueue_alert(
+                            String::from("Tenant settings updated successfully!"),
+                            AlertType::Success,
+                            5000,
+                        );
+                    }
+                    Err(e) => {
+                        logging::error!(
+                            "An error occurred while trying to update the tenant settings {}",
+                            e
+                        );
+                        enqueue_alert(e, AlertType::Error, 5000);
+                    }
+                }
+                req_inprogress_ws.set(false);
+            }
+        });
+    };
+
+    view! {
+        <EditorProvider>
+            <form class="form-control w-full space-y-4 bg-white text-gray-700 font-mono">
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Mandatory Dimensions</span>
+                    </label>
+                    <Input
+                        id="mandatory-dimensions"
+                        class="mt-5 rounded-md resize-y w-full max-w-md pt-3"
+                        schema_type=SchemaType::Single(JsonSchemaType::Array)
+                        value=mandatory_dimensions_rs.get_untracked()
+                        on_change=Callback::new(move |value| mandatory_dimensions_ws.set(value))
+                        r#type=InputType::Monaco
+                    />
+                </div>
+
+                <div class="divider"></div>
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Experiments Webhook</span>
+                    </label>
+                    <Input
+                        id="experiments-webhook-config"
+                        class="mt-5 rounded-md resize-y w-full max-w-md pt-3"
+                        schema_type=SchemaType::Single(JsonSchemaType::Object)
+                        value=webhook_config_rs.get_untracked()
+                        on_change=Callback::new(move |value| webhook_config_ws.set(value))
+                        r#type=InputType::Monaco
+                    />
+                </div>
+
+                <div class="divider"></div>
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Config Webhooks</span>
+                    </label>
+                    <Input
+                        id="config-webhooks"
+                        class="mt-5 rounded-md resize-y w-full max-w-md pt-3"
+                        schema_type=SchemaType::Single(JsonSchemaType::Array)
+                        value=config_webhooks_rs.get_untracked()
+                        on_change=Callback::new(move |value| config_webhooks_ws.set(value))
+                        r#type=InputType::Monaco
+                    />
+                </div>
+
+                <div class="divider"></div>
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Change Reason Required</span>
+                    </label>
+                    <Input
+                        id="change-reason-required"
+                        schema_type=SchemaType::Single(JsonSchemaType::Boolean)
+                        value=change_reason_required_rs.get_untracked()
+                        on_change=Callback::new(move |value| change_reason_required_ws.set(value))
+                        r#type=InputType::Toggle
+                    />
+                </div>
+
+                <div class="divider"></div>
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Rate Limits</span>
+                    </label>
+                    <Input
+                        id="rate-limits"
+                        class="mt-5 rounded-md resize-y w-full max-w-md pt-3"
+                        schema_type=SchemaType::Single(JsonSchemaType::Object)
+                        value=rate_limits_rs.get_untracked()
+                        on_change=Callback::new(move |value| rate_limits_ws.set(value))
+                        r#type=InputType::Monaco
+                    />
+                </div>
+
+                <div class="divider"></div>
+                <div class="form-control">
+                    <label class="label">
+                        <span class="label-text">Quotas</span>
+                    </label>
+                    <Input
+                        id="quotas"
+                        class="mt-5 rounded-md resize-y w-full max-w-md pt-3"
+                        schema_type=SchemaType::Single(JsonSchemaType::Object)
+                        value=quotas_rs.get_untracked()
+                        on_change=Callback::new(move |value| quotas_ws.set(value))
+                        r#type=InputType::Monaco
+                    />
+                </div>
+
+                <div class="form-control grid w-full mt-5 justify-start">
+                    {move || {
+                        let loading = req_inprogess_rs.get();
+                        view! {
+                            <Button
+                                class="pl-[70px] pr-[70px] w-48 h-12".to_string()
+                                text="Submit".to_string()
+                                on_click=on_submit.clone()
+                                loading
+                            />
+                        }
+                    }}
+
+                </div>
+            </form>
+        </EditorProvider>
+    }
+}
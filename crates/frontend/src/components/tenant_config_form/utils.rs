@@ -0,0 +1,20 @@
+use serde_json::Value;
+
+use crate::utils::{construct_request_headers, get_host, request};
+
+pub async fn update_tenant_config(
+    tenant: String,
+    payload: Value,
+) -> Result<Value, String> {
+    let host = get_host();
+    let url = format!("{host}/tenants/{tenant}/config");
+
+    let response = request(
+        url,
+        reqwest::Method::PUT,
+        Some(payload),
+        construct_request_headers(&[])?,
+    )
+    .await?;
+    response.json().await.map_err(|e| e.to_string())
+}
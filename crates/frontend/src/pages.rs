@@ -9,3 +9,4 @@ pub mod experiment_list;
 pub mod function;
 pub mod home;
 pub mod not_found;
+pub mod tenant_settings;
@@ -110,6 +110,10 @@ pub fn config_version_list() -> impl IntoView {
                                                 json!(config_version.created_at),
                                             );
                                             map.insert("tags".to_string(), json!(config_version.tags));
+                                            map.insert(
+                                                "change_reason".to_string(),
+                                                json!(config_version.change_reason),
+                                            );
                                             map
                                         })
                                         .collect();
@@ -199,5 +203,17 @@ pub fn snapshot_table_columns(tenant: String) -> Vec<Column> {
                 .into_view()
             },
         ),
+        Column::new(
+            "change_reason".to_string(),
+            None,
+            |_value: &str, row: &Map<String, Value>| {
+                let change_reason = row
+                    .get("change_reason")
+                    .and_then(Value::as_str)
+                    .unwrap_or("-")
+                    .to_string();
+                view! { <span>{change_reason}</span> }.into_view()
+            },
+        ),
     ]
 }
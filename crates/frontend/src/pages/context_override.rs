@@ -5,7 +5,10 @@ use serde_json::{Map, Value};
 use superposition_types::{Config, Context};
 
 use crate::api::fetch_config;
-use crate::api::{delete_context, fetch_default_config, fetch_dimensions};
+use crate::api::{
+    delete_context, fetch_default_config, fetch_dimensions, fetch_disabled_contexts,
+    update_context_state,
+};
 use crate::components::alert::AlertType;
 use crate::components::button::Button;
 use crate::components::condition_pills::types::{Condition, ConditionOperator};
@@ -20,7 +23,10 @@ use crate::components::skeleton::{Skeleton, SkeletonVariant};
 use crate::providers::alert_provider::enqueue_alert;
 use crate::providers::condition_collapse_provider::ConditionCollapseProvider;
 use crate::providers::editor_provider::EditorProvider;
-use crate::types::{DefaultConfig, Dimension, ListFilters, PaginatedResponse};
+use crate::types::{
+    ContextResponse, DefaultConfig, Dimension, ListFilters, MetadataFilters,
+    PaginatedResponse,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct Data {
@@ -33,6 +39,7 @@ struct PageResource {
     config: Config,
     dimensions: Vec<Dimension>,
     default_config: Vec<DefaultConfig>,
+    disabled_contexts: Vec<ContextResponse>,
 }
 
 #[derive(Debug, Clone)]
@@ -152,10 +159,24 @@ pub fn context_override() -> impl IntoView {
                 count: None,
                 all: Some(true),
             };
-            let (config_result, dimensions_result, default_config_result) = join!(
+            let (
+                config_result,
+                dimensions_result,
+                default_config_result,
+                disabled_contexts_result,
+            ) = join!(
                 fetch_config(current_tenant.to_string(), None),
-                fetch_dimensions(empty_list_filters.clone(), current_tenant.to_string()),
-                fetch_default_config(empty_list_filters, current_tenant.to_string())
+                fetch_dimensions(
+                    empty_list_filters.clone(),
+                    MetadataFilters::default(),
+                    current_tenant.to_string()
+                ),
+                fetch_default_config(
+                    empty_list_filters,
+                    MetadataFilters::default(),
+                    current_tenant.to_string()
+                ),
+                fetch_disabled_contexts(current_tenant.to_string())
             );
             PageResource {
                 config: config_result.unwrap_or_default(),
@@ -168,6 +189,9 @@ pub fn context_override() -> impl IntoView {
                 default_config: default_config_result
                     .unwrap_or(PaginatedResponse::default())
                     .data,
+                disabled_contexts: disabled_contexts_result
+                    .unwrap_or(PaginatedResponse::default())
+                    .data,
             }
         },
     );
@@ -256,6 +280,22 @@ pub fn context_override() -> impl IntoView {
         set_modal_visible.set(false);
     });
 
+    let handle_context_toggle = Callback::new(move |(id, enabled): (String, bool)| {
+        spawn_local(async move {
+            let result = update_context_state(tenant_rs.get(), id, enabled).await;
+
+            match result {
+                Ok(_) => {
+                    logging::log!("Context state updated successfully");
+                    page_resource.refetch();
+                }
+                Err(e) => {
+                    logging::log!("Error updating context state: {:?}", e);
+                }
+            }
+        });
+    });
+
     view! {
         <div class="p-8">
             <div class="flex justify-between">
@@ -275,7 +315,7 @@ pub fn context_override() -> impl IntoView {
                 <div class="space-y-6">
 
                     {move || {
-                        let PageResource { config: _, dimensions, default_config } = page_resource
+                        let PageResource { dimensions, default_config, .. } = page_resource
                             .get()
                             .unwrap_or_default();
                         let data = selected_data.get();
@@ -340,8 +380,10 @@ pub fn context_override() -> impl IntoView {
                         }
                     }}
                     {move || {
-                        let config = page_resource.get().map(|v| v.config).unwrap_or_default();
-                        let ctx_n_overrides = config
+                        let PageResource { config, disabled_contexts, .. } = page_resource
+                            .get()
+                            .unwrap_or_default();
+                        let mut ctx_n_overrides = config
                             .contexts
                             .into_iter()
                             .map(|context| {
@@ -358,9 +400,17 @@ pub fn context_override() -> impl IntoView {
                                             .collect::<Vec<(String, Value)>>()
                                     })
                                     .collect::<Map<String, Value>>();
-                                (context.clone(), overrides)
+                                (context.clone(), overrides, true)
                             })
-                            .collect::<Vec<(Context, Map<String, Value>)>>();
+                            .collect::<Vec<(Context, Map<String, Value>, bool)>>();
+                        ctx_n_overrides
+                            .extend(
+                                disabled_contexts
+                                    .into_iter()
+                                    .map(|context| {
+                                        (context.to_context(), context.override_, false)
+                                    }),
+                            );
                         let is_empty = ctx_n_overrides.is_empty();
                         view! {
                             <Show when=move || is_empty>
@@ -377,14 +427,16 @@ pub fn context_override() -> impl IntoView {
 
                                 {ctx_n_overrides
                                     .into_iter()
-                                    .map(|(context, overrides)| {
+                                    .map(|(context, overrides, enabled)| {
                                         view! {
                                             <ContextCard
                                                 context=context
                                                 overrides=overrides
+                                                enabled=enabled
                                                 handle_edit=handle_context_edit
                                                 handle_clone=handle_context_clone
                                                 handle_delete=handle_context_delete
+                                                handle_toggle=handle_context_toggle
                                             />
                                         }
                                     })
@@ -400,7 +452,7 @@ pub fn context_override() -> impl IntoView {
                     modal_visible=modal_visible
                     confirm_delete=confirm_delete
                     set_modal_visible=set_modal_visible
-                    header_text="Are you sure you want to delete this context? Action is irreversible."
+                    header_text="Are you sure you want to delete this context? It can be restored until it is purged."
                         .to_string()
                 />
 
@@ -2,6 +2,7 @@ use crate::api::{delete_default_config, fetch_default_config};
 
 use crate::components::default_config_form::DefaultConfigForm;
 use crate::components::drawer::{close_drawer, open_drawer, Drawer, DrawerBtn};
+use crate::components::metadata_form::{metadata_columns, MetadataFilterBar};
 use crate::components::skeleton::Skeleton;
 use crate::components::stat::Stat;
 use crate::components::table::{
@@ -9,7 +10,9 @@ use crate::components::table::{
     Table,
 };
 
-use crate::types::{BreadCrums, ListFilters, PaginatedResponse};
+use crate::types::{
+    BreadCrums, EntityMetadata, ListFilters, MetadataFilters, PaginatedResponse,
+};
 use crate::utils::{
     get_local_storage, set_local_storage, unwrap_option_or_default_with_error,
     update_page_direction,
@@ -25,6 +28,8 @@ pub struct RowData {
     pub value: Value,
     pub schema: Value,
     pub function_name: Option<Value>,
+    pub metadata: EntityMetadata,
+    pub secret: bool,
 }
 
 #[component]
@@ -36,10 +41,11 @@ pub fn default_config() -> impl IntoView {
         count: Some(10),
         all: None,
     });
+    let metadata_filters = create_rw_signal(MetadataFilters::default());
     let default_config_resource = create_blocking_resource(
-        move || (tenant_rs.get(), filters.get()),
-        |(current_tenant, filters)| async move {
-            match fetch_default_config(filters, current_tenant).await {
+        move || (tenant_rs.get(), filters.get(), metadata_filters.get()),
+        |(current_tenant, filters, metadata_filters)| async move {
+            match fetch_default_config(filters, metadata_filters, current_tenant).await {
                 Ok(data) => data,
                 Err(_) => PaginatedResponse::default(),
             }
@@ -129,6 +135,14 @@ pub fn default_config() -> impl IntoView {
                 _ => Some(json!(function_name.replace('"', ""))),
             };
 
+            let metadata =
+                serde_json::from_value::<EntityMetadata>(Value::Object(row.clone()))
+                    .unwrap_or_default();
+            let secret = row
+                .get("secret")
+                .and_then(Value::as_bool)
+                .unwrap_or_default();
+
             let key_name = StoredValue::new(row_key.clone());
 
             let edit_click_handler = move |_| {
@@ -137,6 +151,8 @@ pub fn default_config() -> impl IntoView {
                     value: row_value.clone(),
                     schema: schema_object.clone(),
                     function_name: fun_name.clone(),
+                    metadata: metadata.clone(),
+                    secret,
                 };
                 logging::log!("{:?}", row_data);
                 selected_config.set(Some(row_data));
@@ -202,15 +218,19 @@ pub fn default_config() -> impl IntoView {
             }
         };
 
-        vec![
+        let mut columns = vec![
             Column::new("key".to_string(), None, expand),
             Column::default("schema".to_string()),
             Column::default("value".to_string()),
             Column::default("function_name".to_string()),
+        ];
+        columns.extend(metadata_columns());
+        columns.extend(vec![
             Column::default("created_at".to_string()),
             Column::default("created_by".to_string()),
             Column::new("actions".to_string(), None, actions_col_formatter),
-        ]
+        ]);
+        columns
     });
 
     let handle_close = move || {
@@ -239,6 +259,8 @@ pub fn default_config() -> impl IntoView {
                                     config_value=selected_config_data.value
                                     type_schema=selected_config_data.schema
                                     function_name=selected_config_data.function_name
+                                    metadata=selected_config_data.metadata
+                                    secret=selected_config_data.secret
                                     prefix
                                     handle_submit=move || {
                                         default_config_resource.refetch();
@@ -288,6 +310,8 @@ pub fn default_config() -> impl IntoView {
                                     json!(config.created_at.format("%v").to_string()),
                                 );
                             ele_map
+                                .insert("status".to_string(), json!(config.metadata.status()));
+                            ele_map
                         })
                         .collect::<Vec<Map<String, Value>>>();
                     let mut filtered_rows = table_rows.clone();
@@ -329,6 +353,9 @@ pub fn default_config() -> impl IntoView {
                                 <div class="flex justify-between pb-2">
                                     <BreadCrums bread_crums=bread_crums.get() folder_click_handler/>
                                     <div class="flex">
+                                        <div class="mr-10">
+                                            <MetadataFilterBar filters=metadata_filters/>
+                                        </div>
                                         <label
                                             on:click=move |_| {
                                                 folder_click_handler(None);
@@ -3,13 +3,14 @@ use crate::components::drawer::{close_drawer, open_drawer, Drawer, DrawerBtn};
 use crate::components::skeleton::Skeleton;
 use crate::components::{
     delete_modal::DeleteModal,
+    metadata_form::{metadata_columns, MetadataFilterBar},
     stat::Stat,
     table::{
         types::{Column, TablePaginationProps},
         Table,
     },
 };
-use crate::types::{ListFilters, PaginatedResponse};
+use crate::types::{EntityMetadata, ListFilters, MetadataFilters, PaginatedResponse};
 use crate::utils::update_page_direction;
 use leptos::*;
 
@@ -24,6 +25,7 @@ pub struct RowData {
     pub schema: Value,
     pub function_name: Option<Value>,
     pub mandatory: bool,
+    pub metadata: EntityMetadata,
 }
 
 #[component]
@@ -36,10 +38,11 @@ pub fn dimensions() -> impl IntoView {
         count: Some(10),
         all: None,
     });
+    let metadata_filters = create_rw_signal(MetadataFilters::default());
     let dimensions_resource = create_blocking_resource(
-        move || (tenant_rs.get(), filters.get()),
-        |(current_tenant, filters)| async move {
-            match fetch_dimensions(filters, current_tenant).await {
+        move || (tenant_rs.get(), filters.get(), metadata_filters.get()),
+        |(current_tenant, filters, metadata_filters)| async move {
+            match fetch_dimensions(filters, metadata_filters, current_tenant).await {
                 Ok(data) => data,
                 Err(_) => PaginatedResponse::default(),
             }
@@ -95,6 +98,9 @@ pub fn dimensions() -> impl IntoView {
                 _ => Some(json!(function_name.replace('"', ""))),
             };
             let mandatory = row["mandatory"].as_bool().unwrap_or(false);
+            let metadata =
+                serde_json::from_value::<EntityMetadata>(Value::Object(row.clone()))
+                    .unwrap_or_default();
             let dimension_name = row_dimension.clone();
 
             let edit_click_handler = move |_| {
@@ -104,6 +110,7 @@ pub fn dimensions() -> impl IntoView {
                     schema: schema.clone(),
                     function_name: fun_name.clone(),
                     mandatory: mandatory.clone(),
+                    metadata: metadata.clone(),
                 };
                 logging::log!("{:?}", row_data);
                 selected_dimension.set(Some(row_data));
@@ -137,16 +144,20 @@ pub fn dimensions() -> impl IntoView {
                 .into_view()
             }
         };
-        vec![
+        let mut columns = vec![
             Column::default("dimension".to_string()),
             Column::default("priority".to_string()),
             Column::default("schema".to_string()),
             Column::default("mandatory".to_string()),
             Column::default("function_name".to_string()),
+        ];
+        columns.extend(metadata_columns());
+        columns.extend(vec![
             Column::default("created_by".to_string()),
             Column::default("created_at".to_string()),
             Column::new("actions".to_string(), None, action_col_formatter),
-        ]
+        ]);
+        columns
     });
 
     view! {
@@ -169,6 +180,7 @@ pub fn dimensions() -> impl IntoView {
                                 dimension_name=selected_dimension_data.dimension
                                 dimension_schema=selected_dimension_data.schema
                                 function_name=selected_dimension_data.function_name
+                                metadata=selected_dimension_data.metadata
                                 handle_submit=move || {
                                     dimensions_resource.refetch();
                                     selected_dimension.set(None);
@@ -211,6 +223,7 @@ pub fn dimensions() -> impl IntoView {
                                     "created_at".to_string(),
                                     json!(ele.created_at.format("%v").to_string()),
                                 );
+                            ele_map.insert("status".to_string(), json!(ele.metadata.status()));
                             ele_map
                         })
                         .collect::<Vec<Map<String, Value>>>();
@@ -233,10 +246,13 @@ pub fn dimensions() -> impl IntoView {
                                     <h2 class="card-title chat-bubble text-gray-800 dark:text-white bg-white font-mono">
                                         "Dimensions"
                                     </h2>
-                                    <DrawerBtn drawer_id="dimension_drawer"
-                                        .to_string()>
-                                        Create Dimension <i class="ri-edit-2-line ml-2"></i>
-                                    </DrawerBtn>
+                                    <div class="flex gap-4">
+                                        <MetadataFilterBar filters=metadata_filters/>
+                                        <DrawerBtn drawer_id="dimension_drawer"
+                                            .to_string()>
+                                            Create Dimension <i class="ri-edit-2-line ml-2"></i>
+                                        </DrawerBtn>
+                                    </div>
                                 </div>
                                 <Table
                                     cell_class="min-w-48 font-mono".to_string()
@@ -253,7 +269,7 @@ pub fn dimensions() -> impl IntoView {
                     modal_visible=delete_modal_visible_rs
                     confirm_delete=confirm_delete
                     set_modal_visible=delete_modal_visible_ws
-                    header_text="Are you sure you want to delete this dimension? Action is irreversible."
+                    header_text="Are you sure you want to delete this dimension? It can be restored until it is purged."
                         .to_string()
                 />
             </Suspense>
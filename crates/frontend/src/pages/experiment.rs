@@ -14,7 +14,10 @@ use crate::{
         skeleton::{Skeleton, SkeletonVariant},
     },
     providers::editor_provider::EditorProvider,
-    types::{DefaultConfig, Dimension, Experiment, ListFilters, PaginatedResponse},
+    types::{
+        DefaultConfig, Dimension, Experiment, ListFilters, MetadataFilters,
+        PaginatedResponse,
+    },
     utils::{close_modal, show_modal},
 };
 
@@ -48,10 +51,16 @@ pub fn experiment_page() -> impl IntoView {
                 count: None,
                 all: Some(true),
             };
-            let dimensions_future =
-                fetch_dimensions(empty_list_filters.clone(), tenant.to_string());
-            let config_future =
-                fetch_default_config(empty_list_filters, tenant.to_string());
+            let dimensions_future = fetch_dimensions(
+                empty_list_filters.clone(),
+                MetadataFilters::default(),
+                tenant.to_string(),
+            );
+            let config_future = fetch_default_config(
+                empty_list_filters,
+                MetadataFilters::default(),
+                tenant.to_string(),
+            );
 
             let (experiments_result, dimensions_result, config_result) =
                 join!(experiments_future, dimensions_future, config_future);
@@ -13,7 +13,9 @@ use crate::components::{experiment_form::ExperimentForm, stat::Stat, table::Tabl
 
 use crate::providers::condition_collapse_provider::ConditionCollapseProvider;
 use crate::providers::editor_provider::EditorProvider;
-use crate::types::{ExpListFilters, ExperimentResponse, ListFilters, PaginatedResponse};
+use crate::types::{
+    ExpListFilters, ExperimentResponse, ListFilters, MetadataFilters, PaginatedResponse,
+};
 use crate::utils::update_page_direction;
 
 use self::utils::experiment_table_columns;
@@ -65,10 +67,16 @@ pub fn experiment_list() -> impl IntoView {
                 count: None,
                 all: Some(true),
             };
-            let dimensions_future =
-                fetch_dimensions(empty_list_filters.clone(), current_tenant.to_string());
-            let config_future =
-                fetch_default_config(empty_list_filters, current_tenant.to_string());
+            let dimensions_future = fetch_dimensions(
+                empty_list_filters.clone(),
+                MetadataFilters::default(),
+                current_tenant.to_string(),
+            );
+            let config_future = fetch_default_config(
+                empty_list_filters,
+                MetadataFilters::default(),
+                current_tenant.to_string(),
+            );
 
             let (experiments_result, dimensions_result, config_result) =
                 join!(experiments_future, dimensions_future, config_future);
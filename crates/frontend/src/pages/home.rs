@@ -12,7 +12,7 @@ use web_sys::{HtmlButtonElement, HtmlSpanElement, MouseEvent};
 use crate::components::condition_pills::types::Conditions;
 use crate::components::skeleton::{Skeleton, SkeletonVariant};
 use crate::providers::condition_collapse_provider::ConditionCollapseProvider;
-use crate::types::ListFilters;
+use crate::types::{ListFilters, MetadataFilters};
 use crate::{
     api::{fetch_config, fetch_dimensions},
     components::{
@@ -191,6 +191,7 @@ pub fn home() -> impl IntoView {
                     count: None,
                     all: Some(true),
                 },
+                MetadataFilters::default(),
                 tenant,
             )
             .await
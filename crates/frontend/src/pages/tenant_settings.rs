@@ -0,0 +1,64 @@
+use crate::api::fetch_tenant_config;
+use crate::components::{
+    alert::{Alert, AlertType},
+    skeleton::Skeleton,
+    tenant_config_form::TenantConfigForm,
+    toast::Toast,
+};
+use leptos::*;
+
+#[component]
+pub fn tenant_settings() -> impl IntoView {
+    let tenant_rs = use_context::<ReadSignal<String>>().unwrap();
+    let config_resource = create_blocking_resource(
+        move || tenant_rs.get(),
+        |tenant| async move { fetch_tenant_config(tenant).await },
+    );
+
+    view! {
+        <div class="p-8">
+            <Suspense fallback=move || view! { <Skeleton/> }>
+                {move || {
+                    match config_resource.get() {
+                        Some(Ok(config_info)) => {
+                            view! {
+                                <div class="card rounded-xl w-full bg-base-100 shadow">
+                                    <div class="card-body">
+                                        <h2 class="card-title">Tenant Settings</h2>
+                                        <p class="text-sm text-gray-500">
+                                            {format!(
+                                                "Last modified by {} at {}",
+                                                config_info.last_modified_by,
+                                                config_info.last_modified_at.format("%v"),
+                                            )}
+                                        </p>
+                                        <TenantConfigForm
+                                            config=config_info.config
+                                            handle_submit=move || config_resource.refetch()
+                                        />
+                                    </div>
+                                </div>
+                            }
+                                .into_view()
+                        }
+                        Some(Err(_)) => {
+                            view! {
+                                <Toast alerts=vec![
+                                    Alert::new(
+                                        0,
+                                        "Error loading tenant settings.".to_string(),
+                                        AlertType::Error,
+                                        5000,
+                                    ),
+                                ]/>
+                            }
+                                .into_view()
+                        }
+                        None => view! { <Skeleton/> },
+                    }
+                }}
+
+            </Suspense>
+        </div>
+    }
+}
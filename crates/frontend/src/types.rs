@@ -2,9 +2,10 @@ use leptos::{ReadSignal, WriteSignal};
 use serde::{Deserialize, Serialize};
 use std::{str::FromStr, vec::Vec};
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use derive_more::{Deref, DerefMut};
 use serde_json::{json, Map, Value};
+use superposition_types::{Condition as ContextCondition, Context};
 
 use crate::components::{
     condition_pills::{types::Condition, utils::extract_conditions},
@@ -230,6 +231,8 @@ pub struct Dimension {
     pub created_by: String,
     pub schema: Value,
     pub function_name: Option<String>,
+    #[serde(flatten)]
+    pub metadata: EntityMetadata,
     pub mandatory: bool,
 }
 
@@ -250,6 +253,10 @@ pub struct DefaultConfig {
     pub created_by: String,
     pub schema: Value,
     pub function_name: Option<String>,
+    #[serde(flatten)]
+    pub metadata: EntityMetadata,
+    #[serde(default)]
+    pub secret: bool,
 }
 
 impl DropdownOption for DefaultConfig {
@@ -309,6 +316,14 @@ pub struct FetchTypeTemplateResponse {
     pub data: Vec<TypeTemplate>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TenantConfigResponse {
+    pub tenant: String,
+    pub config: Value,
+    pub last_modified_at: NaiveDateTime,
+    pub last_modified_by: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigVersionListResponse {
     pub total_pages: u64,
@@ -323,6 +338,7 @@ pub struct ConfigVersion {
     pub created_at: String,
     pub id: u64,
     pub tags: Option<Vec<String>>,
+    pub change_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -332,6 +348,56 @@ pub struct ListFilters {
     pub all: Option<bool>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataFilters {
+    pub owner: Option<String>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntityMetadata {
+    #[serde(default)]
+    pub description: String,
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    pub sunset_date: Option<NaiveDate>,
+}
+
+impl EntityMetadata {
+    pub fn status(&self) -> String {
+        match (self.deprecated, self.sunset_date) {
+            (true, Some(date)) => format!("deprecated (sunset {date})"),
+            (true, None) => "deprecated".to_string(),
+            (false, _) => "active".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextResponse {
+    pub id: String,
+    pub value: ContextCondition,
+    pub override_id: String,
+    pub priority: i32,
+    #[serde(rename = "override")]
+    pub override_: Map<String, Value>,
+    pub enabled: bool,
+}
+
+impl ContextResponse {
+    pub fn to_context(&self) -> Context {
+        Context {
+            id: self.id.clone(),
+            condition: self.value.clone(),
+            priority: self.priority,
+            override_with_keys: [self.override_id.clone()],
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone, Deserialize)]
 pub struct PaginatedResponse<T> {
     pub total_pages: i64,
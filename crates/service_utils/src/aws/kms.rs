@@ -1,29 +1,20 @@
-use crate::helpers::get_from_env_unsafe;
+use anyhow::anyhow;
 use aws_sdk_kms::{primitives::Blob, Client};
 use base64::{engine::general_purpose, Engine};
 
-pub async fn decrypt(aws_kms_cli: Client, key: &str) -> String {
-    let key_value_env: String =
-        get_from_env_unsafe(key).expect(&format!("{key} not present in env"));
-    let key_value_enc = general_purpose::STANDARD
-        .decode(key_value_env)
-        .expect("Input string does not contain valid base 64 characters.");
+/// Decrypts a base64 encoded ciphertext with KMS.
+pub async fn decrypt(aws_kms_cli: &Client, ciphertext: &str) -> anyhow::Result<String> {
+    let ciphertext = general_purpose::STANDARD.decode(ciphertext)?;
 
-    let key_value_bytes_result = aws_kms_cli
+    let output = aws_kms_cli
         .decrypt()
-        .ciphertext_blob(Blob::new(key_value_enc))
+        .ciphertext_blob(Blob::new(ciphertext))
         .send()
-        .await;
-    let key_value: String = String::from_utf8(
-        key_value_bytes_result
-            .expect(&format!("Failed to decrypt {key}"))
-            .plaintext()
-            .expect(&format!("Failed to get plaintext value for {key}"))
-            .as_ref()
-            .to_vec(),
-    )
-    .expect("Could not convert to UTF-8");
-    key_value
+        .await?;
+    let plaintext = output
+        .plaintext()
+        .ok_or_else(|| anyhow!("KMS returned no plaintext"))?;
+    Ok(String::from_utf8(plaintext.as_ref().to_vec())?)
 }
 
 pub async fn new_client() -> Client {
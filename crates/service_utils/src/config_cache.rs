@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use actix_web::web::Data;
+use futures_util::{stream, StreamExt};
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use postgres_openssl::MakeTlsConnector;
+use tokio_postgres::{config::SslMode, AsyncMessage};
+
+use crate::service::types::AppState;
+
+/// Channel the CAC schema of every new config version is notified on.
+pub const CONFIG_VERSIONS_CHANNEL: &str = "superposition_config_versions";
+
+/// Latest config of a CAC schema, serialized once to be served as is.
+pub struct CachedConfig {
+    pub config: String,
+    pub version: Option<i64>,
+    pub last_modified: String,
+    pub audit_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Entries {
+    configs: HashMap<String, Arc<CachedConfig>>,
+    generation: u64,
+    listening: bool,
+}
+
+/*
+  Latest config of every CAC schema, kept in the memory of every replica.
+  A config is cached when it is first read and dropped when a new config
+  version of its schema is notified, every invalidation bumps the
+  generation so that a config read before it is never cached after it.
+  Nothing is cached while the listener is disconnected, as it could not be
+  invalidated.
+*/
+#[derive(Default)]
+pub struct ConfigCache {
+    entries: RwLock<Entries>,
+}
+
+impl ConfigCache {
+    pub fn get(&self, namespace: &str) -> Option<Arc<CachedConfig>> {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .configs
+            .get(namespace)
+            .cloned()
+    }
+
+    /// Generation to pass to [`ConfigCache::insert`], taken before reading the
+    /// config from the database.
+    pub fn generation(&self) -> u64 {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .generation
+    }
+
+    /// Caches `config` unless the cache was invalidated since `generation` or
+    /// the listener is disconnected.
+    pub fn insert(
+        &self,
+        namespace: String,
+        generation: u64,
+        config: CachedConfig,
+    ) -> Arc<CachedConfig> {
+        let config = Arc::new(config);
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        if entries.listening && entries.generation == generation {
+            entries.configs.insert(namespace, config.clone());
+        }
+        config
+    }
+
+    pub fn invalidate(&self, namespace: &str) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.generation += 1;
+        entries.configs.remove(namespace);
+    }
+
+    /// Drops every config, and caches configs from now on only when
+    /// `listening`.
+    fn reset(&self, listening: bool) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.generation += 1;
+        entries.configs.clear();
+        entries.listening = listening;
+    }
+}
+
+/*
+  Listens on CONFIG_VERSIONS_CHANNEL over a connection of its own and
+  invalidates the config and the deprecations of the notified schema.
+  Notifications sent while the connection is down are lost, so the whole
+  cache is dropped and caching stops as soon as it drops, and the cache is
+  dropped again once it is re-established before caching resumes.
+*/
+pub async fn run_config_cache_listener(state: Data<AppState>, retry_period: Duration) {
+    loop {
+        if let Err(err) = listen_for_config_versions(&state).await {
+            log::error!("config cache listener failed: {err}");
+        }
+        state.config_cache.reset(false);
+        state.deprecations.reset(false);
+        actix_web::rt::time::sleep(retry_period).await;
+    }
+}
+
+/*
+  The database pools connect through libpq, which takes its TLS settings
+  from PGSSLMODE (prefer by default), PGSSLROOTCERT, PGSSLCERT and PGSSLKEY,
+  so the listener connects with the same ones. As with libpq, the
+  certificate of the server is only verified with verify-ca and
+  verify-full, or when a root certificate is given.
+*/
+fn listener_tls() -> anyhow::Result<(SslMode, MakeTlsConnector)> {
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let mode = env("PGSSLMODE").unwrap_or_else(|| String::from("prefer"));
+    let root_cert = env("PGSSLROOTCERT");
+    let (ssl_mode, verify) = match mode.as_str() {
+        "disable" => (SslMode::Disable, false),
+        "allow" | "prefer" => (SslMode::Prefer, root_cert.is_some()),
+        "require" => (SslMode::Require, root_cert.is_some()),
+        "verify-ca" | "verify-full" => (SslMode::Require, true),
+        mode => anyhow::bail!("unsupported PGSSLMODE {mode}"),
+    };
+
+    let mut builder = SslConnector::builder(SslMethod::tls())?;
+    if let Some(root_cert) = root_cert {
+        builder.set_ca_file(root_cert)?;
+    }
+    if let (Some(cert), Some(key)) = (env("PGSSLCERT"), env("PGSSLKEY")) {
+        builder.set_certificate_chain_file(cert)?;
+        builder.set_private_key_file(key, SslFiletype::PEM)?;
+    }
+    if !verify {
+        builder.set_verify(SslVerifyMode::NONE);
+    }
+    let mut connector = MakeTlsConnector::new(builder.build());
+    let verify_hostname = mode == "verify-full";
+    connector.set_callback(move |config, _| {
+        config.set_verify_hostname(verify_hostname);
+        Ok(())
+    });
+    Ok((ssl_mode, connector))
+}
+
+async fn listen_for_config_versions(state: &Data<AppState>) -> anyhow::Result<()> {
+    let (ssl_mode, tls) = listener_tls()?;
+    let mut config = state
+        .db_pool
+        .database_url()
+        .parse::<tokio_postgres::Config>()?;
+    config.ssl_mode(ssl_mode);
+    let (client, mut connection) = config.connect(tls).await?;
+    let listener_state = state.clone();
+    // notifications are only read while the connection is polled
+    let notifications = actix_web::rt::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            if let AsyncMessage::Notification(notification) = message? {
+                let namespace = notification.payload();
+                listener_state.config_cache.invalidate(namespace);
+                listener_state.deprecations.invalidate(namespace);
+            }
+        }
+        Ok::<_, tokio_postgres::Error>(())
+    });
+    client
+        .batch_execute(&format!("LISTEN {CONFIG_VERSIONS_CHANNEL}"))
+        .await?;
+    state.config_cache.reset(true);
+    state.deprecations.reset(true);
+    log::info!("listening for config versions on {CONFIG_VERSIONS_CHANNEL}");
+    notifications.await??;
+    Ok(())
+}
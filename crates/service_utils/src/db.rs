@@ -1,2 +1,4 @@
+pub mod instrumentation;
+pub mod migrations;
 pub mod pgschema_manager;
 pub mod utils;
@@ -0,0 +1,170 @@
+use std::fmt::{self, Write};
+
+use diesel::{
+    connection::{Instrumentation, InstrumentationEvent},
+    r2d2::{self, CustomizeConnection},
+    Connection, PgConnection,
+};
+use opentelemetry::{
+    trace::{SpanKind, Status, TraceContextExt},
+    Context,
+};
+
+use crate::telemetry::{is_tracing_enabled, start_span, KeyValue};
+
+/// Only this much of the sql of a query is read to tell its kind and table.
+const SQL_PREFIX_LEN: usize = 512;
+
+/*
+  Every query run on a connection of the pools gets a client span, named
+  after the kind of statement and the table it touches, which are also set
+  as its attributes. The sql itself is left out, as the values bound into
+  it can be secrets.
+*/
+#[derive(Default)]
+struct QuerySpans {
+    current: Option<Context>,
+}
+
+impl Instrumentation for QuerySpans {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                // the binds are printed after the sql, stop before them
+                let mut sql = SqlPrefix::default();
+                let _ = write!(sql, "{query}");
+                let (operation, table) = describe_statement(&sql.0);
+                let mut attributes = vec![
+                    KeyValue::new("db.system", "postgresql"),
+                    KeyValue::new("db.operation", operation.clone()),
+                ];
+                let name = match table {
+                    Some(table) => {
+                        attributes.push(KeyValue::new("db.sql.table", table.clone()));
+                        format!("{operation} {table}")
+                    }
+                    None => operation,
+                };
+                self.current = Some(start_span(name, SpanKind::Client, attributes));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let (Some(cx), Some(error)) = (self.current.take(), error) {
+                    cx.span().set_status(Status::error(error.to_string()));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Keeps the first `SQL_PREFIX_LEN` bytes written to it and fails the write
+/// after that, which stops the formatting early.
+#[derive(Default)]
+struct SqlPrefix(String);
+
+impl Write for SqlPrefix {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remaining = SQL_PREFIX_LEN.saturating_sub(self.0.len());
+        if s.len() <= remaining {
+            self.0.push_str(s);
+            return Ok(());
+        }
+        let end = (0..=remaining)
+            .rev()
+            .find(|end| s.is_char_boundary(*end))
+            .unwrap_or(0);
+        self.0.push_str(&s[..end]);
+        Err(fmt::Error)
+    }
+}
+
+/// Kind of the statement `sql`, such as `SELECT`, and the table it reads or
+/// writes when it can be told.
+fn describe_statement(sql: &str) -> (String, Option<String>) {
+    let mut words = sql.split_whitespace();
+    let operation = words.next().unwrap_or_default().to_uppercase();
+    let table_keyword = match operation.as_str() {
+        "SELECT" | "DELETE" | "WITH" => "FROM",
+        "INSERT" => "INTO",
+        "UPDATE" => return (operation, words.next().and_then(table_name)),
+        _ => return (operation, None),
+    };
+    let table = words
+        .skip_while(|word| !word.eq_ignore_ascii_case(table_keyword))
+        .nth(1)
+        .and_then(table_name);
+    (operation, table)
+}
+
+/// `"public"."contexts"` and `contexts` both name the `contexts` table.
+fn table_name(word: &str) -> Option<String> {
+    let name = word
+        .rsplit('.')
+        .next()
+        .unwrap_or(word)
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '_');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Installs the query spans on every connection of a pool, as long as
+/// tracing is enabled.
+#[derive(Debug)]
+pub struct QueryTracing;
+
+impl CustomizeConnection<PgConnection, r2d2::Error> for QueryTracing {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+        if is_tracing_enabled() {
+            conn.set_instrumentation(QuerySpans::default());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_statement() {
+        assert_eq!(
+            describe_statement(
+                r#"SELECT "contexts"."id" FROM "contexts" WHERE "contexts"."id" = $1"#
+            ),
+            (String::from("SELECT"), Some(String::from("contexts")))
+        );
+        assert_eq!(
+            describe_statement(
+                r#"INSERT INTO "public"."config_versions" ("id") VALUES ($1)"#
+            ),
+            (
+                String::from("INSERT"),
+                Some(String::from("config_versions"))
+            )
+        );
+        assert_eq!(
+            describe_statement(r#"UPDATE "dimensions" SET "position" = $1"#),
+            (String::from("UPDATE"), Some(String::from("dimensions")))
+        );
+        assert_eq!(
+            describe_statement("delete from default_configs where key = $1"),
+            (
+                String::from("DELETE"),
+                Some(String::from("default_configs"))
+            )
+        );
+        assert_eq!(describe_statement("BEGIN"), (String::from("BEGIN"), None));
+        assert_eq!(
+            describe_statement("SELECT 1"),
+            (String::from("SELECT"), None)
+        );
+    }
+
+    #[test]
+    fn test_sql_prefix() {
+        let mut sql = SqlPrefix::default();
+        let long = "é".repeat(SQL_PREFIX_LEN);
+        assert!(write!(sql, "SELECT {long}").is_err());
+        assert!(sql.0.len() <= SQL_PREFIX_LEN);
+        assert!(sql.0.starts_with("SELECT é"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::any::Any;
+
+use anyhow::anyhow;
+use diesel::{
+    connection::{BoxableConnection, SimpleConnection},
+    migration::MigrationSource,
+    pg::Pg,
+    sql_query,
+    sql_types::Text,
+    PgConnection, QueryResult, QueryableByName, RunQueryDsl,
+};
+use superposition_types::result::{self, AppError};
+
+/*
+  Embedded migrations can only be run against a connection and the
+  migrations of this repository create everything in the `public` schema
+  explicitly. This collector is handed to a migration in place of a
+  connection so that its sql can be rewritten for a tenant schema before it
+  is executed, the same way `scripts/create-tenant.sh` does it.
+*/
+#[derive(Default)]
+struct MigrationSqlCollector(String);
+
+impl SimpleConnection for MigrationSqlCollector {
+    fn batch_execute(&mut self, query: &str) -> QueryResult<()> {
+        self.0.push_str(query);
+        self.0.push('\n');
+        Ok(())
+    }
+}
+
+impl BoxableConnection<Pg> for MigrationSqlCollector {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = Text)]
+    version: String,
+}
+
+/// Creates `schema` and runs the pending migrations of `source` in it,
+/// tracking them in the schema's own `__diesel_schema_migrations` table so
+/// that the diesel cli sees them as applied. `schema` should be a validated
+/// identifier and this should be called inside a transaction, so that a
+/// failing migration does not leave a half provisioned schema behind.
+pub fn run_schema_migrations(
+    conn: &mut PgConnection,
+    schema: &str,
+    source: &dyn MigrationSource<Pg>,
+) -> result::Result<Vec<String>> {
+    conn.batch_execute(&format!(
+        "CREATE SCHEMA IF NOT EXISTS {schema};
+        SET LOCAL search_path TO {schema};
+        CREATE TABLE IF NOT EXISTS {schema}.__diesel_schema_migrations (
+            version VARCHAR(50) PRIMARY KEY NOT NULL,
+            run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );"
+    ))?;
+    let applied = sql_query(format!(
+        "SELECT version FROM {schema}.__diesel_schema_migrations"
+    ))
+    .load::<AppliedMigration>(conn)?
+    .into_iter()
+    .map(|migration| migration.version)
+    .collect::<Vec<_>>();
+
+    let mut migrations = source
+        .migrations()
+        .map_err(|err| AppError::UnexpectedError(anyhow!(err.to_string())))?;
+    migrations.sort_by_key(|migration| migration.name().version().to_string());
+
+    let mut ran = Vec::new();
+    for migration in migrations {
+        let version = migration.name().version().to_string();
+        if applied.contains(&version) {
+            continue;
+        }
+        let mut collector = MigrationSqlCollector::default();
+        migration.run(&mut collector).map_err(|err| {
+            AppError::UnexpectedError(anyhow!(
+                "failed to read migration {}: {}",
+                migration.name(),
+                err
+            ))
+        })?;
+        conn.batch_execute(&collector.0.replace("public", schema))
+            .map_err(|err| {
+                log::error!("migration {} failed on {schema}: {err}", migration.name());
+                err
+            })?;
+        sql_query(format!(
+            "INSERT INTO {schema}.__diesel_schema_migrations (version) VALUES ($1)"
+        ))
+        .bind::<Text, _>(&version)
+        .execute(conn)?;
+        ran.push(version);
+    }
+    Ok(ran)
+}
@@ -1,11 +1,20 @@
 extern crate derive_more;
-use derive_more::{Deref, DerefMut, Display};
+use derive_more::Display;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 use diesel::{
-    r2d2::{ConnectionManager, Pool, PooledConnection},
-    PgConnection,
+    r2d2::{ConnectionManager, Pool, PooledConnection, State},
+    sql_query,
+    sql_types::{Bool, Double, Text},
+    PgConnection, QueryableByName, RunQueryDsl,
+};
+
+use crate::{
+    db::instrumentation::QueryTracing,
+    telemetry::{in_span, KeyValue},
 };
 
 pub type PgSchemaConnectionPool = Pool<ConnectionManager<PgConnection>>;
@@ -45,34 +54,329 @@ impl ConnectionConfig {
     }
 }
 
-#[derive(Deref, DerefMut, Clone)]
-pub struct PgSchemaManager(HashMap<String, PgSchemaConnectionPool>);
+/// A replica is taken off reads for this long once it is found lagging or
+/// unreachable, and trusted for this long once it is found caught up.
+const REPLICA_LAG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const REPLICA_CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
 
-impl From<Vec<ConnectionConfig>> for PgSchemaManager {
-    fn from(value: Vec<ConnectionConfig>) -> Self {
-        let mut schema_manager: PgSchemaManager = PgSchemaManager(HashMap::new());
-        for config in value.into_iter() {
-            let manager = ConnectionManager::<PgConnection>::new(config.conn_url());
-            schema_manager.insert(
-                config.name.clone(),
-                Pool::builder()
-                    .max_size(config.count)
-                    .build(manager)
-                    .unwrap_or_else(|_| {
-                        panic!("Invalid config provided, {}", config.name)
-                    }),
-            );
+#[derive(QueryableByName)]
+struct WalPosition {
+    #[diesel(sql_type = Text)]
+    lsn: String,
+}
+
+#[derive(QueryableByName)]
+struct ReplicaLag {
+    #[diesel(sql_type = Bool)]
+    in_recovery: bool,
+    #[diesel(sql_type = Double)]
+    behind_bytes: f64,
+    #[diesel(sql_type = Bool)]
+    streaming: bool,
+    #[diesel(sql_type = Double)]
+    lag_secs: f64,
+}
+
+/// The pool of a namespace, over the primary or over its replica.
+pub struct PoolState {
+    pub namespace: String,
+    pub replica: bool,
+    pub state: State,
+}
+
+impl PoolState {
+    pub fn name(&self) -> String {
+        if self.replica {
+            format!("{}:replica", self.namespace)
+        } else {
+            self.namespace.clone()
         }
-        schema_manager
     }
 }
 
+/*
+  Pool over the replica of a schema along with the last verdict on its lag,
+  so that the lag is not queried on every checkout.
+*/
+#[derive(Clone)]
+struct ReplicaPool {
+    pool: PgSchemaConnectionPool,
+    verdict: Arc<Mutex<Option<(Instant, bool)>>>,
+}
+
+/*
+  Pools are keyed by namespace and can be added or removed while the
+  server is running, as tenants get provisioned or archived. With a replica
+  configured every namespace gets a second pool over it, which reads that
+  tolerate lag are served from.
+*/
+#[derive(Clone)]
+pub struct PgSchemaManager {
+    database_url: String,
+    pool_size: u32,
+    pools: Arc<RwLock<HashMap<String, PgSchemaConnectionPool>>>,
+    replica_url: Option<String>,
+    max_replica_lag: Duration,
+    replica_pools: Arc<RwLock<HashMap<String, ReplicaPool>>>,
+}
+
 impl PgSchemaManager {
+    pub fn new(database_url: String, pool_size: u32) -> Self {
+        PgSchemaManager {
+            database_url,
+            pool_size,
+            pools: Arc::new(RwLock::new(HashMap::new())),
+            replica_url: None,
+            max_replica_lag: Duration::ZERO,
+            replica_pools: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Serves reads from the replica at `replica_url` of the pools added
+    /// after this, as long as it lags the primary by at most `max_lag`.
+    pub fn with_replica(mut self, replica_url: String, max_lag: Duration) -> Self {
+        self.replica_url = Some(replica_url);
+        self.max_replica_lag = max_lag;
+        self
+    }
+
+    pub fn database_url(&self) -> &str {
+        &self.database_url
+    }
+
+    pub fn add_pool(&self, namespace: String) -> anyhow::Result<()> {
+        let config = ConnectionConfig::new(
+            namespace.clone(),
+            self.database_url.clone(),
+            namespace.clone(),
+            self.pool_size,
+        );
+        let manager = ConnectionManager::<PgConnection>::new(config.conn_url());
+        let pool = Pool::builder()
+            .max_size(config.count)
+            .connection_customizer(Box::new(QueryTracing))
+            .build(manager)
+            .map_err(|err| anyhow!("Invalid config provided, {}: {}", config, err))?;
+        self.pools
+            .write()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .insert(config.name, pool);
+
+        if let Some(replica_url) = &self.replica_url {
+            let config = ConnectionConfig::new(
+                namespace.clone(),
+                replica_url.clone(),
+                namespace,
+                self.pool_size,
+            );
+            let manager = ConnectionManager::<PgConnection>::new(config.conn_url());
+            // a replica which is down falls back to the primary instead of failing
+            let pool = Pool::builder()
+                .max_size(config.count)
+                .connection_timeout(REPLICA_CONNECTION_TIMEOUT)
+                .connection_customizer(Box::new(QueryTracing))
+                .build_unchecked(manager);
+            self.replica_pools
+                .write()
+                .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+                .insert(
+                    config.name,
+                    ReplicaPool {
+                        pool,
+                        verdict: Arc::new(Mutex::new(None)),
+                    },
+                );
+        }
+        Ok(())
+    }
+
+    pub fn remove_pool(&self, namespace: &str) -> anyhow::Result<()> {
+        self.pools
+            .write()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .remove(namespace);
+        self.replica_pools
+            .write()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .remove(namespace);
+        Ok(())
+    }
+
+    /// Connections opened and idle in the pools of every namespace.
+    pub fn pool_states(&self) -> anyhow::Result<Vec<PoolState>> {
+        let mut states = self
+            .pools
+            .read()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .iter()
+            .map(|(name, pool)| PoolState {
+                namespace: name.clone(),
+                replica: false,
+                state: pool.state(),
+            })
+            .collect::<Vec<_>>();
+        states.extend(
+            self.replica_pools
+                .read()
+                .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+                .iter()
+                .map(|(name, replica)| PoolState {
+                    namespace: name.clone(),
+                    replica: true,
+                    state: replica.pool.state(),
+                }),
+        );
+        Ok(states)
+    }
+
     pub fn get_conn(&self, name: String) -> anyhow::Result<PgSchemaConnection> {
-        let conn = self
+        let pool = self
+            .pools
+            .read()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
             .get(&name) // gets the pool for the given namespace
-            .ok_or_else(|| anyhow!("Invalid connection name provided: {}", name))?
-            .get()?; // fetches the connection from the pool
+            .cloned()
+            .ok_or_else(|| anyhow!("Invalid connection name provided: {}", name))?;
+        // fetches the connection from the pool
+        let conn = in_span(
+            "db pool checkout",
+            vec![KeyValue::new("db.namespace", name)],
+            || pool.get(),
+        )?;
         Ok(conn)
     }
+
+    /*
+      Connection for reads which can be served slightly stale, from the
+      replica of the namespace when it is caught up with the primary, from
+      the primary otherwise. Writes, and reads which have to see them, have
+      to use `get_conn`.
+    */
+    pub fn get_read_conn(&self, name: String) -> anyhow::Result<PgSchemaConnection> {
+        let replica = self
+            .replica_pools
+            .read()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .get(&name)
+            .cloned();
+        let Some(replica) = replica else {
+            return self.get_conn(name);
+        };
+        let conn = in_span(
+            "db replica checkout",
+            vec![KeyValue::new("db.namespace", name.clone())],
+            || self.checkout_replica(&name, &replica),
+        );
+        match conn {
+            Some(conn) => Ok(conn),
+            None => self.get_conn(name),
+        }
+    }
+
+    fn checkout_replica(
+        &self,
+        name: &str,
+        replica: &ReplicaPool,
+    ) -> Option<PgSchemaConnection> {
+        let last_verdict = *replica
+            .verdict
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let caught_up = match last_verdict {
+            Some((checked_at, caught_up))
+                if checked_at.elapsed() < REPLICA_LAG_CHECK_INTERVAL =>
+            {
+                Some(caught_up)
+            }
+            _ => None,
+        };
+        if caught_up == Some(false) {
+            return None;
+        }
+        let conn = replica
+            .pool
+            .get()
+            .map_err(|err| log::error!("failed to reach the replica: {err}"))
+            .ok();
+        let conn = match (conn, caught_up) {
+            (Some(conn), Some(true)) => return Some(conn),
+            (Some(mut conn), _) => self.is_caught_up(name, &mut conn).then_some(conn),
+            (None, _) => None,
+        };
+        *replica
+            .verdict
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) =
+            Some((Instant::now(), conn.is_some()));
+        conn
+    }
+
+    fn is_caught_up(&self, name: &str, conn: &mut PgSchemaConnection) -> bool {
+        match self.check_replica_lag(name, conn) {
+            Ok(()) => true,
+            Err(err) => {
+                log::warn!("{err}, reading {name} from the primary");
+                false
+            }
+        }
+    }
+
+    /*
+      The replica is caught up when it has replayed the WAL the primary had
+      written when asked, or lags it by at most `max_replica_lag` while it
+      is still streaming. The time since the last replayed transaction is
+      only meaningful while the primary is ahead, as it just grows while
+      the primary is idle.
+    */
+    fn check_replica_lag(
+        &self,
+        name: &str,
+        conn: &mut PgSchemaConnection,
+    ) -> anyhow::Result<()> {
+        let primary_lsn = sql_query("SELECT pg_current_wal_lsn()::text AS lsn")
+            .get_result::<WalPosition>(&mut self.get_conn(name.to_string())?)
+            .map_err(|err| {
+                anyhow!("failed to read the wal position of the primary: {err}")
+            })?
+            .lsn;
+        let lag = sql_query(
+            "SELECT pg_is_in_recovery() AS in_recovery, \
+             COALESCE(pg_wal_lsn_diff($1::pg_lsn, pg_last_wal_replay_lsn()), 0)::float8 \
+             AS behind_bytes, \
+             EXISTS (SELECT 1 FROM pg_stat_wal_receiver WHERE status = 'streaming') \
+             AS streaming, \
+             COALESCE(EXTRACT(EPOCH FROM \
+             now() - pg_last_xact_replay_timestamp()), 0)::float8 \
+             AS lag_secs",
+        )
+        .bind::<Text, _>(primary_lsn)
+        .get_result::<ReplicaLag>(conn)
+        .map_err(|err| anyhow!("failed to read the replica lag: {err}"))?;
+        let delay = Duration::from_secs_f64(lag.lag_secs.max(0.0));
+        match lag {
+            ReplicaLag {
+                in_recovery: false, ..
+            } => Ok(()),
+            ReplicaLag { behind_bytes, .. } if behind_bytes <= 0.0 => Ok(()),
+            ReplicaLag {
+                streaming: false, ..
+            } => Err(anyhow!("the replica stopped streaming from the primary")),
+            _ if delay <= self.max_replica_lag => Ok(()),
+            _ => Err(anyhow!("the replica lags by {delay:?}")),
+        }
+    }
+
+    /// Checks out a connection from the replica of `name` and checks it is
+    /// caught up with the primary.
+    pub fn check_replica(&self, name: &str) -> anyhow::Result<()> {
+        let replica = self
+            .replica_pools
+            .read()
+            .map_err(|_| anyhow!("pg schema manager lock is poisoned"))?
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("{name} has no replica"))?;
+        let mut conn = replica.pool.get()?;
+        self.check_replica_lag(name, &mut conn)
+    }
 }
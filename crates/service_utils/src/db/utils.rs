@@ -1,79 +1,120 @@
 use std::collections::HashSet;
+use std::time::Duration;
 
-use aws_sdk_kms::Client;
 use urlencoding::encode;
 
-use crate::aws::kms;
-use crate::db::pgschema_manager::{ConnectionConfig, PgSchemaManager};
+use crate::db::pgschema_manager::PgSchemaManager;
 use crate::helpers::{get_from_env_or_default, get_from_env_unsafe};
-use crate::service::types::AppEnv;
+use crate::secrets::SecretProvider;
+use crate::service::types::{AppEnv, AppScope};
+
+/// Namespace of the pool over the schema which keeps the tenant registry.
+pub const TENANT_REGISTRY_NAMESPACE: &str = "superposition";
+
+pub fn get_tenant_namespaces(tenant: &str) -> [String; 2] {
+    [
+        format!("{}_{}", tenant, AppScope::CAC),
+        format!("{}_{}", tenant, AppScope::EXPERIMENTATION),
+    ]
+}
+
+/*
+  Secrets missing in DEV and TEST fall back to the values the local setup
+  uses, they are required everywhere else.
+*/
+async fn get_secret_or_dev_default(
+    secret_provider: &dyn SecretProvider,
+    app_env: &AppEnv,
+    name: &str,
+    dev_default: &str,
+) -> String {
+    match (secret_provider.get_secret(name).await, app_env) {
+        (Ok(secret), _) => secret,
+        (Err(_), AppEnv::DEV | AppEnv::TEST) => dev_default.to_string(),
+        (Err(err), _) => panic!("Failed to get {name}, error: {err:#}"),
+    }
+}
 
 pub async fn get_superposition_token(
-    kms_client: &Option<Client>,
+    secret_provider: &dyn SecretProvider,
     app_env: &AppEnv,
 ) -> String {
-    match app_env {
-        AppEnv::DEV | AppEnv::TEST | AppEnv::SANDBOX => {
-            get_from_env_or_default("SUPERPOSITION_TOKEN", "123456".into())
-        }
-        _ => {
-            let kms_client = kms_client.clone().unwrap();
-            let superposition_token_raw =
-                kms::decrypt(kms_client, "SUPERPOSITION_TOKEN").await;
-            encode(superposition_token_raw.as_str()).to_string()
-        }
+    /*
+      SANDBOX has always taken the token as plain text from the env, while
+      its database password goes through the secret provider.
+    */
+    if let AppEnv::SANDBOX = app_env {
+        return get_from_env_or_default("SUPERPOSITION_TOKEN", String::from("123456"));
     }
+    let superposition_token_raw = get_secret_or_dev_default(
+        secret_provider,
+        app_env,
+        "SUPERPOSITION_TOKEN",
+        "123456",
+    )
+    .await;
+    encode(superposition_token_raw.as_str()).to_string()
 }
 
-pub async fn get_database_url(kms_client: &Option<Client>, app_env: &AppEnv) -> String {
+async fn get_database_url_of_host(
+    secret_provider: &dyn SecretProvider,
+    app_env: &AppEnv,
+    db_host: &str,
+) -> String {
     let db_user: String = get_from_env_unsafe("DB_USER").unwrap();
-    let db_password: String = match app_env {
-        AppEnv::DEV | AppEnv::TEST => {
-            get_from_env_or_default("DB_PASSWORD", "docker".into())
-        }
-        _ => {
-            let kms_client = kms_client.clone().unwrap();
-            let db_password_raw = kms::decrypt(kms_client, "DB_PASSWORD").await;
-            encode(db_password_raw.as_str()).to_string()
-        }
-    };
-    let db_host: String = get_from_env_unsafe("DB_HOST").unwrap();
+    let db_password_raw =
+        get_secret_or_dev_default(secret_provider, app_env, "DB_PASSWORD", "docker")
+            .await;
+    let db_password = encode(db_password_raw.as_str()).to_string();
     let db_name: String = get_from_env_unsafe("DB_NAME").unwrap();
     format!("postgres://{db_user}:{db_password}@{db_host}/{db_name}")
 }
 
+pub async fn get_database_url(
+    secret_provider: &dyn SecretProvider,
+    app_env: &AppEnv,
+) -> String {
+    let db_host: String = get_from_env_unsafe("DB_HOST").unwrap();
+    get_database_url_of_host(secret_provider, app_env, &db_host).await
+}
+
+/// Url of the read replica at `DB_REPLICA_HOST`, which is reached with the
+/// credentials of the primary.
+pub async fn get_replica_database_url(
+    secret_provider: &dyn SecretProvider,
+    app_env: &AppEnv,
+) -> Option<String> {
+    let db_host: String = std::env::var("DB_REPLICA_HOST").ok()?;
+    Some(get_database_url_of_host(secret_provider, app_env, &db_host).await)
+}
+
 pub async fn init_pool_manager(
     tenants: HashSet<String>,
     enable_tenant_and_scope: bool,
-    kms_client: &Option<Client>,
+    secret_provider: &dyn SecretProvider,
     app_env: &AppEnv,
     max_pool_size: u32,
 ) -> PgSchemaManager {
-    let database_url = get_database_url(kms_client, app_env).await;
+    let database_url = get_database_url(secret_provider, app_env).await;
     let namespaces = match (enable_tenant_and_scope, app_env) {
         (true, _) => tenants
             .iter()
-            .flat_map(|tenant| {
-                [
-                    format!("{}_cac", tenant),
-                    format!("{}_experimentation", tenant),
-                ]
-            })
+            .flat_map(|tenant| get_tenant_namespaces(tenant))
+            .chain([TENANT_REGISTRY_NAMESPACE.to_string()])
             .collect::<Vec<String>>(),
         (false, _) => vec!["cac_v1".to_string()],
     };
 
-    let connection_configs = namespaces
-        .iter()
-        .map(|namespace| {
-            ConnectionConfig::new(
-                namespace.to_string(),
-                database_url.clone(),
-                namespace.to_string(),
-                max_pool_size,
-            )
-        })
-        .collect::<Vec<ConnectionConfig>>();
-
-    PgSchemaManager::from(connection_configs)
+    let mut pool_manager = PgSchemaManager::new(database_url, max_pool_size);
+    if let Some(replica_url) = get_replica_database_url(secret_provider, app_env).await {
+        let max_lag_secs = get_from_env_or_default("DB_REPLICA_MAX_LAG_SECS", 5);
+        pool_manager =
+            pool_manager.with_replica(replica_url, Duration::from_secs(max_lag_secs));
+    }
+    for namespace in namespaces {
+        pool_manager
+            .add_pool(namespace)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+    pool_manager
 }
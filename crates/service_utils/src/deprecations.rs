@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+
+/// How long deprecations are reused while no listener invalidates them, which
+/// bounds how late the writes of other replicas show up.
+const UNLISTENED_TTL: Duration = Duration::from_secs(60);
+
+/// Deprecated default configs and dimensions of a CAC schema, with their
+/// sunset dates.
+pub struct Deprecations {
+    pub keys: HashMap<String, Option<NaiveDate>>,
+    pub dimensions: HashMap<String, Option<NaiveDate>>,
+}
+
+#[derive(Default)]
+struct Entries {
+    deprecations: HashMap<String, (Instant, Arc<Deprecations>)>,
+    generation: u64,
+    listening: bool,
+}
+
+/*
+  Deprecations of every CAC schema, kept in the memory of every replica so
+  that serving a config does not query them on every request. They are
+  dropped along with the config of their schema when the config cache
+  listener is notified of a change, the writes to default configs and
+  dimensions both notifying it. While no listener runs they are only kept for
+  UNLISTENED_TTL, as the writes of other replicas cannot be seen.
+*/
+#[derive(Default)]
+pub struct DeprecationCache {
+    entries: RwLock<Entries>,
+}
+
+impl DeprecationCache {
+    pub fn get(&self, namespace: &str) -> Option<Arc<Deprecations>> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .deprecations
+            .get(namespace)
+            .filter(|(loaded_at, _)| {
+                entries.listening || loaded_at.elapsed() < UNLISTENED_TTL
+            })
+            .map(|(_, deprecations)| deprecations.clone())
+    }
+
+    /// Generation to pass to [`DeprecationCache::insert`], taken before
+    /// reading the deprecations from the database.
+    pub fn generation(&self) -> u64 {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .generation
+    }
+
+    /// Caches `deprecations` unless the cache was invalidated since
+    /// `generation`.
+    pub fn insert(
+        &self,
+        namespace: String,
+        generation: u64,
+        deprecations: Deprecations,
+    ) -> Arc<Deprecations> {
+        let deprecations = Arc::new(deprecations);
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        if entries.generation == generation {
+            entries
+                .deprecations
+                .insert(namespace, (Instant::now(), deprecations.clone()));
+        }
+        deprecations
+    }
+
+    pub fn invalidate(&self, namespace: &str) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.generation += 1;
+        entries.deprecations.remove(namespace);
+    }
+
+    /// Drops all the deprecations, and keeps them until they are invalidated
+    /// from now on only when `listening`.
+    #[cfg(feature = "config-cache")]
+    pub(crate) fn reset(&self, listening: bool) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.generation += 1;
+        entries.deprecations.clear();
+        entries.listening = listening;
+    }
+}
@@ -0,0 +1,176 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, error, web::Data, Error, FromRequest, HttpRequest};
+use base64::{engine::general_purpose, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, OsRng},
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+};
+use serde_json::Value;
+
+use crate::service::types::{AppState, Tenant};
+
+/// Prefix of the values of secret configs, as they are stored.
+pub const ENCRYPTED_VALUE_PREFIX: &str = "enc:v1:";
+/// What the values of secret configs are shown as to those who cannot read them.
+pub const SECRET_MASK: &str = "********";
+
+const NONCE_LENGTH: usize = 12;
+
+/*
+  Values of secret configs are encrypted with keys derived for every tenant
+  from `CONFIG_SECRETS_KEY`, each with a random nonce stored in front of its
+  ciphertext, so that equal values do not encrypt to the same text.
+*/
+pub struct TenantCipher(Option<[u8; 32]>);
+
+impl TenantCipher {
+    pub fn new(master_key: Option<&str>, tenant: &str) -> Self {
+        TenantCipher(master_key.map(|master_key| {
+            let material = format!("{tenant}:{master_key}");
+            blake3::derive_key(
+                "superposition config secrets v1 encryption",
+                material.as_bytes(),
+            )
+        }))
+    }
+
+    fn key(&self) -> Result<&[u8; 32], String> {
+        self.0.as_ref().ok_or_else(|| {
+            String::from(
+                "CONFIG_SECRETS_KEY is not set, secret configs are not available",
+            )
+        })
+    }
+
+    pub fn encrypt(&self, value: &Value) -> Result<Value, String> {
+        let key = self.key()?;
+        let plaintext = serde_json::to_vec(value).map_err(|err| err.to_string())?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| String::from("failed to encrypt the secret value"))?;
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(Value::String(format!(
+            "{ENCRYPTED_VALUE_PREFIX}{}",
+            general_purpose::STANDARD.encode(payload)
+        )))
+    }
+
+    /// Encrypts `value`, or keeps `stored` when it already holds `value`, so
+    /// that writing a secret again unchanged does not change what is stored
+    /// nor the ids of the overrides holding it.
+    pub fn encrypt_keeping(
+        &self,
+        value: &Value,
+        stored: Option<&Value>,
+    ) -> Result<Value, String> {
+        match stored {
+            Some(stored)
+                if is_encrypted(stored)
+                    && self.decrypt(stored).is_ok_and(|stored| stored == *value) =>
+            {
+                Ok(stored.clone())
+            }
+            _ => self.encrypt(value),
+        }
+    }
+
+    pub fn decrypt(&self, value: &Value) -> Result<Value, String> {
+        let key = self.key()?;
+        let payload = encrypted_payload(value)
+            .ok_or_else(|| String::from("the value is not encrypted"))?;
+        let payload = general_purpose::STANDARD
+            .decode(payload)
+            .map_err(|err| format!("the encrypted value is not valid base64: {err}"))?;
+        if payload.len() < NONCE_LENGTH {
+            return Err(String::from("the encrypted value is too short"));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LENGTH);
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| String::from("failed to decrypt the secret value"))?;
+        serde_json::from_slice(&plaintext).map_err(|err| err.to_string())
+    }
+}
+
+impl FromRequest for TenantCipher {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let app_state = match req.app_data::<Data<AppState>>() {
+            Some(val) => val,
+            None => {
+                log::error!("app state not set");
+                return ready(Err(error::ErrorInternalServerError("")));
+            }
+        };
+        let result = Tenant::from_request(req, payload)
+            .into_inner()
+            .map(|tenant| {
+                TenantCipher::new(app_state.config_secrets_key.as_deref(), &tenant)
+            });
+        ready(result)
+    }
+}
+
+fn encrypted_payload(value: &Value) -> Option<&str> {
+    value
+        .as_str()
+        .and_then(|value| value.strip_prefix(ENCRYPTED_VALUE_PREFIX))
+}
+
+pub fn is_encrypted(value: &Value) -> bool {
+    encrypted_payload(value).is_some()
+}
+
+pub fn is_mask(value: &Value) -> bool {
+    value.as_str() == Some(SECRET_MASK)
+}
+
+/// Replaces every encrypted value nested in `value` with the mask.
+pub fn mask_secrets(value: &mut Value) {
+    if is_encrypted(value) {
+        *value = Value::String(SECRET_MASK.to_string());
+        return;
+    }
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(mask_secrets),
+        Value::Object(map) => map.values_mut().for_each(mask_secrets),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tenant_cipher() {
+        let cipher = TenantCipher::new(Some("master key"), "tenant");
+        let value = json!({"token": "abc"});
+
+        let encrypted = cipher.encrypt(&value).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_ne!(encrypted, cipher.encrypt(&value).unwrap());
+        assert_eq!(cipher.decrypt(&encrypted), Ok(value.clone()));
+        assert!(TenantCipher::new(Some("master key"), "other")
+            .decrypt(&encrypted)
+            .is_err());
+        assert!(TenantCipher::new(None, "tenant").encrypt(&value).is_err());
+
+        assert_eq!(
+            cipher.encrypt_keeping(&value, Some(&encrypted)),
+            Ok(encrypted.clone())
+        );
+        let changed = cipher
+            .encrypt_keeping(&json!({"token": "def"}), Some(&encrypted))
+            .unwrap();
+        assert_ne!(changed, encrypted);
+        assert_eq!(cipher.decrypt(&changed), Ok(json!({"token": "def"})));
+    }
+}
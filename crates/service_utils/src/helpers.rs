@@ -1,18 +1,16 @@
-use crate::service::types::{AppState, Tenant};
-use actix_web::{error::ErrorInternalServerError, web::Data, Error};
+use crate::{service::types::AppState, telemetry::send_traced};
+use actix_web::{
+    error::ErrorInternalServerError,
+    web::{self, Data},
+    Error,
+};
 use anyhow::anyhow;
-use chrono::Utc;
 use jsonschema::{error::ValidationErrorKind, ValidationError};
 use log::info;
+use opentelemetry::Context;
 use regex::Regex;
-use reqwest::{
-    header::{HeaderMap, HeaderName, HeaderValue},
-    StatusCode,
-};
-use serde::{
-    de::{self, IntoDeserializer},
-    Serialize,
-};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::{self, IntoDeserializer};
 use serde_json::{Map, Value};
 use std::{
     env::VarError,
@@ -21,9 +19,6 @@ use std::{
 };
 use superposition_types::{
     result::{self, AppError},
-    webhook::{
-        HeadersEnum, HttpMethod, Webhook, WebhookEvent, WebhookEventInfo, WebhookResponse,
-    },
     Condition,
 };
 
@@ -373,10 +368,30 @@ where
         _ => request_builder,
     };
 
-    let response = request_builder.send().await?;
+    let response = send_traced("internal request", request_builder).await?;
 
     response.json::<R>().await
 }
+
+/*
+  Runs `f` on the blocking thread pool, so that diesel queries hold up
+  neither the worker nor the other requests it serves. Spans started by
+  `f` stay children of the span of the caller.
+*/
+pub async fn run_blocking<T, F>(f: F) -> result::Result<T>
+where
+    F: FnOnce() -> result::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let cx = Context::current();
+    web::block(move || {
+        let _guard = cx.attach();
+        f()
+    })
+    .await
+    .map_err(|err| AppError::UnexpectedError(err.into()))?
+}
+
 pub fn generate_snowflake_id(state: &Data<AppState>) -> result::Result<i64> {
     let mut snowflake_generator = state.snowflake_generator.lock().map_err(|e| {
         log::error!("snowflake_id generation failed {}", e);
@@ -415,95 +430,20 @@ pub fn parse_config_tags(
     }
 }
 
-pub async fn execute_webhook_call<T>(
-    webhook_config: &Webhook,
-    payload: &T,
-    config_version_opt: &Option<String>,
-    tenant: &Tenant,
-    event: WebhookEvent,
-    http_client: &reqwest::Client,
-) -> Result<(), AppError>
-where
-    T: Serialize,
-{
-    let mut header_array = webhook_config
-        .service_headers
-        .clone()
-        .into_iter()
-        .filter_map(|key| match key {
-            HeadersEnum::ConfigVersion => {
-                if let Some(config_version) = config_version_opt {
-                    Some((key.to_string(), config_version.to_string()))
-                } else {
-                    None
-                }
-            }
-            HeadersEnum::TenantId => Some((key.to_string(), tenant.to_string())),
+/*
+  Splits an If-Match / If-None-Match header into its entity tags, dropping
+  the weak validator prefix and the surrounding quotes.
+*/
+pub fn parse_etags(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(|tag| {
+            let tag = tag.trim();
+            tag.strip_prefix("W/")
+                .unwrap_or(tag)
+                .trim_matches('"')
+                .to_string()
         })
-        .collect::<Vec<(String, String)>>();
-
-    webhook_config
-        .custom_headers
-        .clone()
-        .into_iter()
-        .for_each(|(key, value)| header_array.push((key, value)));
-
-    if let Some(auth) = &webhook_config.authorization {
-        let auth_token_value: String =
-                get_from_env_unsafe(&auth.value).map_err(|err| {
-                    log::error!("Failed to retrieve authentication token for the webhook with error: {}", err);
-                    AppError::WebhookError(
-                        String::from("Failed to retrieve authentication token for the webhook. Please verify the credentials in TenantConfig.")
-                    )
-                })?;
-        header_array.push((auth.key.clone(), auth_token_value));
-    }
-
-    let mut headers = HeaderMap::new();
-    header_array.iter().for_each(|(name, value)| {
-        let h_name = HeaderName::from_str(name);
-        let h_value = HeaderValue::from_str(value);
-
-        if let (Ok(key), Ok(value)) = (h_name, h_value) {
-            headers.insert(key, value);
-        }
-    });
-
-    let request_builder = match webhook_config.method {
-        HttpMethod::Post => http_client.post(&webhook_config.url),
-        HttpMethod::Get => http_client.get(&webhook_config.url),
-    };
-
-    let response = request_builder
-        .headers(headers.into())
-        .json(&WebhookResponse {
-            event_info: WebhookEventInfo {
-                webhook_event: event,
-                time: Utc::now().naive_utc().to_string(),
-                tenant_id: tenant.to_string(),
-                config_version: config_version_opt.clone(),
-            },
-            payload,
-        })
-        .send()
-        .await;
-
-    match response {
-        Ok(res) => {
-            match res.status() {
-                StatusCode::OK => {
-                    log::info!("webhook call succeeded: {:?}", res.status());
-                    Ok(())
-                }
-                _ => {
-                    log::error!("Webhook call failed with status code: {:?}, response headers: {:?}", res.status(), res.headers());
-                    Err(AppError::WebhookError(format!("Webhook call failed with status code: {:?}, Please check the logs and retry.", res.status())))
-                }
-            }
-        }
-        Err(err) => {
-            log::error!("Webhook call failed with error: {:?}", err);
-            Err(AppError::WebhookError(err.to_string()))
-        }
-    }
+        .filter(|tag| !tag.is_empty())
+        .collect()
 }
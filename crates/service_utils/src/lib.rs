@@ -1,6 +1,17 @@
 #![deny(unused_crate_dependencies)]
+#[cfg(all(feature = "high-performance-mode", feature = "config-cache"))]
+compile_error!("high-performance-mode and config-cache serve /config/fast differently, enable only one of them");
+
 pub mod aws;
+#[cfg(feature = "config-cache")]
+pub mod config_cache;
 pub mod db;
+pub mod deprecations;
+pub mod encryption;
 pub mod helpers;
+pub mod metrics;
 pub mod middlewares;
+pub mod secrets;
 pub mod service;
+pub mod telemetry;
+pub mod webhooks;
@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// Content type of the metrics rendered by [`Metrics::encode`].
+pub const METRICS_CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/*
+  Metrics exported in the prometheus text format from `/metrics`. Counters
+  and histograms are updated where the events happen, gauges of the pools
+  and of the contexts of every tenant are refreshed on every scrape.
+*/
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub config_versions_created: IntCounter,
+    pub contexts: IntGaugeVec,
+    pub webhook_calls: IntCounterVec,
+    pub validation_function_duration: HistogramVec,
+}
+
+fn register<T>(registry: &Registry, metric: T) -> T
+where
+    T: prometheus::core::Collector + Clone + 'static,
+{
+    registry
+        .register(Box::new(metric.clone()))
+        .expect("metric names should be unique");
+    metric
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some(String::from("superposition")), None)
+            .expect("metrics prefix should be valid");
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requests served, by route and tenant"),
+            &["method", "route", "tenant", "status"],
+        )
+        .expect("http_requests_total should be valid");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to serve requests, by route and tenant",
+            ),
+            &["method", "route", "tenant"],
+        )
+        .expect("http_request_duration_seconds should be valid");
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Connections of the database pool of every namespace",
+            ),
+            &["pool", "state"],
+        )
+        .expect("db_pool_connections should be valid");
+        let config_versions_created = IntCounter::new(
+            "config_versions_created_total",
+            "Config versions created by writes",
+        )
+        .expect("config_versions_created_total should be valid");
+        let contexts = IntGaugeVec::new(
+            Opts::new("contexts", "Active contexts of every tenant"),
+            &["tenant"],
+        )
+        .expect("contexts should be valid");
+        let webhook_calls = IntCounterVec::new(
+            Opts::new("webhook_calls_total", "Webhook calls made, by outcome"),
+            &["tenant", "outcome"],
+        )
+        .expect("webhook_calls_total should be valid");
+        let validation_function_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "validation_function_duration_seconds",
+                "Time taken to execute validation functions",
+            ),
+            &["outcome"],
+        )
+        .expect("validation_function_duration_seconds should be valid");
+
+        Metrics {
+            http_requests: register(&registry, http_requests),
+            http_request_duration: register(&registry, http_request_duration),
+            db_pool_connections: register(&registry, db_pool_connections),
+            config_versions_created: register(&registry, config_versions_created),
+            contexts: register(&registry, contexts),
+            webhook_calls: register(&registry, webhook_calls),
+            validation_function_duration: register(
+                &registry,
+                validation_function_duration,
+            ),
+            registry,
+        }
+    }
+
+    /// Renders every metric in the prometheus text format.
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Label of the outcome of an operation, for counters and histograms.
+pub fn outcome_label<T, E>(result: &Result<T, E>) -> &'static str {
+    match result {
+        Ok(_) => "success",
+        Err(_) => "failure",
+    }
+}
@@ -1,2 +1,8 @@
 pub mod app_scope;
+pub mod authentication;
+pub mod authorization;
+pub mod idempotency;
+pub mod rate_limit;
+pub mod request_metrics;
+pub mod request_tracing;
 pub mod tenant;
@@ -0,0 +1,145 @@
+pub mod api_key;
+pub mod internal;
+pub mod jwt;
+
+use std::future::{ready, Ready};
+use std::{rc::Rc, sync::Arc};
+
+use crate::service::types::AppState;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error,
+    http::header,
+    web::Data,
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use serde_json::json;
+use superposition_types::User;
+
+/*
+  A provider returns `Ok(None)` for requests which carry no credentials it
+  understands, so that the next provider gets to look at them, and an error
+  for credentials it understands but which are not valid.
+*/
+pub trait AuthenticationProvider: Send + Sync {
+    fn authenticate<'a>(
+        &'a self,
+        req: &'a ServiceRequest,
+        app_state: &'a AppState,
+    ) -> LocalBoxFuture<'a, Result<Option<User>, String>>;
+}
+
+pub type AuthenticationProviders = Arc<Vec<Arc<dyn AuthenticationProvider>>>;
+
+/// Splits the authorization header of `req` into its scheme and credentials.
+fn get_authorization(req: &ServiceRequest) -> Option<(&str, &str)> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|auth| auth.to_str().ok())
+        .and_then(|auth| auth.split_once(' '))
+        .map(|(scheme, credentials)| (scheme, credentials.trim()))
+}
+
+pub struct AuthenticationMiddlewareFactory {
+    providers: AuthenticationProviders,
+    allow_anonymous: bool,
+}
+
+impl AuthenticationMiddlewareFactory {
+    /*
+      Requests no provider authenticates are served as the default user when
+      `allow_anonymous` is set, otherwise no user is set on them and every
+      api which needs one responds with a 401.
+    */
+    pub fn new(providers: AuthenticationProviders, allow_anonymous: bool) -> Self {
+        AuthenticationMiddlewareFactory {
+            providers,
+            allow_anonymous,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuthenticationMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service: Rc::new(service),
+            providers: self.providers.clone(),
+            allow_anonymous: self.allow_anonymous,
+        }))
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: Rc<S>,
+    providers: AuthenticationProviders,
+    allow_anonymous: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let providers = self.providers.clone();
+        let allow_anonymous = self.allow_anonymous;
+
+        Box::pin(async move {
+            let app_state = match req.app_data::<Data<AppState>>() {
+                Some(val) => val,
+                None => {
+                    log::error!("app state not set");
+                    return Err(error::ErrorInternalServerError(""));
+                }
+            };
+
+            let mut user = None;
+            for provider in providers.iter() {
+                match provider.authenticate(&req, app_state).await {
+                    Ok(None) => continue,
+                    Ok(Some(authenticated)) => {
+                        user = Some(authenticated);
+                        break;
+                    }
+                    Err(err) => {
+                        log::info!("failed to authenticate request: {err}");
+                        return Err(error::ErrorUnauthorized(json!({ "message": err })));
+                    }
+                }
+            }
+
+            match user {
+                Some(user) => {
+                    req.extensions_mut().insert::<User>(user);
+                }
+                None if allow_anonymous => {
+                    req.extensions_mut().insert::<User>(User::default());
+                }
+                None => (),
+            }
+
+            let res = srv.call(req).await?;
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use actix_web::dev::ServiceRequest;
+use chrono::{DateTime, Utc};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use superposition_types::{authorization::ApiKey, User};
+
+use crate::service::types::AppState;
+
+use super::{get_authorization, AuthenticationProvider};
+
+/// Only hashes of API keys are stored, keys are looked up by this hash.
+pub fn hash_api_key(key: &str) -> String {
+    blake3::hash(key.as_bytes()).to_hex().to_string()
+}
+
+/*
+  API keys are sent as `Authorization: ApiKey <key>` and looked up in the
+  keys loaded from the tenant registry.
+*/
+pub struct ApiKeyProvider;
+
+fn authenticate_api_key(
+    api_keys: &HashMap<String, ApiKey>,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Result<User, String> {
+    let api_key = api_keys
+        .get(&hash_api_key(key))
+        .ok_or_else(|| String::from("invalid API key"))?;
+    if api_key.is_expired(now) {
+        return Err(format!("API key {} has expired", api_key.name));
+    }
+    Ok(User {
+        email: format!("api-key:{}/{}", api_key.tenant, api_key.name),
+        username: api_key.name.clone(),
+        auth_token: String::new(),
+        auth_type: String::from("ApiKey"),
+        scopes: Some(api_key.role_bindings()),
+    })
+}
+
+impl AuthenticationProvider for ApiKeyProvider {
+    fn authenticate<'a>(
+        &'a self,
+        req: &'a ServiceRequest,
+        app_state: &'a AppState,
+    ) -> LocalBoxFuture<'a, Result<Option<User>, String>> {
+        async move {
+            let key = match get_authorization(req) {
+                Some(("ApiKey", key)) => key,
+                _ => return Ok(None),
+            };
+            let api_keys = app_state
+                .api_keys
+                .read()
+                .map_err(|_| String::from("api keys lock is poisoned"))?;
+            authenticate_api_key(&api_keys, key, Utc::now()).map(Some)
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use superposition_types::authorization::{ApiKeyScope, Role};
+
+    use super::*;
+
+    fn api_key(name: &str, expires_at: Option<DateTime<Utc>>) -> ApiKey {
+        ApiKey {
+            id: 1,
+            tenant: String::from("dev"),
+            name: String::from(name),
+            scopes: vec![ApiKeyScope {
+                role: Role::Editor,
+                key_prefix: Some(String::from("checkout.")),
+            }],
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_api_key_provider() {
+        let now = Utc::now();
+        let api_keys = HashMap::from([
+            (hash_api_key("ci-key"), api_key("ci", None)),
+            (
+                hash_api_key("old-key"),
+                api_key("old", Some(now - Duration::days(1))),
+            ),
+        ]);
+
+        let user = authenticate_api_key(&api_keys, "ci-key", now).unwrap();
+        assert_eq!(user.email, "api-key:dev/ci");
+        let scopes = user.scopes.unwrap();
+        assert_eq!(scopes.len(), 1);
+        assert_eq!(scopes[0].tenant, "dev");
+        assert_eq!(scopes[0].role, Role::Editor);
+        assert_eq!(scopes[0].key_prefix.as_deref(), Some("checkout."));
+
+        assert!(authenticate_api_key(&api_keys, "old-key", now).is_err());
+        assert!(authenticate_api_key(&api_keys, "CI-KEY", now).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::dev::ServiceRequest;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use superposition_types::User;
+
+use crate::service::types::AppState;
+
+use super::{get_authorization, AuthenticationProvider};
+
+/*
+  Calls between the services of superposition carry the shared token and
+  the user they are made on behalf of, in the `x-user` header.
+*/
+pub struct InternalTokenProvider;
+
+/// Compares the hashes of the tokens, whose equality takes constant time, so
+/// that the time taken does not leak how much of the token was guessed.
+fn is_internal_token(token: &str, superposition_token: &str) -> bool {
+    blake3::hash(token.as_bytes()) == blake3::hash(superposition_token.as_bytes())
+}
+
+fn authenticate_internal(
+    req: &ServiceRequest,
+    superposition_token: &str,
+) -> Result<Option<User>, String> {
+    match get_authorization(req) {
+        Some(("Internal", token)) if is_internal_token(token, superposition_token) => req
+            .headers()
+            .get("x-user")
+            .and_then(|user| user.to_str().ok())
+            .and_then(|user| serde_json::from_str::<User>(user).ok())
+            .map(Some)
+            .ok_or_else(|| String::from("x-user header is missing or invalid")),
+        Some(("Internal", _)) => Err(String::from("invalid internal token")),
+        _ => Ok(None),
+    }
+}
+
+impl AuthenticationProvider for InternalTokenProvider {
+    fn authenticate<'a>(
+        &'a self,
+        req: &'a ServiceRequest,
+        app_state: &'a AppState,
+    ) -> LocalBoxFuture<'a, Result<Option<User>, String>> {
+        async move { authenticate_internal(req, &app_state.superposition_token) }
+            .boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test::TestRequest};
+
+    use super::*;
+
+    fn request(authorization: Option<&str>, user: Option<&str>) -> ServiceRequest {
+        let mut req = TestRequest::default();
+        if let Some(authorization) = authorization {
+            req = req.insert_header((header::AUTHORIZATION, authorization));
+        }
+        if let Some(user) = user {
+            req = req.insert_header(("x-user", user));
+        }
+        req.to_srv_request()
+    }
+
+    #[test]
+    fn test_internal_token_provider() {
+        let user = serde_json::json!({
+            "email": "jane@example.com",
+            "username": "jane",
+            "auth_token": "",
+            "auth_type": "Internal",
+        })
+        .to_string();
+        let user = user.as_str();
+
+        let authenticated = authenticate_internal(
+            &request(Some("Internal 123456"), Some(user)),
+            "123456",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(authenticated.email, "jane@example.com");
+
+        assert!(authenticate_internal(
+            &request(Some("Internal 12345"), Some(user)),
+            "123456"
+        )
+        .is_err());
+        assert!(
+            authenticate_internal(&request(Some("Internal 123456"), None), "123456")
+                .is_err()
+        );
+        assert!(authenticate_internal(
+            &request(Some("Bearer 123456"), Some(user)),
+            "123456"
+        )
+        .unwrap()
+        .is_none());
+        assert!(authenticate_internal(&request(None, None), "123456")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use actix_web::dev::ServiceRequest;
+use anyhow::anyhow;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::{Map, Value};
+use superposition_types::User;
+
+use crate::{
+    helpers::{get_from_env_or_default, get_from_env_unsafe},
+    service::types::AppState,
+};
+
+use super::{get_authorization, AuthenticationProvider};
+
+pub struct JwtConfig {
+    /// Path or url of the JWKS the tokens are signed with.
+    pub jwks: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub email_claim: String,
+    /// Cookie the UI gets the token in, when it sits behind an OIDC proxy.
+    pub cookie: Option<String>,
+    /// Algorithm of the keys whose JWK does not name one.
+    pub algorithm: Option<Algorithm>,
+}
+
+impl JwtConfig {
+    /// Reads the config from the env, JWT validation is disabled when no
+    /// JWKS is configured.
+    pub fn from_env() -> Option<Self> {
+        let jwks = get_from_env_unsafe::<String>("JWT_JWKS").ok()?;
+        Some(JwtConfig {
+            jwks,
+            issuer: get_from_env_unsafe("JWT_ISSUER").ok(),
+            audience: get_from_env_unsafe("JWT_AUDIENCE").ok(),
+            email_claim: get_from_env_or_default(
+                "JWT_EMAIL_CLAIM",
+                String::from("email"),
+            ),
+            cookie: get_from_env_unsafe("JWT_COOKIE").ok(),
+            algorithm: get_from_env_unsafe("JWT_ALGORITHM").ok(),
+        })
+    }
+
+    fn is_remote(&self) -> bool {
+        self.jwks.starts_with("http://") || self.jwks.starts_with("https://")
+    }
+}
+
+async fn fetch_jwks(
+    config: &JwtConfig,
+    http_client: &reqwest::Client,
+) -> anyhow::Result<JwkSet> {
+    if config.is_remote() {
+        let jwks = http_client
+            .get(&config.jwks)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+        Ok(jwks)
+    } else {
+        let jwks = std::fs::read_to_string(&config.jwks)?;
+        Ok(serde_json::from_str::<JwkSet>(&jwks)?)
+    }
+}
+
+/// Least time between two fetches of the JWKS for tokens signed with a key
+/// missing from it.
+const UNKNOWN_KEY_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+
+/*
+  Bearer tokens are validated against the keys of the configured JWKS and
+  the user is read from their claims. Keys of a remote JWKS are refreshed
+  periodically to pick up key rotations, and right away when a token is
+  signed with a key missing from them.
+*/
+pub struct JwtProvider {
+    config: JwtConfig,
+    jwks: RwLock<JwkSet>,
+    last_refetch: Mutex<Option<Instant>>,
+}
+
+impl JwtProvider {
+    pub async fn new(
+        config: JwtConfig,
+        http_client: &reqwest::Client,
+    ) -> anyhow::Result<Self> {
+        let jwks = fetch_jwks(&config, http_client).await?;
+        Ok(JwtProvider {
+            config,
+            jwks: RwLock::new(jwks),
+            last_refetch: Mutex::new(None),
+        })
+    }
+
+    pub async fn refresh_jwks(
+        &self,
+        http_client: &reqwest::Client,
+    ) -> anyhow::Result<()> {
+        let jwks = fetch_jwks(&self.config, http_client).await?;
+        *self
+            .jwks
+            .write()
+            .map_err(|_| anyhow!("jwks lock is poisoned"))? = jwks;
+        Ok(())
+    }
+
+    fn get_token(&self, req: &ServiceRequest) -> Option<String> {
+        match get_authorization(req) {
+            Some(("Bearer", token)) => Some(token.to_string()),
+            _ => self
+                .config
+                .cookie
+                .as_ref()
+                .and_then(|cookie| req.cookie(cookie))
+                .map(|cookie| cookie.value().to_string()),
+        }
+    }
+
+    fn find_key(&self, kid: Option<&str>) -> Result<Option<Jwk>, String> {
+        let jwks = self
+            .jwks
+            .read()
+            .map_err(|_| String::from("jwks lock is poisoned"))?;
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        };
+        Ok(jwk.cloned())
+    }
+
+    /*
+      A key missing from the JWKS may have been rotated in since it was
+      fetched. It is fetched again at most once every
+      UNKNOWN_KEY_REFETCH_INTERVAL, so that tokens naming made up keys can't
+      be used to flood the identity provider.
+    */
+    async fn refetch_jwks(&self, http_client: &reqwest::Client) -> Result<(), String> {
+        {
+            let mut last_refetch = self
+                .last_refetch
+                .lock()
+                .map_err(|_| String::from("jwks refetch lock is poisoned"))?;
+            if last_refetch.is_some_and(|fetched_at| {
+                fetched_at.elapsed() < UNKNOWN_KEY_REFETCH_INTERVAL
+            }) {
+                return Ok(());
+            }
+            *last_refetch = Some(Instant::now());
+        }
+        self.refresh_jwks(http_client)
+            .await
+            .map_err(|err| format!("failed to fetch the JWKS: {err}"))
+    }
+
+    /// The algorithm tokens signed with `jwk` have to use, which is never
+    /// taken from the token itself.
+    fn key_algorithm(&self, jwk: &Jwk) -> Result<Algorithm, String> {
+        match &jwk.common.key_algorithm {
+            Some(key_algorithm) => Algorithm::from_str(&format!("{key_algorithm:?}"))
+                .map_err(|_| {
+                    format!("key algorithm {key_algorithm:?} can't sign tokens")
+                }),
+            None => self.config.algorithm.ok_or_else(|| {
+                String::from(
+                    "the key does not name its algorithm and JWT_ALGORITHM is not set",
+                )
+            }),
+        }
+    }
+
+    async fn validate_token(
+        &self,
+        token: &str,
+        http_client: &reqwest::Client,
+    ) -> Result<Map<String, Value>, String> {
+        let header = decode_header(token).map_err(|err| err.to_string())?;
+        let jwk = match self.find_key(header.kid.as_deref())? {
+            Some(jwk) => jwk,
+            None => {
+                self.refetch_jwks(http_client).await?;
+                self.find_key(header.kid.as_deref())?.ok_or_else(|| {
+                    String::from("no key found to validate the token with")
+                })?
+            }
+        };
+        let algorithm = self.key_algorithm(&jwk)?;
+        if header.alg != algorithm {
+            return Err(format!(
+                "token is signed with {:?} instead of {algorithm:?}",
+                header.alg
+            ));
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(|err| err.to_string())?;
+
+        let mut validation = Validation::new(algorithm);
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        decode::<Map<String, Value>>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl AuthenticationProvider for JwtProvider {
+    fn authenticate<'a>(
+        &'a self,
+        req: &'a ServiceRequest,
+        app_state: &'a AppState,
+    ) -> LocalBoxFuture<'a, Result<Option<User>, String>> {
+        async move {
+            let Some(token) = self.get_token(req) else {
+                return Ok(None);
+            };
+            let claims = self.validate_token(&token, &app_state.http_client).await?;
+            let get_claim =
+                |claim: &str| claims.get(claim).and_then(Value::as_str).map(String::from);
+            let email = get_claim(&self.config.email_claim).ok_or_else(|| {
+                format!("token does not have the {} claim", self.config.email_claim)
+            })?;
+            let username = get_claim("preferred_username")
+                .or_else(|| get_claim("name"))
+                .unwrap_or_else(|| email.clone());
+            Ok(Some(User {
+                email,
+                username,
+                auth_token: token,
+                auth_type: String::from("Bearer"),
+                scopes: None,
+            }))
+        }
+        .boxed_local()
+    }
+}
+
+pub async fn run_jwks_refresh_job(
+    provider: Arc<JwtProvider>,
+    http_client: reqwest::Client,
+    period: Duration,
+) {
+    if !provider.config.is_remote() {
+        return;
+    }
+    let mut interval = actix_web::rt::time::interval(period);
+    // the first tick completes right away, the keys were just fetched
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(err) = provider.refresh_jwks(&http_client).await {
+            log::error!("failed to refresh the JWKS: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+
+    const SECRET: &[u8] = b"superposition-jwt-test-secret";
+
+    fn jwks_file(name: &str, keys: Value) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "superposition-jwks-{name}-{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, json!({ "keys": keys }).to_string()).unwrap();
+        path
+    }
+
+    fn oct_key(kid: &str, alg: Option<&str>) -> Value {
+        let mut key = json!({
+            "kty": "oct",
+            "kid": kid,
+            "k": URL_SAFE_NO_PAD.encode(SECRET),
+        });
+        if let Some(alg) = alg {
+            key["alg"] = json!(alg);
+        }
+        key
+    }
+
+    fn provider(jwks: &PathBuf, algorithm: Option<Algorithm>) -> JwtProvider {
+        let config = JwtConfig {
+            jwks: jwks.to_string_lossy().to_string(),
+            issuer: None,
+            audience: None,
+            email_claim: String::from("email"),
+            cookie: None,
+            algorithm,
+        };
+        JwtProvider::new(config, &reqwest::Client::new())
+            .now_or_never()
+            .expect("reading a local JWKS should not wait")
+            .unwrap()
+    }
+
+    fn token(alg: Algorithm, kid: &str) -> String {
+        let header = Header {
+            kid: Some(String::from(kid)),
+            ..Header::new(alg)
+        };
+        let claims = json!({
+            "email": "jane@example.com",
+            "exp": chrono::Utc::now().timestamp() + 3600,
+        });
+        encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    fn validate(
+        provider: &JwtProvider,
+        token: &str,
+    ) -> Result<Map<String, Value>, String> {
+        provider
+            .validate_token(token, &reqwest::Client::new())
+            .now_or_never()
+            .expect("reading a local JWKS should not wait")
+    }
+
+    #[test]
+    fn test_jwt_algorithm_is_pinned() {
+        let path = jwks_file("pinned", json!([oct_key("k1", Some("HS256"))]));
+        let provider = provider(&path, None);
+
+        let claims = validate(&provider, &token(Algorithm::HS256, "k1")).unwrap();
+        assert_eq!(claims["email"], "jane@example.com");
+        // the key says HS256, whatever the token claims
+        assert!(validate(&provider, &token(Algorithm::HS384, "k1")).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_jwt_algorithm_from_config() {
+        let path = jwks_file("config", json!([oct_key("k1", None)]));
+
+        assert!(
+            validate(&provider(&path, None), &token(Algorithm::HS256, "k1")).is_err()
+        );
+        let provider = provider(&path, Some(Algorithm::HS512));
+        assert!(validate(&provider, &token(Algorithm::HS512, "k1")).is_ok());
+        assert!(validate(&provider, &token(Algorithm::HS256, "k1")).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_jwks_refetched_on_unknown_key() {
+        let path = jwks_file("rotation", json!([oct_key("k1", Some("HS256"))]));
+        let provider = provider(&path, None);
+
+        // a key rotated in after the JWKS was loaded
+        jwks_file(
+            "rotation",
+            json!([oct_key("k1", Some("HS256")), oct_key("k2", Some("HS256"))]),
+        );
+        assert!(validate(&provider, &token(Algorithm::HS256, "k2")).is_ok());
+
+        // the JWKS was just fetched, so it is not fetched again
+        jwks_file(
+            "rotation",
+            json!([oct_key("k2", Some("HS256")), oct_key("k3", Some("HS256"))]),
+        );
+        assert!(validate(&provider, &token(Algorithm::HS256, "k3")).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::future::{ready, Ready};
+
+use crate::service::types::{AppState, Tenant};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error,
+    http::Method,
+    web::Data,
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use regex::Regex;
+use serde_json::json;
+use superposition_types::{
+    authorization::{Action, KeyAccess, Role, ALL_TENANTS},
+    result::{self, AppError},
+    User,
+};
+
+use std::rc::Rc;
+
+/*
+  Resources of a `Keys` scope are guarded per default config key, so
+  bindings limited to a key prefix can write to them and the handlers check
+  the keys being changed against the `KeyAccess` of the request. Writes to
+  resources of a `Tenant` scope need a binding without a key prefix.
+*/
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResourceScope {
+    Tenant,
+    Keys,
+}
+
+/// Resolves what `user` can do on `tenant`, from the scopes of its API key
+/// or the bindings of the user on that tenant and on every tenant. Reading
+/// secrets always needs a `secret_reader` binding.
+pub fn authorize(
+    app_state: &AppState,
+    user: &User,
+    tenant: Option<&str>,
+    action: Action,
+) -> result::Result<KeyAccess> {
+    let email = user.get_email();
+    /*
+      The scopes of API keys are checked even with authorization disabled,
+      a key never does more than what it was created for.
+    */
+    if user.scopes.is_none()
+        && action != Action::ReadSecrets
+        && (!app_state.enable_authorization
+            || app_state.superposition_admins.contains(&email))
+    {
+        return Ok(KeyAccess::All);
+    }
+    let role_bindings = app_state.role_bindings.read().map_err(|_| {
+        AppError::UnexpectedError(anyhow::anyhow!("role bindings lock is poisoned"))
+    })?;
+    let bindings = user
+        .scopes
+        .as_ref()
+        .or_else(|| role_bindings.get(&email))
+        .into_iter()
+        .flatten()
+        .filter(|binding| {
+            (binding.tenant == ALL_TENANTS || Some(binding.tenant.as_str()) == tenant)
+                && binding.role.can(action)
+        });
+    match (KeyAccess::from_bindings(bindings), action) {
+        (Some(_), Action::Read) => Ok(KeyAccess::All),
+        (Some(key_access), _) => Ok(key_access),
+        (None, _) => Err(AppError::Forbidden(format!(
+            "{email} is not allowed to {action} on this tenant"
+        ))),
+    }
+}
+
+/// Administering a tenant needs an admin binding without a key prefix, a
+/// prefix limited admin could otherwise grant itself every key.
+pub fn authorize_admin(
+    app_state: &AppState,
+    user: &User,
+    tenant: Option<&str>,
+) -> result::Result<()> {
+    authorize(app_state, user, tenant, Action::Administer)?
+        .validate_unrestricted()
+        .map_err(AppError::Forbidden)
+}
+
+/*
+  A role is only granted, through a role binding or the scope of an API key,
+  by users who can do everything the role can on the keys it covers, so that
+  granting never widens the access of the one granting. Superposition admins
+  grant any role, for there to be a first secret reader.
+*/
+pub fn authorize_grant(
+    app_state: &AppState,
+    user: &User,
+    tenant: &str,
+    role: Role,
+    key_prefix: Option<&str>,
+) -> result::Result<()> {
+    let email = user.get_email();
+    if user.scopes.is_none() && app_state.superposition_admins.contains(&email) {
+        return Ok(());
+    }
+    for action in Action::ALL.into_iter().filter(|action| role.can(*action)) {
+        let key_access = authorize(app_state, user, Some(tenant), action)?;
+        if !key_access.covers(key_prefix) {
+            return Err(AppError::Forbidden(format!(
+                "{email} is not allowed to grant {role} on {}",
+                key_prefix.unwrap_or("every key")
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn required_action(req: &ServiceRequest) -> Result<Action, Error> {
+    let approve_regex = Regex::new(".*/experiments/[^/]+/(conclude|ramp)$")
+        .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
+    let is_approve = req
+        .headers()
+        .get("x-approve")
+        .and_then(|value| value.to_str().ok().and_then(|s| s.parse::<bool>().ok()))
+        .unwrap_or(false);
+    let action = match *req.method() {
+        Method::GET | Method::HEAD => Action::Read,
+        // fetching a context by its condition is a read, sent as a post
+        Method::POST if req.path().ends_with("/context/get") => Action::Read,
+        _ if is_approve || approve_regex.is_match(req.path()) => Action::Approve,
+        _ => Action::Write,
+    };
+    Ok(action)
+}
+
+pub struct AuthorizationMiddlewareFactory {
+    resource_scope: ResourceScope,
+}
+
+impl AuthorizationMiddlewareFactory {
+    pub fn new(resource_scope: ResourceScope) -> Self {
+        AuthorizationMiddlewareFactory { resource_scope }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuthorizationMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthorizationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizationMiddleware {
+            service: Rc::new(service),
+            resource_scope: self.resource_scope,
+        }))
+    }
+}
+
+pub struct AuthorizationMiddleware<S> {
+    service: Rc<S>,
+    resource_scope: ResourceScope,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let resource_scope = self.resource_scope;
+
+        Box::pin(async move {
+            let app_state = match req.app_data::<Data<AppState>>() {
+                Some(val) => val,
+                None => {
+                    log::error!("app state not set");
+                    return Err(error::ErrorInternalServerError(""));
+                }
+            };
+            let user = match req.extensions().get::<User>().cloned() {
+                Some(user) => user,
+                None => {
+                    return Err(error::ErrorUnauthorized(
+                        json!({"message": "invalid token provided"}),
+                    ))
+                }
+            };
+            let tenant = req
+                .extensions()
+                .get::<Tenant>()
+                .map(|tenant| tenant.0.clone());
+            let action = required_action(&req)?;
+
+            let key_access = authorize(app_state, &user, tenant.as_deref(), action)?;
+            if resource_scope == ResourceScope::Tenant {
+                key_access
+                    .validate_unrestricted()
+                    .map_err(AppError::Forbidden)?;
+            }
+            req.extensions_mut().insert(key_access);
+
+            let res = srv.call(req).await?;
+            Ok(res)
+        })
+    }
+}
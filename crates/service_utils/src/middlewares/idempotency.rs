@@ -0,0 +1,519 @@
+use std::future::{ready, Ready};
+
+use crate::db::pgschema_manager::PgSchemaConnection;
+use crate::helpers::run_blocking;
+use crate::service::types::{AppExecutionNamespace, AppHeader, AppState};
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{self, PayloadError},
+    http::{header, Method, StatusCode},
+    web::{Bytes, BytesMut, Data},
+    Error, HttpMessage, HttpResponse,
+};
+use chrono::{Duration, Utc};
+use diesel::{
+    sql_query,
+    sql_types::{Binary, Integer, Json, Nullable, Text, Timestamp},
+    OptionalExtension, QueryableByName, RunQueryDsl,
+};
+use futures_util::{future::LocalBoxFuture, stream, StreamExt};
+use serde_json::{Map, Value};
+use superposition_types::{
+    result::{AppError, ResponseError},
+    User,
+};
+
+use std::rc::Rc;
+
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Replays the stored response of a mutating request when it is retried with
+/// the same `Idempotency-Key` header by the same caller within the configured
+/// TTL. Keys are kept per tenant, in the schema of the app scope the request
+/// executes in, so this has to run after `AppExecutionScopeMiddleware` has set
+/// the scope.
+pub struct IdempotencyMiddlewareFactory;
+impl<S, B> Transform<S, ServiceRequest> for IdempotencyMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+}
+
+#[derive(QueryableByName)]
+struct IdempotencyRecord {
+    #[diesel(sql_type = Text)]
+    request_hash: String,
+    #[diesel(sql_type = Nullable<Integer>)]
+    response_status: Option<i32>,
+    #[diesel(sql_type = Nullable<Json>)]
+    response_headers: Option<Value>,
+    #[diesel(sql_type = Nullable<Binary>)]
+    response_body: Option<Vec<u8>>,
+}
+
+enum KeyState {
+    Acquired,
+    InFlight,
+    Mismatch,
+    Completed(IdempotencyRecord),
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+fn extract_idempotency_key(req: &ServiceRequest) -> Result<Option<String>, AppError> {
+    match req.headers().get(AppHeader::IdempotencyKey.to_string()) {
+        None => Ok(None),
+        Some(value) => {
+            let key = value.to_str().map(str::trim).unwrap_or_default();
+            if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+                Err(AppError::BadArgument(format!(
+                    "Idempotency-Key should be a non empty string of at most {MAX_IDEMPOTENCY_KEY_LENGTH} characters"
+                )))
+            } else {
+                Ok(Some(key.to_string()))
+            }
+        }
+    }
+}
+
+fn get_caller(req: &ServiceRequest) -> String {
+    req.extensions()
+        .get::<User>()
+        .map(User::get_email)
+        .unwrap_or_default()
+}
+
+/*
+  Keys are scoped by the caller, so that a caller reusing the key of another
+  caller neither gets its response replayed nor blocks it. The caller is
+  hashed so that it cannot run into the key.
+*/
+fn scope_key(caller: &str, key: &str) -> String {
+    format!("{}:{key}", blake3::hash(caller.as_bytes()))
+}
+
+/*
+  The headers a handler acts on besides the body are part of the request,
+  so a retry changing the precondition or the change reason is a different
+  request than the one whose response is stored.
+*/
+const FINGERPRINT_HEADERS: [&str; 2] = ["if-match", "x-change-reason"];
+
+fn request_fingerprint(req: &ServiceRequest, caller: &str, body: &Bytes) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(caller.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(req.method().as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(req.path().as_bytes());
+    hasher.update(b"?");
+    hasher.update(req.query_string().as_bytes());
+    hasher.update(b"\n");
+    for name in FINGERPRINT_HEADERS {
+        for value in req.headers().get_all(name) {
+            hasher.update(name.as_bytes());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+            hasher.update(b"\n");
+        }
+    }
+    hasher.update(body);
+    hasher.finalize().to_string()
+}
+
+fn bytes_to_payload(body: Bytes) -> Payload {
+    let payload = stream::once(async move { Ok::<_, PayloadError>(body) });
+    Payload::Stream {
+        payload: Box::pin(payload),
+    }
+}
+
+fn get_db_conn(
+    app_state: &Data<AppState>,
+    namespace: &AppExecutionNamespace,
+) -> Result<PgSchemaConnection, AppError> {
+    app_state
+        .db_pool
+        .get_conn(namespace.as_str().to_string())
+        .map_err(|err| {
+            log::error!("Unable to get db connection from pool, error: {err}");
+            AppError::UnexpectedError(err)
+        })
+}
+
+/// The key the CAC call made by a request carrying `key` is sent with, so
+/// that a retry of the request does not repeat the call. It is scoped to
+/// `operation` as the keys of a tenant are shared by both apps when they run
+/// in the same schema, and the call is a different request than its caller.
+pub fn derive_idempotency_key(key: &str, operation: &str) -> String {
+    format!("{operation}:{}", blake3::hash(key.as_bytes()))
+}
+
+fn acquire_key(
+    conn: &mut PgSchemaConnection,
+    key: &str,
+    request_hash: &str,
+    ttl_secs: i64,
+    lease_secs: i64,
+) -> diesel::QueryResult<KeyState> {
+    let now = Utc::now().naive_utc();
+    let locked_until = now + Duration::seconds(lease_secs);
+    sql_query("DELETE FROM idempotency_keys WHERE expires_at < $1")
+        .bind::<Timestamp, _>(now)
+        .execute(conn)?;
+    let inserted = sql_query(
+        "INSERT INTO idempotency_keys \
+         (key, request_hash, created_at, expires_at, locked_until) \
+         VALUES ($1, $2, $3, $4, $5) ON CONFLICT (key) DO NOTHING",
+    )
+    .bind::<Text, _>(key)
+    .bind::<Text, _>(request_hash)
+    .bind::<Timestamp, _>(now)
+    .bind::<Timestamp, _>(now + Duration::seconds(ttl_secs))
+    .bind::<Timestamp, _>(locked_until)
+    .execute(conn)?;
+    if inserted == 1 {
+        return Ok(KeyState::Acquired);
+    }
+
+    /*
+     * A key still in flight past its lease was left behind by a request
+     * which never finished, like one whose instance went down, so a retry
+     * of the same request takes it over instead of waiting for the TTL.
+     */
+    let taken_over = sql_query(
+        "UPDATE idempotency_keys SET locked_until = $3 WHERE key = $1 \
+         AND request_hash = $2 AND response_status IS NULL AND locked_until < $4",
+    )
+    .bind::<Text, _>(key)
+    .bind::<Text, _>(request_hash)
+    .bind::<Timestamp, _>(locked_until)
+    .bind::<Timestamp, _>(now)
+    .execute(conn)?;
+    if taken_over == 1 {
+        log::warn!("took over idempotency key {key} after its lease expired");
+        return Ok(KeyState::Acquired);
+    }
+
+    let record = sql_query(
+        "SELECT request_hash, response_status, response_headers, response_body \
+         FROM idempotency_keys WHERE key = $1",
+    )
+    .bind::<Text, _>(key)
+    .get_result::<IdempotencyRecord>(conn)
+    .optional()?;
+    Ok(match record {
+        Some(record) if record.request_hash != request_hash => KeyState::Mismatch,
+        Some(record) if record.response_status.is_some() => KeyState::Completed(record),
+        _ => KeyState::InFlight,
+    })
+}
+
+async fn release_key(
+    app_state: &Data<AppState>,
+    namespace: &AppExecutionNamespace,
+    key: &str,
+) {
+    let (app_state, namespace, idempotency_key) =
+        (app_state.clone(), namespace.clone(), key.to_string());
+    let result = run_blocking(move || {
+        let mut conn = get_db_conn(&app_state, &namespace)?;
+        sql_query("DELETE FROM idempotency_keys WHERE key = $1")
+            .bind::<Text, _>(idempotency_key)
+            .execute(&mut conn)
+            .map_err(AppError::DbError)
+    })
+    .await;
+    if let Err(err) = result {
+        log::error!("failed to release idempotency key {key}: {err}");
+    }
+}
+
+async fn store_response(
+    app_state: &Data<AppState>,
+    namespace: &AppExecutionNamespace,
+    key: &str,
+    status: StatusCode,
+    headers: &header::HeaderMap,
+    body: &Bytes,
+) {
+    let headers = headers
+        .iter()
+        .filter(|(name, _)| {
+            *name != header::CONTENT_LENGTH && *name != header::TRANSFER_ENCODING
+        })
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), Value::String(value.to_string())))
+        })
+        .collect::<Map<String, Value>>();
+    let (app_state, namespace, idempotency_key, body) = (
+        app_state.clone(),
+        namespace.clone(),
+        key.to_string(),
+        body.to_vec(),
+    );
+    let result = run_blocking(move || {
+        let mut conn = get_db_conn(&app_state, &namespace)?;
+        sql_query(
+            "UPDATE idempotency_keys SET response_status = $2, response_headers = $3, \
+             response_body = $4 WHERE key = $1",
+        )
+        .bind::<Text, _>(idempotency_key)
+        .bind::<Integer, _>(i32::from(status.as_u16()))
+        .bind::<Json, _>(Value::Object(headers))
+        .bind::<Binary, _>(body)
+        .execute(&mut conn)
+        .map_err(AppError::DbError)
+    })
+    .await;
+    if let Err(err) = result {
+        log::error!("failed to store response for idempotency key {key}: {err}");
+    }
+}
+
+fn replay_response(record: IdempotencyRecord) -> HttpResponse {
+    let status = record
+        .response_status
+        .and_then(|status| u16::try_from(status).ok())
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .unwrap_or(StatusCode::OK);
+    let mut builder = HttpResponse::build(status);
+    if let Some(Value::Object(headers)) = record.response_headers {
+        for (name, value) in headers {
+            if let Value::String(value) = value {
+                builder.insert_header((name, value));
+            }
+        }
+    }
+    builder.insert_header((AppHeader::IdempotentReplayed.to_string(), "true"));
+    builder.body(record.response_body.unwrap_or_default())
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+
+        Box::pin(async move {
+            let key = match extract_idempotency_key(&req)? {
+                Some(key) if is_mutating(req.method()) => key,
+                _ => {
+                    return srv
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_boxed_body)
+                }
+            };
+            let app_state = match req.app_data::<Data<AppState>>() {
+                Some(val) => val.clone(),
+                None => {
+                    log::error!("app state not set");
+                    return Err(error::ErrorInternalServerError(""));
+                }
+            };
+            let namespace = AppExecutionNamespace::from_request_sync(req.request())?;
+
+            let caller = get_caller(&req);
+            let scoped_key = scope_key(&caller, &key);
+
+            /*
+             * The body is buffered to be fingerprinted, so it is held to the
+             * limit the handlers would have held it to before being read.
+             */
+            let limit = app_state.max_request_body_bytes;
+            let mut payload = req.take_payload();
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                if body.len() + chunk.len() > limit {
+                    return Err(AppError::ResponseError(ResponseError {
+                        message: format!("Request body is larger than {limit} bytes"),
+                        status_code: StatusCode::PAYLOAD_TOO_LARGE,
+                    })
+                    .into());
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+            let request_hash = request_fingerprint(&req, &caller, &body);
+            req.set_payload(bytes_to_payload(body));
+
+            /*
+             * The connection is dropped before calling the handler, which
+             * checks out its own, so that a request never holds two pool
+             * connections at once.
+             */
+            let key_state = run_blocking({
+                let (app_state, namespace, scoped_key) =
+                    (app_state.clone(), namespace.clone(), scoped_key.clone());
+                move || {
+                    let mut conn = get_db_conn(&app_state, &namespace)?;
+                    acquire_key(
+                        &mut conn,
+                        &scoped_key,
+                        &request_hash,
+                        app_state.idempotency_key_ttl_secs,
+                        app_state.idempotency_lease_secs,
+                    )
+                    .map_err(AppError::DbError)
+                }
+            })
+            .await?;
+            match key_state {
+                KeyState::Acquired => (),
+                KeyState::Completed(record) => {
+                    log::info!("replaying stored response for idempotency key {key}");
+                    return Ok(req.into_response(replay_response(record)));
+                }
+                KeyState::InFlight => {
+                    return Err(AppError::ResponseError(ResponseError {
+                        message: "A request with this Idempotency-Key is still being processed, retry after some time".to_string(),
+                        status_code: StatusCode::CONFLICT,
+                    })
+                    .into());
+                }
+                KeyState::Mismatch => {
+                    return Err(AppError::ResponseError(ResponseError {
+                        message: "Idempotency-Key has already been used for a different request".to_string(),
+                        status_code: StatusCode::UNPROCESSABLE_ENTITY,
+                    })
+                    .into());
+                }
+            }
+
+            let res = match srv.call(req).await {
+                Ok(res) => res,
+                Err(err) => {
+                    release_key(&app_state, &namespace, &scoped_key).await;
+                    return Err(err);
+                }
+            };
+            /*
+             * Server errors are not stored so that the caller can retry them
+             * with the same key, everything else is replayed as is.
+             */
+            if res.status().is_server_error() {
+                release_key(&app_state, &namespace, &scoped_key).await;
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (req, res) = res.into_parts();
+            let (res, res_body) = res.into_parts();
+            let res_body = match body::to_bytes(res_body).await {
+                Ok(res_body) => res_body,
+                Err(err) => {
+                    let err: Box<dyn std::error::Error> = err.into();
+                    log::error!(
+                        "failed to read response body for idempotency key {key}: {err}"
+                    );
+                    release_key(&app_state, &namespace, &scoped_key).await;
+                    return Err(error::ErrorInternalServerError(""));
+                }
+            };
+            store_response(
+                &app_state,
+                &namespace,
+                &scoped_key,
+                res.status(),
+                res.headers(),
+                &res_body,
+            )
+            .await;
+            Ok(ServiceResponse::new(
+                req,
+                res.set_body(res_body).map_into_boxed_body(),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn fingerprint(headers: &[(&str, &str)], caller: &str) -> String {
+        let mut req = TestRequest::put().uri("/context?dry=false");
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        request_fingerprint(&req.to_srv_request(), caller, &Bytes::from_static(b"{}"))
+    }
+
+    #[test]
+    fn test_request_fingerprint() {
+        let fingerprint_of = fingerprint(&[("if-match", "\"7\"")], "jane@example.com");
+        assert_eq!(
+            fingerprint_of,
+            fingerprint(&[("if-match", "\"7\"")], "jane@example.com")
+        );
+        assert_ne!(
+            fingerprint_of,
+            fingerprint(&[("if-match", "\"8\"")], "jane@example.com")
+        );
+        assert_ne!(
+            fingerprint_of,
+            fingerprint(&[("if-match", "\"7\"")], "john@example.com")
+        );
+        assert_ne!(
+            fingerprint_of,
+            fingerprint(
+                &[("if-match", "\"7\""), ("x-change-reason", "retry")],
+                "jane@example.com"
+            )
+        );
+        assert_ne!(
+            fingerprint(&[("x-change-reason", "a")], "jane@example.com"),
+            fingerprint(&[("x-change-reason", "b")], "jane@example.com")
+        );
+    }
+
+    #[test]
+    fn test_scope_key() {
+        assert_eq!(
+            scope_key("jane@example.com", "key"),
+            scope_key("jane@example.com", "key")
+        );
+        assert_ne!(
+            scope_key("jane@example.com", "key"),
+            scope_key("john@example.com", "key")
+        );
+    }
+}
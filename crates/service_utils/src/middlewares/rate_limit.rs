@@ -0,0 +1,301 @@
+use std::future::{ready, Ready};
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::service::types::Tenant;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error,
+    http::header,
+    Error, HttpMessage, HttpResponse,
+};
+use futures_util::future::LocalBoxFuture;
+use serde_json::json;
+use superposition_types::{authorization::Action, TenantConfig, User};
+
+use super::authorization::required_action;
+
+/// Time an empty bucket takes to refill completely, and how often the buckets
+/// left alone that long are swept.
+const REFILL_PERIOD: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum RequestClass {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct BucketKey {
+    tenant: String,
+    /// Caller the bucket is kept for, the tenant as a whole when unset.
+    caller: Option<String>,
+    class: RequestClass,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let capacity = f64::from(per_minute);
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * capacity / REFILL_PERIOD.as_secs_f64())
+            .min(capacity);
+        self.updated_at = now;
+    }
+
+    /// How long until the bucket holds a token again.
+    fn wait(&self, per_minute: u32) -> Duration {
+        Duration::from_secs_f64(
+            (1.0 - self.tokens).max(0.0) * REFILL_PERIOD.as_secs_f64()
+                / f64::from(per_minute),
+        )
+    }
+}
+
+/*
+  Token buckets of every tenant and of every caller of every tenant, one for
+  reads and one for writes, refilled continuously at the rate the tenant
+  allows per minute. Buckets live in the memory of the process, so every
+  replica of the service enforces the limits on its own.
+*/
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+}
+
+impl RateLimiter {
+    /*
+      Takes a token from the bucket of every limit, or from none of them when
+      one is empty, in which case the index of the limit to wait the longest
+      for is returned along with the wait.
+    */
+    fn acquire(&self, limits: &[(BucketKey, u32)]) -> Result<(), (usize, Duration)> {
+        let now = Instant::now();
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(_) => {
+                log::error!("rate limiter lock is poisoned, letting the request through");
+                return Ok(());
+            }
+        };
+        let mut exceeded: Option<(usize, Duration)> = None;
+        for (index, (key, per_minute)) in limits.iter().enumerate() {
+            let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                tokens: f64::from(*per_minute),
+                updated_at: now,
+            });
+            bucket.refill(*per_minute, now);
+            if bucket.tokens < 1.0 {
+                let wait = bucket.wait(*per_minute);
+                if exceeded.map_or(true, |(_, longest)| wait > longest) {
+                    exceeded = Some((index, wait));
+                }
+            }
+        }
+        if let Some(exceeded) = exceeded {
+            return Err(exceeded);
+        }
+        for (key, _) in limits {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the buckets left alone for a whole refill period, which are full
+    /// again so dropping them loses nothing.
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        match self.buckets.lock() {
+            Ok(mut buckets) => buckets.retain(|_, bucket| {
+                now.duration_since(bucket.updated_at) < REFILL_PERIOD
+            }),
+            Err(_) => log::error!("rate limiter lock is poisoned, skipping the sweep"),
+        }
+    }
+}
+
+/// Sweeps the buckets of `limiter` once every refill period.
+pub async fn run_rate_limit_sweeper(limiter: Arc<RateLimiter>) {
+    let mut interval = actix_web::rt::time::interval(REFILL_PERIOD);
+    loop {
+        interval.tick().await;
+        limiter.sweep();
+    }
+}
+
+/// Limits the requests made to a tenant, by every caller and by all of them
+/// together, to the budgets in the tenant config, so this has to run after
+/// the tenant and the user are set.
+pub struct RateLimitMiddlewareFactory {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitMiddlewareFactory {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        RateLimitMiddlewareFactory { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            // requests outside of a tenant, like the tenant admin apis, are not limited
+            let tenant = req.extensions().get::<Tenant>().cloned();
+            let rate_limits = req
+                .extensions()
+                .get::<TenantConfig>()
+                .map(|config| config.rate_limits.clone());
+            let (Some(tenant), Some(rate_limits)) = (tenant, rate_limits) else {
+                return srv.call(req).await;
+            };
+
+            let (class, per_minute, tenant_per_minute) = match required_action(&req)? {
+                Action::Read => (
+                    RequestClass::Read,
+                    rate_limits.reads_per_minute,
+                    rate_limits.tenant_reads_per_minute,
+                ),
+                _ => (
+                    RequestClass::Write,
+                    rate_limits.writes_per_minute,
+                    rate_limits.tenant_writes_per_minute,
+                ),
+            };
+            let caller = req
+                .extensions()
+                .get::<User>()
+                .map(User::get_email)
+                .unwrap_or_default();
+            let limits = [
+                per_minute.map(|limit| {
+                    let key = BucketKey {
+                        tenant: tenant.0.clone(),
+                        caller: Some(caller),
+                        class,
+                    };
+                    (key, limit)
+                }),
+                tenant_per_minute.map(|limit| {
+                    let key = BucketKey {
+                        tenant: tenant.0,
+                        caller: None,
+                        class,
+                    };
+                    (key, limit)
+                }),
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|(_, limit)| *limit > 0)
+            .collect::<Vec<_>>();
+            if limits.is_empty() {
+                return srv.call(req).await;
+            }
+
+            if let Err((index, wait)) = limiter.acquire(&limits) {
+                let (key, per_minute) = &limits[index];
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                let kind = match class {
+                    RequestClass::Read => "reads",
+                    RequestClass::Write => "writes",
+                };
+                let scope = match key.caller {
+                    Some(_) => "",
+                    None => " of the tenant",
+                };
+                let message = format!(
+                    "Rate limit of {per_minute} {kind} per minute{scope} exceeded, retry after {retry_after} seconds"
+                );
+                log::info!("{message}");
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                    .json(json!({ "message": message }));
+                return Err(error::InternalError::from_response(message, response).into());
+            }
+
+            srv.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(caller: Option<&str>) -> BucketKey {
+        BucketKey {
+            tenant: String::from("dev"),
+            caller: caller.map(String::from),
+            class: RequestClass::Write,
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::default();
+        let jane = [(key(Some("jane")), 2), (key(None), 3)];
+        let john = [(key(Some("john")), 2), (key(None), 3)];
+
+        assert!(limiter.acquire(&jane).is_ok());
+        assert!(limiter.acquire(&jane).is_ok());
+        // the bucket of jane is empty, the tenant keeps its last token
+        assert_eq!(limiter.acquire(&jane).map_err(|(index, _)| index), Err(0));
+        assert!(limiter.acquire(&john).is_ok());
+        // the tenant is out of tokens, even though john has one left
+        let (index, wait) = limiter.acquire(&john).unwrap_err();
+        assert_eq!(index, 1);
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(20));
+
+        limiter.sweep();
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,84 @@
+use std::future::{ready, Ready};
+use std::{rc::Rc, time::Instant};
+
+use crate::{metrics::METRICS, service::types::Tenant};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+
+/// Counts and times every request by its route and tenant, so this has to
+/// wrap every other middleware to see the responses they short circuit.
+pub struct RequestMetricsMiddlewareFactory;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetricsMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+
+        Box::pin(async move {
+            let started_at = Instant::now();
+            let method = req.method().to_string();
+            // the route pattern and not the path, which would make a series per key
+            let route = req
+                .match_pattern()
+                .unwrap_or_else(|| String::from("unmatched"));
+            let http_req = req.request().clone();
+
+            let res = srv.call(req).await;
+
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+            let tenant = http_req
+                .extensions()
+                .get::<Tenant>()
+                .map(|tenant| tenant.0.clone())
+                .unwrap_or_default();
+            METRICS
+                .http_requests
+                .with_label_values(&[&method, &route, &tenant, status.as_str()])
+                .inc();
+            METRICS
+                .http_request_duration
+                .with_label_values(&[&method, &route, &tenant])
+                .observe(started_at.elapsed().as_secs_f64());
+            res
+        })
+    }
+}
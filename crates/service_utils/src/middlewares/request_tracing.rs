@@ -0,0 +1,115 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use crate::{service::types::Tenant, telemetry::TRACER_NAME};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::HeaderMap,
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer},
+    KeyValue,
+};
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+/// Serves every request in a server span, continuing the trace of the
+/// caller when it sends a `traceparent` header. Spans started while the
+/// request is handled are children of this one.
+pub struct RequestTracingMiddlewareFactory;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracingMiddlewareFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestTracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+
+        Box::pin(async move {
+            let parent = global::get_text_map_propagator(|propagator| {
+                propagator.extract(&HeaderExtractor(req.headers()))
+            });
+            let method = req.method().to_string();
+            let route = req
+                .match_pattern()
+                .unwrap_or_else(|| String::from("unmatched"));
+            let tracer = global::tracer(TRACER_NAME);
+            let span = tracer
+                .span_builder(format!("{method} {route}"))
+                .with_kind(SpanKind::Server)
+                .with_attributes(vec![
+                    KeyValue::new("http.request.method", method),
+                    KeyValue::new("http.route", route),
+                    KeyValue::new("url.path", req.path().to_string()),
+                ])
+                .start_with_context(&tracer, &parent);
+            let cx = parent.with_span(span);
+            let http_req = req.request().clone();
+
+            let res = srv.call(req).with_context(cx.clone()).await;
+
+            let span = cx.span();
+            if let Some(tenant) = http_req.extensions().get::<Tenant>() {
+                span.set_attribute(KeyValue::new("tenant", tenant.0.clone()));
+            }
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+            span.set_attribute(KeyValue::new(
+                "http.response.status_code",
+                i64::from(status.as_u16()),
+            ));
+            if status.is_server_error() {
+                span.set_status(Status::error(status.to_string()));
+            }
+            span.end();
+            res
+        })
+    }
+}
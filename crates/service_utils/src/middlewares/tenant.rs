@@ -98,11 +98,14 @@ where
                 .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
             let assets_regex = Regex::new(".*/assets/.+")
                 .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
+            let tenants_regex = Regex::new("^/tenants(/.*)?$")
+                .map_err(|err| error::ErrorInternalServerError(err.to_string()))?;
             let is_excluded: bool = app_state
                 .tenant_middleware_exclusion_list
                 .contains(&request_path)
                 || pkg_regex.is_match(&request_path)
-                || assets_regex.is_match(&request_path);
+                || assets_regex.is_match(&request_path)
+                || tenants_regex.is_match(&request_path);
 
             if !is_excluded && app_state.enable_tenant_and_scope {
                 debug!(
@@ -122,10 +125,15 @@ where
                     .or_else(|| extract_tenant_from_url(req.path(), req.match_pattern()))
                     .or_else(|| extract_tenant_from_query_params(req.query_string()));
 
+                let is_active_tenant = |tenant: &str| {
+                    app_state
+                        .tenants
+                        .read()
+                        .map(|tenants| tenants.contains(tenant))
+                        .unwrap_or(false)
+                };
                 let validated_tenant: Tenant = match tenant {
-                    Some(val) if app_state.tenants.contains(val) => {
-                        Tenant(String::from(val))
-                    }
+                    Some(val) if is_active_tenant(val) => Tenant(String::from(val)),
                     Some(_) => {
                         return Err(error::ErrorBadRequest("invalid x-tenant value"));
                     }
@@ -136,6 +144,10 @@ where
 
                 let tenant_config = app_state
                     .tenant_configs
+                    .read()
+                    .map_err(|_| {
+                        error::ErrorInternalServerError("tenant configs lock is poisoned")
+                    })?
                     .get(&validated_tenant.0)
                     .cloned()
                     .ok_or_else(|| {
@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    fs,
+    future::ready,
+    io::{Cursor, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context};
+use aws_sdk_kms::Client;
+use futures_util::future::BoxFuture;
+use pgp::{Deserializable, Message, SignedSecretKey};
+
+use crate::{aws::kms, helpers::get_from_env_unsafe};
+
+/*
+  Source of the secrets superposition needs to boot, like the database
+  password and the token the services call each other with.
+*/
+pub trait SecretProvider: Send + Sync {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>>;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum SecretBackend {
+    Kms,
+    Env,
+    File,
+    Age,
+    Pgp,
+}
+
+impl FromStr for SecretBackend {
+    type Err = String;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "kms" => Ok(SecretBackend::Kms),
+            "env" => Ok(SecretBackend::Env),
+            "file" => Ok(SecretBackend::File),
+            "age" => Ok(SecretBackend::Age),
+            "pgp" => Ok(SecretBackend::Pgp),
+            _ => Err(format!("invalid secret provider {val}")),
+        }
+    }
+}
+
+fn get_env_secret(name: &str) -> anyhow::Result<String> {
+    get_from_env_unsafe::<String>(name).with_context(|| format!("{name} is not set"))
+}
+
+/// Secrets kept as plain text in the env.
+pub struct EnvSecretProvider;
+
+impl SecretProvider for EnvSecretProvider {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(ready(get_env_secret(name)))
+    }
+}
+
+/// Secrets kept in the env encrypted with KMS and base64 encoded.
+pub struct KmsSecretProvider {
+    client: Client,
+}
+
+impl KmsSecretProvider {
+    pub fn new(client: Client) -> Self {
+        KmsSecretProvider { client }
+    }
+}
+
+impl SecretProvider for KmsSecretProvider {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(async move {
+            let ciphertext = get_env_secret(name)?;
+            kms::decrypt(&self.client, &ciphertext)
+                .await
+                .with_context(|| format!("failed to decrypt {name}"))
+        })
+    }
+}
+
+/*
+  Secrets kept one per file in a directory, named after the secret, the
+  way kubernetes mounts secrets into a pod.
+*/
+pub struct FileSecretProvider {
+    dir: PathBuf,
+}
+
+impl FileSecretProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileSecretProvider { dir: dir.into() }
+    }
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        let path = self.dir.join(name);
+        let secret = fs::read_to_string(&path)
+            .map(|secret| secret.trim_end_matches(['\r', '\n']).to_string())
+            .with_context(|| format!("failed to read {name} from {}", path.display()));
+        Box::pin(ready(secret))
+    }
+}
+
+/*
+  Secrets kept in a single file encrypted with age, holding a json object
+  of the secrets by name. It is decrypted once with the x25519 identities
+  of the identity file, as generated by `age-keygen`.
+*/
+pub struct AgeSecretProvider {
+    secrets: HashMap<String, String>,
+}
+
+impl AgeSecretProvider {
+    pub fn new(secrets_file: &str, identity_file: &str) -> anyhow::Result<Self> {
+        let identities = fs::read_to_string(identity_file)
+            .with_context(|| format!("failed to read {identity_file}"))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                age::x25519::Identity::from_str(line)
+                    .map_err(|err| anyhow!("invalid identity in {identity_file}: {err}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let encrypted = fs::read(secrets_file)
+            .with_context(|| format!("failed to read {secrets_file}"))?;
+        let decryptor = match age::Decryptor::new(&encrypted[..])? {
+            age::Decryptor::Recipients(decryptor) => decryptor,
+            _ => return Err(anyhow!("{secrets_file} is not encrypted to recipients")),
+        };
+        let mut decrypted = String::new();
+        decryptor
+            .decrypt(
+                identities
+                    .iter()
+                    .map(|identity| identity as &dyn age::Identity),
+            )?
+            .read_to_string(&mut decrypted)?;
+
+        Ok(AgeSecretProvider {
+            secrets: parse_secrets(secrets_file, &decrypted)?,
+        })
+    }
+}
+
+impl SecretProvider for AgeSecretProvider {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(ready(get_file_secret(&self.secrets, "age", name)))
+    }
+}
+
+fn parse_secrets(
+    secrets_file: &str,
+    decrypted: &str,
+) -> anyhow::Result<HashMap<String, String>> {
+    serde_json::from_str::<HashMap<String, String>>(decrypted)
+        .with_context(|| format!("{secrets_file} is not a json object of strings"))
+}
+
+fn get_file_secret(
+    secrets: &HashMap<String, String>,
+    kind: &str,
+    name: &str,
+) -> anyhow::Result<String> {
+    secrets
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("{name} is not in the {kind} secrets file"))
+}
+
+/*
+  Secrets kept like the age ones, in a json object encrypted with OpenPGP
+  and armored, as `gpg --encrypt --armor` writes it. It is decrypted once
+  with the armored secret key of the key file, unlocked with `passphrase`.
+*/
+pub struct PgpSecretProvider {
+    secrets: HashMap<String, String>,
+}
+
+impl PgpSecretProvider {
+    pub fn new(
+        secrets_file: &str,
+        key_file: &str,
+        passphrase: String,
+    ) -> anyhow::Result<Self> {
+        let key =
+            fs::read(key_file).with_context(|| format!("failed to read {key_file}"))?;
+        let (key, _) = SignedSecretKey::from_armor_single(Cursor::new(key))
+            .map_err(|err| anyhow!("invalid secret key in {key_file}: {err}"))?;
+
+        let encrypted = fs::read(secrets_file)
+            .with_context(|| format!("failed to read {secrets_file}"))?;
+        let (message, _) = Message::from_armor_single(Cursor::new(encrypted))
+            .map_err(|err| anyhow!("{secrets_file} is not an armored message: {err}"))?;
+        let (message, _) = message
+            .decrypt(|| passphrase, &[&key])
+            .map_err(|err| anyhow!("failed to decrypt {secrets_file}: {err}"))?;
+        let decrypted = message
+            .get_content()
+            .map_err(|err| anyhow!("failed to read {secrets_file}: {err}"))?
+            .ok_or_else(|| anyhow!("{secrets_file} has no content"))?;
+        let decrypted = String::from_utf8(decrypted)
+            .with_context(|| format!("{secrets_file} is not utf-8"))?;
+
+        Ok(PgpSecretProvider {
+            secrets: parse_secrets(secrets_file, &decrypted)?,
+        })
+    }
+}
+
+impl SecretProvider for PgpSecretProvider {
+    fn get_secret<'a>(&'a self, name: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        Box::pin(ready(get_file_secret(&self.secrets, "pgp", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    fn read(provider: &dyn SecretProvider, name: &str) -> anyhow::Result<String> {
+        provider
+            .get_secret(name)
+            .now_or_never()
+            .expect("the provider should not wait")
+    }
+
+    fn secrets_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "superposition-secrets-{name}-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_env_secret_provider() {
+        std::env::set_var("SECRETS_TEST_ENV_TOKEN", "s3cret");
+        assert_eq!(
+            read(&EnvSecretProvider, "SECRETS_TEST_ENV_TOKEN").unwrap(),
+            "s3cret"
+        );
+        assert!(read(&EnvSecretProvider, "SECRETS_TEST_ENV_MISSING").is_err());
+    }
+
+    #[test]
+    fn test_file_secret_provider() {
+        let dir = secrets_dir("file");
+        fs::write(dir.join("DB_PASSWORD"), "docker\n").unwrap();
+        fs::write(dir.join("SUPERPOSITION_TOKEN"), "  1234 56\r\n").unwrap();
+        let provider = FileSecretProvider::new(&dir);
+
+        // only the trailing line break is dropped
+        assert_eq!(read(&provider, "DB_PASSWORD").unwrap(), "docker");
+        assert_eq!(read(&provider, "SUPERPOSITION_TOKEN").unwrap(), "  1234 56");
+        assert!(read(&provider, "CONFIG_SECRETS_KEY").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_secrets_parsing() {
+        let secrets = parse_secrets("secrets", r#"{"DB_PASSWORD": "docker"}"#).unwrap();
+        assert_eq!(
+            get_file_secret(&secrets, "age", "DB_PASSWORD").unwrap(),
+            "docker"
+        );
+        assert!(get_file_secret(&secrets, "age", "SUPERPOSITION_TOKEN").is_err());
+        assert!(parse_secrets("secrets", r#"{"DB_PASSWORD": 1}"#).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 use std::{
     collections::{HashMap, HashSet},
     future::{ready, Ready},
@@ -8,12 +8,17 @@ use std::{
 
 use actix_web::{error, web::Data, Error, FromRequest, HttpMessage};
 use derive_more::{Deref, DerefMut};
+use futures_util::future::LocalBoxFuture;
 use jsonschema::JSONSchema;
 use serde_json::json;
 use snowflake::SnowflakeIdGenerator;
-use superposition_types::TenantConfig;
+use superposition_types::{
+    authorization::{ApiKey, RoleBinding},
+    result as superposition, TenantConfig,
+};
 
 use crate::db::pgschema_manager::{PgSchemaConnection, PgSchemaManager};
+use crate::helpers::run_blocking;
 
 pub struct ExperimentationFlags {
     pub allow_same_keys_overlapping_ctx: bool,
@@ -35,12 +40,17 @@ pub enum AppHeader {
     XConfigVersion,
     XAuditId,
     LastModified,
+    Warning,
+    #[strum(serialize = "etag")]
+    ETag,
+    IdempotencyKey,
+    IdempotentReplayed,
 }
 
 pub struct AppState {
     pub cac_host: String,
     pub app_env: AppEnv,
-    pub tenants: HashSet<String>,
+    pub tenants: RwLock<HashSet<String>>,
     pub cac_version: String,
     pub db_pool: PgSchemaManager,
     pub meta_schema: JSONSchema,
@@ -49,10 +59,37 @@ pub struct AppState {
     pub enable_tenant_and_scope: bool,
     pub tenant_middleware_exclusion_list: HashSet<String>,
     pub service_prefix: String,
-    pub tenant_configs: HashMap<String, TenantConfig>,
+    pub tenant_configs: RwLock<HashMap<String, TenantConfig>>,
     pub superposition_token: String,
+    pub soft_delete_retention_days: i64,
+    /// Config versions in a chain of patches before a full checkpoint.
+    pub config_version_checkpoint_interval: i64,
+    /// Untagged config versions past the newest this many are pruned.
+    pub config_version_retention_count: Option<i64>,
+    /// Untagged config versions older than this are pruned.
+    pub config_version_retention_days: Option<i64>,
+    pub idempotency_key_ttl_secs: i64,
+    /// How long a request holds its idempotency key before a retry can take
+    /// it over.
+    pub idempotency_lease_secs: i64,
+    /// Largest request body accepted, by the JSON extractor and by the
+    /// idempotency middleware which buffers the body.
+    pub max_request_body_bytes: usize,
+    pub enable_authorization: bool,
+    pub superposition_admins: HashSet<String>,
+    pub role_bindings: RwLock<HashMap<String, Vec<RoleBinding>>>,
+    /// API keys by the hash of the key.
+    pub api_keys: RwLock<HashMap<String, ApiKey>>,
+    /// Key the tenant keys of secret configs are derived from.
+    pub config_secrets_key: Option<String>,
+    /// Key the secrets webhook payloads are signed with are derived from.
+    pub webhook_signing_key: Option<String>,
+    pub webhook_policy: crate::webhooks::policy::WebhookPolicy,
     #[cfg(feature = "high-performance-mode")]
     pub redis: fred::clients::RedisPool,
+    #[cfg(feature = "config-cache")]
+    pub config_cache: crate::config_cache::ConfigCache,
+    pub deprecations: crate::deprecations::DeprecationCache,
     pub http_client: reqwest::Client,
 }
 
@@ -183,45 +220,136 @@ impl FromRequest for Tenant {
 
 #[derive(Deref, DerefMut)]
 pub struct DbConnection(pub PgSchemaConnection);
+
+impl DbConnection {
+    /// Checks out a connection of the pool of `namespace`, waiting for one on
+    /// the blocking thread pool when the pool is exhausted.
+    pub async fn checkout(
+        db_pool: PgSchemaManager,
+        namespace: String,
+    ) -> superposition::Result<Self> {
+        run_blocking(move || {
+            db_pool
+                .get_conn(namespace)
+                .map(DbConnection)
+                .map_err(superposition::AppError::UnexpectedError)
+        })
+        .await
+    }
+
+    /// Runs `f` with the connection on the blocking thread pool, `f` owns the
+    /// connection and can hand it back when more work follows.
+    pub async fn run<T, F>(self, f: F) -> superposition::Result<T>
+    where
+        F: FnOnce(PgSchemaConnection) -> superposition::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let DbConnection(conn) = self;
+        run_blocking(move || f(conn)).await
+    }
+}
+
+/// Namespace of the request and the pools to check its connection out of.
+fn request_db_pool(
+    req: &actix_web::HttpRequest,
+) -> Result<(PgSchemaManager, String), Error> {
+    let namespace = AppExecutionNamespace::from_request_sync(req)?;
+    match req.app_data::<Data<AppState>>() {
+        Some(state) => Ok((state.db_pool.clone(), namespace.as_str().to_string())),
+        None => {
+            log::info!("DbConnection-FromRequest: Unable to get app_data from request");
+            Err(error::ErrorInternalServerError(""))
+        }
+    }
+}
+
 impl FromRequest for DbConnection {
     type Error = Error;
-    type Future = Ready<Result<DbConnection, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<DbConnection, Self::Error>>;
 
     fn from_request(
         req: &actix_web::HttpRequest,
         _: &mut actix_web::dev::Payload,
     ) -> Self::Future {
-        let namespace = match AppExecutionNamespace::from_request_sync(req) {
-            Ok(val) => val.as_str().to_string(),
-            Err(e) => {
-                return ready(Err(e));
-            }
+        let (db_pool, namespace) = match request_db_pool(req) {
+            Ok(val) => val,
+            Err(e) => return Box::pin(ready(Err(e))),
         };
 
-        let app_state = match req.app_data::<Data<AppState>>() {
-            Some(state) => state,
-            None => {
-                log::info!(
-                    "DbConnection-FromRequest: Unable to get app_data from request"
-                );
-                return ready(Err(error::ErrorInternalServerError("")));
-            }
-        };
+        Box::pin(async move {
+            DbConnection::checkout(db_pool, namespace)
+                .await
+                .map_err(|e| {
+                    log::info!("Unable to get db connection from pool, error: {e}");
+                    error::ErrorInternalServerError("")
+                })
+        })
+    }
+}
 
-        let result = match app_state.db_pool.get_conn(namespace) {
-            Ok(conn) => Ok(DbConnection(conn)),
-            Err(e) => {
-                log::info!("Unable to get db connection from pool, error: {e}");
-                Err(error::ErrorInternalServerError(""))
-            }
+/*
+  Connection for handlers which only read and can be served slightly
+  stale data, taken from the replica of the schema when one is configured
+  and caught up. Writes, and reads which have to see them, use
+  `DbConnection`.
+*/
+#[derive(Deref, DerefMut)]
+pub struct ReadDbConnection(pub PgSchemaConnection);
+
+impl ReadDbConnection {
+    pub async fn checkout(
+        db_pool: PgSchemaManager,
+        namespace: String,
+    ) -> superposition::Result<Self> {
+        run_blocking(move || {
+            db_pool
+                .get_read_conn(namespace)
+                .map(ReadDbConnection)
+                .map_err(superposition::AppError::UnexpectedError)
+        })
+        .await
+    }
+
+    /// Same as [`DbConnection::run`].
+    pub async fn run<T, F>(self, f: F) -> superposition::Result<T>
+    where
+        F: FnOnce(PgSchemaConnection) -> superposition::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let ReadDbConnection(conn) = self;
+        run_blocking(move || f(conn)).await
+    }
+}
+
+impl FromRequest for ReadDbConnection {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<ReadDbConnection, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let (db_pool, namespace) = match request_db_pool(req) {
+            Ok(val) => val,
+            Err(e) => return Box::pin(ready(Err(e))),
         };
 
-        ready(result)
+        Box::pin(async move {
+            ReadDbConnection::checkout(db_pool, namespace)
+                .await
+                .map_err(|e| {
+                    log::info!("Unable to get db connection from pool, error: {e}");
+                    error::ErrorInternalServerError("")
+                })
+        })
     }
 }
 
 pub struct CustomHeaders {
     pub config_tags: Option<String>,
+    pub change_reason: Option<String>,
+    pub if_match: Option<String>,
+    pub idempotency_key: Option<String>,
 }
 impl FromRequest for CustomHeaders {
     type Error = Error;
@@ -236,6 +364,17 @@ impl FromRequest for CustomHeaders {
             config_tags: header_val.get("x-config-tags").and_then(|header_val| {
                 header_val.to_str().map_or(None, |v| Some(v.to_string()))
             }),
+            change_reason: header_val.get("x-change-reason").and_then(|header_val| {
+                header_val.to_str().map_or(None, |v| Some(v.to_string()))
+            }),
+            if_match: header_val.get("if-match").and_then(|header_val| {
+                header_val.to_str().map_or(None, |v| Some(v.to_string()))
+            }),
+            idempotency_key: header_val
+                .get(AppHeader::IdempotencyKey.to_string())
+                .and_then(|header_val| {
+                    header_val.to_str().map_or(None, |v| Some(v.to_string()))
+                }),
         };
         ready(Ok(val))
     }
@@ -0,0 +1,161 @@
+use std::{
+    borrow::Cow,
+    fs::OpenOptions,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::bail;
+use opentelemetry::{
+    global,
+    propagation::Injector,
+    trace::{FutureExt, SpanKind, Status, TraceContextExt, Tracer},
+    Context,
+};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, TracerProvider},
+    Resource,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::helpers::{get_from_env_or_default, get_from_env_unsafe};
+
+pub use opentelemetry::KeyValue;
+
+/// Name of the tracer every span of superposition is started with.
+pub const TRACER_NAME: &str = "superposition";
+
+static TRACING_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether spans are exported, for work which is only worth doing then.
+pub fn is_tracing_enabled() -> bool {
+    TRACING_ENABLED.load(Ordering::Relaxed)
+}
+
+/*
+  Spans are exported to the exporter set in `OTEL_TRACES_EXPORTER`:
+  `otlp` sends them over http to `OTEL_EXPORTER_OTLP_ENDPOINT`, `stdout`
+  prints them and `file` appends them to `OTEL_TRACES_FILE`, for local
+  runs. Tracing is disabled when it is not set, spans are then no-ops.
+*/
+pub fn init_tracing() -> anyhow::Result<()> {
+    let exporter = get_from_env_or_default("OTEL_TRACES_EXPORTER", String::from("none"));
+    let config = sdktrace::config().with_resource(Resource::new(vec![KeyValue::new(
+        "service.name",
+        get_from_env_or_default("OTEL_SERVICE_NAME", String::from("superposition")),
+    )]));
+    let provider = match exporter.as_str() {
+        "none" => return Ok(()),
+        "otlp" => {
+            let exporter = opentelemetry_otlp::new_exporter()
+                .http()
+                .build_span_exporter()?;
+            TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::TokioCurrentThread)
+                .with_config(config)
+                .build()
+        }
+        "stdout" => TracerProvider::builder()
+            .with_simple_exporter(opentelemetry_stdout::SpanExporter::default())
+            .with_config(config)
+            .build(),
+        "file" => {
+            let path = get_from_env_unsafe::<String>("OTEL_TRACES_FILE")
+                .map_err(|_| anyhow::anyhow!("OTEL_TRACES_FILE is not set"))?;
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let exporter = opentelemetry_stdout::SpanExporter::builder()
+                .with_writer(file)
+                .build();
+            TracerProvider::builder()
+                .with_simple_exporter(exporter)
+                .with_config(config)
+                .build()
+        }
+        other => bail!("invalid traces exporter {other}"),
+    };
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider);
+    TRACING_ENABLED.store(true, Ordering::Relaxed);
+    log::info!("exporting traces to {exporter}");
+    Ok(())
+}
+
+/// Flushes the spans which have not been exported yet.
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+/// Starts a span as a child of the current one, it ends when the returned
+/// context and its clones are dropped.
+pub fn start_span(
+    name: impl Into<Cow<'static, str>>,
+    kind: SpanKind,
+    attributes: Vec<KeyValue>,
+) -> Context {
+    let tracer = global::tracer(TRACER_NAME);
+    let parent = Context::current();
+    let span = tracer
+        .span_builder(name)
+        .with_kind(kind)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &parent);
+    parent.with_span(span)
+}
+
+/// Runs `f`, which must not await, in a span of its own.
+pub fn in_span<T>(
+    name: &'static str,
+    attributes: Vec<KeyValue>,
+    f: impl FnOnce() -> T,
+) -> T {
+    let _guard = start_span(name, SpanKind::Internal, attributes).attach();
+    f()
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_str(key), HeaderValue::from_str(&value))
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Sends `request` in a client span, with the trace context in its headers
+/// so that the service it is sent to continues the trace.
+pub async fn send_traced(
+    name: impl Into<Cow<'static, str>>,
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    let cx = start_span(name, SpanKind::Client, Vec::new());
+    let mut headers = HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(&mut headers))
+    });
+    let response = request
+        .headers(headers)
+        .send()
+        .with_context(cx.clone())
+        .await;
+
+    let span = cx.span();
+    match &response {
+        Ok(response) => {
+            let status = response.status();
+            span.set_attribute(KeyValue::new(
+                "http.response.status_code",
+                i64::from(status.as_u16()),
+            ));
+            if !status.is_success() {
+                span.set_status(Status::error(status.to_string()));
+            }
+        }
+        Err(err) => span.set_status(Status::error(err.to_string())),
+    }
+    response
+}
@@ -0,0 +1,634 @@
+pub mod policy;
+pub mod registry;
+
+use std::str::FromStr;
+
+use actix_web::{http::StatusCode, web::Data};
+use anyhow::anyhow;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{
+    sql_query,
+    sql_types::{BigInt, Integer, Json, Nullable, Text, Timestamp},
+    OptionalExtension, PgConnection, QueryableByName, RunQueryDsl,
+};
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use superposition_types::{
+    result::{self, AppError, ResponseError},
+    webhook::{
+        render_payload_template, HeadersEnum, HttpMethod, Webhook, WebhookEndpoint,
+        WebhookEvent, WebhookEventInfo, WebhookResponse,
+    },
+};
+
+use crate::{
+    db::utils::get_tenant_namespaces,
+    encryption::TenantCipher,
+    metrics::{outcome_label, METRICS},
+    service::types::{AppState, DbConnection, Tenant},
+    telemetry::send_traced,
+};
+
+/// Header carrying the id of the event, the same on every attempt.
+pub const WEBHOOK_ID_HEADER: &str = "x-webhook-id";
+/// Header carrying `t=<unix time>,v1=<hex HMAC-SHA256 of "<unix time>.<body>">`.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Events of a tenant sent by a single dispatch.
+const DISPATCH_BATCH_SIZE: usize = 100;
+const FIRST_RETRY_SECS: i64 = 30;
+const MAX_RETRY_SECS: i64 = 60 * 60;
+/// How long a claimed event is hidden from other dispatchers, well past the
+/// lookup and call timeouts a round of deliveries is bounded by.
+const CLAIM_SECS: i64 = 5 * 60;
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Failed on every attempt, sent again only when redelivered.
+    Dead,
+}
+
+/// A webhook event of the outbox of a schema.
+#[derive(QueryableByName, Serialize)]
+pub struct OutboxEvent {
+    #[diesel(sql_type = BigInt)]
+    pub id: i64,
+    #[diesel(sql_type = Text)]
+    pub event: String,
+    #[diesel(sql_type = Json)]
+    pub webhook: Value,
+    #[diesel(sql_type = Json)]
+    pub payload: Value,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub config_version: Option<String>,
+    #[diesel(sql_type = Text)]
+    pub status: String,
+    #[diesel(sql_type = Integer)]
+    pub attempts: i32,
+    #[diesel(sql_type = Timestamp)]
+    pub next_attempt_at: NaiveDateTime,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub last_error: Option<String>,
+    #[diesel(sql_type = Timestamp)]
+    pub created_at: NaiveDateTime,
+    #[diesel(sql_type = Nullable<Timestamp>)]
+    pub delivered_at: Option<NaiveDateTime>,
+    /// Endpoint of the registry the event is for, if any.
+    #[diesel(sql_type = Nullable<Text>)]
+    pub endpoint: Option<String>,
+    #[diesel(sql_type = Nullable<Json>)]
+    pub payload_template: Option<Value>,
+}
+
+#[derive(QueryableByName)]
+struct EventCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+fn encoding_error(err: serde_json::Error) -> AppError {
+    log::error!("failed to encode the webhook event: {err}");
+    AppError::UnexpectedError(anyhow!("failed to encode the webhook event"))
+}
+
+/// Queues `event` for `webhook` in the transaction of `conn`, so that it is
+/// only sent once the write which caused it is committed.
+pub fn enqueue_webhook_event<T>(
+    conn: &mut PgConnection,
+    webhook: &Webhook,
+    event: WebhookEvent,
+    payload: &T,
+    config_version: &Option<String>,
+) -> result::Result<()>
+where
+    T: Serialize,
+{
+    insert_event(conn, webhook, None, event, payload, config_version)
+}
+
+fn insert_event<T>(
+    conn: &mut PgConnection,
+    webhook: &Webhook,
+    endpoint: Option<&WebhookEndpoint>,
+    event: WebhookEvent,
+    payload: &T,
+    config_version: &Option<String>,
+) -> result::Result<()>
+where
+    T: Serialize,
+{
+    let event = serde_json::to_value(event).map_err(encoding_error)?;
+    let webhook = serde_json::to_value(webhook).map_err(encoding_error)?;
+    let payload = serde_json::to_value(payload).map_err(encoding_error)?;
+    sql_query(
+        "INSERT INTO webhook_events (event, webhook, payload, config_version, \
+         next_attempt_at, created_at, endpoint, payload_template) \
+         VALUES ($1, $2, $3, $4, $5, $5, $6, $7)",
+    )
+    .bind::<Text, _>(event.as_str().unwrap_or_default())
+    .bind::<Json, _>(webhook)
+    .bind::<Json, _>(payload)
+    .bind::<Nullable<Text>, _>(config_version.clone())
+    .bind::<Timestamp, _>(Utc::now().naive_utc())
+    .bind::<Nullable<Text>, _>(endpoint.map(|endpoint| endpoint.name.clone()))
+    .bind::<Nullable<Json>, _>(
+        endpoint.and_then(|endpoint| endpoint.payload_template.clone()),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// The events of the outbox with `status`, or all of them, newest first.
+pub fn list_webhook_events(
+    conn: &mut PgConnection,
+    status: Option<DeliveryStatus>,
+    limit: Option<i64>,
+    offset: i64,
+) -> result::Result<(i64, Vec<OutboxEvent>)> {
+    let status = status.map(|status| status.to_string());
+    let total = sql_query(
+        "SELECT COUNT(*) AS count FROM webhook_events \
+         WHERE $1::TEXT IS NULL OR status = $1",
+    )
+    .bind::<Nullable<Text>, _>(status.clone())
+    .get_result::<EventCount>(conn)?
+    .count;
+    let events = sql_query(
+        "SELECT * FROM webhook_events WHERE $1::TEXT IS NULL OR status = $1 \
+         ORDER BY id DESC LIMIT $2 OFFSET $3",
+    )
+    .bind::<Nullable<Text>, _>(status)
+    .bind::<Nullable<BigInt>, _>(limit)
+    .bind::<BigInt, _>(offset)
+    .load::<OutboxEvent>(conn)?;
+    Ok((total, events))
+}
+
+pub fn get_webhook_event(
+    conn: &mut PgConnection,
+    id: i64,
+) -> result::Result<Option<OutboxEvent>> {
+    Ok(sql_query("SELECT * FROM webhook_events WHERE id = $1")
+        .bind::<BigInt, _>(id)
+        .get_result::<OutboxEvent>(conn)
+        .optional()?)
+}
+
+/*
+  Sends the event `id` again on the next dispatch, with a fresh set of
+  attempts, whether it was delivered or dead lettered. A pending event is
+  only reset once it is due, as it is otherwise claimed by a dispatcher which
+  is sending it, or waiting to be retried.
+*/
+pub fn redeliver_webhook_event(
+    conn: &mut PgConnection,
+    id: i64,
+) -> result::Result<Option<OutboxEvent>> {
+    let now = Utc::now().naive_utc();
+    let redelivered = sql_query(
+        "UPDATE webhook_events SET status = $2, attempts = 0, next_attempt_at = $3, \
+         delivered_at = NULL WHERE id = $1 \
+         AND (status IN ($4, $5) OR next_attempt_at <= $3) RETURNING *",
+    )
+    .bind::<BigInt, _>(id)
+    .bind::<Text, _>(DeliveryStatus::Pending.to_string())
+    .bind::<Timestamp, _>(now)
+    .bind::<Text, _>(DeliveryStatus::Delivered.to_string())
+    .bind::<Text, _>(DeliveryStatus::Dead.to_string())
+    .get_result::<OutboxEvent>(conn)
+    .optional()?;
+    match redelivered {
+        Some(event) => Ok(Some(event)),
+        None if get_webhook_event(conn, id)?.is_some() => {
+            Err(AppError::ResponseError(ResponseError {
+                message: format!(
+                    "Webhook event {id} is being sent or waiting to be retried, \
+                     redeliver it once it is delivered or dead lettered"
+                ),
+                status_code: StatusCode::CONFLICT,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The secret the webhook payloads of `tenant` are signed with, derived from
+/// `WEBHOOK_SIGNING_KEY`.
+pub fn webhook_signing_secret(master_key: Option<&str>, tenant: &str) -> Option<String> {
+    master_key.map(|master_key| {
+        let material = format!("{tenant}:{master_key}");
+        to_hex(&blake3::derive_key(
+            "superposition webhook signing v1",
+            material.as_bytes(),
+        ))
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("t={timestamp},v1={}", to_hex(&mac.finalize().into_bytes()))
+}
+
+fn webhook_headers(
+    state: &AppState,
+    webhook: &Webhook,
+    config_version: &Option<String>,
+    tenant: &Tenant,
+) -> Result<HeaderMap, String> {
+    let mut header_array = webhook
+        .service_headers
+        .iter()
+        .filter_map(|key| match key {
+            HeadersEnum::ConfigVersion => config_version
+                .as_ref()
+                .map(|config_version| (key.to_string(), config_version.clone())),
+            HeadersEnum::TenantId => Some((key.to_string(), tenant.to_string())),
+        })
+        .collect::<Vec<(String, String)>>();
+
+    webhook
+        .custom_headers
+        .iter()
+        .for_each(|(key, value)| header_array.push((key.clone(), value.clone())));
+
+    if let Some(auth) = &webhook.authorization {
+        let cipher = TenantCipher::new(state.config_secrets_key.as_deref(), tenant);
+        let auth_token_value = state
+            .webhook_policy
+            .authorization_value(auth, &cipher)
+            .map_err(|err| {
+                log::error!("Failed to retrieve authentication token for the webhook with error: {err}");
+                String::from("Failed to retrieve authentication token for the webhook. Please verify the credentials in TenantConfig.")
+            })?;
+        header_array.push((auth.key.clone(), auth_token_value));
+    }
+
+    let mut headers = HeaderMap::new();
+    header_array.iter().for_each(|(name, value)| {
+        let h_name = HeaderName::from_str(name);
+        let h_value = HeaderValue::from_str(value);
+
+        if let (Ok(key), Ok(value)) = (h_name, h_value) {
+            headers.insert(key, value);
+        }
+    });
+    Ok(headers)
+}
+
+async fn deliver(
+    state: &AppState,
+    tenant: &Tenant,
+    event: &OutboxEvent,
+) -> Result<(), String> {
+    let webhook = serde_json::from_value::<Webhook>(event.webhook.clone())
+        .map_err(|err| format!("invalid webhook: {err}"))?;
+    let webhook_event =
+        serde_json::from_value::<WebhookEvent>(Value::String(event.event.clone()))
+            .map_err(|err| format!("invalid event: {err}"))?;
+    let client = state
+        .webhook_policy
+        .destination_client(&webhook.url)
+        .await?;
+    let headers = webhook_headers(state, &webhook, &event.config_version, tenant)?;
+    let mut body = serde_json::to_value(WebhookResponse {
+        event_info: WebhookEventInfo {
+            webhook_event,
+            time: event.created_at.to_string(),
+            tenant_id: tenant.to_string(),
+            config_version: event.config_version.clone(),
+        },
+        payload: &event.payload,
+    })
+    .map_err(|err| err.to_string())?;
+    if let Some(template) = &event.payload_template {
+        body = render_payload_template(template, &body);
+    }
+    let body = serde_json::to_vec(&body).map_err(|err| err.to_string())?;
+
+    let mut request_builder = match webhook.method {
+        HttpMethod::Post => client.post(&webhook.url),
+        HttpMethod::Get => client.get(&webhook.url),
+        HttpMethod::Put => client.put(&webhook.url),
+        HttpMethod::Patch => client.patch(&webhook.url),
+        HttpMethod::Delete => client.delete(&webhook.url),
+    }
+    .headers(headers)
+    .header(CONTENT_TYPE, "application/json")
+    .header(WEBHOOK_ID_HEADER, event.id.to_string());
+    if let Some(secret) =
+        webhook_signing_secret(state.webhook_signing_key.as_deref(), tenant)
+    {
+        request_builder = request_builder.header(
+            WEBHOOK_SIGNATURE_HEADER,
+            sign(&secret, Utc::now().timestamp(), &body),
+        );
+    }
+
+    let result = match send_traced("webhook", request_builder.body(body)).await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!(
+            "webhook call failed with status code {}",
+            response.status()
+        )),
+        Err(err) => Err(format!("webhook call failed with error: {err}")),
+    };
+    METRICS
+        .webhook_calls
+        .with_label_values(&[tenant.as_str(), outcome_label(&result)])
+        .inc();
+    result
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    Duration::seconds((FIRST_RETRY_SECS * 2_i64.pow(exponent)).min(MAX_RETRY_SECS))
+}
+
+/*
+  Events are claimed by moving their next attempt past the time the
+  deliveries can take, so that the dispatchers of other instances skip them
+  without a lock being held over the calls.
+*/
+fn claim_due_events(
+    conn: &mut PgConnection,
+    limit: i64,
+) -> result::Result<Vec<OutboxEvent>> {
+    let now = Utc::now().naive_utc();
+    let mut events = sql_query(
+        "UPDATE webhook_events SET next_attempt_at = $1 WHERE id IN ( \
+         SELECT id FROM webhook_events WHERE status = $2 AND next_attempt_at <= $3 \
+         ORDER BY id LIMIT $4 FOR UPDATE SKIP LOCKED) RETURNING *",
+    )
+    .bind::<Timestamp, _>(now + Duration::seconds(CLAIM_SECS))
+    .bind::<Text, _>(DeliveryStatus::Pending.to_string())
+    .bind::<Timestamp, _>(now)
+    .bind::<BigInt, _>(limit)
+    .load::<OutboxEvent>(conn)?;
+    events.sort_by_key(|event| event.id);
+    Ok(events)
+}
+
+/// The status, next attempt and delivery time of an event after its
+/// `attempts`th attempt.
+fn attempt_outcome(
+    attempts: i32,
+    delivered: bool,
+    max_attempts: i32,
+    now: NaiveDateTime,
+) -> (DeliveryStatus, NaiveDateTime, Option<NaiveDateTime>) {
+    if delivered {
+        (DeliveryStatus::Delivered, now, Some(now))
+    } else if attempts >= max_attempts {
+        (DeliveryStatus::Dead, now, None)
+    } else {
+        (DeliveryStatus::Pending, now + retry_delay(attempts), None)
+    }
+}
+
+fn record_attempt(
+    conn: &mut PgConnection,
+    event: &OutboxEvent,
+    error: Option<String>,
+    max_attempts: i32,
+) -> result::Result<()> {
+    let attempts = event.attempts + 1;
+    let (status, next_attempt_at, delivered_at) = attempt_outcome(
+        attempts,
+        error.is_none(),
+        max_attempts,
+        Utc::now().naive_utc(),
+    );
+    sql_query(
+        "UPDATE webhook_events SET status = $2, attempts = $3, next_attempt_at = $4, \
+         last_error = $5, delivered_at = $6 WHERE id = $1",
+    )
+    .bind::<BigInt, _>(event.id)
+    .bind::<Text, _>(status.to_string())
+    .bind::<Integer, _>(attempts)
+    .bind::<Timestamp, _>(next_attempt_at)
+    .bind::<Nullable<Text>, _>(error)
+    .bind::<Nullable<Timestamp>, _>(delivered_at)
+    .execute(conn)?;
+    Ok(())
+}
+
+pub struct DispatchOptions {
+    pub period: std::time::Duration,
+    /// How many events of a tenant are sent at the same time.
+    pub concurrency: usize,
+    pub max_attempts: i32,
+    pub retention_days: i64,
+}
+
+fn prune_events(conn: &mut PgConnection, retention_days: i64) -> result::Result<()> {
+    sql_query(
+        "DELETE FROM webhook_events WHERE (status = $1 AND delivered_at < $3) \
+         OR (status = $2 AND next_attempt_at < $3)",
+    )
+    .bind::<Text, _>(DeliveryStatus::Delivered.to_string())
+    .bind::<Text, _>(DeliveryStatus::Dead.to_string())
+    .bind::<Timestamp, _>(Utc::now().naive_utc() - Duration::days(retention_days))
+    .execute(conn)?;
+    Ok(())
+}
+
+async fn prune_outbox(
+    state: &Data<AppState>,
+    namespace: &str,
+    retention_days: i64,
+) -> result::Result<()> {
+    DbConnection::checkout(state.db_pool.clone(), namespace.to_string())
+        .await?
+        .run(move |mut conn| prune_events(&mut conn, retention_days))
+        .await
+}
+
+async fn claim_events(
+    state: &Data<AppState>,
+    namespace: &str,
+    limit: usize,
+) -> result::Result<Vec<OutboxEvent>> {
+    let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+    DbConnection::checkout(state.db_pool.clone(), namespace.to_string())
+        .await?
+        .run(move |mut conn| claim_due_events(&mut conn, limit))
+        .await
+}
+
+async fn deliver_and_record(
+    state: &Data<AppState>,
+    tenant: &Tenant,
+    namespace: &str,
+    event: OutboxEvent,
+    max_attempts: i32,
+) -> result::Result<()> {
+    let delivered = deliver(state, tenant, &event).await;
+    if let Err(err) = &delivered {
+        log::error!(
+            "failed to deliver webhook event {} of {namespace}: {err}",
+            event.id
+        );
+    }
+    DbConnection::checkout(state.db_pool.clone(), namespace.to_string())
+        .await?
+        .run(move |mut conn| {
+            record_attempt(&mut conn, &event, delivered.err(), max_attempts)
+        })
+        .await
+}
+
+/*
+  The events of the outboxes of a tenant are claimed and sent in rounds of at
+  most `concurrency`, so that every event claimed is being sent and its claim
+  cannot run out while it waits for the others. Each attempt is recorded as
+  soon as it is made so that a restart of the server does not send the
+  delivered ones again.
+*/
+async fn dispatch(
+    state: &Data<AppState>,
+    tenant: &Tenant,
+    namespaces: &[String],
+    options: &DispatchOptions,
+) {
+    for namespace in namespaces {
+        if let Err(e) = prune_outbox(state, namespace, options.retention_days).await {
+            log::error!("failed to prune the webhook events of {namespace}: {e}");
+        }
+    }
+    let mut dispatched = 0;
+    while dispatched < DISPATCH_BATCH_SIZE {
+        let mut events = Vec::new();
+        for namespace in namespaces {
+            let limit = options
+                .concurrency
+                .min(DISPATCH_BATCH_SIZE - dispatched)
+                .saturating_sub(events.len());
+            if limit == 0 {
+                break;
+            }
+            match claim_events(state, namespace, limit).await {
+                Ok(claimed) => events
+                    .extend(claimed.into_iter().map(|event| (namespace.as_str(), event))),
+                Err(e) => {
+                    log::error!("failed to claim the webhook events of {namespace}: {e}")
+                }
+            }
+        }
+        if events.is_empty() {
+            break;
+        }
+        dispatched += events.len();
+        join_all(events.into_iter().map(|(namespace, event)| async move {
+            let id = event.id;
+            if let Err(e) =
+                deliver_and_record(state, tenant, namespace, event, options.max_attempts)
+                    .await
+            {
+                log::error!("failed to record webhook event {id} of {namespace}: {e}");
+            }
+        }))
+        .await;
+    }
+}
+
+/*
+  Sends the due events of the outboxes of every tenant, the tenants being
+  dispatched concurrently. A failed delivery is retried with an exponential
+  backoff, from FIRST_RETRY_SECS up to MAX_RETRY_SECS, and dead lettered
+  after `max_attempts`. Delivered and dead lettered events are deleted after
+  `retention_days`.
+*/
+pub async fn run_webhook_dispatcher(state: Data<AppState>, options: DispatchOptions) {
+    let mut interval = actix_web::rt::time::interval(options.period);
+    loop {
+        interval.tick().await;
+        let outboxes = if state.enable_tenant_and_scope {
+            state
+                .tenants
+                .read()
+                .map(|tenants| {
+                    tenants
+                        .iter()
+                        .map(|tenant| {
+                            (
+                                Tenant(tenant.clone()),
+                                get_tenant_namespaces(tenant).to_vec(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        } else {
+            vec![(Tenant("mjos".into()), vec![String::from("cac_v1")])]
+        };
+        join_all(
+            outboxes.iter().map(|(tenant, namespaces)| {
+                dispatch(&state, tenant, namespaces, &options)
+            }),
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(0), Duration::seconds(30));
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(3), Duration::seconds(120));
+        assert_eq!(retry_delay(7), Duration::seconds(1920));
+        assert_eq!(retry_delay(8), Duration::seconds(MAX_RETRY_SECS));
+        assert_eq!(retry_delay(i32::MAX), Duration::seconds(MAX_RETRY_SECS));
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1700000000, br#"{"hello":"world"}"#),
+            "t=1700000000,\
+             v1=654f06c856baf080af3fa272934823257a542d35cf1f88099338f850a60601a4"
+        );
+    }
+
+    #[test]
+    fn test_attempt_outcome() {
+        let now =
+            NaiveDateTime::parse_from_str("2024-11-04 10:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap();
+
+        let (status, next_attempt_at, delivered_at) = attempt_outcome(3, true, 3, now);
+        assert_eq!(status, DeliveryStatus::Delivered);
+        assert_eq!((next_attempt_at, delivered_at), (now, Some(now)));
+
+        let (status, next_attempt_at, delivered_at) = attempt_outcome(2, false, 3, now);
+        assert_eq!(status, DeliveryStatus::Pending);
+        assert_eq!(next_attempt_at, now + Duration::seconds(60));
+        assert_eq!(delivered_at, None);
+
+        let (status, next_attempt_at, delivered_at) = attempt_outcome(3, false, 3, now);
+        assert_eq!(status, DeliveryStatus::Dead);
+        assert_eq!((next_attempt_at, delivered_at), (now, None));
+    }
+}
@@ -0,0 +1,405 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use actix_web::{rt::time::timeout, web};
+use ipnet::IpNet;
+use reqwest::{redirect, Client, Url};
+use serde_json::Value;
+use superposition_types::webhook::{Authorization, Webhook};
+
+use crate::encryption::{is_encrypted, is_mask, TenantCipher, SECRET_MASK};
+
+/// Networks webhooks cannot be sent to unless `WEBHOOK_DENIED_NETWORKS` is set.
+pub const DEFAULT_DENIED_NETWORKS: &str = "0.0.0.0/8,10.0.0.0/8,100.64.0.0/10,\
+    127.0.0.0/8,169.254.0.0/16,172.16.0.0/12,192.168.0.0/16,::/128,::1/128,\
+    fc00::/7,fe80::/10";
+
+/*
+  What the operator allows webhooks to do, as webhooks are defined by the
+  admins of tenants and sent from inside the network of the server:
+  - the env vars an authorization can be read from, so that it cannot name
+    the ones holding the credentials of the server
+  - the hosts a webhook can be sent to, any when empty
+  - the networks the addresses of those hosts cannot be in
+  along with the timeouts of the calls.
+*/
+pub struct WebhookPolicy {
+    auth_env_vars: HashSet<String>,
+    allowed_hosts: Vec<String>,
+    denied_networks: Vec<IpNet>,
+    connect_timeout: Duration,
+    timeout: Duration,
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+impl WebhookPolicy {
+    pub fn new(
+        auth_env_vars: &str,
+        allowed_hosts: &str,
+        denied_networks: &str,
+        connect_timeout: Duration,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            auth_env_vars: split_list(auth_env_vars).map(String::from).collect(),
+            allowed_hosts: split_list(allowed_hosts).map(str::to_lowercase).collect(),
+            denied_networks: split_list(denied_networks)
+                .map(|network| {
+                    network
+                        .parse::<IpNet>()
+                        .map_err(|err| format!("invalid network {network}: {err}"))
+                })
+                .collect::<Result<_, _>>()?,
+            connect_timeout,
+            timeout,
+        })
+    }
+
+    fn is_allowed_host(&self, host: &str) -> bool {
+        self.allowed_hosts.is_empty()
+            || self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| match allowed.strip_prefix("*.") {
+                    Some(domain) => host
+                        .strip_suffix(domain)
+                        .is_some_and(|sub| sub.ends_with('.')),
+                    None => host == allowed,
+                })
+    }
+
+    fn is_denied_address(&self, address: IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(address),
+            IpAddr::V4(_) => address,
+        };
+        self.denied_networks
+            .iter()
+            .any(|network| network.contains(&address))
+    }
+
+    /// Checks the scheme and host of `url`, and its address when it is one.
+    pub fn validate_url(&self, url: &str) -> Result<Url, String> {
+        let url = Url::parse(url).map_err(|err| format!("invalid url {url}: {err}"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(String::from("The url of a webhook should be http or https"));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| String::from("The url of a webhook should have a host"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_lowercase();
+        if !self.is_allowed_host(&host) {
+            return Err(format!("Webhooks cannot be sent to {host}"));
+        }
+        if let Ok(address) = host.parse::<IpAddr>() {
+            if self.is_denied_address(address) {
+                return Err(format!("Webhooks cannot be sent to {host}"));
+            }
+        }
+        Ok(url)
+    }
+
+    /*
+      Run before every call, as the addresses a host resolves to can change
+      after the webhook is saved. The host is resolved once, within the
+      connect timeout, and the call is made with a client pinned to the
+      addresses checked, so that the host cannot resolve to another one by
+      the time it is called. Redirects are not followed by that client, so
+      the destination checked is the one called.
+    */
+    pub async fn destination_client(&self, url: &str) -> Result<Client, String> {
+        let url = self.validate_url(url)?;
+        let host = url
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+        let lookup = web::block({
+            let host = host.clone();
+            move || {
+                (host.as_str(), port)
+                    .to_socket_addrs()
+                    .map(|addresses| addresses.collect::<Vec<_>>())
+            }
+        });
+        let addresses = timeout(self.connect_timeout, lookup)
+            .await
+            .map_err(|_| {
+                format!(
+                    "resolving the webhook url timed out after {}s",
+                    self.connect_timeout.as_secs()
+                )
+            })?
+            .map_err(|err| err.to_string())?
+            .map_err(|err| format!("failed to resolve the webhook url: {err}"))?;
+        if addresses.is_empty() {
+            return Err(String::from("the webhook url resolves to no address"));
+        }
+        if let Some(address) = addresses
+            .iter()
+            .find(|address| self.is_denied_address(address.ip()))
+        {
+            return Err(format!(
+                "Webhooks cannot be sent to {}, which the url resolves to",
+                address.ip()
+            ));
+        }
+
+        let mut builder = Client::builder()
+            .redirect(redirect::Policy::none())
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout);
+        if host.parse::<IpAddr>().is_err() {
+            builder = builder.resolve_to_addrs(&host, &addresses);
+        }
+        builder.build().map_err(|err| err.to_string())
+    }
+
+    fn validate_authorization(&self, auth: &Authorization) -> Result<(), String> {
+        match (&auth.value, &auth.secret) {
+            (Some(_), Some(_)) | (None, None) => Err(String::from(
+                "The authorization of a webhook should have either a value or a secret",
+            )),
+            (Some(env_var), None) if !self.auth_env_vars.contains(env_var) => Err(
+                format!("{env_var} is not an env var webhooks can be authorized with"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates `webhook` before it is saved, encrypting its secret.
+    pub fn prepare_webhook(
+        &self,
+        webhook: &mut Webhook,
+        cipher: &TenantCipher,
+    ) -> Result<(), String> {
+        self.validate_url(&webhook.url)?;
+        let Some(auth) = webhook.authorization.as_mut() else {
+            return Ok(());
+        };
+        self.validate_authorization(auth)?;
+        if let Some(secret) = auth.secret.as_mut() {
+            let value = Value::String(secret.clone());
+            /*
+              Only plain secrets are taken, so that the encrypted values of
+              the secret configs of the tenant cannot be sent out as one.
+            */
+            if is_mask(&value) || is_encrypted(&value) {
+                return Err(String::from(
+                    "The secret of a webhook authorization should be sent in plain \
+                     text every time the webhook is saved",
+                ));
+            }
+            if let Value::String(encrypted) = cipher.encrypt(&value)? {
+                *secret = encrypted;
+            }
+        }
+        Ok(())
+    }
+
+    /// The value of the authorization header of a webhook about to be sent.
+    pub fn authorization_value(
+        &self,
+        auth: &Authorization,
+        cipher: &TenantCipher,
+    ) -> Result<String, String> {
+        self.validate_authorization(auth)?;
+        match (&auth.value, &auth.secret) {
+            (Some(env_var), _) => std::env::var(env_var)
+                .map_err(|err| format!("failed to read {env_var}: {err}")),
+            (_, Some(secret)) => match cipher.decrypt(&Value::String(secret.clone()))? {
+                Value::String(secret) => Ok(secret),
+                _ => Err(String::from("the webhook secret is not a string")),
+            },
+            (None, None) => Err(String::from("the webhook has no authorization")),
+        }
+    }
+}
+
+/// Hides the secret of the authorization of `webhook`.
+pub fn mask_webhook_secret(webhook: &mut Webhook) {
+    if let Some(secret) = webhook
+        .authorization
+        .as_mut()
+        .and_then(|auth| auth.secret.as_mut())
+    {
+        *secret = String::from(SECRET_MASK);
+    }
+}
+
+/// Hides the custom headers of `webhook` along with its secret, for the
+/// users who cannot read the secrets of the tenant.
+pub fn redact_webhook(webhook: &mut Webhook) {
+    mask_webhook_secret(webhook);
+    webhook
+        .custom_headers
+        .values_mut()
+        .for_each(|value| *value = String::from(SECRET_MASK));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use superposition_types::webhook::HttpMethod;
+
+    use super::*;
+
+    fn policy(allowed_hosts: &str) -> WebhookPolicy {
+        WebhookPolicy::new(
+            "TOKEN_FOR_WEBHOOK",
+            allowed_hosts,
+            DEFAULT_DENIED_NETWORKS,
+            Duration::from_secs(5),
+            Duration::from_secs(10),
+        )
+        .unwrap()
+    }
+
+    fn webhook(url: &str, value: Option<&str>, secret: Option<&str>) -> Webhook {
+        Webhook {
+            url: url.to_string(),
+            method: HttpMethod::Post,
+            custom_headers: HashMap::new(),
+            service_headers: Vec::new(),
+            authorization: (value.is_some() || secret.is_some()).then(|| Authorization {
+                key: String::from("Authorization"),
+                value: value.map(String::from),
+                secret: secret.map(String::from),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_is_allowed_host() {
+        assert!(policy("").is_allowed_host("anything.example.com"));
+
+        let policy = policy("hooks.example.com, *.example.org");
+        assert!(policy.is_allowed_host("hooks.example.com"));
+        assert!(!policy.is_allowed_host("other.example.com"));
+        assert!(!policy.is_allowed_host("hooks.example.com.evil.com"));
+        assert!(policy.is_allowed_host("a.example.org"));
+        assert!(policy.is_allowed_host("a.b.example.org"));
+        assert!(!policy.is_allowed_host("example.org"));
+        assert!(!policy.is_allowed_host("evilexample.org"));
+    }
+
+    #[test]
+    fn test_is_denied_address() {
+        let policy = policy("");
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "169.254.169.254",
+            "192.168.0.1",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(
+                policy.is_denied_address(address.parse().unwrap()),
+                "{address} should be denied"
+            );
+        }
+        for address in [
+            "93.184.216.34",
+            "2606:2800:220:1::1",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(
+                !policy.is_denied_address(address.parse().unwrap()),
+                "{address} should be allowed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_url() {
+        let allow_list = policy("*.example.com, 93.184.216.34, ::ffff:7f00:1");
+        assert!(allow_list
+            .validate_url("https://hooks.example.com/events")
+            .is_ok());
+        assert!(allow_list.validate_url("http://HOOKS.Example.com").is_ok());
+        assert!(allow_list
+            .validate_url("http://93.184.216.34:8080/")
+            .is_ok());
+        assert!(allow_list.validate_url("ftp://hooks.example.com").is_err());
+        assert!(allow_list.validate_url("not a url").is_err());
+        assert!(allow_list.validate_url("https://example.net").is_err());
+        // an allowed host is still refused when it is a denied address
+        assert!(allow_list.validate_url("http://[::ffff:7f00:1]/").is_err());
+        assert!(allow_list
+            .validate_url("http://[::ffff:127.0.0.1]/")
+            .is_err());
+
+        let any_host = policy("");
+        assert!(any_host.validate_url("http://[::1]:8080/").is_err());
+        assert!(any_host.validate_url("http://127.0.0.1/").is_err());
+        assert!(any_host
+            .validate_url("http://[2606:2800:220:1::1]/")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_prepare_webhook() {
+        let policy = policy("");
+        let cipher = TenantCipher::new(Some("master key"), "tenant");
+        let url = "https://hooks.example.com";
+
+        let mut plain = webhook(url, None, None);
+        assert!(policy.prepare_webhook(&mut plain, &cipher).is_ok());
+        assert!(policy
+            .prepare_webhook(&mut webhook("http://10.0.0.1", None, None), &cipher)
+            .is_err());
+
+        let mut from_env = webhook(url, Some("TOKEN_FOR_WEBHOOK"), None);
+        assert!(policy.prepare_webhook(&mut from_env, &cipher).is_ok());
+        assert!(policy
+            .prepare_webhook(&mut webhook(url, Some("DATABASE_URL"), None), &cipher)
+            .is_err());
+        assert!(policy
+            .prepare_webhook(
+                &mut webhook(url, Some("TOKEN_FOR_WEBHOOK"), Some("secret")),
+                &cipher
+            )
+            .is_err());
+
+        let mut with_secret = webhook(url, None, Some("secret"));
+        policy.prepare_webhook(&mut with_secret, &cipher).unwrap();
+        let auth = with_secret.authorization.as_ref().unwrap();
+        let stored = Value::String(auth.secret.clone().unwrap());
+        assert!(is_encrypted(&stored));
+        assert_eq!(
+            policy.authorization_value(auth, &cipher),
+            Ok(String::from("secret"))
+        );
+
+        // a masked or encrypted secret is not taken as the secret
+        let encrypted = stored.as_str().unwrap();
+        for secret in [SECRET_MASK, encrypted] {
+            assert!(policy
+                .prepare_webhook(&mut webhook(url, None, Some(secret)), &cipher)
+                .is_err());
+        }
+        assert!(policy
+            .prepare_webhook(
+                &mut webhook(url, None, Some("secret")),
+                &TenantCipher::new(None, "tenant")
+            )
+            .is_err());
+    }
+}
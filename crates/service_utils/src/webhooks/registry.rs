@@ -0,0 +1,250 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    sql_query,
+    sql_types::{Bool, Json, Nullable, Text, Timestamp},
+    OptionalExtension, PgConnection, QueryableByName, RunQueryDsl,
+};
+use serde::Serialize;
+use serde_json::Value;
+use superposition_types::{
+    result,
+    webhook::{ConfigWebhook, WebhookEndpoint, WebhookEvent},
+    Condition,
+};
+
+use super::{encoding_error, insert_event};
+use crate::helpers::extract_dimensions;
+
+/*
+  The webhook registry of a schema, endpoints managed through the API on top
+  of the webhooks of the tenant config. Each is stored with its webhook,
+  events and key prefixes as JSON, like the events of the outbox.
+*/
+
+#[derive(QueryableByName)]
+struct EndpointRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    description: String,
+    #[diesel(sql_type = Bool)]
+    enabled: bool,
+    #[diesel(sql_type = Json)]
+    webhook: Value,
+    #[diesel(sql_type = Json)]
+    events: Value,
+    #[diesel(sql_type = Json)]
+    key_prefixes: Value,
+    #[diesel(sql_type = Nullable<Json>)]
+    context: Option<Value>,
+    #[diesel(sql_type = Nullable<Json>)]
+    payload_template: Option<Value>,
+    #[diesel(sql_type = Timestamp)]
+    created_at: NaiveDateTime,
+    #[diesel(sql_type = Text)]
+    created_by: String,
+    #[diesel(sql_type = Timestamp)]
+    last_modified_at: NaiveDateTime,
+    #[diesel(sql_type = Text)]
+    last_modified_by: String,
+}
+
+impl TryFrom<EndpointRow> for WebhookEndpoint {
+    type Error = result::AppError;
+
+    fn try_from(row: EndpointRow) -> result::Result<Self> {
+        let decoding_error = |err: serde_json::Error| {
+            log::error!("failed to decode the webhook endpoint {}: {err}", row.name);
+            result::AppError::UnexpectedError(anyhow::anyhow!(
+                "failed to read the webhook endpoint {}",
+                row.name
+            ))
+        };
+        let subscription = ConfigWebhook {
+            webhook: serde_json::from_value(row.webhook).map_err(decoding_error)?,
+            events: serde_json::from_value(row.events).map_err(decoding_error)?,
+            key_prefixes: serde_json::from_value(row.key_prefixes)
+                .map_err(decoding_error)?,
+        };
+        let context = row
+            .context
+            .map(serde_json::from_value::<Condition>)
+            .transpose()
+            .map_err(decoding_error)?;
+        Ok(Self {
+            name: row.name,
+            description: row.description,
+            enabled: row.enabled,
+            subscription,
+            context,
+            payload_template: row.payload_template,
+            created_at: row.created_at,
+            created_by: row.created_by,
+            last_modified_at: row.last_modified_at,
+            last_modified_by: row.last_modified_by,
+        })
+    }
+}
+
+fn into_endpoints(rows: Vec<EndpointRow>) -> result::Result<Vec<WebhookEndpoint>> {
+    rows.into_iter().map(WebhookEndpoint::try_from).collect()
+}
+
+pub fn list_webhook_endpoints(
+    conn: &mut PgConnection,
+) -> result::Result<Vec<WebhookEndpoint>> {
+    into_endpoints(
+        sql_query("SELECT * FROM webhooks ORDER BY name").load::<EndpointRow>(conn)?,
+    )
+}
+
+pub fn get_webhook_endpoint(
+    conn: &mut PgConnection,
+    name: &str,
+) -> result::Result<Option<WebhookEndpoint>> {
+    sql_query("SELECT * FROM webhooks WHERE name = $1")
+        .bind::<Text, _>(name)
+        .get_result::<EndpointRow>(conn)
+        .optional()?
+        .map(WebhookEndpoint::try_from)
+        .transpose()
+}
+
+/// Creates `endpoint`, or returns `None` when one of the same name exists.
+pub fn create_webhook_endpoint(
+    conn: &mut PgConnection,
+    endpoint: &WebhookEndpoint,
+) -> result::Result<Option<WebhookEndpoint>> {
+    let subscription = &endpoint.subscription;
+    let context = endpoint
+        .context
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(encoding_error)?;
+    sql_query(
+        "INSERT INTO webhooks (name, description, enabled, webhook, events, \
+         key_prefixes, context, payload_template, created_at, created_by, \
+         last_modified_at, last_modified_by) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) \
+         ON CONFLICT (name) DO NOTHING RETURNING *",
+    )
+    .bind::<Text, _>(endpoint.name.clone())
+    .bind::<Text, _>(endpoint.description.clone())
+    .bind::<Bool, _>(endpoint.enabled)
+    .bind::<Json, _>(serde_json::to_value(&subscription.webhook).map_err(encoding_error)?)
+    .bind::<Json, _>(serde_json::to_value(&subscription.events).map_err(encoding_error)?)
+    .bind::<Json, _>(
+        serde_json::to_value(&subscription.key_prefixes).map_err(encoding_error)?,
+    )
+    .bind::<Nullable<Json>, _>(context)
+    .bind::<Nullable<Json>, _>(endpoint.payload_template.clone())
+    .bind::<Timestamp, _>(endpoint.created_at)
+    .bind::<Text, _>(endpoint.created_by.clone())
+    .bind::<Timestamp, _>(endpoint.last_modified_at)
+    .bind::<Text, _>(endpoint.last_modified_by.clone())
+    .get_result::<EndpointRow>(conn)
+    .optional()?
+    .map(WebhookEndpoint::try_from)
+    .transpose()
+}
+
+/// Replaces the definition of the endpoint named like `endpoint`, keeping
+/// when and by whom it was created.
+pub fn update_webhook_endpoint(
+    conn: &mut PgConnection,
+    endpoint: &WebhookEndpoint,
+) -> result::Result<Option<WebhookEndpoint>> {
+    let subscription = &endpoint.subscription;
+    let context = endpoint
+        .context
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(encoding_error)?;
+    sql_query(
+        "UPDATE webhooks SET description = $2, enabled = $3, webhook = $4, \
+         events = $5, key_prefixes = $6, context = $7, payload_template = $8, \
+         last_modified_at = $9, last_modified_by = $10 WHERE name = $1 RETURNING *",
+    )
+    .bind::<Text, _>(endpoint.name.clone())
+    .bind::<Text, _>(endpoint.description.clone())
+    .bind::<Bool, _>(endpoint.enabled)
+    .bind::<Json, _>(serde_json::to_value(&subscription.webhook).map_err(encoding_error)?)
+    .bind::<Json, _>(serde_json::to_value(&subscription.events).map_err(encoding_error)?)
+    .bind::<Json, _>(
+        serde_json::to_value(&subscription.key_prefixes).map_err(encoding_error)?,
+    )
+    .bind::<Nullable<Json>, _>(context)
+    .bind::<Nullable<Json>, _>(endpoint.payload_template.clone())
+    .bind::<Timestamp, _>(endpoint.last_modified_at)
+    .bind::<Text, _>(endpoint.last_modified_by.clone())
+    .get_result::<EndpointRow>(conn)
+    .optional()?
+    .map(WebhookEndpoint::try_from)
+    .transpose()
+}
+
+pub fn delete_webhook_endpoint(
+    conn: &mut PgConnection,
+    name: &str,
+) -> result::Result<Option<WebhookEndpoint>> {
+    sql_query("DELETE FROM webhooks WHERE name = $1 RETURNING *")
+        .bind::<Text, _>(name)
+        .get_result::<EndpointRow>(conn)
+        .optional()?
+        .map(WebhookEndpoint::try_from)
+        .transpose()
+}
+
+pub fn enabled_webhook_endpoints(
+    conn: &mut PgConnection,
+) -> result::Result<Vec<WebhookEndpoint>> {
+    into_endpoints(
+        sql_query("SELECT * FROM webhooks WHERE enabled ORDER BY name")
+            .load::<EndpointRow>(conn)?,
+    )
+}
+
+/*
+  Queues `event` for every config webhook of the tenant and every endpoint
+  subscribed to it, in the transaction of `conn`. The condition of an
+  endpoint is matched against the dimensions `context` sets, events without
+  a context skip it.
+*/
+#[allow(clippy::too_many_arguments)]
+pub fn enqueue_endpoint_events<T>(
+    conn: &mut PgConnection,
+    webhooks: &[ConfigWebhook],
+    endpoints: &[WebhookEndpoint],
+    event: WebhookEvent,
+    keys: &[String],
+    context: Option<&Condition>,
+    payload: &T,
+    config_version: &Option<String>,
+) -> result::Result<()>
+where
+    T: Serialize,
+{
+    let subscribed = webhooks
+        .iter()
+        .filter(|webhook| webhook.is_subscribed(event, keys));
+    for webhook in subscribed {
+        insert_event(conn, &webhook.webhook, None, event, payload, config_version)?;
+    }
+    let dimensions = context.and_then(|context| extract_dimensions(context).ok());
+    let subscribed = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.is_subscribed(event, keys, dimensions.as_ref()));
+    for endpoint in subscribed {
+        insert_event(
+            conn,
+            &endpoint.subscription.webhook,
+            Some(endpoint),
+            event,
+            payload,
+            config_version,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS superposition.tenants (
+    name TEXT PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    archived_at TIMESTAMP,
+    archived_by TEXT
+);
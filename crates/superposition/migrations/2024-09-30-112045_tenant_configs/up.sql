@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS superposition.tenant_configs (
+    tenant TEXT PRIMARY KEY REFERENCES superposition.tenants(name),
+    config JSON NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
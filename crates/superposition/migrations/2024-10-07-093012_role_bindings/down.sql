@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX IF EXISTS superposition.role_bindings_unique_index;
+DROP TABLE IF EXISTS superposition.role_bindings;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS superposition.role_bindings (
+    id BIGINT PRIMARY KEY,
+    user_email TEXT NOT NULL,
+    tenant TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'editor', 'approver', 'admin')),
+    key_prefix TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS role_bindings_unique_index
+    ON superposition.role_bindings (user_email, tenant, role, COALESCE(key_prefix, ''));
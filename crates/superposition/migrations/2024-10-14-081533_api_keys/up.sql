@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+CREATE TABLE IF NOT EXISTS superposition.api_keys (
+    id BIGINT PRIMARY KEY,
+    tenant TEXT NOT NULL REFERENCES superposition.tenants(name),
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes JSON NOT NULL,
+    expires_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    UNIQUE (tenant, name)
+);
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM superposition.role_bindings WHERE role = 'secret_reader';
+
+ALTER TABLE superposition.role_bindings
+    DROP CONSTRAINT IF EXISTS role_bindings_role_check;
+
+ALTER TABLE superposition.role_bindings
+    ADD CONSTRAINT role_bindings_role_check
+    CHECK (role IN ('viewer', 'editor', 'approver', 'admin'));
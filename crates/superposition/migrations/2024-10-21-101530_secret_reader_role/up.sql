@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+ALTER TABLE superposition.role_bindings
+    DROP CONSTRAINT IF EXISTS role_bindings_role_check;
+
+ALTER TABLE superposition.role_bindings
+    ADD CONSTRAINT role_bindings_role_check
+    CHECK (role IN ('viewer', 'editor', 'approver', 'admin', 'secret_reader'));
@@ -1,11 +1,9 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 
-#[cfg(feature = "high-performance-mode")]
-use std::time::Duration;
-
 use cac_toml::ContextAwareConfig;
 use context_aware_config::helpers::get_meta_schema;
 
@@ -19,12 +17,19 @@ use service_utils::{
     aws::kms,
     db::utils::{get_superposition_token, init_pool_manager},
     helpers::{get_from_env_or_default, get_from_env_unsafe},
+    secrets::{
+        AgeSecretProvider, EnvSecretProvider, FileSecretProvider, KmsSecretProvider,
+        PgpSecretProvider, SecretBackend, SecretProvider,
+    },
     service::types::{AppEnv, AppState, ExperimentationFlags},
+    webhooks::policy::{WebhookPolicy, DEFAULT_DENIED_NETWORKS},
 };
 use snowflake::SnowflakeIdGenerator;
-use superposition_types::TenantConfig;
 
-const TENANT_CONFIG_FILE: &str = "crates/superposition/Superposition.cac.toml";
+use crate::tenants::{
+    get_default_tenant_config, load_api_keys, load_role_bindings, load_tenant_configs,
+    load_tenants, parse_tenant_config, TENANT_CONFIG_FILE,
+};
 
 pub async fn get(
     service_prefix: String,
@@ -41,25 +46,79 @@ pub async fn get(
     let cac = ContextAwareConfig::parse(TENANT_CONFIG_FILE)
         .expect(&format!("File {TENANT_CONFIG_FILE} not found"));
 
-    let tenant_configs = tenants
-        .clone()
-        .into_iter()
-        .filter_map(|tenant| {
-            serde_json::to_value(cac.get_resolved_config(&HashMap::from_iter(vec![(
-                String::from("tenant"),
-                toml::Value::String(tenant.clone()),
-            )])))
-            .and_then(serde_json::from_value::<TenantConfig>)
-            .map(|config| (tenant, config))
-            .ok()
-        })
-        .collect::<HashMap<_, _>>();
-
     let snowflake_generator = Arc::new(Mutex::new(SnowflakeIdGenerator::new(1, 1)));
 
-    let kms_client = match app_env {
-        AppEnv::DEV | AppEnv::TEST => None,
-        _ => Some(kms::new_client().await),
+    let secret_backend =
+        get_from_env_unsafe("SECRET_PROVIDER").unwrap_or(match app_env {
+            AppEnv::DEV | AppEnv::TEST => SecretBackend::Env,
+            _ => SecretBackend::Kms,
+        });
+    let secret_provider: Box<dyn SecretProvider> = match secret_backend {
+        SecretBackend::Kms => Box::new(KmsSecretProvider::new(kms::new_client().await)),
+        SecretBackend::Env => Box::new(EnvSecretProvider),
+        SecretBackend::File => Box::new(FileSecretProvider::new(
+            get_from_env_or_default("SECRETS_DIR", String::from("/run/secrets")),
+        )),
+        SecretBackend::Age => Box::new(
+            AgeSecretProvider::new(
+                &get_from_env_unsafe::<String>("AGE_SECRETS_FILE")
+                    .expect("AGE_SECRETS_FILE is not set"),
+                &get_from_env_unsafe::<String>("AGE_IDENTITY_FILE")
+                    .expect("AGE_IDENTITY_FILE is not set"),
+            )
+            .unwrap_or_else(|err| panic!("Failed to load age secrets, error: {err:#}")),
+        ),
+        SecretBackend::Pgp => Box::new(
+            PgpSecretProvider::new(
+                &get_from_env_unsafe::<String>("PGP_SECRETS_FILE")
+                    .expect("PGP_SECRETS_FILE is not set"),
+                &get_from_env_unsafe::<String>("PGP_KEY_FILE")
+                    .expect("PGP_KEY_FILE is not set"),
+                get_from_env_or_default("PGP_KEY_PASSPHRASE", String::new()),
+            )
+            .unwrap_or_else(|err| panic!("Failed to load pgp secrets, error: {err:#}")),
+        ),
+    };
+
+    let db_pool = init_pool_manager(
+        tenants.clone(),
+        enable_tenant_and_scope,
+        secret_provider.as_ref(),
+        &app_env,
+        max_pool_size,
+    )
+    .await;
+    let tenants = if enable_tenant_and_scope {
+        load_tenants(&db_pool, tenants)
+            .unwrap_or_else(|err| panic!("Failed to load tenants, error: {err}"))
+    } else {
+        tenants.clone()
+    };
+    let tenant_configs = if enable_tenant_and_scope {
+        load_tenant_configs(&db_pool, &cac, &tenants)
+            .unwrap_or_else(|err| panic!("Failed to load tenant configs, error: {err}"))
+    } else {
+        tenants
+            .iter()
+            .filter_map(|tenant| {
+                get_default_tenant_config(&cac, tenant)
+                    .and_then(|config| parse_tenant_config(&config))
+                    .map(|config| (tenant.clone(), config))
+                    .ok()
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let role_bindings = if enable_tenant_and_scope {
+        load_role_bindings(&db_pool)
+            .unwrap_or_else(|err| panic!("Failed to load role bindings, error: {err}"))
+    } else {
+        HashMap::new()
+    };
+    let api_keys = if enable_tenant_and_scope {
+        load_api_keys(&db_pool)
+            .unwrap_or_else(|err| panic!("Failed to load api keys, error: {err}"))
+    } else {
+        HashMap::new()
     };
 
     cfg_if::cfg_if! {
@@ -97,15 +156,15 @@ pub async fn get(
 
     }
 
+    let http_connect_timeout = Duration::from_secs(get_from_env_or_default(
+        "HTTP_CLIENT_CONNECT_TIMEOUT_SECS",
+        5,
+    ));
+    let http_timeout =
+        Duration::from_secs(get_from_env_or_default("HTTP_CLIENT_TIMEOUT_SECS", 10));
+
     AppState {
-        db_pool: init_pool_manager(
-            tenants.clone(),
-            enable_tenant_and_scope,
-            &kms_client,
-            &app_env,
-            max_pool_size,
-        )
-        .await,
+        db_pool,
         cac_host,
         cac_version: get_from_env_unsafe("SUPERPOSITION_VERSION")
             .expect("SUPERPOSITION_VERSION is not set"),
@@ -127,7 +186,7 @@ pub async fn get(
         meta_schema: get_meta_schema(),
         app_env,
         enable_tenant_and_scope,
-        tenants: tenants.clone(),
+        tenants: RwLock::new(tenants),
         tenant_middleware_exclusion_list: get_from_env_unsafe::<String>(
             "TENANT_MIDDLEWARE_EXCLUSION_LIST",
         )
@@ -136,10 +195,68 @@ pub async fn get(
         .map(String::from)
         .collect::<HashSet<_>>(),
         service_prefix,
-        tenant_configs,
-        superposition_token: get_superposition_token(&kms_client, &app_env).await,
+        tenant_configs: RwLock::new(tenant_configs),
+        superposition_token: get_superposition_token(secret_provider.as_ref(), &app_env)
+            .await,
+        soft_delete_retention_days: get_from_env_or_default(
+            "SOFT_DELETE_RETENTION_DAYS",
+            30,
+        ),
+        config_version_checkpoint_interval: get_from_env_or_default(
+            "CONFIG_VERSION_CHECKPOINT_INTERVAL",
+            50,
+        ),
+        config_version_retention_count: get_from_env_unsafe(
+            "CONFIG_VERSION_RETENTION_COUNT",
+        )
+        .ok(),
+        config_version_retention_days: get_from_env_unsafe(
+            "CONFIG_VERSION_RETENTION_DAYS",
+        )
+        .ok(),
+        idempotency_key_ttl_secs: get_from_env_or_default(
+            "IDEMPOTENCY_KEY_TTL_SECS",
+            24 * 60 * 60,
+        ),
+        idempotency_lease_secs: get_from_env_or_default("IDEMPOTENCY_LEASE_SECS", 5 * 60),
+        max_request_body_bytes: get_from_env_or_default(
+            "MAX_REQUEST_BODY_BYTES",
+            2 * 1024 * 1024,
+        ),
+        enable_authorization: get_from_env_or_default("ENABLE_AUTHORIZATION", false),
+        superposition_admins: get_from_env_or_default(
+            "SUPERPOSITION_ADMINS",
+            String::new(),
+        )
+        .split(',')
+        .filter(|email| !email.is_empty())
+        .map(String::from)
+        .collect::<HashSet<_>>(),
+        role_bindings: RwLock::new(role_bindings),
+        api_keys: RwLock::new(api_keys),
+        config_secrets_key: secret_provider.get_secret("CONFIG_SECRETS_KEY").await.ok(),
+        webhook_signing_key: secret_provider.get_secret("WEBHOOK_SIGNING_KEY").await.ok(),
+        webhook_policy: WebhookPolicy::new(
+            &get_from_env_or_default("WEBHOOK_AUTH_ENV_VARS", String::new()),
+            &get_from_env_or_default("WEBHOOK_ALLOWED_HOSTS", String::new()),
+            &get_from_env_or_default(
+                "WEBHOOK_DENIED_NETWORKS",
+                String::from(DEFAULT_DENIED_NETWORKS),
+            ),
+            http_connect_timeout,
+            http_timeout,
+        )
+        .expect("WEBHOOK_DENIED_NETWORKS should be a list of networks"),
         #[cfg(feature = "high-performance-mode")]
         redis: redis_pool,
-        http_client: reqwest::Client::new(),
+        #[cfg(feature = "config-cache")]
+        config_cache: service_utils::config_cache::ConfigCache::default(),
+        deprecations: service_utils::deprecations::DeprecationCache::default(),
+        http_client: reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(http_connect_timeout)
+            .timeout(http_timeout)
+            .build()
+            .expect("the http client should build"),
     }
 }
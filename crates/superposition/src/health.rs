@@ -0,0 +1,251 @@
+use std::{future::Future, process::Command, time::Duration};
+
+use actix_web::{get, rt::time::timeout, web::Data, HttpResponse};
+use diesel::{sql_query, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+#[cfg(feature = "high-performance-mode")]
+use fred::interfaces::ClientLike;
+use futures_util::future::{join_all, FutureExt, LocalBoxFuture};
+use serde::Serialize;
+use serde_json::Map;
+use service_utils::{
+    db::utils::{get_tenant_namespaces, TENANT_REGISTRY_NAMESPACE},
+    helpers::run_blocking,
+    service::types::AppState,
+};
+use superposition_types::cac::schema::config_versions::dsl as config_versions;
+
+/// How long a single check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum ComponentStatus {
+    Ok,
+    Error {
+        error: String,
+    },
+    /// Broken without keeping the pod from serving requests.
+    Degraded {
+        error: String,
+    },
+}
+
+impl ComponentStatus {
+    fn is_ok(&self) -> bool {
+        !matches!(self, ComponentStatus::Error { .. })
+    }
+
+    fn degraded(self) -> Self {
+        match self {
+            ComponentStatus::Error { error } => ComponentStatus::Degraded { error },
+            status => status,
+        }
+    }
+}
+
+impl From<Result<(), String>> for ComponentStatus {
+    fn from(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => ComponentStatus::Ok,
+            Err(error) => ComponentStatus::Error { error },
+        }
+    }
+}
+
+/// Checks out a connection from the pool of `namespace` and runs a query on it.
+fn check_pool(state: &AppState, namespace: &str) -> Result<(), String> {
+    let mut conn = state
+        .db_pool
+        .get_conn(namespace.to_string())
+        .map_err(|err| err.to_string())?;
+    sql_query("SELECT 1")
+        .execute(&mut conn)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Reads the latest config version of the CAC schema `namespace`, a schema
+/// without config versions is fine.
+fn check_config_versions(state: &AppState, namespace: &str) -> Result<(), String> {
+    let mut conn = state
+        .db_pool
+        .get_conn(namespace.to_string())
+        .map_err(|err| err.to_string())?;
+    config_versions::config_versions
+        .select(config_versions::id)
+        .order(config_versions::created_at.desc())
+        .first::<i64>(&mut conn)
+        .optional()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Fails a check that does not finish within `CHECK_TIMEOUT`.
+async fn with_deadline<F>(check: F) -> ComponentStatus
+where
+    F: Future<Output = Result<(), String>>,
+{
+    timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())))
+        .into()
+}
+
+/// Runs a synchronous check on the blocking thread pool.
+fn run_check<F>(check: F) -> LocalBoxFuture<'static, ComponentStatus>
+where
+    F: FnOnce() -> Result<(), String> + Send + 'static,
+{
+    with_deadline(async move {
+        run_blocking(move || Ok(check()))
+            .await
+            .unwrap_or_else(|err| Err(err.to_string()))
+    })
+    .boxed_local()
+}
+
+/// Runs `node`, which runs the validation functions.
+fn check_node() -> Result<(), String> {
+    let output = Command::new("node")
+        .arg("--version")
+        .output()
+        .map_err(|err| format!("failed to run node: {err}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("node exited with {}", output.status))
+    }
+}
+
+#[cfg(feature = "high-performance-mode")]
+async fn check_redis(state: &AppState) -> Result<(), String> {
+    state
+        .redis
+        .next()
+        .ping::<()>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/*
+  Checks every dependency the pod needs to serve requests, all at once, and
+  responds with the status of each. It responds with a 503 when a shared one
+  is broken so that the pod is taken out of rotation. The schemas of a
+  single tenant are only reported as degraded, as taking every pod out of
+  rotation would not fix them and would break all the other tenants.
+*/
+#[get("/health/ready")]
+pub async fn ready(state: Data<AppState>) -> HttpResponse {
+    let mut components = Vec::new();
+    // the name of each check, whether it covers a single tenant, and the check
+    let mut checks: Vec<(String, bool, LocalBoxFuture<'static, ComponentStatus>)> =
+        Vec::new();
+
+    match state.db_pool.pool_states() {
+        Ok(pool_states) => {
+            for pool_state in pool_states {
+                let (name, namespace) = (pool_state.name(), pool_state.namespace);
+                let per_tenant = state.enable_tenant_and_scope
+                    && namespace != TENANT_REGISTRY_NAMESPACE;
+                // reads fall back to the primary while the replica is broken
+                let check = if pool_state.replica {
+                    let state = state.clone();
+                    run_check(move || {
+                        state
+                            .db_pool
+                            .check_replica(&namespace)
+                            .map_err(|err| err.to_string())
+                    })
+                    .map(ComponentStatus::degraded)
+                    .boxed_local()
+                } else {
+                    let state = state.clone();
+                    run_check(move || check_pool(&state, &namespace))
+                };
+                checks.push((format!("db:{name}"), per_tenant, check));
+            }
+        }
+        Err(err) => components.push((
+            String::from("db"),
+            ComponentStatus::Error {
+                error: err.to_string(),
+            },
+        )),
+    }
+
+    let cac_namespaces = if state.enable_tenant_and_scope {
+        match state.tenants.read() {
+            Ok(tenants) => tenants
+                .iter()
+                .map(|tenant| {
+                    let [cac_namespace, _] = get_tenant_namespaces(tenant);
+                    cac_namespace
+                })
+                .collect(),
+            Err(_) => {
+                components.push((
+                    String::from("tenants"),
+                    ComponentStatus::Error {
+                        error: String::from("tenants lock is poisoned"),
+                    },
+                ));
+                Vec::new()
+            }
+        }
+    } else {
+        vec![String::from("cac_v1")]
+    };
+    for namespace in cac_namespaces {
+        let name = format!("config_versions:{namespace}");
+        let per_tenant = state.enable_tenant_and_scope;
+        let state = state.clone();
+        let check = run_check(move || check_config_versions(&state, &namespace));
+        checks.push((name, per_tenant, check));
+    }
+
+    checks.push((String::from("node"), false, run_check(check_node)));
+
+    #[cfg(feature = "high-performance-mode")]
+    {
+        let state = state.clone();
+        let check = async move { with_deadline(check_redis(&state)).await };
+        checks.push((String::from("redis"), false, check.boxed_local()));
+    }
+
+    let (names, checks): (Vec<_>, Vec<_>) = checks
+        .into_iter()
+        .map(|(name, per_tenant, check)| ((name, per_tenant), check))
+        .unzip();
+    for ((name, per_tenant), status) in names.into_iter().zip(join_all(checks).await) {
+        let status = if per_tenant {
+            status.degraded()
+        } else {
+            status
+        };
+        components.push((name, status));
+    }
+    components.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let is_ready = components.iter().all(|(_, status)| status.is_ok());
+    let is_degraded = components
+        .iter()
+        .any(|(_, status)| matches!(status, ComponentStatus::Degraded { .. }));
+    let components = components
+        .into_iter()
+        .map(|(name, status)| (name, serde_json::json!(status)))
+        .collect::<Map<_, _>>();
+    let body = serde_json::json!({
+        "status": match (is_ready, is_degraded) {
+            (false, _) => "error",
+            (true, true) => "degraded",
+            (true, false) => "ok",
+        },
+        "components": components,
+    });
+    if is_ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        log::error!("readiness check failed: {body}");
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
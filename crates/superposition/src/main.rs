@@ -1,30 +1,48 @@
 #![deny(unused_crate_dependencies)]
 mod app_state;
+mod health;
+mod metrics;
+mod tenants;
+mod webhook_events;
+mod webhooks;
 
-use std::{collections::HashSet, io::Result, time::Duration};
+use std::{collections::HashSet, io::Result, sync::Arc, time::Duration};
 
 use actix_files::Files;
 use actix_web::{
-    dev::Service,
-    http::header,
     middleware::Compress,
-    web::{self, get, scope, Data, PathConfig},
-    App, HttpMessage, HttpResponse, HttpServer,
+    web::{self, get, scope, Data, JsonConfig, PathConfig},
+    App, HttpResponse, HttpServer,
 };
 use context_aware_config::api::*;
+use context_aware_config::helpers::run_purge_job;
 use experimentation_platform::api::*;
 use frontend::app::*;
 use frontend::types::Envs as UIEnvs;
 use leptos::*;
 use leptos_actix::{generate_route_list, LeptosRoutes};
 use service_utils::{
-    helpers::get_from_env_unsafe,
+    helpers::{get_from_env_or_default, get_from_env_unsafe},
     middlewares::{
-        app_scope::AppExecutionScopeMiddlewareFactory, tenant::TenantMiddlewareFactory,
+        app_scope::AppExecutionScopeMiddlewareFactory,
+        authentication::{
+            api_key::ApiKeyProvider,
+            internal::InternalTokenProvider,
+            jwt::{run_jwks_refresh_job, JwtConfig, JwtProvider},
+            AuthenticationMiddlewareFactory, AuthenticationProvider,
+        },
+        authorization::{AuthorizationMiddlewareFactory, ResourceScope},
+        idempotency::IdempotencyMiddlewareFactory,
+        rate_limit::{run_rate_limit_sweeper, RateLimitMiddlewareFactory, RateLimiter},
+        request_metrics::RequestMetricsMiddlewareFactory,
+        request_tracing::RequestTracingMiddlewareFactory,
+        tenant::TenantMiddlewareFactory,
     },
     service::types::{AppScope, AppState},
+    telemetry::{init_tracing, shutdown_tracing},
+    webhooks::{run_webhook_dispatcher, DispatchOptions},
 };
-use superposition_types::User;
+use tenants::run_registry_refresh_job;
 
 #[actix_web::get("favicon.ico")]
 async fn favicon(
@@ -41,6 +59,7 @@ async fn favicon(
 async fn main() -> Result<()> {
     dotenv::dotenv().ok();
     env_logger::init();
+    init_tracing().unwrap_or_else(|err| panic!("Failed to set up tracing, error: {err}"));
     let service_prefix: String =
         get_from_env_unsafe("SERVICE_PREFIX").expect("SERVICE_PREFIX is not set");
 
@@ -92,38 +111,86 @@ async fn main() -> Result<()> {
     let app_state =
         Data::new(app_state::get(service_prefix_str.to_owned(), &base, &tenants).await);
 
-    HttpServer::new(move || {
+    actix_web::rt::spawn(run_purge_job(
+        app_state.clone(),
+        Duration::from_secs(60 * 60),
+    ));
+    actix_web::rt::spawn(run_webhook_dispatcher(
+        app_state.clone(),
+        DispatchOptions {
+            period: Duration::from_secs(get_from_env_or_default(
+                "WEBHOOK_DISPATCH_INTERVAL_SECS",
+                5,
+            )),
+            concurrency: get_from_env_or_default("WEBHOOK_CONCURRENCY_PER_TENANT", 4),
+            max_attempts: get_from_env_or_default("WEBHOOK_MAX_ATTEMPTS", 8),
+            retention_days: get_from_env_or_default("WEBHOOK_EVENT_RETENTION_DAYS", 30),
+        },
+    ));
+    #[cfg(feature = "config-cache")]
+    actix_web::rt::spawn(service_utils::config_cache::run_config_cache_listener(
+        app_state.clone(),
+        Duration::from_secs(get_from_env_or_default(
+            "CONFIG_CACHE_LISTENER_RETRY_SECS",
+            5,
+        )),
+    ));
+    if app_state.enable_tenant_and_scope {
+        actix_web::rt::spawn(run_registry_refresh_job(
+            app_state.clone(),
+            Duration::from_secs(get_from_env_or_default(
+                "REGISTRY_REFRESH_INTERVAL_SECS",
+                60,
+            )),
+        ));
+    }
+
+    let mut auth_providers: Vec<Arc<dyn AuthenticationProvider>> =
+        vec![Arc::new(InternalTokenProvider), Arc::new(ApiKeyProvider)];
+    if let Some(jwt_config) = JwtConfig::from_env() {
+        let jwt_provider = Arc::new(
+            JwtProvider::new(jwt_config, &app_state.http_client)
+                .await
+                .unwrap_or_else(|err| panic!("Failed to load the JWKS, error: {err}")),
+        );
+        actix_web::rt::spawn(run_jwks_refresh_job(
+            jwt_provider.clone(),
+            app_state.http_client.clone(),
+            Duration::from_secs(get_from_env_or_default(
+                "JWKS_REFRESH_INTERVAL_SECS",
+                60 * 60,
+            )),
+        ));
+        auth_providers.push(jwt_provider);
+    }
+    let auth_providers = Arc::new(auth_providers);
+    let allow_anonymous_access = get_from_env_or_default("ALLOW_ANONYMOUS_ACCESS", false);
+    let rate_limiter = Arc::new(RateLimiter::default());
+    actix_web::rt::spawn(run_rate_limit_sweeper(rate_limiter.clone()));
+
+    let result = HttpServer::new(move || {
         let leptos_options = &conf.leptos_options;
         let site_root = &leptos_options.site_root;
         let leptos_envs = ui_envs.clone();
         App::new()
             .wrap(Compress::default())
             .app_data(app_state.clone())
-            .wrap_fn(|req, srv| {
-                let state = req.app_data::<Data<AppState>>().unwrap();
-                let user = req.headers().get(header::AUTHORIZATION).and_then(|auth| auth.to_str().ok()).and_then(|auth| {
-                    let mut token = auth.split(' ').into_iter();
-                    match (token.next(), token.next()) {
-                        (Some("Internal"), Some(token)) if token == state.superposition_token =>
-                            req.headers().get("x-user").and_then(|auth| auth.to_str().ok()).and_then(|user_str| {
-                                serde_json::from_str::<User>(user_str).ok()
-                            }),
-                        (_, _) => None
-                    }
-                }).unwrap_or_default();
-
-                req.extensions_mut().insert::<User>(user);
-                srv.call(req)
-            })
+            .wrap(AuthenticationMiddlewareFactory::new(
+                auth_providers.clone(),
+                allow_anonymous_access,
+            ))
             .wrap(TenantMiddlewareFactory)
             .app_data(PathConfig::default().error_handler(|err, _| {
                 actix_web::error::ErrorBadRequest(err)
             }))
+            .app_data(JsonConfig::default().limit(app_state.max_request_body_bytes))
             .wrap(
                 actix_web::middleware::DefaultHeaders::new()
                     .add(("X-SERVER-VERSION", app_state.cac_version.to_string()))
                     .add(("Cache-Control", "no-store".to_string()))
             )
+            .wrap(RequestMetricsMiddlewareFactory)
+            .wrap(RequestTracingMiddlewareFactory)
             .service(web::redirect("/", ui_redirect_path.to_string()))
             .service(web::redirect("/admin", ui_redirect_path.to_string()))
             .service(web::redirect("/admin/{tenant}/", "default-config"))
@@ -134,50 +201,111 @@ async fn main() -> Result<()> {
             )
             .service(
                 scope(&base)
+                    .wrap(RateLimitMiddlewareFactory::new(rate_limiter.clone()))
                     .route(
                         "/health",
                         get().to(|| async { HttpResponse::Ok().body("Health is good :D") }),
                     )
                     /***************************** V1 Routes *****************************/
+                    .service(scope("/tenants").service(tenants::endpoints()))
                     .service(
                         scope("/context")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Keys))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(context::endpoints()),
                     )
                     .service(
                         scope("/dimension")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(dimension::endpoints()),
                     )
                     .service(
                         scope("/default-config")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Keys))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(default_config::endpoints()),
                     )
                     .service(
                         scope("/config")
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(config::endpoints()),
                     )
                     .service(
                         scope("/audit")
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(audit_log::endpoints()),
                     )
                     .service(
                         scope("/function")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(functions::endpoints()),
                     )
+                    .service(
+                        scope("/change-set")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Keys))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(change_set::endpoints()),
+                    )
                     .service(
                         scope("/types")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
                             .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
                             .service(type_templates::endpoints()),
                     )
                     .service(
-                        experiments::endpoints(scope("/experiments")).wrap(
-                            AppExecutionScopeMiddlewareFactory::new(AppScope::EXPERIMENTATION),
-                        ),
+                        scope("/validation-rule")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(validation_rules::endpoints()),
+                    )
+                    .service(
+                        scope("/webhooks")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(webhooks::endpoints()),
+                    )
+                    .service(
+                        scope("/webhook-events")
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(AppScope::CAC))
+                            .service(webhook_events::endpoints()),
+                    )
+                    .service(
+                        scope("/experiments/webhooks")
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(
+                                AppScope::EXPERIMENTATION,
+                            ))
+                            .service(webhooks::endpoints()),
+                    )
+                    .service(
+                        scope("/experiments/webhook-events")
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Tenant))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(
+                                AppScope::EXPERIMENTATION,
+                            ))
+                            .service(webhook_events::endpoints()),
+                    )
+                    .service(
+                        experiments::endpoints(scope("/experiments"))
+                            .wrap(IdempotencyMiddlewareFactory)
+                            .wrap(AuthorizationMiddlewareFactory::new(ResourceScope::Keys))
+                            .wrap(AppExecutionScopeMiddlewareFactory::new(
+                                AppScope::EXPERIMENTATION,
+                            )),
                     )
                     /***************************** UI Routes ******************************/
                     .route("/fxn/{tail:.*}", leptos_actix::handle_server_fns())
@@ -191,6 +319,8 @@ async fn main() -> Result<()> {
                 "/health",
                 get().to(|| async { HttpResponse::Ok().body("Health is good :D") }),
             )
+            .service(health::ready)
+            .service(metrics::metrics)
             .app_data(Data::new(leptos_options.to_owned()))
     })
     .bind(("0.0.0.0", cac_port))?
@@ -199,5 +329,7 @@ async fn main() -> Result<()> {
         get_from_env_unsafe("ACTIX_KEEP_ALIVE").unwrap_or(120),
     ))
     .run()
-    .await
+    .await;
+    shutdown_tracing();
+    result
 }
@@ -0,0 +1,90 @@
+use actix_web::{get, web::Data, HttpResponse};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use service_utils::{
+    db::utils::get_tenant_namespaces,
+    helpers::run_blocking,
+    metrics::{METRICS, METRICS_CONTENT_TYPE},
+    service::types::AppState,
+};
+use superposition_macros::unexpected_error;
+use superposition_types::{
+    cac::schema::contexts::dsl as contexts, result as superposition,
+};
+
+fn refresh_pool_gauges(state: &AppState) -> superposition::Result<()> {
+    let pool_states = state
+        .db_pool
+        .pool_states()
+        .map_err(|err| unexpected_error!(err))?;
+    METRICS.db_pool_connections.reset();
+    for pool_state in pool_states {
+        let pool = pool_state.name();
+        let idle = pool_state.state.idle_connections;
+        let in_use = pool_state.state.connections.saturating_sub(idle);
+        METRICS
+            .db_pool_connections
+            .with_label_values(&[&pool, "idle"])
+            .set(i64::from(idle));
+        METRICS
+            .db_pool_connections
+            .with_label_values(&[&pool, "in_use"])
+            .set(i64::from(in_use));
+    }
+    Ok(())
+}
+
+/*
+  Contexts are counted in the schema of every tenant, a tenant which
+  cannot be counted is logged and left out of the scrape instead of
+  failing it.
+*/
+fn refresh_context_gauges(state: &AppState) -> superposition::Result<()> {
+    let tenants = if state.enable_tenant_and_scope {
+        state
+            .tenants
+            .read()
+            .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+            .iter()
+            .map(|tenant| {
+                let [cac_namespace, _] = get_tenant_namespaces(tenant);
+                (tenant.clone(), cac_namespace)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![(String::from("mjos"), String::from("cac_v1"))]
+    };
+
+    METRICS.contexts.reset();
+    for (tenant, namespace) in tenants {
+        let count = state
+            .db_pool
+            .get_conn(namespace)
+            .map_err(|err| err.to_string())
+            .and_then(|mut conn| {
+                contexts::contexts
+                    .filter(contexts::deleted_at.is_null())
+                    .count()
+                    .get_result::<i64>(&mut conn)
+                    .map_err(|err| err.to_string())
+            });
+        match count {
+            Ok(count) => METRICS.contexts.with_label_values(&[&tenant]).set(count),
+            Err(err) => log::error!("failed to count the contexts of {tenant}: {err}"),
+        }
+    }
+    Ok(())
+}
+
+#[get("/metrics")]
+pub async fn metrics(state: Data<AppState>) -> superposition::Result<HttpResponse> {
+    refresh_pool_gauges(&state)?;
+    run_blocking({
+        let state = state.clone();
+        move || refresh_context_gauges(&state)
+    })
+    .await?;
+    let body = METRICS.encode().map_err(|err| unexpected_error!(err))?;
+    Ok(HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(body))
+}
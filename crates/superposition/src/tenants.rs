@@ -0,0 +1,9 @@
+mod handlers;
+mod helpers;
+mod schema;
+mod types;
+pub use handlers::endpoints;
+pub use helpers::{
+    get_default_tenant_config, load_api_keys, load_role_bindings, load_tenant_configs,
+    load_tenants, parse_tenant_config, run_registry_refresh_job, TENANT_CONFIG_FILE,
+};
@@ -0,0 +1,527 @@
+use actix_web::{
+    delete, get, post, put,
+    web::{Data, Json, Path},
+    HttpResponse, Scope,
+};
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use serde_json::{Map, Value};
+use service_utils::{
+    db::utils::get_tenant_namespaces,
+    helpers::{generate_snowflake_id, run_blocking},
+    middlewares::{
+        authentication::api_key::hash_api_key,
+        authorization::{authorize, authorize_admin, authorize_grant},
+    },
+    service::types::AppState,
+};
+use superposition_macros::{bad_argument, db_error, not_found, unexpected_error};
+use superposition_types::{
+    authorization::{Action, ALL_TENANTS},
+    result as superposition, PaginatedResponse, User,
+};
+
+use super::{
+    helpers::{
+        deregister_tenant, generate_api_key, get_default_tenant_config,
+        get_registry_conn, mask_tenant_webhooks, parse_tenant_config,
+        parse_tenant_config_file, prepare_tenant_webhooks, provision_tenant,
+        refresh_api_keys, refresh_role_bindings, register_tenant, validate_tenant_config,
+    },
+    schema::{
+        api_keys::dsl as keys, role_bindings::dsl as bindings,
+        tenant_configs::dsl as configs, tenants::dsl,
+    },
+    types::{
+        ApiKeyCreateResp, ApiKeyInfo, ApiKeyReq, CreateReq, RoleBindingInfo,
+        RoleBindingReq, TenantConfigInfo, TenantInfo,
+    },
+};
+
+pub fn endpoints() -> Scope {
+    Scope::new("")
+        .service(list_tenants)
+        .service(create_tenant)
+        .service(archive_tenant)
+        .service(get_config)
+        .service(update_config)
+        .service(list_role_bindings)
+        .service(create_role_binding)
+        .service(delete_role_binding)
+        .service(list_api_keys)
+        .service(create_api_key)
+        .service(delete_api_key)
+}
+
+fn validate_tenant_mode(state: &Data<AppState>) -> superposition::Result<()> {
+    if state.enable_tenant_and_scope {
+        Ok(())
+    } else {
+        Err(bad_argument!(
+            "Tenants can only be managed when ENABLE_TENANT_AND_SCOPE is set"
+        ))
+    }
+}
+
+#[get("")]
+async fn list_tenants(
+    state: Data<AppState>,
+    user: User,
+) -> superposition::Result<Json<PaginatedResponse<TenantInfo>>> {
+    validate_tenant_mode(&state)?;
+    let db_pool = state.db_pool.clone();
+    let data = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(dsl::tenants
+            .order(dsl::created_at.asc())
+            .select(TenantInfo::as_select())
+            .load::<TenantInfo>(&mut conn)?)
+    })
+    .await?
+    .into_iter()
+    .filter(|tenant| authorize(&state, &user, Some(&tenant.name), Action::Read).is_ok())
+    .collect::<Vec<_>>();
+    Ok(Json(PaginatedResponse {
+        total_pages: 1,
+        total_items: data.len() as i64,
+        data,
+    }))
+}
+
+#[post("")]
+async fn create_tenant(
+    state: Data<AppState>,
+    request: Json<CreateReq>,
+    user: User,
+) -> superposition::Result<Json<TenantInfo>> {
+    validate_tenant_mode(&state)?;
+    authorize_admin(&state, &user, Some(ALL_TENANTS))?;
+    let tenant: String = request.into_inner().name.into();
+    let cac = parse_tenant_config_file()?;
+    let config = get_default_tenant_config(&cac, &tenant)?;
+    let tenant_config = parse_tenant_config(&config)?;
+
+    let tenant_info = run_blocking({
+        let (db_pool, tenant, user) =
+            (state.db_pool.clone(), tenant.clone(), user.clone());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            provision_tenant(&mut conn, &tenant, config, &user)
+        }
+    })
+    .await?;
+    register_tenant(&state, &tenant, tenant_config)?;
+    log::info!("tenant {tenant} created by {}", user.get_email());
+    Ok(Json(tenant_info))
+}
+
+#[put("/{tenant}/archive")]
+async fn archive_tenant(
+    state: Data<AppState>,
+    path: Path<String>,
+    user: User,
+) -> superposition::Result<Json<TenantInfo>> {
+    validate_tenant_mode(&state)?;
+    authorize_admin(&state, &user, Some(ALL_TENANTS))?;
+    let tenant = path.into_inner();
+    let tenant_info = run_blocking({
+        let (db_pool, tenant, email) =
+            (state.db_pool.clone(), tenant.clone(), user.get_email());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            diesel::update(
+                dsl::tenants
+                    .find(&tenant)
+                    .filter(dsl::archived_at.is_null()),
+            )
+            .set((
+                dsl::archived_at.eq(Utc::now().naive_utc()),
+                dsl::archived_by.eq(email),
+            ))
+            .returning(TenantInfo::as_returning())
+            .get_result(&mut conn)
+            .map_err(|err| match err {
+                diesel::result::Error::NotFound => {
+                    not_found!("Tenant {} not found or already archived", tenant)
+                }
+                err => db_error!(err),
+            })
+        }
+    })
+    .await?;
+    /*
+      The schemas of an archived tenant are kept as they are, it only stops
+      being served.
+    */
+    deregister_tenant(&state, &tenant)?;
+    log::info!("tenant {tenant} archived by {}", user.get_email());
+    Ok(Json(tenant_info))
+}
+
+fn validate_active_tenant(
+    state: &Data<AppState>,
+    tenant: &str,
+) -> superposition::Result<()> {
+    let is_active = state
+        .tenants
+        .read()
+        .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+        .contains(tenant);
+    if is_active {
+        Ok(())
+    } else {
+        Err(not_found!("Tenant {} not found", tenant))
+    }
+}
+
+#[get("/{tenant}/config")]
+async fn get_config(
+    state: Data<AppState>,
+    path: Path<String>,
+    user: User,
+) -> superposition::Result<Json<TenantConfigInfo>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_active_tenant(&state, &tenant)?;
+    authorize(&state, &user, Some(&tenant), Action::Read)?;
+    let db_pool = state.db_pool.clone();
+    let mut config_info = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(configs::tenant_configs
+            .find(&tenant)
+            .select(TenantConfigInfo::as_select())
+            .get_result(&mut conn)?)
+    })
+    .await?;
+    mask_tenant_webhooks(&mut config_info.config);
+    Ok(Json(config_info))
+}
+
+/*
+  Only the settings present in the request are changed, the rest of the
+  stored config is kept as it is.
+*/
+#[put("/{tenant}/config")]
+async fn update_config(
+    state: Data<AppState>,
+    path: Path<String>,
+    request: Json<Map<String, Value>>,
+    user: User,
+) -> superposition::Result<Json<TenantConfigInfo>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_active_tenant(&state, &tenant)?;
+    authorize_admin(&state, &user, Some(&tenant))?;
+    let cac = parse_tenant_config_file()?;
+    let mut request = request.into_inner();
+    prepare_tenant_webhooks(&state, &tenant, &mut request)?;
+
+    let (mut config_info, tenant_config) = run_blocking({
+        let (db_pool, tenant, email) =
+            (state.db_pool.clone(), tenant.clone(), user.get_email());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            let stored_config: Value = configs::tenant_configs
+                .find(&tenant)
+                .select(configs::config)
+                .get_result(&mut conn)?;
+            let mut config = match stored_config {
+                Value::Object(config) => config,
+                _ => Map::new(),
+            };
+            config.extend(request);
+
+            let [cac_schema, _] = get_tenant_namespaces(&tenant);
+            let mut cac_conn = db_pool.get_conn(cac_schema).map_err(|err| {
+                log::error!("failed to get a connection for tenant {tenant}: {err}");
+                unexpected_error!("Something went wrong")
+            })?;
+            let tenant_config = validate_tenant_config(&cac, &mut cac_conn, &config)?;
+
+            let config_info = diesel::update(configs::tenant_configs.find(&tenant))
+                .set((
+                    configs::config.eq(Value::Object(config)),
+                    configs::last_modified_at.eq(Utc::now().naive_utc()),
+                    configs::last_modified_by.eq(email),
+                ))
+                .returning(TenantConfigInfo::as_returning())
+                .get_result(&mut conn)?;
+            Ok((config_info, tenant_config))
+        }
+    })
+    .await?;
+    state
+        .tenant_configs
+        .write()
+        .map_err(|_| unexpected_error!("tenant configs lock is poisoned"))?
+        .insert(tenant.clone(), tenant_config);
+    log::info!("config of tenant {tenant} updated by {}", user.get_email());
+    mask_tenant_webhooks(&mut config_info.config);
+    Ok(Json(config_info))
+}
+
+/*
+  Bindings on the `*` tenant apply to every tenant and can only be managed
+  by admins of every tenant.
+*/
+fn validate_binding_tenant(
+    state: &Data<AppState>,
+    user: &User,
+    tenant: &str,
+) -> superposition::Result<()> {
+    if tenant != ALL_TENANTS {
+        validate_active_tenant(state, tenant)?;
+    }
+    authorize_admin(state, user, Some(tenant))?;
+    Ok(())
+}
+
+#[get("/{tenant}/roles")]
+async fn list_role_bindings(
+    state: Data<AppState>,
+    path: Path<String>,
+    user: User,
+) -> superposition::Result<Json<PaginatedResponse<RoleBindingInfo>>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_binding_tenant(&state, &user, &tenant)?;
+    let db_pool = state.db_pool.clone();
+    let data = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(bindings::role_bindings
+            .filter(bindings::tenant.eq(&tenant))
+            .order(bindings::created_at.asc())
+            .select(RoleBindingInfo::as_select())
+            .load(&mut conn)?)
+    })
+    .await?;
+    Ok(Json(PaginatedResponse {
+        total_pages: 1,
+        total_items: data.len() as i64,
+        data,
+    }))
+}
+
+#[post("/{tenant}/roles")]
+async fn create_role_binding(
+    state: Data<AppState>,
+    path: Path<String>,
+    request: Json<RoleBindingReq>,
+    user: User,
+) -> superposition::Result<Json<RoleBindingInfo>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_binding_tenant(&state, &user, &tenant)?;
+    let RoleBindingReq {
+        user_email,
+        role,
+        key_prefix,
+    } = request.into_inner();
+    let user_email = user_email.trim().to_string();
+    if user_email.is_empty() {
+        return Err(bad_argument!("user_email should not be empty"));
+    }
+    if key_prefix.as_ref().is_some_and(|prefix| prefix.is_empty()) {
+        return Err(bad_argument!(
+            "key_prefix should not be empty, leave it out to grant every key"
+        ));
+    }
+    authorize_grant(&state, &user, &tenant, role, key_prefix.as_deref())?;
+
+    let binding = RoleBindingInfo {
+        id: generate_snowflake_id(&state)?,
+        user_email,
+        tenant,
+        role: role.to_string(),
+        key_prefix,
+        created_at: Utc::now().naive_utc(),
+        created_by: user.get_email(),
+    };
+    let db_pool = state.db_pool.clone();
+    let binding = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(diesel::insert_into(bindings::role_bindings)
+            .values(binding)
+            .returning(RoleBindingInfo::as_returning())
+            .get_result(&mut conn)?)
+    })
+    .await?;
+    refresh_role_bindings(&state).await?;
+    log::info!(
+        "{} granted {} on {} to {} by {}",
+        binding.role,
+        binding.key_prefix.as_deref().unwrap_or("every key"),
+        binding.tenant,
+        binding.user_email,
+        user.get_email()
+    );
+    Ok(Json(binding))
+}
+
+#[delete("/{tenant}/roles/{id}")]
+async fn delete_role_binding(
+    state: Data<AppState>,
+    path: Path<(String, i64)>,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    validate_tenant_mode(&state)?;
+    let (tenant, id) = path.into_inner();
+    validate_binding_tenant(&state, &user, &tenant)?;
+    let deleted = run_blocking({
+        let (db_pool, tenant) = (state.db_pool.clone(), tenant.clone());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            Ok(diesel::delete(
+                bindings::role_bindings
+                    .find(id)
+                    .filter(bindings::tenant.eq(&tenant)),
+            )
+            .execute(&mut conn)?)
+        }
+    })
+    .await?;
+    if deleted == 0 {
+        return Err(not_found!("Role binding {} not found on {}", id, tenant));
+    }
+    refresh_role_bindings(&state).await?;
+    log::info!(
+        "role binding {id} on {tenant} deleted by {}",
+        user.get_email()
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/{tenant}/api-keys")]
+async fn list_api_keys(
+    state: Data<AppState>,
+    path: Path<String>,
+    user: User,
+) -> superposition::Result<Json<PaginatedResponse<ApiKeyInfo>>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_active_tenant(&state, &tenant)?;
+    authorize_admin(&state, &user, Some(&tenant))?;
+    let db_pool = state.db_pool.clone();
+    let data = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(keys::api_keys
+            .filter(keys::tenant.eq(&tenant))
+            .order(keys::created_at.asc())
+            .select(ApiKeyInfo::as_select())
+            .load(&mut conn)?)
+    })
+    .await?;
+    Ok(Json(PaginatedResponse {
+        total_pages: 1,
+        total_items: data.len() as i64,
+        data,
+    }))
+}
+
+#[post("/{tenant}/api-keys")]
+async fn create_api_key(
+    state: Data<AppState>,
+    path: Path<String>,
+    request: Json<ApiKeyReq>,
+    user: User,
+) -> superposition::Result<Json<ApiKeyCreateResp>> {
+    validate_tenant_mode(&state)?;
+    let tenant = path.into_inner();
+    validate_active_tenant(&state, &tenant)?;
+    authorize_admin(&state, &user, Some(&tenant))?;
+    let ApiKeyReq {
+        name,
+        scopes,
+        expires_at,
+    } = request.into_inner();
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(bad_argument!("name should not be empty"));
+    }
+    if scopes.is_empty() {
+        return Err(bad_argument!("an API key needs at least one scope"));
+    }
+    if scopes.iter().any(|scope| {
+        scope
+            .key_prefix
+            .as_ref()
+            .is_some_and(|prefix| prefix.is_empty())
+    }) {
+        return Err(bad_argument!(
+            "key_prefix should not be empty, leave it out to grant every key"
+        ));
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(bad_argument!("expires_at should be in the future"));
+    }
+    for scope in scopes.iter() {
+        authorize_grant(
+            &state,
+            &user,
+            &tenant,
+            scope.role,
+            scope.key_prefix.as_deref(),
+        )?;
+    }
+
+    let key = generate_api_key();
+    let info = ApiKeyInfo {
+        id: generate_snowflake_id(&state)?,
+        tenant,
+        name,
+        key_hash: hash_api_key(&key),
+        scopes: serde_json::to_value(scopes).map_err(|err| {
+            log::error!("failed to serialize api key scopes: {err}");
+            unexpected_error!("Something went wrong")
+        })?,
+        expires_at: expires_at.map(|expires_at| expires_at.naive_utc()),
+        created_at: Utc::now().naive_utc(),
+        created_by: user.get_email(),
+    };
+    let db_pool = state.db_pool.clone();
+    let info = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(diesel::insert_into(keys::api_keys)
+            .values(info)
+            .returning(ApiKeyInfo::as_returning())
+            .get_result(&mut conn)?)
+    })
+    .await?;
+    refresh_api_keys(&state).await?;
+    log::info!(
+        "api key {} created on {} by {}",
+        info.name,
+        info.tenant,
+        user.get_email()
+    );
+    Ok(Json(ApiKeyCreateResp { info, key }))
+}
+
+#[delete("/{tenant}/api-keys/{id}")]
+async fn delete_api_key(
+    state: Data<AppState>,
+    path: Path<(String, i64)>,
+    user: User,
+) -> superposition::Result<HttpResponse> {
+    validate_tenant_mode(&state)?;
+    let (tenant, id) = path.into_inner();
+    validate_active_tenant(&state, &tenant)?;
+    authorize_admin(&state, &user, Some(&tenant))?;
+    let deleted = run_blocking({
+        let (db_pool, tenant) = (state.db_pool.clone(), tenant.clone());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            Ok(
+                diesel::delete(keys::api_keys.find(id).filter(keys::tenant.eq(&tenant)))
+                    .execute(&mut conn)?,
+            )
+        }
+    })
+    .await?;
+    if deleted == 0 {
+        return Err(not_found!("API key {} not found on {}", id, tenant));
+    }
+    refresh_api_keys(&state).await?;
+    log::info!("api key {id} on {tenant} deleted by {}", user.get_email());
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,639 @@
+use std::collections::{HashMap, HashSet};
+
+use actix_web::web::Data;
+use cac_toml::ContextAwareConfig;
+use chrono::Utc;
+use diesel::{
+    connection::SimpleConnection, migration::MigrationSource, pg::Pg, sql_query,
+    sql_types::Text, Connection, ExpressionMethods, OptionalExtension, PgConnection,
+    QueryDsl, RunQueryDsl, SelectableHelper,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use jsonschema::{Draft, JSONSchema, ValidationError};
+use serde_json::{Map, Value};
+use service_utils::{
+    db::{
+        migrations::run_schema_migrations,
+        pgschema_manager::{PgSchemaConnection, PgSchemaManager},
+        utils::{get_tenant_namespaces, TENANT_REGISTRY_NAMESPACE},
+    },
+    encryption::{TenantCipher, SECRET_MASK},
+    helpers::{run_blocking, validation_err_to_str},
+    service::types::AppState,
+};
+use superposition_macros::{bad_argument, unexpected_error, validation_error};
+use superposition_types::{
+    authorization::{ApiKey, ApiKeyScope, RoleBinding},
+    cac::schema::dimensions::dsl as dimensions,
+    result as superposition,
+    webhook::Webhook,
+    TenantConfig, User,
+};
+
+use super::{
+    schema::{
+        api_keys::dsl as keys, role_bindings::dsl as bindings,
+        tenant_configs::dsl as configs, tenants::dsl,
+    },
+    types::{ApiKeyInfo, RoleBindingInfo, TenantConfigInfo, TenantInfo},
+};
+
+const REGISTRY_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/*
+  Only the defaults of tenant configs come from this file, new tenants
+  start with the config resolved for them here and it is edited through the
+  api after that.
+*/
+pub const TENANT_CONFIG_FILE: &str = "crates/superposition/Superposition.cac.toml";
+
+pub fn get_registry_conn(
+    db_pool: &PgSchemaManager,
+) -> superposition::Result<PgSchemaConnection> {
+    db_pool
+        .get_conn(TENANT_REGISTRY_NAMESPACE.to_string())
+        .map_err(|err| {
+            log::error!("failed to get a connection to the tenant registry: {err}");
+            unexpected_error!("Something went wrong")
+        })
+}
+
+/*
+  Tenants from the TENANTS env are added to the registry on boot, so that
+  the registry alone decides which tenants are served. A tenant archived
+  through the API stays archived even if it is still listed in the env.
+*/
+pub fn load_tenants(
+    db_pool: &PgSchemaManager,
+    env_tenants: &HashSet<String>,
+) -> superposition::Result<HashSet<String>> {
+    let mut conn = get_registry_conn(db_pool)?;
+    conn.batch_execute(&format!(
+        "CREATE SCHEMA IF NOT EXISTS {TENANT_REGISTRY_NAMESPACE}"
+    ))?;
+    let registry_conn: &mut PgConnection = &mut conn;
+    registry_conn
+        .run_pending_migrations(REGISTRY_MIGRATIONS)
+        .map_err(|err| {
+            log::error!("failed to migrate the tenant registry: {err}");
+            unexpected_error!("Something went wrong")
+        })?;
+
+    if !env_tenants.is_empty() {
+        let created_by = User::default().get_email();
+        let registered = env_tenants
+            .iter()
+            .map(|tenant| (dsl::name.eq(tenant), dsl::created_by.eq(&created_by)))
+            .collect::<Vec<_>>();
+        diesel::insert_into(dsl::tenants)
+            .values(registered)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+    }
+    let active_tenants = dsl::tenants
+        .filter(dsl::archived_at.is_null())
+        .select(dsl::name)
+        .load::<String>(&mut conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    for tenant in active_tenants.difference(env_tenants) {
+        for namespace in get_tenant_namespaces(tenant) {
+            db_pool
+                .add_pool(namespace)
+                .map_err(|err| unexpected_error!(err))?;
+        }
+    }
+    for tenant in env_tenants.difference(&active_tenants) {
+        log::info!("skipping archived tenant {tenant}");
+        for namespace in get_tenant_namespaces(tenant) {
+            db_pool
+                .remove_pool(&namespace)
+                .map_err(|err| unexpected_error!(err))?;
+        }
+    }
+    Ok(active_tenants)
+}
+
+/// Registers `tenant` with `config` and creates its cac and experimentation
+/// schemas with the embedded migrations of both crates, in a single
+/// transaction.
+pub fn provision_tenant(
+    conn: &mut PgSchemaConnection,
+    tenant: &str,
+    config: Value,
+    user: &User,
+) -> superposition::Result<TenantInfo> {
+    conn.transaction::<_, superposition::AppError, _>(|transaction_conn| {
+        let existing = dsl::tenants
+            .find(tenant)
+            .select(TenantInfo::as_select())
+            .first(transaction_conn)
+            .optional()?;
+        match existing {
+            Some(TenantInfo {
+                archived_at: Some(_),
+                ..
+            }) => return Err(bad_argument!("Tenant {} has been archived", tenant)),
+            Some(_) => return Err(bad_argument!("Tenant {} already exists", tenant)),
+            None => (),
+        }
+
+        let tenant_info = diesel::insert_into(dsl::tenants)
+            .values(TenantInfo {
+                name: tenant.to_string(),
+                created_at: Utc::now().naive_utc(),
+                created_by: user.get_email(),
+                archived_at: None,
+                archived_by: None,
+            })
+            .returning(TenantInfo::as_returning())
+            .get_result(transaction_conn)?;
+        diesel::insert_into(configs::tenant_configs)
+            .values(TenantConfigInfo {
+                tenant: tenant.to_string(),
+                config,
+                last_modified_at: Utc::now().naive_utc(),
+                last_modified_by: user.get_email(),
+            })
+            .execute(transaction_conn)?;
+
+        let [cac_schema, experimentation_schema] = get_tenant_namespaces(tenant);
+        let schema_migrations: [(&String, &dyn MigrationSource<Pg>); 2] = [
+            (&cac_schema, &context_aware_config::MIGRATIONS),
+            (
+                &experimentation_schema,
+                &experimentation_platform::MIGRATIONS,
+            ),
+        ];
+        for (schema, migrations) in schema_migrations {
+            let ran = run_schema_migrations(transaction_conn, schema, migrations)?;
+            log::info!("ran {} migrations on {schema}", ran.len());
+        }
+
+        /*
+          Experiments put their variants under the variantIds dimension, it
+          is seeded the same way scripts/create-tenant.sh does.
+        */
+        sql_query(format!(
+            "INSERT INTO {cac_schema}.dimensions \
+             (dimension, priority, created_at, created_by, schema, function_name) \
+             VALUES ('variantIds', 1, CURRENT_TIMESTAMP, $1, \
+             '{{\"type\": \"string\",\"pattern\": \".*\"}}'::json, null) \
+             ON CONFLICT DO NOTHING"
+        ))
+        .bind::<Text, _>(user.get_email())
+        .execute(transaction_conn)?;
+
+        Ok(tenant_info)
+    })
+}
+
+pub fn register_tenant(
+    state: &Data<AppState>,
+    tenant: &str,
+    tenant_config: TenantConfig,
+) -> superposition::Result<()> {
+    for namespace in get_tenant_namespaces(tenant) {
+        state
+            .db_pool
+            .add_pool(namespace)
+            .map_err(|err| unexpected_error!(err))?;
+    }
+    state
+        .tenant_configs
+        .write()
+        .map_err(|_| unexpected_error!("tenant configs lock is poisoned"))?
+        .insert(tenant.to_string(), tenant_config);
+    state
+        .tenants
+        .write()
+        .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+        .insert(tenant.to_string());
+    Ok(())
+}
+
+pub fn deregister_tenant(
+    state: &Data<AppState>,
+    tenant: &str,
+) -> superposition::Result<()> {
+    state
+        .tenants
+        .write()
+        .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+        .remove(tenant);
+    state
+        .tenant_configs
+        .write()
+        .map_err(|_| unexpected_error!("tenant configs lock is poisoned"))?
+        .remove(tenant);
+    for namespace in get_tenant_namespaces(tenant) {
+        state
+            .db_pool
+            .remove_pool(&namespace)
+            .map_err(|err| unexpected_error!(err))?;
+    }
+    Ok(())
+}
+
+pub fn parse_tenant_config_file() -> superposition::Result<ContextAwareConfig> {
+    ContextAwareConfig::parse(TENANT_CONFIG_FILE).map_err(|err| {
+        log::error!("failed to parse {TENANT_CONFIG_FILE}: {err}");
+        unexpected_error!("Something went wrong")
+    })
+}
+
+pub fn get_default_tenant_config(
+    cac: &ContextAwareConfig,
+    tenant: &str,
+) -> superposition::Result<Value> {
+    serde_json::to_value(cac.get_resolved_config(&HashMap::from_iter(vec![(
+        String::from("tenant"),
+        toml::Value::String(tenant.to_string()),
+    )])))
+    .map_err(|err| {
+        log::error!("failed to resolve the tenant config for {tenant}: {err}");
+        unexpected_error!("Something went wrong")
+    })
+}
+
+pub fn parse_tenant_config(config: &Value) -> superposition::Result<TenantConfig> {
+    serde_json::from_value::<TenantConfig>(config.clone())
+        .map_err(|err| validation_error!("Invalid tenant config: {}", err))
+}
+
+/// Validates every setting in `config` against its schema in the tenant
+/// config file and checks that the mandatory dimensions exist for the tenant.
+pub fn validate_tenant_config(
+    cac: &ContextAwareConfig,
+    cac_conn: &mut PgConnection,
+    config: &Map<String, Value>,
+) -> superposition::Result<TenantConfig> {
+    for (key, value) in config {
+        let schema = cac
+            .get_default_config_schema(key)
+            .ok_or_else(|| bad_argument!("{} is not a tenant setting", key))
+            .and_then(|schema| {
+                serde_json::to_value(schema).map_err(|err| unexpected_error!(err))
+            })?;
+        let jschema = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .compile(&schema)
+            .map_err(|err| {
+                log::error!(
+                    "failed to compile the schema of tenant setting {key}: {err}"
+                );
+                unexpected_error!("Something went wrong")
+            })?;
+        if let Err(e) = jschema.validate(value) {
+            let verrors = e.collect::<Vec<ValidationError>>();
+            return Err(validation_error!(
+                "Schema validation failed for {}: {}",
+                key,
+                validation_err_to_str(verrors)
+                    .first()
+                    .unwrap_or(&String::new())
+            ));
+        }
+    }
+    let tenant_config = parse_tenant_config(&Value::Object(config.clone()))?;
+
+    let existing_dimensions = dimensions::dimensions
+        .filter(dimensions::dimension.eq_any(&tenant_config.mandatory_dimensions))
+        .filter(dimensions::deleted_at.is_null())
+        .select(dimensions::dimension)
+        .load::<String>(cac_conn)?;
+    let missing_dimensions = tenant_config
+        .mandatory_dimensions
+        .iter()
+        .filter(|dimension| !existing_dimensions.contains(dimension))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !missing_dimensions.is_empty() {
+        return Err(bad_argument!(
+            "Mandatory dimensions {} do not exist",
+            missing_dimensions.join(", ")
+        ));
+    }
+    Ok(tenant_config)
+}
+
+/// The webhooks of the tenant config settings in `config`.
+fn tenant_webhooks(config: &mut Map<String, Value>) -> Vec<&mut Value> {
+    config
+        .iter_mut()
+        .flat_map(|(key, value)| match key.as_str() {
+            "config_webhooks" => value
+                .as_array_mut()
+                .map(|webhooks| webhooks.iter_mut().collect())
+                .unwrap_or_default(),
+            "experiments_webhook_config" => {
+                value.get_mut("configuration").into_iter().collect()
+            }
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/*
+  Checks the webhooks of the settings in `config` against the webhook policy
+  of the server and encrypts the secrets they are authorized with, before
+  the settings are stored. Webhooks which do not parse are left to the
+  validation of the settings.
+*/
+pub fn prepare_tenant_webhooks(
+    state: &AppState,
+    tenant: &str,
+    config: &mut Map<String, Value>,
+) -> superposition::Result<()> {
+    let cipher = TenantCipher::new(state.config_secrets_key.as_deref(), tenant);
+    for value in tenant_webhooks(config) {
+        let Ok(mut webhook) = serde_json::from_value::<Webhook>(value.clone()) else {
+            continue;
+        };
+        state
+            .webhook_policy
+            .prepare_webhook(&mut webhook, &cipher)
+            .map_err(|err| bad_argument!(err))?;
+        if let (Some(auth), Some(object)) = (webhook.authorization, value.as_object_mut())
+        {
+            object.insert(
+                String::from("authorization"),
+                serde_json::to_value(auth).map_err(|err| unexpected_error!(err))?,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Hides the secrets the webhooks of the tenant config are authorized with.
+pub fn mask_tenant_webhooks(config: &mut Value) {
+    let Some(config) = config.as_object_mut() else {
+        return;
+    };
+    for webhook in tenant_webhooks(config) {
+        if let Some(secret) = webhook.pointer_mut("/authorization/secret") {
+            *secret = Value::String(String::from(SECRET_MASK));
+        }
+    }
+}
+
+/*
+  Tenants without a stored config get the one resolved from the tenant
+  config file, which is then stored so that it can be edited. A stored
+  config which no longer parses is logged and the resolved one is served
+  in its place.
+*/
+pub fn load_tenant_configs(
+    db_pool: &PgSchemaManager,
+    cac: &ContextAwareConfig,
+    tenants: &HashSet<String>,
+) -> superposition::Result<HashMap<String, TenantConfig>> {
+    let mut conn = get_registry_conn(db_pool)?;
+    let stored_configs = configs::tenant_configs
+        .filter(configs::tenant.eq_any(tenants))
+        .select(TenantConfigInfo::as_select())
+        .load(&mut conn)?
+        .into_iter()
+        .map(|info| (info.tenant, info.config))
+        .collect::<HashMap<_, _>>();
+
+    let mut tenant_configs = HashMap::new();
+    for tenant in tenants {
+        let default_config = get_default_tenant_config(cac, tenant)?;
+        let config = match stored_configs.get(tenant) {
+            Some(config) => config.clone(),
+            None => {
+                diesel::insert_into(configs::tenant_configs)
+                    .values(TenantConfigInfo {
+                        tenant: tenant.clone(),
+                        config: default_config.clone(),
+                        last_modified_at: Utc::now().naive_utc(),
+                        last_modified_by: User::default().get_email(),
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(&mut conn)?;
+                default_config.clone()
+            }
+        };
+        let tenant_config = parse_tenant_config(&config).or_else(|err| {
+            log::error!("invalid config stored for tenant {tenant}: {err}");
+            parse_tenant_config(&default_config)
+        })?;
+        tenant_configs.insert(tenant.clone(), tenant_config);
+    }
+    Ok(tenant_configs)
+}
+
+/*
+  Tenants created or archived through another instance are picked up here,
+  with the pools of their schemas. A new tenant is served once its config
+  is loaded, falling back to the config resolved from the tenant config
+  file like on boot.
+*/
+async fn refresh_tenants(state: &Data<AppState>) -> superposition::Result<()> {
+    let served = state
+        .tenants
+        .read()
+        .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+        .clone();
+    let (active, added, stored_configs) = run_blocking({
+        let (db_pool, served) = (state.db_pool.clone(), served.clone());
+        move || {
+            let mut conn = get_registry_conn(&db_pool)?;
+            let active = dsl::tenants
+                .filter(dsl::archived_at.is_null())
+                .select(dsl::name)
+                .load::<String>(&mut conn)?
+                .into_iter()
+                .collect::<HashSet<_>>();
+            let added = active.difference(&served).cloned().collect::<Vec<_>>();
+            let stored_configs = configs::tenant_configs
+                .filter(configs::tenant.eq_any(&added))
+                .select((configs::tenant, configs::config))
+                .load::<(String, Value)>(&mut conn)?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+            Ok((active, added, stored_configs))
+        }
+    })
+    .await?;
+
+    /*
+      Only the tenants served before the registry was read are compared, so
+      that one created on this instance in the meantime is not dropped.
+    */
+    for tenant in served.difference(&active) {
+        log::info!("tenant {tenant} was archived, it is no longer served");
+        deregister_tenant(state, tenant)?;
+    }
+
+    if added.is_empty() {
+        return Ok(());
+    }
+    let cac = parse_tenant_config_file()?;
+    for tenant in added {
+        let tenant_config = match stored_configs.get(&tenant).map(parse_tenant_config) {
+            Some(Ok(tenant_config)) => tenant_config,
+            stored => {
+                if let Some(Err(err)) = stored {
+                    log::error!("invalid config stored for tenant {tenant}: {err}");
+                }
+                parse_tenant_config(&get_default_tenant_config(&cac, &tenant)?)?
+            }
+        };
+        log::info!("tenant {tenant} was created, it is now served");
+        let state = state.clone();
+        run_blocking(move || register_tenant(&state, &tenant, tenant_config)).await?;
+    }
+    Ok(())
+}
+
+async fn refresh_tenant_configs(state: &Data<AppState>) -> superposition::Result<()> {
+    let tenants = state
+        .tenants
+        .read()
+        .map_err(|_| unexpected_error!("tenants lock is poisoned"))?
+        .clone();
+    let db_pool = state.db_pool.clone();
+    let stored_configs = run_blocking(move || {
+        let mut conn = get_registry_conn(&db_pool)?;
+        Ok(configs::tenant_configs
+            .filter(configs::tenant.eq_any(&tenants))
+            .select(TenantConfigInfo::as_select())
+            .load(&mut conn)?)
+    })
+    .await?;
+
+    let mut tenant_configs = state
+        .tenant_configs
+        .write()
+        .map_err(|_| unexpected_error!("tenant configs lock is poisoned"))?;
+    for info in stored_configs {
+        match parse_tenant_config(&info.config) {
+            Ok(config) => {
+                tenant_configs.insert(info.tenant, config);
+            }
+            Err(err) => {
+                log::error!("invalid config stored for tenant {}: {err}", info.tenant)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Role bindings of every user, keyed by their email.
+pub fn load_role_bindings(
+    db_pool: &PgSchemaManager,
+) -> superposition::Result<HashMap<String, Vec<RoleBinding>>> {
+    let mut conn = get_registry_conn(db_pool)?;
+    let mut role_bindings = HashMap::<String, Vec<RoleBinding>>::new();
+    for info in bindings::role_bindings
+        .select(RoleBindingInfo::as_select())
+        .load(&mut conn)?
+    {
+        match info.role.parse() {
+            Ok(role) => {
+                role_bindings
+                    .entry(info.user_email)
+                    .or_default()
+                    .push(RoleBinding {
+                        tenant: info.tenant,
+                        role,
+                        key_prefix: info.key_prefix,
+                    })
+            }
+            Err(err) => log::error!("invalid role binding {}: {err}", info.id),
+        }
+    }
+    Ok(role_bindings)
+}
+
+pub async fn refresh_role_bindings(state: &Data<AppState>) -> superposition::Result<()> {
+    let db_pool = state.db_pool.clone();
+    let role_bindings = run_blocking(move || load_role_bindings(&db_pool)).await?;
+    *state
+        .role_bindings
+        .write()
+        .map_err(|_| unexpected_error!("role bindings lock is poisoned"))? =
+        role_bindings;
+    Ok(())
+}
+
+/// API keys of every tenant, keyed by the hash of the key.
+pub fn load_api_keys(
+    db_pool: &PgSchemaManager,
+) -> superposition::Result<HashMap<String, ApiKey>> {
+    let mut conn = get_registry_conn(db_pool)?;
+    let mut api_keys = HashMap::new();
+    for info in keys::api_keys
+        .select(ApiKeyInfo::as_select())
+        .load(&mut conn)?
+    {
+        match serde_json::from_value::<Vec<ApiKeyScope>>(info.scopes) {
+            Ok(scopes) => {
+                api_keys.insert(
+                    info.key_hash,
+                    ApiKey {
+                        id: info.id,
+                        tenant: info.tenant,
+                        name: info.name,
+                        scopes,
+                        expires_at: info.expires_at.map(|at| at.and_utc()),
+                    },
+                );
+            }
+            Err(err) => log::error!("invalid scopes on api key {}: {err}", info.id),
+        }
+    }
+    Ok(api_keys)
+}
+
+pub async fn refresh_api_keys(state: &Data<AppState>) -> superposition::Result<()> {
+    let db_pool = state.db_pool.clone();
+    let api_keys = run_blocking(move || load_api_keys(&db_pool)).await?;
+    *state
+        .api_keys
+        .write()
+        .map_err(|_| unexpected_error!("api keys lock is poisoned"))? = api_keys;
+    Ok(())
+}
+
+/*
+  Keys are 64 random hex characters, prefixed so that they are easy to
+  spot in logs and configs.
+*/
+pub fn generate_api_key() -> String {
+    format!(
+        "sp_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/*
+  Edits are applied to the instance serving them right away, this job
+  picks them up on every other instance, the tenants created or archived
+  through it included.
+*/
+pub async fn run_registry_refresh_job(
+    state: Data<AppState>,
+    period: std::time::Duration,
+) {
+    let mut interval = actix_web::rt::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(err) = refresh_tenants(&state).await {
+            log::error!("failed to refresh tenants: {err}");
+        }
+        if let Err(err) = refresh_tenant_configs(&state).await {
+            log::error!("failed to refresh tenant configs: {err}");
+        }
+        if let Err(err) = refresh_role_bindings(&state).await {
+            log::error!("failed to refresh role bindings: {err}");
+        }
+        if let Err(err) = refresh_api_keys(&state).await {
+            log::error!("failed to refresh api keys: {err}");
+        }
+    }
+}
@@ -0,0 +1,43 @@
+diesel::table! {
+    superposition.tenants (name) {
+        name -> Text,
+        created_at -> Timestamp,
+        created_by -> Text,
+        archived_at -> Nullable<Timestamp>,
+        archived_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    superposition.tenant_configs (tenant) {
+        tenant -> Text,
+        config -> Json,
+        last_modified_at -> Timestamp,
+        last_modified_by -> Text,
+    }
+}
+
+diesel::table! {
+    superposition.role_bindings (id) {
+        id -> Int8,
+        user_email -> Text,
+        tenant -> Text,
+        role -> Text,
+        key_prefix -> Nullable<Text>,
+        created_at -> Timestamp,
+        created_by -> Text,
+    }
+}
+
+diesel::table! {
+    superposition.api_keys (id) {
+        id -> Int8,
+        tenant -> Text,
+        name -> Text,
+        key_hash -> Text,
+        scopes -> Json,
+        expires_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        created_by -> Text,
+    }
+}
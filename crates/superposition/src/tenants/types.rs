@@ -0,0 +1,111 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use superposition_types::{
+    authorization::{ApiKeyScope, Role},
+    RegexEnum,
+};
+
+use super::schema::{api_keys, role_bindings, tenant_configs, tenants};
+
+#[derive(Queryable, Selectable, Insertable, Serialize)]
+#[diesel(table_name = tenants)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TenantInfo {
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+    pub archived_at: Option<NaiveDateTime>,
+    pub archived_by: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize)]
+#[diesel(table_name = tenant_configs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TenantConfigInfo {
+    pub tenant: String,
+    pub config: Value,
+    pub last_modified_at: NaiveDateTime,
+    pub last_modified_by: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize)]
+#[diesel(table_name = role_bindings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RoleBindingInfo {
+    pub id: i64,
+    pub user_email: String,
+    pub tenant: String,
+    pub role: String,
+    pub key_prefix: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+}
+
+#[derive(Deserialize)]
+pub struct RoleBindingReq {
+    pub user_email: String,
+    pub role: Role,
+    pub key_prefix: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize)]
+#[diesel(table_name = api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKeyInfo {
+    pub id: i64,
+    pub tenant: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Value,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+}
+
+#[derive(Deserialize)]
+pub struct ApiKeyReq {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// The key itself is only returned once, when it is created.
+#[derive(Serialize)]
+pub struct ApiKeyCreateResp {
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+    pub key: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateReq {
+    pub name: TenantName,
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+pub struct TenantName(String);
+impl TenantName {
+    pub fn validate_data(name: String) -> Result<Self, String> {
+        let name = name.trim();
+        RegexEnum::TenantName
+            .match_regex(name)
+            .map(|_| Self(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for TenantName {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::validate_data(value)
+    }
+}
+
+impl From<TenantName> for String {
+    fn from(value: TenantName) -> Self {
+        value.0
+    }
+}
@@ -0,0 +1,142 @@
+use actix_web::{
+    get, post,
+    web::{Data, Json, Path, Query},
+    Scope,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use service_utils::{
+    middlewares::authorization::authorize,
+    service::types::{AppState, DbConnection, ReadDbConnection, Tenant},
+    webhooks::{
+        get_webhook_event, list_webhook_events,
+        policy::{mask_webhook_secret, redact_webhook},
+        redeliver_webhook_event, webhook_signing_secret, DeliveryStatus, OutboxEvent,
+    },
+};
+use superposition_macros::{bad_argument, not_found};
+use superposition_types::{
+    authorization::Action, custom_query::PaginationParams, result as superposition,
+    webhook::Webhook, PaginatedResponse, User,
+};
+
+use crate::webhooks::can_read_webhook_headers;
+
+pub fn endpoints() -> Scope {
+    Scope::new("")
+        .service(list_events)
+        .service(get_signing_secret)
+        .service(get_event)
+        .service(redeliver_event)
+}
+
+/*
+  The webhook of an event is only shown as a whole to the users who can read
+  the secrets of the tenant, and its authorization secret to no one.
+*/
+fn redact_event(mut event: OutboxEvent, show_headers: bool) -> OutboxEvent {
+    event.webhook = match serde_json::from_value::<Webhook>(event.webhook) {
+        Ok(mut webhook) => {
+            match show_headers {
+                true => mask_webhook_secret(&mut webhook),
+                false => redact_webhook(&mut webhook),
+            }
+            serde_json::to_value(webhook).unwrap_or_default()
+        }
+        Err(_) => Value::Null,
+    };
+    event
+}
+
+#[derive(Deserialize)]
+struct StatusFilter {
+    status: Option<DeliveryStatus>,
+}
+
+#[get("")]
+async fn list_events(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: ReadDbConnection,
+    pagination: Query<PaginationParams>,
+    filter: Query<StatusFilter>,
+    user: Option<User>,
+) -> superposition::Result<Json<PaginatedResponse<OutboxEvent>>> {
+    let show_headers = can_read_webhook_headers(&state, &tenant, user);
+    let pagination = pagination.into_inner();
+    let status = filter.into_inner().status;
+    let limit = match pagination.all {
+        Some(true) => None,
+        _ => Some(pagination.count.unwrap_or(10)),
+    };
+    let offset = match (limit, pagination.page) {
+        (Some(limit), Some(page)) => (page - 1) * limit,
+        _ => 0,
+    };
+    let (total_items, data) = db_conn
+        .run(move |mut conn| list_webhook_events(&mut conn, status, limit, offset))
+        .await?;
+    let total_pages = match limit {
+        Some(limit) => (total_items as f64 / limit as f64).ceil() as i64,
+        None => 1,
+    };
+    Ok(Json(PaginatedResponse {
+        total_pages,
+        total_items,
+        data: data
+            .into_iter()
+            .map(|event| redact_event(event, show_headers))
+            .collect(),
+    }))
+}
+
+#[get("/signing-secret")]
+async fn get_signing_secret(
+    state: Data<AppState>,
+    tenant: Tenant,
+    user: User,
+) -> superposition::Result<Json<Value>> {
+    authorize(&state, &user, Some(tenant.as_str()), Action::ReadSecrets)?;
+    let secret =
+        webhook_signing_secret(state.webhook_signing_key.as_deref(), tenant.as_str())
+            .ok_or_else(|| {
+                bad_argument!(
+                    "Webhook payloads are not signed, WEBHOOK_SIGNING_KEY is not set"
+                )
+            })?;
+    Ok(Json(json!({ "secret": secret })))
+}
+
+#[get("/{id}")]
+async fn get_event(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: ReadDbConnection,
+    path: Path<i64>,
+    user: Option<User>,
+) -> superposition::Result<Json<OutboxEvent>> {
+    let show_headers = can_read_webhook_headers(&state, &tenant, user);
+    let id = path.into_inner();
+    db_conn
+        .run(move |mut conn| get_webhook_event(&mut conn, id))
+        .await?
+        .map(|event| Json(redact_event(event, show_headers)))
+        .ok_or_else(|| not_found!("Webhook event {} not found", id))
+}
+
+#[post("/{id}/redeliver")]
+async fn redeliver_event(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: DbConnection,
+    path: Path<i64>,
+    user: User,
+) -> superposition::Result<Json<OutboxEvent>> {
+    let show_headers = can_read_webhook_headers(&state, &tenant, Some(user));
+    let id = path.into_inner();
+    db_conn
+        .run(move |mut conn| redeliver_webhook_event(&mut conn, id))
+        .await?
+        .map(|event| Json(redact_event(event, show_headers)))
+        .ok_or_else(|| not_found!("Webhook event {} not found", id))
+}
@@ -0,0 +1,241 @@
+use actix_web::{
+    delete, get, post, put,
+    web::{Data, Json, Path},
+    Scope,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+use service_utils::{
+    encryption::TenantCipher,
+    middlewares::authorization::{authorize, authorize_admin},
+    service::types::{AppState, DbConnection, ReadDbConnection, Tenant},
+    webhooks::{
+        policy::{mask_webhook_secret, redact_webhook},
+        registry::{
+            create_webhook_endpoint, delete_webhook_endpoint, get_webhook_endpoint,
+            list_webhook_endpoints, update_webhook_endpoint,
+        },
+    },
+};
+use superposition_macros::{bad_argument, not_found};
+use superposition_types::{
+    authorization::{Action, KeyAccess},
+    result as superposition,
+    webhook::{ConfigWebhook, WebhookEndpoint},
+    Condition, Exp, PaginatedResponse, RegexEnum, User,
+};
+
+pub fn endpoints() -> Scope {
+    Scope::new("")
+        .service(list_endpoints)
+        .service(create_endpoint)
+        .service(get_endpoint)
+        .service(update_endpoint)
+        .service(delete_endpoint)
+}
+
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+struct WebhookName(String);
+impl WebhookName {
+    fn validate_data(name: String) -> Result<Self, String> {
+        let name = name.trim();
+        RegexEnum::WebhookName
+            .match_regex(name)
+            .map(|_| Self(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for WebhookName {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::validate_data(value)
+    }
+}
+
+#[derive(Deserialize)]
+struct UpdateReq {
+    description: String,
+    enabled: Option<bool>,
+    #[serde(flatten)]
+    subscription: ConfigWebhook,
+    context: Option<Exp<Condition>>,
+    payload_template: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct CreateReq {
+    name: WebhookName,
+    #[serde(flatten)]
+    definition: UpdateReq,
+}
+
+impl UpdateReq {
+    fn into_endpoint(
+        mut self,
+        name: String,
+        state: &AppState,
+        tenant: &Tenant,
+        user: &User,
+    ) -> superposition::Result<WebhookEndpoint> {
+        let cipher = TenantCipher::new(state.config_secrets_key.as_deref(), tenant);
+        state
+            .webhook_policy
+            .prepare_webhook(&mut self.subscription.webhook, &cipher)
+            .map_err(|err| bad_argument!(err))?;
+        let now = Utc::now().naive_utc();
+        Ok(WebhookEndpoint {
+            name,
+            description: self.description,
+            enabled: self.enabled.unwrap_or(true),
+            subscription: self.subscription,
+            context: self.context.map(Exp::into_inner),
+            payload_template: self.payload_template,
+            created_at: now,
+            created_by: user.get_email(),
+            last_modified_at: now,
+            last_modified_by: user.get_email(),
+        })
+    }
+}
+
+fn mask_endpoint(mut endpoint: WebhookEndpoint) -> WebhookEndpoint {
+    mask_webhook_secret(&mut endpoint.subscription.webhook);
+    endpoint
+}
+
+/*
+  Custom headers often carry credentials, so they are only shown to the
+  users who can read all the secrets of the tenant.
+*/
+pub(crate) fn can_read_webhook_headers(
+    state: &AppState,
+    tenant: &Tenant,
+    user: Option<User>,
+) -> bool {
+    user.is_some_and(|user| {
+        matches!(
+            authorize(state, &user, Some(tenant.as_str()), Action::ReadSecrets),
+            Ok(KeyAccess::All)
+        )
+    })
+}
+
+fn redact_endpoint(mut endpoint: WebhookEndpoint) -> WebhookEndpoint {
+    redact_webhook(&mut endpoint.subscription.webhook);
+    endpoint
+}
+
+#[get("")]
+async fn list_endpoints(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: ReadDbConnection,
+    user: Option<User>,
+) -> superposition::Result<Json<PaginatedResponse<WebhookEndpoint>>> {
+    let redact = match can_read_webhook_headers(&state, &tenant, user) {
+        true => mask_endpoint,
+        false => redact_endpoint,
+    };
+    let data = db_conn
+        .run(|mut conn| list_webhook_endpoints(&mut conn))
+        .await?;
+    Ok(Json(PaginatedResponse {
+        total_pages: 1,
+        total_items: data.len() as i64,
+        data: data.into_iter().map(redact).collect(),
+    }))
+}
+
+/*
+  The authorization of an endpoint is sent along with its events, so like
+  the webhooks of the tenant config, endpoints are managed by the admins of
+  the tenant.
+*/
+
+#[post("")]
+async fn create_endpoint(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: DbConnection,
+    request: Json<CreateReq>,
+    user: User,
+) -> superposition::Result<Json<WebhookEndpoint>> {
+    authorize_admin(&state, &user, Some(tenant.as_str()))?;
+    let CreateReq { name, definition } = request.into_inner();
+    let endpoint = definition.into_endpoint(name.0, &state, &tenant, &user)?;
+    let name = endpoint.name.clone();
+    db_conn
+        .run(move |mut conn| create_webhook_endpoint(&mut conn, &endpoint))
+        .await?
+        .map(mask_endpoint)
+        .map(Json)
+        .ok_or_else(|| bad_argument!("Webhook {} already exists", name))
+}
+
+#[get("/{name}")]
+async fn get_endpoint(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: ReadDbConnection,
+    path: Path<String>,
+    user: Option<User>,
+) -> superposition::Result<Json<WebhookEndpoint>> {
+    let redact = match can_read_webhook_headers(&state, &tenant, user) {
+        true => mask_endpoint,
+        false => redact_endpoint,
+    };
+    let name = path.into_inner();
+    db_conn
+        .run({
+            let name = name.clone();
+            move |mut conn| get_webhook_endpoint(&mut conn, &name)
+        })
+        .await?
+        .map(redact)
+        .map(Json)
+        .ok_or_else(|| not_found!("Webhook {} not found", name))
+}
+
+#[put("/{name}")]
+async fn update_endpoint(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: DbConnection,
+    path: Path<String>,
+    request: Json<UpdateReq>,
+    user: User,
+) -> superposition::Result<Json<WebhookEndpoint>> {
+    authorize_admin(&state, &user, Some(tenant.as_str()))?;
+    let name = path.into_inner();
+    let definition = request.into_inner();
+    let endpoint = definition.into_endpoint(name.clone(), &state, &tenant, &user)?;
+    db_conn
+        .run(move |mut conn| update_webhook_endpoint(&mut conn, &endpoint))
+        .await?
+        .map(mask_endpoint)
+        .map(Json)
+        .ok_or_else(|| not_found!("Webhook {} not found", name))
+}
+
+#[delete("/{name}")]
+async fn delete_endpoint(
+    state: Data<AppState>,
+    tenant: Tenant,
+    db_conn: DbConnection,
+    path: Path<String>,
+    user: User,
+) -> superposition::Result<Json<WebhookEndpoint>> {
+    authorize_admin(&state, &user, Some(tenant.as_str()))?;
+    let name = path.into_inner();
+    db_conn
+        .run({
+            let name = name.clone();
+            move |mut conn| delete_webhook_endpoint(&mut conn, &name)
+        })
+        .await?
+        .map(mask_endpoint)
+        .map(Json)
+        .ok_or_else(|| not_found!("Webhook {} not found", name))
+}
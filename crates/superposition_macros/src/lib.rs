@@ -39,6 +39,16 @@ macro_rules! not_found {
     };
 }
 
+#[macro_export]
+macro_rules! forbidden {
+    ($msg: literal, $($args: tt)*) => {
+        superposition_types::result::AppError::Forbidden(format!($msg, $($args)*))
+    };
+    ($err: tt) => {
+        superposition_types::result::AppError::Forbidden($err.to_string())
+    };
+}
+
 #[macro_export]
 macro_rules! db_error {
     ($error: expr) => {
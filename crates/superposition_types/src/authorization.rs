@@ -0,0 +1,326 @@
+#[cfg(feature = "server")]
+use std::future::{ready, Ready};
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "server")]
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Tenant of the role bindings which apply to every tenant.
+pub const ALL_TENANTS: &str = "*";
+
+/*
+  Roles are ordered, every role can do what the roles before it can. The
+  secret reader stands apart, it only reads, the values of secret configs
+  included, which no other role can.
+*/
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Approver,
+    Admin,
+    #[serde(rename = "secret_reader")]
+    SecretReader,
+}
+
+impl Role {
+    pub fn can(&self, action: Action) -> bool {
+        match (self, action) {
+            (Self::SecretReader, Action::Read | Action::ReadSecrets) => true,
+            (Self::SecretReader, _) | (_, Action::ReadSecrets) => false,
+            _ => *self >= action.minimum_role(),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Viewer => write!(f, "viewer"),
+            Self::Editor => write!(f, "editor"),
+            Self::Approver => write!(f, "approver"),
+            Self::Admin => write!(f, "admin"),
+            Self::SecretReader => write!(f, "secret_reader"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "approver" => Ok(Self::Approver),
+            "admin" => Ok(Self::Admin),
+            "secret_reader" => Ok(Self::SecretReader),
+            _ => Err(format!("invalid role {role}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Read,
+    Write,
+    Approve,
+    Administer,
+    ReadSecrets,
+}
+
+impl Action {
+    pub const ALL: [Self; 5] = [
+        Self::Read,
+        Self::Write,
+        Self::Approve,
+        Self::Administer,
+        Self::ReadSecrets,
+    ];
+
+    pub fn minimum_role(&self) -> Role {
+        match self {
+            Self::Read => Role::Viewer,
+            Self::Write => Role::Editor,
+            Self::Approve => Role::Approver,
+            Self::Administer => Role::Admin,
+            Self::ReadSecrets => Role::SecretReader,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::Approve => write!(f, "approve"),
+            Self::Administer => write!(f, "administer"),
+            Self::ReadSecrets => write!(f, "read secrets"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleBinding {
+    pub tenant: String,
+    pub role: Role,
+    pub key_prefix: Option<String>,
+}
+
+/*
+  Scopes of an API key work like role bindings on the tenant of the key.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyScope {
+    pub role: Role,
+    pub key_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub id: i64,
+    pub tenant: String,
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn role_bindings(&self) -> Vec<RoleBinding> {
+        self.scopes
+            .iter()
+            .map(|scope| RoleBinding {
+                tenant: self.tenant.clone(),
+                role: scope.role,
+                key_prefix: scope.key_prefix.clone(),
+            })
+            .collect()
+    }
+}
+
+/*
+  Default config keys a request is allowed to change. Bindings without a
+  key prefix give access to every key, the rest only to keys starting with
+  one of their prefixes.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum KeyAccess {
+    #[default]
+    All,
+    Prefixes(Vec<String>),
+}
+
+impl KeyAccess {
+    pub fn from_bindings<'a, I>(bindings: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a RoleBinding>,
+    {
+        let mut prefixes = Vec::new();
+        for binding in bindings {
+            match &binding.key_prefix {
+                None => return Some(Self::All),
+                Some(prefix) => prefixes.push(prefix.clone()),
+            }
+        }
+        if prefixes.is_empty() {
+            None
+        } else {
+            Some(Self::Prefixes(prefixes))
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        matches!(self, Self::All)
+    }
+
+    pub fn allows(&self, key: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Prefixes(prefixes) => {
+                prefixes.iter().any(|prefix| key.starts_with(prefix))
+            }
+        }
+    }
+
+    /// Whether this access covers every key of a binding limited to
+    /// `key_prefix`, or of one without a prefix.
+    pub fn covers(&self, key_prefix: Option<&str>) -> bool {
+        match (self, key_prefix) {
+            (Self::All, _) => true,
+            (Self::Prefixes(_), None) => false,
+            (Self::Prefixes(_), Some(prefix)) => self.allows(prefix),
+        }
+    }
+
+    pub fn validate_keys<'a, I>(&self, keys: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let denied_keys = keys
+            .into_iter()
+            .filter(|key| !self.allows(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        if denied_keys.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "You are not allowed to change the keys {}",
+                denied_keys.join(", ")
+            ))
+        }
+    }
+
+    pub fn validate_unrestricted(&self) -> Result<(), String> {
+        if self.is_unrestricted() {
+            Ok(())
+        } else {
+            Err(String::from(
+                "This action needs access to every key of the tenant",
+            ))
+        }
+    }
+}
+
+/*
+  Requests which did not go through the authorization middleware, like the
+  ones served with authorization disabled, have access to every key.
+*/
+#[cfg(feature = "server")]
+impl FromRequest for KeyAccess {
+    type Error = actix_web::error::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(role: Role, key_prefix: Option<&str>) -> RoleBinding {
+        RoleBinding {
+            tenant: String::from("dev"),
+            role,
+            key_prefix: key_prefix.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_role_can() {
+        assert!(Role::Admin.can(Action::Approve));
+        assert!(Role::Approver.can(Action::Write));
+        assert!(Role::Editor.can(Action::Read));
+        assert!(!Role::Editor.can(Action::Approve));
+        assert!(!Role::Viewer.can(Action::Write));
+        assert!(Role::SecretReader.can(Action::Read));
+        assert!(Role::SecretReader.can(Action::ReadSecrets));
+        assert!(!Role::SecretReader.can(Action::Write));
+        assert!(!Role::Admin.can(Action::ReadSecrets));
+    }
+
+    #[test]
+    fn test_key_access_from_bindings() {
+        let scoped = [
+            binding(Role::Editor, Some("payments.")),
+            binding(Role::Editor, Some("wallet.")),
+        ];
+        let key_access = KeyAccess::from_bindings(&scoped).unwrap();
+        assert!(key_access.allows("payments.timeout"));
+        assert!(key_access.allows("wallet.limit"));
+        assert!(!key_access.allows("ride_pricing.surge"));
+        assert!(key_access
+            .validate_keys(&[String::from("ride_pricing.surge")])
+            .is_err());
+        assert!(key_access.validate_unrestricted().is_err());
+        assert!(key_access.covers(Some("payments.upi.")));
+        assert!(!key_access.covers(Some("pay")));
+        assert!(!key_access.covers(None));
+        assert!(KeyAccess::All.covers(None));
+
+        let unrestricted = [
+            binding(Role::Editor, Some("payments.")),
+            binding(Role::Editor, None),
+        ];
+        assert_eq!(
+            KeyAccess::from_bindings(&unrestricted),
+            Some(KeyAccess::All)
+        );
+        assert_eq!(KeyAccess::from_bindings(&Vec::<RoleBinding>::new()), None);
+    }
+
+    #[test]
+    fn test_api_key_role_bindings() {
+        let api_key = ApiKey {
+            id: 1,
+            tenant: String::from("dev"),
+            name: String::from("deploy"),
+            scopes: vec![ApiKeyScope {
+                role: Role::Editor,
+                key_prefix: Some(String::from("payments.")),
+            }],
+            expires_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+        };
+        assert!(api_key.is_expired(Utc::now()));
+        let bindings = api_key.role_bindings();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].tenant, "dev");
+        assert_eq!(bindings[0].role, Role::Editor);
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{offset::Utc, DateTime, NaiveDateTime};
+use chrono::{offset::Utc, DateTime, NaiveDate, NaiveDateTime};
 #[cfg(feature = "diesel_derives")]
 use diesel::{AsChangeset, Insertable, Queryable, Selectable};
 use serde::Serialize;
@@ -9,7 +9,7 @@ use crate::{Cac, Condition, Contextual, Overridden, Overrides};
 #[cfg(feature = "diesel_derives")]
 use super::schema::{
     config_versions, contexts, default_configs, dimensions, event_log, functions,
-    type_templates,
+    type_templates, validation_rules,
 };
 
 #[derive(Clone, Serialize, Debug)]
@@ -30,6 +30,9 @@ pub struct Context {
     pub override_: Overrides,
     pub last_modified_at: NaiveDateTime,
     pub last_modified_by: String,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<String>,
+    pub enabled: bool,
 }
 
 impl Contextual for Context {
@@ -61,6 +64,13 @@ pub struct Dimension {
     pub function_name: Option<String>,
     pub last_modified_at: NaiveDateTime,
     pub last_modified_by: String,
+    pub description: String,
+    pub owner: Option<String>,
+    pub labels: Vec<String>,
+    pub deprecated: bool,
+    pub sunset_date: Option<NaiveDate>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -80,6 +90,15 @@ pub struct DefaultConfig {
     pub function_name: Option<String>,
     pub last_modified_at: NaiveDateTime,
     pub last_modified_by: String,
+    pub description: String,
+    pub owner: Option<String>,
+    pub labels: Vec<String>,
+    pub deprecated: bool,
+    pub sunset_date: Option<NaiveDate>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<String>,
+    /// Values of secret configs are stored encrypted with a key of the tenant.
+    pub secret: bool,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -118,6 +137,7 @@ pub struct EventLog {
     pub original_data: Option<Value>,
     pub new_data: Option<Value>,
     pub query: String,
+    pub change_reason: Option<String>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -130,6 +150,13 @@ pub struct ConfigVersion {
     pub config_hash: String,
     pub tags: Option<Vec<String>>,
     pub created_at: NaiveDateTime,
+    pub change_reason: Option<String>,
+    /// Version `config` is a JSON patch against, the full config when unset.
+    #[serde(skip_serializing)]
+    pub parent_id: Option<i64>,
+    /// Full config the deltas of this version are replayed onto.
+    #[serde(skip_serializing)]
+    pub checkpoint_id: i64,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -148,3 +175,21 @@ pub struct TypeTemplates {
     pub last_modified_at: NaiveDateTime,
     pub last_modified_by: String,
 }
+
+#[derive(Serialize, Clone, Debug)]
+#[cfg_attr(
+    feature = "diesel_derives",
+    derive(Queryable, Selectable, Insertable, AsChangeset)
+)]
+#[cfg_attr(feature = "diesel_derives", diesel(check_for_backend(diesel::pg::Pg)))]
+#[cfg_attr(feature = "diesel_derives", diesel(table_name = validation_rules))]
+#[cfg_attr(feature = "diesel_derives", diesel(primary_key(rule_name)))]
+pub struct ValidationRule {
+    pub rule_name: String,
+    pub description: String,
+    pub rule: Value,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+    pub last_modified_at: NaiveDateTime,
+    pub last_modified_by: String,
+}
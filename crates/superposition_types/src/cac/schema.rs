@@ -7,6 +7,9 @@ diesel::table! {
         config_hash -> Text,
         tags -> Nullable<Array<Varchar>>,
         created_at -> Timestamp,
+        change_reason -> Nullable<Text>,
+        parent_id -> Nullable<Int8>,
+        checkpoint_id -> Int8,
     }
 }
 
@@ -23,6 +26,10 @@ diesel::table! {
         last_modified_at -> Timestamp,
         #[max_length = 200]
         last_modified_by -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 200]
+        deleted_by -> Nullable<Varchar>,
+        enabled -> Bool,
     }
 }
 
@@ -37,6 +44,16 @@ diesel::table! {
         last_modified_at -> Timestamp,
         #[max_length = 200]
         last_modified_by -> Varchar,
+        description -> Text,
+        #[max_length = 200]
+        owner -> Nullable<Varchar>,
+        labels -> Array<Text>,
+        deprecated -> Bool,
+        sunset_date -> Nullable<Date>,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 200]
+        deleted_by -> Nullable<Varchar>,
+        secret -> Bool,
     }
 }
 
@@ -51,6 +68,15 @@ diesel::table! {
         last_modified_at -> Timestamp,
         #[max_length = 200]
         last_modified_by -> Varchar,
+        description -> Text,
+        #[max_length = 200]
+        owner -> Nullable<Varchar>,
+        labels -> Array<Text>,
+        deprecated -> Bool,
+        sunset_date -> Nullable<Date>,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 200]
+        deleted_by -> Nullable<Varchar>,
     }
 }
 
@@ -64,6 +90,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -77,6 +104,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -90,6 +118,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -103,6 +132,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -116,6 +146,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -129,6 +160,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -142,6 +174,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -155,6 +188,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -168,6 +202,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -181,6 +216,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -194,6 +230,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -207,6 +244,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -220,6 +258,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -233,6 +272,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -246,6 +286,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -259,6 +300,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -272,6 +314,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -285,6 +328,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -298,6 +342,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -311,6 +356,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -324,6 +370,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -337,6 +384,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -350,6 +398,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -363,6 +412,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -376,6 +426,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -389,6 +440,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -402,6 +454,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -415,6 +468,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -428,6 +482,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -441,6 +496,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -454,6 +510,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -467,6 +524,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -480,6 +538,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -493,6 +552,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -506,6 +566,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -519,6 +580,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -532,6 +594,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -545,6 +608,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -558,6 +622,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -571,6 +636,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -584,6 +650,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -597,6 +664,7 @@ diesel::table! {
         original_data -> Nullable<Json>,
         new_data -> Nullable<Json>,
         query -> Text,
+        change_reason -> Nullable<Text>,
     }
 }
 
@@ -620,6 +688,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    idempotency_keys (key) {
+        key -> Text,
+        request_hash -> Text,
+        response_status -> Nullable<Int4>,
+        response_headers -> Nullable<Json>,
+        response_body -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        locked_until -> Timestamp,
+    }
+}
+
 diesel::table! {
     type_templates (type_name) {
         type_name -> Text,
@@ -632,9 +713,58 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    validation_rules (rule_name) {
+        rule_name -> Text,
+        description -> Text,
+        rule -> Json,
+        created_at -> Timestamp,
+        #[max_length = 200]
+        created_by -> Varchar,
+        last_modified_at -> Timestamp,
+        #[max_length = 200]
+        last_modified_by -> Varchar,
+    }
+}
+
 diesel::joinable!(default_configs -> functions (function_name));
 diesel::joinable!(dimensions -> functions (function_name));
 
+diesel::table! {
+    webhook_events (id) {
+        id -> Int8,
+        event -> Text,
+        webhook -> Json,
+        payload -> Json,
+        config_version -> Nullable<Text>,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+        endpoint -> Nullable<Text>,
+        payload_template -> Nullable<Json>,
+    }
+}
+
+diesel::table! {
+    webhooks (name) {
+        name -> Text,
+        description -> Text,
+        enabled -> Bool,
+        webhook -> Json,
+        events -> Json,
+        key_prefixes -> Json,
+        context -> Nullable<Json>,
+        payload_template -> Nullable<Json>,
+        created_at -> Timestamp,
+        created_by -> Text,
+        last_modified_at -> Timestamp,
+        last_modified_by -> Text,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     config_versions,
     contexts,
@@ -683,5 +813,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     event_log_y2026m11,
     event_log_y2026m12,
     functions,
+    idempotency_keys,
     type_templates,
+    validation_rules,
+    webhook_events,
+    webhooks,
 );
@@ -199,6 +199,26 @@ impl<'de> Deserialize<'de> for PaginationParams {
     }
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MetadataFilters {
+    pub owner: Option<String>,
+    pub label: Option<String>,
+}
+
+impl MetadataFilters {
+    pub fn owners(&self) -> Option<Vec<String>> {
+        self.owner
+            .as_ref()
+            .map(|owners| owners.split(',').map(String::from).collect())
+    }
+
+    pub fn labels(&self) -> Option<Vec<String>> {
+        self.label
+            .as_ref()
+            .map(|labels| labels.split(',').map(String::from).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
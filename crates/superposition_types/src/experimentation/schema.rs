@@ -572,6 +572,54 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    idempotency_keys (key) {
+        key -> Text,
+        request_hash -> Text,
+        response_status -> Nullable<Int4>,
+        response_headers -> Nullable<Json>,
+        response_body -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        locked_until -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhook_events (id) {
+        id -> Int8,
+        event -> Text,
+        webhook -> Json,
+        payload -> Json,
+        config_version -> Nullable<Text>,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+        endpoint -> Nullable<Text>,
+        payload_template -> Nullable<Json>,
+    }
+}
+
+diesel::table! {
+    webhooks (name) {
+        name -> Text,
+        description -> Text,
+        enabled -> Bool,
+        webhook -> Json,
+        events -> Json,
+        key_prefixes -> Json,
+        context -> Nullable<Json>,
+        payload_template -> Nullable<Json>,
+        created_at -> Timestamp,
+        created_by -> Text,
+        last_modified_at -> Timestamp,
+        last_modified_by -> Text,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     event_log,
     event_log_y2023m08,
@@ -616,4 +664,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     event_log_y2026m11,
     event_log_y2026m12,
     experiments,
+    idempotency_keys,
+    webhook_events,
+    webhooks,
 );
@@ -1,4 +1,5 @@
 #![deny(unused_crate_dependencies)]
+pub mod authorization;
 pub mod cac;
 mod config;
 mod contextual;
@@ -25,7 +26,9 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "server")]
 use serde_json::json;
-use webhook::WebhookConfig;
+use webhook::{ConfigWebhook, WebhookConfig};
+
+use crate::authorization::RoleBinding;
 
 pub use crate::config::{Condition, Config, Context, Overrides};
 pub use crate::contextual::Contextual;
@@ -37,6 +40,10 @@ pub struct User {
     pub username: String,
     pub auth_token: String,
     pub auth_type: String,
+    /// Set for users authenticated with an API key, who can only do what
+    /// the key allows instead of what their role bindings allow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<RoleBinding>>,
 }
 
 impl User {
@@ -64,6 +71,7 @@ impl Default for User {
             username: "superposition".into(),
             auth_token: "1234abcd".into(),
             auth_type: "Bearer".into(),
+            scopes: None,
         }
     }
 }
@@ -111,11 +119,19 @@ const ALPHANUMERIC_WITHOUT_DOT: &str = "^[a-zA-Z0-9-_]{1,64}$";
 const ALPHANUMERIC_WITHOUT_DOT_WORDS: &str =
     "It can contain the following characters only [a-zA-Z0-9-_]";
 
+const TENANT_NAME: &str = "^[a-z][a-z0-9_]{0,30}$";
+const TENANT_NAME_WORDS: &str =
+    "It can contain the following characters only [a-z0-9_], \
+                                    it should start with a letter and be at most 31 characters long.";
+
 pub enum RegexEnum {
     DefaultConfigKey,
     DimensionName,
     FunctionName,
     TypeTemplateName,
+    ValidationRuleName,
+    TenantName,
+    WebhookName,
 }
 
 impl RegexEnum {
@@ -143,6 +159,9 @@ impl RegexEnum {
             Self::DimensionName => ALPHANUMERIC_WITH_DOT_WORDS,
             Self::FunctionName => ALPHANUMERIC_WITHOUT_DOT_WORDS,
             Self::TypeTemplateName => ALPHANUMERIC_WITHOUT_DOT_WORDS,
+            Self::ValidationRuleName => ALPHANUMERIC_WITHOUT_DOT_WORDS,
+            Self::TenantName => TENANT_NAME_WORDS,
+            Self::WebhookName => ALPHANUMERIC_WITHOUT_DOT_WORDS,
         }
         .to_string()
     }
@@ -155,6 +174,9 @@ impl Display for RegexEnum {
             Self::DimensionName => ALPHANUMERIC_WITH_DOT,
             Self::FunctionName => ALPHANUMERIC_WITHOUT_DOT,
             Self::TypeTemplateName => ALPHANUMERIC_WITHOUT_DOT,
+            Self::ValidationRuleName => ALPHANUMERIC_WITHOUT_DOT,
+            Self::TenantName => TENANT_NAME,
+            Self::WebhookName => ALPHANUMERIC_WITHOUT_DOT,
         }
         .to_string();
         write!(f, "{regex}")
@@ -165,6 +187,32 @@ impl Display for RegexEnum {
 pub struct TenantConfig {
     pub mandatory_dimensions: Vec<String>,
     pub experiments_webhook_config: WebhookConfig,
+    #[serde(default)]
+    pub change_reason_required: bool,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    #[serde(default)]
+    pub quotas: TenantQuotas,
+    #[serde(default)]
+    pub config_webhooks: Vec<ConfigWebhook>,
+}
+
+/// Requests a caller can make to a tenant per minute, and all the callers of
+/// the tenant together, unlimited when unset.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RateLimits {
+    pub reads_per_minute: Option<u32>,
+    pub writes_per_minute: Option<u32>,
+    pub tenant_reads_per_minute: Option<u32>,
+    pub tenant_writes_per_minute: Option<u32>,
+}
+
+/// Hard limits on what a tenant can hold, unlimited when unset.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TenantQuotas {
+    pub max_contexts: Option<i64>,
+    pub max_default_configs: Option<i64>,
+    pub max_active_experiments: Option<i64>,
 }
 
 #[cfg(feature = "server")]
@@ -370,4 +418,29 @@ mod tests {
             true
         );
     }
+
+    #[test]
+    fn test_deserialize_tenant_config_limits() {
+        let config = json!({
+            "mandatory_dimensions": [],
+            "experiments_webhook_config": { "enabled": false },
+        });
+        let tenant_config = serde_json::from_value::<TenantConfig>(config).unwrap();
+        assert_eq!(tenant_config.rate_limits.reads_per_minute, None);
+        assert_eq!(tenant_config.quotas.max_contexts, None);
+        assert!(tenant_config.config_webhooks.is_empty());
+
+        let config = json!({
+            "mandatory_dimensions": [],
+            "experiments_webhook_config": { "enabled": false },
+            "rate_limits": { "reads_per_minute": 600 },
+            "quotas": { "max_contexts": 100, "max_active_experiments": 5 },
+        });
+        let tenant_config = serde_json::from_value::<TenantConfig>(config).unwrap();
+        assert_eq!(tenant_config.rate_limits.reads_per_minute, Some(600));
+        assert_eq!(tenant_config.rate_limits.writes_per_minute, None);
+        assert_eq!(tenant_config.quotas.max_contexts, Some(100));
+        assert_eq!(tenant_config.quotas.max_default_configs, None);
+        assert_eq!(tenant_config.quotas.max_active_experiments, Some(5));
+    }
 }
@@ -15,6 +15,8 @@ pub enum AppError {
     BadArgument(String),
     #[error("not found ( `{0}` )")]
     NotFound(String),
+    #[error("forbidden ( `{0}` )")]
+    Forbidden(String),
     #[error(transparent)]
     DbError(#[from] diesel::result::Error),
     #[error(transparent)]
@@ -58,7 +60,8 @@ impl AppError {
         match self {
             AppError::ValidationError(msg)
             | AppError::BadArgument(msg)
-            | AppError::NotFound(msg) => msg.to_owned(),
+            | AppError::NotFound(msg)
+            | AppError::Forbidden(msg) => msg.to_owned(),
             AppError::UnexpectedError(_) => String::from("Something went wrong"),
             AppError::ResponseError(error) => error.message.clone(),
             AppError::DbError(diesel_error::InvalidCString(_)) => {
@@ -90,6 +93,9 @@ impl error::ResponseError for AppError {
             AppError::NotFound(msg) => {
                 Self::generate_err_response(StatusCode::NOT_FOUND, msg)
             }
+            AppError::Forbidden(msg) => {
+                Self::generate_err_response(StatusCode::FORBIDDEN, msg)
+            }
             AppError::UnexpectedError(_) => Self::generate_err_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Something went wrong",
@@ -1,9 +1,14 @@
+use chrono::NaiveDateTime;
+use regex::{Captures, Regex};
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 use std::{
     collections::HashMap,
     fmt::{self},
 };
 
+use crate::Condition;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum HeadersEnum {
     ConfigVersion,
@@ -23,12 +28,20 @@ impl fmt::Display for HeadersEnum {
 pub enum HttpMethod {
     Get,
     Post,
+    Put,
+    Patch,
+    Delete,
 }
 
+/// Header a webhook is authorized with, its value read either from an env var
+/// the operator allows or from a secret stored encrypted.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Authorization {
     pub key: String,
-    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,13 +53,19 @@ pub struct Webhook {
     pub authorization: Option<Authorization>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum WebhookEvent {
     ExperimentCreated,
     ExperimentStarted,
     ExperimentInprogress,
     ExperimentUpdated,
     ExperimentConcluded,
+    ConfigVersionCreated,
+    ContextCreated,
+    ContextUpdated,
+    ContextDeleted,
+    DefaultConfigChanged,
+    DimensionChanged,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -63,6 +82,128 @@ pub struct WebhookResponse<T> {
     pub payload: T,
 }
 
+/// Webhook of the CAC events of a tenant, delivering the subscribed events
+/// which touch a key under one of the prefixes. Leaving either list empty
+/// subscribes to every event or key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    #[serde(default)]
+    pub key_prefixes: Vec<String>,
+}
+
+impl ConfigWebhook {
+    /// Events touching no key in particular, like dimension changes, are
+    /// delivered whatever the prefixes.
+    pub fn is_subscribed(&self, event: WebhookEvent, keys: &[String]) -> bool {
+        (self.events.is_empty() || self.events.contains(&event))
+            && (self.key_prefixes.is_empty()
+                || keys.is_empty()
+                || keys.iter().any(|key| {
+                    self.key_prefixes
+                        .iter()
+                        .any(|prefix| key.starts_with(prefix.as_str()))
+                }))
+    }
+}
+
+/// Endpoint of the webhook registry of a tenant. On top of the events and
+/// key prefixes of a config webhook, it can be limited to the changes whose
+/// context satisfies `context`, and send its `payload_template` filled from
+/// the event in place of the event itself.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub subscription: ConfigWebhook,
+    pub context: Option<Condition>,
+    pub payload_template: Option<Value>,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+    pub last_modified_at: NaiveDateTime,
+    pub last_modified_by: String,
+}
+
+impl WebhookEndpoint {
+    /// `dimensions` are the values set by the context of the change, changes
+    /// without a context are delivered whatever the condition.
+    pub fn is_subscribed(
+        &self,
+        event: WebhookEvent,
+        keys: &[String],
+        dimensions: Option<&Map<String, Value>>,
+    ) -> bool {
+        self.enabled
+            && self.subscription.is_subscribed(event, keys)
+            && match (&self.context, dimensions) {
+                (Some(condition), Some(dimensions)) => {
+                    jsonlogic::apply(
+                        &Value::Object(condition.clone().into()),
+                        &Value::Object(dimensions.clone()),
+                    ) == Ok(Value::Bool(true))
+                }
+                _ => true,
+            }
+    }
+}
+
+/*
+  Fills a payload template from the body a webhook gets by default, with its
+  `event_info` and `payload`. A string made of a single `{{path}}`, like
+  `{{payload.name}}`, is replaced by the value at that dotted path, while the
+  placeholders within a longer string are replaced by their text. Missing
+  paths render as null, or as nothing within a string.
+*/
+pub fn render_payload_template(template: &Value, body: &Value) -> Value {
+    let placeholder = Regex::new(r"\{\{\s*([a-zA-Z0-9_.-]+)\s*\}\}")
+        .expect("the placeholder regex is valid");
+    render(template, body, &placeholder)
+}
+
+fn lookup<'a>(body: &'a Value, path: &str) -> &'a Value {
+    body.pointer(&format!("/{}", path.replace('.', "/")))
+        .unwrap_or(&Value::Null)
+}
+
+fn render(template: &Value, body: &Value, placeholder: &Regex) -> Value {
+    match template {
+        Value::String(text) => match placeholder.captures(text) {
+            Some(captures) if captures[0].len() == text.len() => {
+                lookup(body, &captures[1]).clone()
+            }
+            _ => Value::String(
+                placeholder
+                    .replace_all(text, |captures: &Captures| {
+                        match lookup(body, &captures[1]) {
+                            Value::String(value) => value.clone(),
+                            Value::Null => String::new(),
+                            value => value.to_string(),
+                        }
+                    })
+                    .into_owned(),
+            ),
+        },
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render(item, body, placeholder))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, body, placeholder)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
 #[derive(Clone, Serialize)]
 pub enum WebhookConfig {
     Disbled,
@@ -90,3 +231,119 @@ impl<'de> Deserialize<'de> for WebhookConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_config_webhook_subscription() {
+        let webhook = serde_json::from_value::<ConfigWebhook>(json!({
+            "url": "http://localhost:8080/purge",
+            "method": "Post",
+            "custom_headers": {},
+            "service_headers": ["ConfigVersion"],
+            "events": ["ContextUpdated", "DimensionChanged"],
+            "key_prefixes": ["payments."]
+        }))
+        .unwrap();
+        let keys =
+            |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+
+        assert!(webhook.is_subscribed(
+            WebhookEvent::ContextUpdated,
+            &keys(&["checkout.timeout", "payments.retries"])
+        ));
+        assert!(!webhook
+            .is_subscribed(WebhookEvent::ContextUpdated, &keys(&["checkout.timeout"])));
+        assert!(!webhook
+            .is_subscribed(WebhookEvent::ContextCreated, &keys(&["payments.retries"])));
+        assert!(webhook.is_subscribed(WebhookEvent::DimensionChanged, &[]));
+
+        let webhook = ConfigWebhook {
+            events: Vec::new(),
+            key_prefixes: Vec::new(),
+            ..webhook
+        };
+        assert!(webhook.is_subscribed(WebhookEvent::ConfigVersionCreated, &keys(&["a"])));
+    }
+
+    #[test]
+    fn test_webhook_endpoint_context() {
+        let endpoint = serde_json::from_value::<WebhookEndpoint>(json!({
+            "name": "qa",
+            "description": "experiments run in Bangalore",
+            "enabled": true,
+            "url": "http://localhost:8080/qa",
+            "method": "Put",
+            "custom_headers": {},
+            "service_headers": [],
+            "authorization": null,
+            "events": ["ExperimentCreated"],
+            "context": { "==": [{ "var": "city" }, "Bangalore"] },
+            "payload_template": null,
+            "created_at": "2024-11-04T10:12:45",
+            "created_by": "user@superposition.io",
+            "last_modified_at": "2024-11-04T10:12:45",
+            "last_modified_by": "user@superposition.io"
+        }))
+        .unwrap();
+        let dimensions = |city: &str| {
+            json!({ "city": city, "os": "android" })
+                .as_object()
+                .cloned()
+                .unwrap()
+        };
+
+        assert!(endpoint.is_subscribed(
+            WebhookEvent::ExperimentCreated,
+            &[],
+            Some(&dimensions("Bangalore"))
+        ));
+        assert!(!endpoint.is_subscribed(
+            WebhookEvent::ExperimentCreated,
+            &[],
+            Some(&dimensions("Delhi"))
+        ));
+        assert!(!endpoint.is_subscribed(
+            WebhookEvent::ExperimentConcluded,
+            &[],
+            Some(&dimensions("Bangalore"))
+        ));
+        assert!(endpoint.is_subscribed(WebhookEvent::ExperimentCreated, &[], None));
+
+        let endpoint = WebhookEndpoint {
+            enabled: false,
+            ..endpoint
+        };
+        assert!(!endpoint.is_subscribed(WebhookEvent::ExperimentCreated, &[], None));
+    }
+
+    #[test]
+    fn test_render_payload_template() {
+        let body = json!({
+            "event_info": { "webhook_event": "ExperimentCreated", "tenant_id": "dev" },
+            "payload": { "name": "checkout", "override_keys": ["a", "b"] }
+        });
+        let template = json!({
+            "text": "{{event_info.webhook_event}} on {{ event_info.tenant_id }}: {{payload.name}}",
+            "keys": "{{payload.override_keys}}",
+            "first_key": ["{{payload.override_keys.0}}"],
+            "missing": "{{payload.id}}",
+            "static": 1
+        });
+
+        assert_eq!(
+            render_payload_template(&template, &body),
+            json!({
+                "text": "ExperimentCreated on dev: checkout",
+                "keys": ["a", "b"],
+                "first_key": ["a"],
+                "missing": null,
+                "static": 1
+            })
+        );
+    }
+}
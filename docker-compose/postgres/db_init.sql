@@ -19271,6 +19271,770 @@ VALUES (
         NOW()
     );
 
+--
+-- Migrations added after the dump was taken, applied to every schema the
+-- way scripts/create-tenant.sh does it.
+--
+
+SELECT pg_catalog.set_config('search_path', 'public', false);
+
+-- context_aware_config/migrations/2024-08-12-094512_validation_rules
+-- Name: validation_rules; Type: TABLE; Schema: public; Owner: -
+--
+CREATE TABLE IF NOT EXISTS public.validation_rules (
+    rule_name text PRIMARY KEY,
+    description text NOT NULL,
+    rule json NOT NULL,
+    created_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by varchar(200) NOT NULL,
+    last_modified_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by varchar(200) NOT NULL
+);
+--
+-- Name: validation_rules validation_rules_audit; Type: TRIGGER; Schema: public; Owner: -
+--
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON public.validation_rules FOR EACH ROW EXECUTE FUNCTION public.event_logger();
+
+-- context_aware_config/migrations/2024-08-19-113025_key_dimension_metadata
+ALTER TABLE public.default_configs
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+ALTER TABLE public.dimensions
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+CREATE INDEX IF NOT EXISTS default_configs_owner_index ON public.default_configs(owner);
+CREATE INDEX IF NOT EXISTS dimensions_owner_index ON public.dimensions(owner);
+
+-- context_aware_config/migrations/2024-08-26-081432_soft_delete
+ALTER TABLE public.contexts
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE public.default_configs
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE public.dimensions
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+CREATE INDEX IF NOT EXISTS contexts_deleted_at_index ON public.contexts(deleted_at);
+CREATE INDEX IF NOT EXISTS default_configs_deleted_at_index ON public.default_configs(deleted_at);
+CREATE INDEX IF NOT EXISTS dimensions_deleted_at_index ON public.dimensions(deleted_at);
+
+-- context_aware_config/migrations/2024-09-02-064810_context_enabled
+ALTER TABLE public.contexts
+add column enabled BOOLEAN NOT NULL DEFAULT true;
+
+-- context_aware_config/migrations/2024-09-09-102344_change_reason
+ALTER TABLE public.config_versions add column change_reason TEXT;
+ALTER TABLE public.event_log add column IF NOT EXISTS change_reason TEXT;
+--
+-- Name: cac_event_logger(); Type: FUNCTION; Schema: public; Owner: -
+-- The change reason is set per transaction through the superposition.change_reason setting.
+-- event_logger() is left as it is, the experimentation tables share it.
+--
+CREATE OR REPLACE FUNCTION public.cac_event_logger() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+DECLARE
+    old_data json;
+    new_data json;
+    change_reason text;
+BEGIN
+    change_reason := NULLIF(current_setting('superposition.change_reason', true), '');
+    IF (TG_OP = 'UPDATE') THEN
+        old_data := row_to_json(OLD);
+        new_data := row_to_json(NEW);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, original_data, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'DELETE') THEN
+        old_data := row_to_json(OLD);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, original_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'INSERT') THEN
+        new_data = row_to_json(NEW);
+        INSERT INTO public.event_log
+            (table_name, user_name, action, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    END IF;
+    RETURN NULL;
+END;
+$$;
+DROP TRIGGER IF EXISTS contexts_audit ON public.contexts;
+CREATE TRIGGER contexts_audit AFTER INSERT OR DELETE OR UPDATE ON public.contexts FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS default_configs_audit ON public.default_configs;
+CREATE TRIGGER default_configs_audit AFTER INSERT OR DELETE OR UPDATE ON public.default_configs FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS dimensions_audit ON public.dimensions;
+CREATE TRIGGER dimensions_audit AFTER INSERT OR DELETE OR UPDATE ON public.dimensions FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS functions_audit ON public.functions;
+CREATE TRIGGER functions_audit AFTER INSERT OR DELETE OR UPDATE ON public.functions FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+DROP TRIGGER IF EXISTS validation_rules_audit ON public.validation_rules;
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON public.validation_rules FOR EACH ROW EXECUTE FUNCTION public.cac_event_logger();
+
+-- context_aware_config/migrations/2024-09-16-093045_idempotency_keys
+CREATE TABLE IF NOT EXISTS public.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON public.idempotency_keys (expires_at);
+
+-- context_aware_config/migrations/2024-10-21-101204_secret_default_configs
+ALTER TABLE public.default_configs
+add column secret BOOLEAN NOT NULL DEFAULT false;
+
+-- context_aware_config/migrations/2024-10-28-093211_config_version_deltas
+ALTER TABLE public.config_versions
+add column parent_id BIGINT,
+add column checkpoint_id BIGINT;
+
+UPDATE public.config_versions SET checkpoint_id = id;
+
+ALTER TABLE public.config_versions
+alter column checkpoint_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS config_versions_checkpoint_id_index
+ON public.config_versions (checkpoint_id);
+
+-- context_aware_config/migrations/2024-11-04-101245_webhook_events
+CREATE TABLE IF NOT EXISTS public.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON public.webhook_events (status, next_attempt_at);
+
+-- context_aware_config/migrations/2024-11-11-094512_webhooks
+CREATE TABLE IF NOT EXISTS public.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', 'dev_cac', false);
+
+-- context_aware_config/migrations/2024-08-12-094512_validation_rules
+-- Name: validation_rules; Type: TABLE; Schema: dev_cac; Owner: -
+--
+CREATE TABLE IF NOT EXISTS dev_cac.validation_rules (
+    rule_name text PRIMARY KEY,
+    description text NOT NULL,
+    rule json NOT NULL,
+    created_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by varchar(200) NOT NULL,
+    last_modified_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by varchar(200) NOT NULL
+);
+--
+-- Name: validation_rules validation_rules_audit; Type: TRIGGER; Schema: dev_cac; Owner: -
+--
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.validation_rules FOR EACH ROW EXECUTE FUNCTION dev_cac.event_logger();
+
+-- context_aware_config/migrations/2024-08-19-113025_key_dimension_metadata
+ALTER TABLE dev_cac.default_configs
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+ALTER TABLE dev_cac.dimensions
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+CREATE INDEX IF NOT EXISTS default_configs_owner_index ON dev_cac.default_configs(owner);
+CREATE INDEX IF NOT EXISTS dimensions_owner_index ON dev_cac.dimensions(owner);
+
+-- context_aware_config/migrations/2024-08-26-081432_soft_delete
+ALTER TABLE dev_cac.contexts
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE dev_cac.default_configs
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE dev_cac.dimensions
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+CREATE INDEX IF NOT EXISTS contexts_deleted_at_index ON dev_cac.contexts(deleted_at);
+CREATE INDEX IF NOT EXISTS default_configs_deleted_at_index ON dev_cac.default_configs(deleted_at);
+CREATE INDEX IF NOT EXISTS dimensions_deleted_at_index ON dev_cac.dimensions(deleted_at);
+
+-- context_aware_config/migrations/2024-09-02-064810_context_enabled
+ALTER TABLE dev_cac.contexts
+add column enabled BOOLEAN NOT NULL DEFAULT true;
+
+-- context_aware_config/migrations/2024-09-09-102344_change_reason
+ALTER TABLE dev_cac.config_versions add column change_reason TEXT;
+ALTER TABLE dev_cac.event_log add column IF NOT EXISTS change_reason TEXT;
+--
+-- Name: cac_event_logger(); Type: FUNCTION; Schema: dev_cac; Owner: -
+-- The change reason is set per transaction through the superposition.change_reason setting.
+-- event_logger() is left as it is, the experimentation tables share it.
+--
+CREATE OR REPLACE FUNCTION dev_cac.cac_event_logger() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+DECLARE
+    old_data json;
+    new_data json;
+    change_reason text;
+BEGIN
+    change_reason := NULLIF(current_setting('superposition.change_reason', true), '');
+    IF (TG_OP = 'UPDATE') THEN
+        old_data := row_to_json(OLD);
+        new_data := row_to_json(NEW);
+        INSERT INTO dev_cac.event_log
+            (table_name, user_name, action, original_data, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'DELETE') THEN
+        old_data := row_to_json(OLD);
+        INSERT INTO dev_cac.event_log
+            (table_name, user_name, action, original_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'INSERT') THEN
+        new_data = row_to_json(NEW);
+        INSERT INTO dev_cac.event_log
+            (table_name, user_name, action, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    END IF;
+    RETURN NULL;
+END;
+$$;
+DROP TRIGGER IF EXISTS contexts_audit ON dev_cac.contexts;
+CREATE TRIGGER contexts_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.contexts FOR EACH ROW EXECUTE FUNCTION dev_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS default_configs_audit ON dev_cac.default_configs;
+CREATE TRIGGER default_configs_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.default_configs FOR EACH ROW EXECUTE FUNCTION dev_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS dimensions_audit ON dev_cac.dimensions;
+CREATE TRIGGER dimensions_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.dimensions FOR EACH ROW EXECUTE FUNCTION dev_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS functions_audit ON dev_cac.functions;
+CREATE TRIGGER functions_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.functions FOR EACH ROW EXECUTE FUNCTION dev_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS validation_rules_audit ON dev_cac.validation_rules;
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON dev_cac.validation_rules FOR EACH ROW EXECUTE FUNCTION dev_cac.cac_event_logger();
+
+-- context_aware_config/migrations/2024-09-16-093045_idempotency_keys
+CREATE TABLE IF NOT EXISTS dev_cac.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON dev_cac.idempotency_keys (expires_at);
+
+-- context_aware_config/migrations/2024-10-21-101204_secret_default_configs
+ALTER TABLE dev_cac.default_configs
+add column secret BOOLEAN NOT NULL DEFAULT false;
+
+-- context_aware_config/migrations/2024-10-28-093211_config_version_deltas
+ALTER TABLE dev_cac.config_versions
+add column parent_id BIGINT,
+add column checkpoint_id BIGINT;
+
+UPDATE dev_cac.config_versions SET checkpoint_id = id;
+
+ALTER TABLE dev_cac.config_versions
+alter column checkpoint_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS config_versions_checkpoint_id_index
+ON dev_cac.config_versions (checkpoint_id);
+
+-- context_aware_config/migrations/2024-11-04-101245_webhook_events
+CREATE TABLE IF NOT EXISTS dev_cac.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON dev_cac.webhook_events (status, next_attempt_at);
+
+-- context_aware_config/migrations/2024-11-11-094512_webhooks
+CREATE TABLE IF NOT EXISTS dev_cac.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE dev_cac.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE dev_cac.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', 'test_cac', false);
+
+-- context_aware_config/migrations/2024-08-12-094512_validation_rules
+-- Name: validation_rules; Type: TABLE; Schema: test_cac; Owner: -
+--
+CREATE TABLE IF NOT EXISTS test_cac.validation_rules (
+    rule_name text PRIMARY KEY,
+    description text NOT NULL,
+    rule json NOT NULL,
+    created_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_by varchar(200) NOT NULL,
+    last_modified_at timestamp without time zone DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    last_modified_by varchar(200) NOT NULL
+);
+--
+-- Name: validation_rules validation_rules_audit; Type: TRIGGER; Schema: test_cac; Owner: -
+--
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.validation_rules FOR EACH ROW EXECUTE FUNCTION test_cac.event_logger();
+
+-- context_aware_config/migrations/2024-08-19-113025_key_dimension_metadata
+ALTER TABLE test_cac.default_configs
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+ALTER TABLE test_cac.dimensions
+add column description TEXT NOT NULL DEFAULT '',
+add column owner varchar(200),
+add column labels TEXT[] NOT NULL DEFAULT '{}',
+add column deprecated BOOLEAN NOT NULL DEFAULT false,
+add column sunset_date DATE;
+
+CREATE INDEX IF NOT EXISTS default_configs_owner_index ON test_cac.default_configs(owner);
+CREATE INDEX IF NOT EXISTS dimensions_owner_index ON test_cac.dimensions(owner);
+
+-- context_aware_config/migrations/2024-08-26-081432_soft_delete
+ALTER TABLE test_cac.contexts
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE test_cac.default_configs
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+ALTER TABLE test_cac.dimensions
+add column deleted_at TIMESTAMP,
+add column deleted_by varchar(200);
+
+CREATE INDEX IF NOT EXISTS contexts_deleted_at_index ON test_cac.contexts(deleted_at);
+CREATE INDEX IF NOT EXISTS default_configs_deleted_at_index ON test_cac.default_configs(deleted_at);
+CREATE INDEX IF NOT EXISTS dimensions_deleted_at_index ON test_cac.dimensions(deleted_at);
+
+-- context_aware_config/migrations/2024-09-02-064810_context_enabled
+ALTER TABLE test_cac.contexts
+add column enabled BOOLEAN NOT NULL DEFAULT true;
+
+-- context_aware_config/migrations/2024-09-09-102344_change_reason
+ALTER TABLE test_cac.config_versions add column change_reason TEXT;
+ALTER TABLE test_cac.event_log add column IF NOT EXISTS change_reason TEXT;
+--
+-- Name: cac_event_logger(); Type: FUNCTION; Schema: test_cac; Owner: -
+-- The change reason is set per transaction through the superposition.change_reason setting.
+-- event_logger() is left as it is, the experimentation tables share it.
+--
+CREATE OR REPLACE FUNCTION test_cac.cac_event_logger() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+DECLARE
+    old_data json;
+    new_data json;
+    change_reason text;
+BEGIN
+    change_reason := NULLIF(current_setting('superposition.change_reason', true), '');
+    IF (TG_OP = 'UPDATE') THEN
+        old_data := row_to_json(OLD);
+        new_data := row_to_json(NEW);
+        INSERT INTO test_cac.event_log
+            (table_name, user_name, action, original_data, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'DELETE') THEN
+        old_data := row_to_json(OLD);
+        INSERT INTO test_cac.event_log
+            (table_name, user_name, action, original_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                old_data,
+                current_query(),
+                change_reason
+            );
+    ELSIF (TG_OP = 'INSERT') THEN
+        new_data = row_to_json(NEW);
+        INSERT INTO test_cac.event_log
+            (table_name, user_name, action, new_data, query, change_reason)
+            VALUES (
+                TG_TABLE_NAME::TEXT,
+                session_user::TEXT,
+                TG_OP,
+                new_data,
+                current_query(),
+                change_reason
+            );
+    END IF;
+    RETURN NULL;
+END;
+$$;
+DROP TRIGGER IF EXISTS contexts_audit ON test_cac.contexts;
+CREATE TRIGGER contexts_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.contexts FOR EACH ROW EXECUTE FUNCTION test_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS default_configs_audit ON test_cac.default_configs;
+CREATE TRIGGER default_configs_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.default_configs FOR EACH ROW EXECUTE FUNCTION test_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS dimensions_audit ON test_cac.dimensions;
+CREATE TRIGGER dimensions_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.dimensions FOR EACH ROW EXECUTE FUNCTION test_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS functions_audit ON test_cac.functions;
+CREATE TRIGGER functions_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.functions FOR EACH ROW EXECUTE FUNCTION test_cac.cac_event_logger();
+DROP TRIGGER IF EXISTS validation_rules_audit ON test_cac.validation_rules;
+CREATE TRIGGER validation_rules_audit AFTER INSERT OR DELETE OR UPDATE ON test_cac.validation_rules FOR EACH ROW EXECUTE FUNCTION test_cac.cac_event_logger();
+
+-- context_aware_config/migrations/2024-09-16-093045_idempotency_keys
+CREATE TABLE IF NOT EXISTS test_cac.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON test_cac.idempotency_keys (expires_at);
+
+-- context_aware_config/migrations/2024-10-21-101204_secret_default_configs
+ALTER TABLE test_cac.default_configs
+add column secret BOOLEAN NOT NULL DEFAULT false;
+
+-- context_aware_config/migrations/2024-10-28-093211_config_version_deltas
+ALTER TABLE test_cac.config_versions
+add column parent_id BIGINT,
+add column checkpoint_id BIGINT;
+
+UPDATE test_cac.config_versions SET checkpoint_id = id;
+
+ALTER TABLE test_cac.config_versions
+alter column checkpoint_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS config_versions_checkpoint_id_index
+ON test_cac.config_versions (checkpoint_id);
+
+-- context_aware_config/migrations/2024-11-04-101245_webhook_events
+CREATE TABLE IF NOT EXISTS test_cac.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON test_cac.webhook_events (status, next_attempt_at);
+
+-- context_aware_config/migrations/2024-11-11-094512_webhooks
+CREATE TABLE IF NOT EXISTS test_cac.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE test_cac.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE test_cac.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', 'public', false);
+
+-- experimentation_platform/migrations/2024-09-16-093112_idempotency_keys
+CREATE TABLE IF NOT EXISTS public.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON public.idempotency_keys (expires_at);
+
+-- experimentation_platform/migrations/2024-11-04-101312_webhook_events
+CREATE TABLE IF NOT EXISTS public.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON public.webhook_events (status, next_attempt_at);
+
+-- experimentation_platform/migrations/2024-11-11-094538_webhooks
+CREATE TABLE IF NOT EXISTS public.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE public.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', 'dev_experimentation', false);
+
+-- experimentation_platform/migrations/2024-09-16-093112_idempotency_keys
+CREATE TABLE IF NOT EXISTS dev_experimentation.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON dev_experimentation.idempotency_keys (expires_at);
+
+-- experimentation_platform/migrations/2024-11-04-101312_webhook_events
+CREATE TABLE IF NOT EXISTS dev_experimentation.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON dev_experimentation.webhook_events (status, next_attempt_at);
+
+-- experimentation_platform/migrations/2024-11-11-094538_webhooks
+CREATE TABLE IF NOT EXISTS dev_experimentation.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE dev_experimentation.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE dev_experimentation.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', 'test_experimentation', false);
+
+-- experimentation_platform/migrations/2024-09-16-093112_idempotency_keys
+CREATE TABLE IF NOT EXISTS test_experimentation.idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers JSON,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_expires_at_index ON test_experimentation.idempotency_keys (expires_at);
+
+-- experimentation_platform/migrations/2024-11-04-101312_webhook_events
+CREATE TABLE IF NOT EXISTS test_experimentation.webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    event TEXT NOT NULL,
+    webhook JSON NOT NULL,
+    payload JSON NOT NULL,
+    config_version TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS webhook_events_status_next_attempt_at_index ON test_experimentation.webhook_events (status, next_attempt_at);
+
+-- experimentation_platform/migrations/2024-11-11-094538_webhooks
+CREATE TABLE IF NOT EXISTS test_experimentation.webhooks (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    webhook JSON NOT NULL,
+    events JSON NOT NULL DEFAULT '[]',
+    key_prefixes JSON NOT NULL DEFAULT '[]',
+    context JSON,
+    payload_template JSON,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by TEXT NOT NULL,
+    last_modified_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_modified_by TEXT NOT NULL
+);
+
+ALTER TABLE test_experimentation.webhook_events ADD COLUMN IF NOT EXISTS endpoint TEXT;
+ALTER TABLE test_experimentation.webhook_events ADD COLUMN IF NOT EXISTS payload_template JSON;
+
+SELECT pg_catalog.set_config('search_path', '', false);
+
+INSERT INTO public.__diesel_schema_migrations (version) VALUES
+    ('20240812094512'),
+    ('20240819113025'),
+    ('20240826081432'),
+    ('20240902064810'),
+    ('20240909102344'),
+    ('20240916093045'),
+    ('20240916093112'),
+    ('20241021101204'),
+    ('20241028093211'),
+    ('20241104101245'),
+    ('20241104101312'),
+    ('20241111094512'),
+    ('20241111094538');
+
+
 --
 -- PostgreSQL database dump complete
 --
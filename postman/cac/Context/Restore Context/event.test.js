@@ -0,0 +1,29 @@
+const host = pm.variables.get("host");
+const context_id = pm.environment.get("context_id");
+
+pm.test("200 check", function() {
+    pm.response.to.have.status(200);
+    const response = pm.response.json();
+    pm.expect(response.id).to.be.eq(context_id);
+    pm.expect(response.deleted_at).to.be.null;
+})
+
+pm.test("Fetch for restored context should succeed", function () {
+    const getRequest = {
+        url: `${host}/context/${context_id}`,
+        method: 'GET',
+        header: {
+            'Content-Type': 'application/json',
+            'x-tenant': 'test',
+        }
+    };
+
+    pm.sendRequest(getRequest, (error, response) => {
+        if(error) {
+            console.log("Failed to fetch context");
+            throw error;
+        }
+
+        pm.expect(response.code).to.be.eq(200);
+    });
+});
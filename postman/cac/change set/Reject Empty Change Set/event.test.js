@@ -0,0 +1,5 @@
+pm.test("400 check", function () {
+    pm.response.to.have.status(400);
+    const response = pm.response.json();
+    pm.expect(response.message).to.be.eq("change set should contain at least one change");
+});
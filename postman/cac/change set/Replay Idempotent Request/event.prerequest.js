@@ -0,0 +1,24 @@
+const host = pm.variables.get("host");
+const idempotency_key = `postman-${Date.now()}`;
+pm.environment.set("idempotency_key", idempotency_key);
+
+const options = {
+    'method': 'POST',
+    'url': `${host}/change-set`,
+    'header': {
+        'x-tenant': 'test',
+        'Content-Type': 'application/json',
+        'Idempotency-Key': idempotency_key
+    },
+    "body": {
+        "mode": "raw",
+        "raw": JSON.stringify({ "changes": [] })
+    }
+};
+pm.sendRequest(options, function (error, response) {
+    if (error) {
+        console.log("Failed to send the first request for the idempotency key");
+        throw error;
+    }
+    pm.expect(response.headers.get("idempotent-replayed")).to.be.undefined;
+});
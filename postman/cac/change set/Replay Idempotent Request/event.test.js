@@ -0,0 +1,6 @@
+pm.test("Stored response is replayed", function () {
+    pm.response.to.have.status(400);
+    pm.response.to.have.header("idempotent-replayed", "true");
+    const response = pm.response.json();
+    pm.expect(response.message).to.be.eq("change set should contain at least one change");
+});
@@ -0,0 +1,25 @@
+const host = pm.variables.get("host");
+
+pm.test("400 check", function () {
+    pm.response.to.have.status(400);
+});
+
+pm.test("Default config from the failed change set should not exist", function () {
+    const getRequest = {
+        url: `${host}/config`,
+        method: 'GET',
+        header: {
+            'Content-Type': 'application/json',
+            'x-tenant': 'test',
+        }
+    };
+
+    pm.sendRequest(getRequest, (error, response) => {
+        if (error) {
+            console.log("Failed to fetch config");
+            throw error;
+        }
+
+        pm.expect(response.json().default_configs).to.not.have.property("change_set_key");
+    });
+});